@@ -0,0 +1,171 @@
+use teloxide::dispatching::UpdateHandler;
+use teloxide::prelude::*;
+use teloxide::RequestError;
+
+use crate::config::{AccessConfig, Config, Role};
+use crate::i18n::Msg;
+use crate::telegram::{Bot, SendRetrying};
+
+/// Role of the sender of `upd`, or `None` if they may not use the bot.
+pub fn role_of(config: &Config, upd: &Update) -> Option<Role> {
+    role_for(
+        &config.access,
+        config.telegram.admin_chat_id,
+        upd.from().map(|user| user.id.0),
+    )
+}
+
+/// Role the sender of `upd` is given by name, in a list or as the owner of
+/// `admin_chat_id`; the default role doesn't count.
+pub fn listed_role_of(config: &Config, upd: &Update) -> Option<Role> {
+    listed_role(
+        &config.access,
+        config.telegram.admin_chat_id,
+        upd.from().map(|user| user.id.0),
+    )
+}
+
+fn role_for(
+    access: &AccessConfig,
+    admin_chat_id: Option<i64>,
+    user_id: Option<u64>,
+) -> Option<Role> {
+    if let Some(role) = listed_role(access, admin_chat_id, user_id) {
+        return Some(role);
+    }
+    if access.admins.is_empty() && access.writers.is_empty() && access.readers.is_empty() {
+        Some(access.default_role.unwrap_or(Role::Writer))
+    } else {
+        access.default_role
+    }
+}
+
+fn listed_role(
+    access: &AccessConfig,
+    admin_chat_id: Option<i64>,
+    user_id: Option<u64>,
+) -> Option<Role> {
+    // The owner of a private alert chat keeps the admin role it always had;
+    // members of an alert group don't get it by being there
+    let admin_user = admin_chat_id
+        .is_some_and(|admin| user_id.is_some_and(|user| i64::try_from(user) == Ok(admin)));
+    if admin_user {
+        return Some(Role::Admin);
+    }
+
+    let listed = |ids: &[u64]| user_id.is_some_and(|user| ids.contains(&user));
+    if listed(&access.admins) {
+        Some(Role::Admin)
+    } else if listed(&access.writers) {
+        Some(Role::Writer)
+    } else if listed(&access.readers) {
+        Some(Role::Reader)
+    } else {
+        None
+    }
+}
+
+/// Whether the sender of `upd` has at least `role`.
+pub fn allows(config: &Config, upd: &Update, role: Role) -> bool {
+    role_of(config, upd).is_some_and(|have| have >= role)
+}
+
+/// Filter passing updates from users with at least `role`.
+pub fn require(role: Role) -> UpdateHandler<RequestError> {
+    dptree::filter(move |upd: Update, config: Config| allows(&config, &upd, role))
+}
+
+/// Run `handler` only for users listed with at least `role`, whatever the
+/// default role: for updates anyone can send from any chat, like inline
+/// queries.
+pub fn guarded_listed(
+    role: Role,
+    handler: UpdateHandler<RequestError>,
+) -> UpdateHandler<RequestError> {
+    dptree::entry()
+        .branch(
+            dptree::filter(move |upd: Update, config: Config| {
+                listed_role_of(&config, &upd).is_some_and(|have| have >= role)
+            })
+            .chain(handler),
+        )
+        .endpoint(deny_handler)
+}
+
+/// Run `handler` for users with at least `role` and refuse everyone else.
+pub fn guarded(role: Role, handler: UpdateHandler<RequestError>) -> UpdateHandler<RequestError> {
+    dptree::entry()
+        .branch(require(role).chain(handler))
+        .endpoint(deny_handler)
+}
+
+/// Tell the sender they lack the permissions for what they tried. In groups
+/// only commands get a reply, so ordinary chatter isn't answered.
+pub async fn deny_handler(bot: Bot, upd: Update, config: Config) -> ResponseResult<()> {
+    log::info!(
+        "Access denied for user {:?}",
+        upd.from().map(|user| user.id.0)
+    );
+    let text = Msg::AccessDenied.text(config.general.language);
+    match &upd.kind {
+        teloxide::types::UpdateKind::CallbackQuery(query) => {
+            bot.answer_callback_query(query.id.clone())
+                .text(text)
+                .send_retrying()
+                .await?;
+        }
+        teloxide::types::UpdateKind::Message(msg)
+            if msg.chat.is_private() || msg.text().is_some_and(|text| text.starts_with('/')) =>
+        {
+            bot.send_message(msg.chat.id, text).send_retrying().await?;
+        }
+        // Reactions and other updates are ignored quietly
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roles_from_lists_admin_chat_and_default() {
+        let open = AccessConfig::default();
+        assert_eq!(role_for(&open, None, Some(1)), Some(Role::Writer));
+        // Members of an alert group are not admins for being there
+        assert_eq!(role_for(&open, Some(-100), Some(1)), Some(Role::Writer));
+        assert_eq!(role_for(&open, Some(7), Some(7)), Some(Role::Admin));
+
+        let access = AccessConfig {
+            admins: vec![1],
+            writers: vec![2],
+            readers: vec![3],
+            default_role: None,
+        };
+        assert_eq!(role_for(&access, None, Some(1)), Some(Role::Admin));
+        assert_eq!(role_for(&access, None, Some(2)), Some(Role::Writer));
+        assert_eq!(role_for(&access, None, Some(3)), Some(Role::Reader));
+        assert_eq!(role_for(&access, None, Some(4)), None);
+        assert_eq!(role_for(&access, Some(4), Some(4)), Some(Role::Admin));
+
+        let readers_by_default = AccessConfig {
+            default_role: Some(Role::Reader),
+            ..access
+        };
+        assert_eq!(
+            role_for(&readers_by_default, None, Some(4)),
+            Some(Role::Reader)
+        );
+        assert!(Role::Admin > Role::Writer && Role::Writer > Role::Reader);
+
+        // Only names in a list (or the alert chat's owner) are listed roles
+        assert_eq!(listed_role(&open, None, Some(1)), None);
+        assert_eq!(listed_role(&open, Some(7), Some(7)), Some(Role::Admin));
+        assert_eq!(
+            listed_role(&readers_by_default, None, Some(3)),
+            Some(Role::Reader)
+        );
+        assert_eq!(listed_role(&readers_by_default, None, Some(4)), None);
+    }
+}
@@ -0,0 +1,453 @@
+use anyhow::{Context, Result};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::anthropic::AnthropicProvider;
+use crate::config::AiModelConfig;
+use crate::json_repair;
+use crate::ollama::OllamaProvider;
+use crate::openai_compatible::OpenAiCompatibleProvider;
+use crate::usage::TokenUsage;
+
+/// Parameters for a chat request to the language model.
+#[derive(Clone)]
+pub struct ChatRequest {
+    pub system_prompt: String,
+    pub user_prompt: String,
+    pub temperature: f32,
+    pub top_p: f32,
+    pub format: ResponseFormat,
+}
+
+/// Shape of the answer asked of the model.
+#[derive(Clone)]
+pub enum ResponseFormat {
+    Text,
+    /// Any JSON value.
+    Json,
+    /// JSON following this schema, enforced by the provider where supported.
+    Schema(serde_json::Value),
+}
+
+/// Receives the number of tokens generated so far while an answer streams in.
+pub type TokenUpdates = tokio::sync::mpsc::UnboundedSender<usize>;
+
+/// JSON schema of `T` with every subschema inlined, as Ollama expects.
+pub fn schema_for<T: schemars::JsonSchema>() -> serde_json::Value {
+    let generator = schemars::gen::SchemaSettings::draft07()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator();
+    serde_json::to_value(generator.into_root_schema_for::<T>()).expect("JSON schemas serialize")
+}
+
+/// A language model service the note pipeline and its tools talk to.
+#[async_trait::async_trait]
+pub trait AiProvider: Send + Sync {
+    /// Model answering the requests.
+    fn model(&self) -> &str;
+
+    /// Where the model is served, for error messages ("Ollama at ...").
+    fn describe(&self) -> String;
+
+    /// Whether the service answers, checked before a memo is processed.
+    async fn is_available(&self) -> bool;
+
+    /// Tokens of the requests answered so far, as the service reported them.
+    /// A provider is created per memo, so this is the memo's usage.
+    fn usage(&self) -> TokenUsage {
+        TokenUsage::default()
+    }
+
+    /// Model that answered in place of this one after it failed, if any
+    /// request needed the fallback.
+    fn fallback_model(&self) -> Option<String> {
+        None
+    }
+
+    /// Send a chat request and return the answer's text.
+    async fn chat(&self, request: ChatRequest) -> Result<String>;
+
+    /// Like `chat`, sending the number of tokens generated so far to
+    /// `tokens` as the answer streams in.
+    ///
+    /// Providers that answer in one piece just ignore `tokens`.
+    async fn chat_streaming(&self, request: ChatRequest, tokens: TokenUpdates) -> Result<String> {
+        drop(tokens);
+        self.chat(request).await
+    }
+
+    /// Like `chat`, asking for JSON (unless the request already gives a
+    /// schema) and parsing the answer leniently (see [`json_repair::parse`]).
+    async fn chat_json(&self, mut request: ChatRequest) -> Result<serde_json::Value> {
+        if matches!(request.format, ResponseFormat::Text) {
+            request.format = ResponseFormat::Json;
+        }
+        let response = self.chat(request).await?;
+        json_repair::parse(&response)
+            .with_context(|| format!("{} answered invalid JSON", self.model()))
+    }
+}
+
+/// Stand-in for `provider = "none"`: the pipeline doesn't call a model then,
+/// and anything that tries gets an error.
+struct NoModel;
+
+#[async_trait::async_trait]
+impl AiProvider for NoModel {
+    fn model(&self) -> &str {
+        "none"
+    }
+
+    fn describe(&self) -> String {
+        "no model (ai_model.provider = \"none\")".to_string()
+    }
+
+    async fn is_available(&self) -> bool {
+        false
+    }
+
+    async fn chat(&self, _request: ChatRequest) -> Result<String> {
+        anyhow::bail!("No language model configured (ai_model.provider = \"none\")")
+    }
+}
+
+/// A model request that got no answer within `ai_model.timeout_secs`.
+#[derive(Debug, thiserror::Error)]
+#[error("{service} did not answer within {secs}s")]
+pub struct AiTimeout {
+    pub service: String,
+    pub secs: u64,
+}
+
+/// Provider giving up on requests that take longer than `timeout`. The
+/// request in flight is dropped, which closes its connection.
+struct TimeoutProvider {
+    inner: Arc<dyn AiProvider>,
+    timeout: Duration,
+}
+
+impl TimeoutProvider {
+    async fn within<T>(&self, request: impl Future<Output = Result<T>>) -> Result<T> {
+        tokio::time::timeout(self.timeout, request)
+            .await
+            .map_err(|_| {
+                AiTimeout {
+                    service: self.inner.describe(),
+                    secs: self.timeout.as_secs(),
+                }
+                .into()
+            })
+            .and_then(|answer| answer)
+    }
+}
+
+#[async_trait::async_trait]
+impl AiProvider for TimeoutProvider {
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn describe(&self) -> String {
+        self.inner.describe()
+    }
+
+    async fn is_available(&self) -> bool {
+        self.inner.is_available().await
+    }
+
+    fn usage(&self) -> TokenUsage {
+        self.inner.usage()
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<String> {
+        self.within(self.inner.chat(request)).await
+    }
+
+    async fn chat_streaming(&self, request: ChatRequest, tokens: TokenUpdates) -> Result<String> {
+        self.within(self.inner.chat_streaming(request, tokens))
+            .await
+    }
+}
+
+/// Provider sending requests to `fallback` once `primary` failed one
+/// (errors, timeouts). The switch sticks: a provider is created per memo,
+/// so the rest of the memo doesn't wait on a model that is down.
+struct FallbackProvider {
+    primary: Arc<dyn AiProvider>,
+    fallback: Arc<dyn AiProvider>,
+    failed_over: AtomicBool,
+}
+
+impl FallbackProvider {
+    async fn answer<F, Fut>(&self, request: ChatRequest, send: F) -> Result<String>
+    where
+        F: Fn(Arc<dyn AiProvider>, ChatRequest) -> Fut,
+        Fut: Future<Output = Result<String>>,
+    {
+        if !self.failed_over.load(Ordering::Relaxed) {
+            match send(self.primary.clone(), request.clone()).await {
+                Ok(answer) => return Ok(answer),
+                Err(e) => {
+                    log::warn!(
+                        "{} failed, falling back to {}: {:#}",
+                        self.primary.describe(),
+                        self.fallback.describe(),
+                        e
+                    );
+                    self.failed_over.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+        send(self.fallback.clone(), request).await
+    }
+}
+
+#[async_trait::async_trait]
+impl AiProvider for FallbackProvider {
+    fn model(&self) -> &str {
+        self.primary.model()
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} (fallback: {})",
+            self.primary.describe(),
+            self.fallback.describe()
+        )
+    }
+
+    async fn is_available(&self) -> bool {
+        self.primary.is_available().await || self.fallback.is_available().await
+    }
+
+    fn usage(&self) -> TokenUsage {
+        let mut usage = self.primary.usage();
+        usage.add(&self.fallback.usage());
+        usage
+    }
+
+    fn fallback_model(&self) -> Option<String> {
+        if !self.failed_over.load(Ordering::Relaxed) {
+            return self.primary.fallback_model();
+        }
+        Some(
+            self.fallback
+                .fallback_model()
+                .unwrap_or_else(|| self.fallback.model().to_string()),
+        )
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<String> {
+        self.answer(request, |ai, request| async move { ai.chat(request).await })
+            .await
+    }
+
+    async fn chat_streaming(&self, request: ChatRequest, tokens: TokenUpdates) -> Result<String> {
+        self.answer(request, |ai, request| {
+            let tokens = tokens.clone();
+            async move { ai.chat_streaming(request, tokens).await }
+        })
+        .await
+    }
+}
+
+/// The provider of `config`, with requests cut off after `timeout_secs`
+/// and handed to `fallback` (itself possibly with a fallback) on failure.
+pub fn create_ai_provider(config: &AiModelConfig) -> Result<Arc<dyn AiProvider>> {
+    let mut provider = create_provider(config)?;
+    if config.timeout_secs > 0 && config.enabled() {
+        provider = Arc::new(TimeoutProvider {
+            inner: provider,
+            timeout: Duration::from_secs(config.timeout_secs),
+        });
+    }
+    if let Some(fallback) = config.fallback.as_deref().filter(|_| config.enabled()) {
+        provider = Arc::new(FallbackProvider {
+            primary: provider,
+            fallback: create_ai_provider(fallback)?,
+            failed_over: AtomicBool::new(false),
+        });
+    }
+    Ok(provider)
+}
+
+fn create_provider(config: &AiModelConfig) -> Result<Arc<dyn AiProvider>> {
+    match config.provider.as_str() {
+        "ollama_local" | "ollama_remote" => Ok(Arc::new(
+            OllamaProvider::new(config.endpoint.clone(), config.model.clone())
+                .with_retry(config.retry)
+                .with_options(config.options.clone(), config.keep_alive.clone()),
+        )),
+        "openai_compatible" => {
+            let api_key = match config.api_key_env.as_deref() {
+                Some(api_key_env) => Some(std::env::var(api_key_env).with_context(|| {
+                    format!(
+                        "Environment variable '{}' not set. Required for openai_compatible provider.",
+                        api_key_env
+                    )
+                })?),
+                None => None,
+            };
+            Ok(Arc::new(
+                OpenAiCompatibleProvider::new(&config.endpoint, config.model.clone(), api_key)
+                    .with_retry(config.retry),
+            ))
+        }
+        "anthropic" => {
+            let api_key_env = config
+                .api_key_env
+                .as_deref()
+                .unwrap_or("ANTHROPIC_API_KEY");
+            let api_key = std::env::var(api_key_env).with_context(|| {
+                format!(
+                    "Environment variable '{}' not set. Required for anthropic provider.",
+                    api_key_env
+                )
+            })?;
+            Ok(Arc::new(
+                AnthropicProvider::new(&config.endpoint, config.model.clone(), api_key)
+                    .with_retry(config.retry),
+            ))
+        }
+        "none" => Ok(Arc::new(NoModel)),
+        other => anyhow::bail!(
+            "Unknown AI provider: '{}'. Use 'ollama_local', 'ollama_remote', 'openai_compatible', 'anthropic', or 'none'.",
+            other
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RetryConfig;
+
+    fn ai_config(provider: &str) -> AiModelConfig {
+        AiModelConfig {
+            provider: provider.to_string(),
+            model: "llama3.2:3b".to_string(),
+            endpoint: "http://localhost:11434".to_string(),
+            api_key_env: None,
+            retry: RetryConfig::default(),
+            structured_output: true,
+            cache_ttl_secs: None,
+            options: serde_json::Map::new(),
+            keep_alive: None,
+            timeout_secs: 300,
+            input_cost_per_mtok: None,
+            output_cost_per_mtok: None,
+            fallback: None,
+            pull_model: false,
+        }
+    }
+
+    /// Answers after `delay`.
+    struct SlowModel {
+        delay: Duration,
+    }
+
+    #[async_trait::async_trait]
+    impl AiProvider for SlowModel {
+        fn model(&self) -> &str {
+            "slow"
+        }
+
+        fn describe(&self) -> String {
+            "a slow model".to_string()
+        }
+
+        async fn is_available(&self) -> bool {
+            true
+        }
+
+        async fn chat(&self, _request: ChatRequest) -> Result<String> {
+            tokio::time::sleep(self.delay).await;
+            Ok("{}".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_time_out() {
+        let provider = |delay| TimeoutProvider {
+            inner: Arc::new(SlowModel { delay }),
+            timeout: Duration::from_millis(50),
+        };
+        let request = || ChatRequest {
+            system_prompt: String::new(),
+            user_prompt: String::new(),
+            temperature: 0.7,
+            top_p: 0.9,
+            format: ResponseFormat::Json,
+        };
+
+        let answer = provider(Duration::ZERO).chat_json(request()).await.unwrap();
+        assert_eq!(answer, serde_json::json!({}));
+
+        let error = provider(Duration::from_secs(60))
+            .chat(request())
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<AiTimeout>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_after_a_failure() {
+        let fallback = FallbackProvider {
+            primary: Arc::new(TimeoutProvider {
+                inner: Arc::new(SlowModel {
+                    delay: Duration::from_secs(60),
+                }),
+                timeout: Duration::from_millis(50),
+            }),
+            fallback: Arc::new(SlowModel {
+                delay: Duration::ZERO,
+            }),
+            failed_over: AtomicBool::new(false),
+        };
+        assert_eq!(fallback.fallback_model(), None);
+        let request = ChatRequest {
+            system_prompt: String::new(),
+            user_prompt: String::new(),
+            temperature: 0.7,
+            top_p: 0.9,
+            format: ResponseFormat::Text,
+        };
+        assert_eq!(fallback.chat(request.clone()).await.unwrap(), "{}");
+        assert_eq!(fallback.fallback_model().as_deref(), Some("slow"));
+
+        // Later requests of the memo skip the failed primary
+        let answer = tokio::time::timeout(Duration::from_millis(40), fallback.chat(request));
+        assert_eq!(answer.await.unwrap().unwrap(), "{}");
+
+        let chain = AiModelConfig {
+            fallback: Some(Box::new(ai_config("openai_compatible"))),
+            ..ai_config("ollama_local")
+        };
+        let provider = create_ai_provider(&chain).unwrap();
+        assert_eq!(
+            provider.describe(),
+            "Ollama at http://localhost:11434 (fallback: the chat API at http://localhost:11434)"
+        );
+    }
+
+    #[test]
+    fn test_create_ai_provider() {
+        let provider = create_ai_provider(&ai_config("ollama_remote")).unwrap();
+        assert_eq!(provider.model(), "llama3.2:3b");
+        assert_eq!(provider.describe(), "Ollama at http://localhost:11434");
+        assert_eq!(
+            create_ai_provider(&ai_config("none")).unwrap().model(),
+            "none"
+        );
+        assert!(create_ai_provider(&ai_config("openai_compatible")).is_ok());
+        assert!(create_ai_provider(&ai_config("llamafile")).is_err());
+
+        let anthropic = AiModelConfig {
+            api_key_env: Some("DOT_TEST_UNSET_ANTHROPIC_KEY".to_string()),
+            ..ai_config("anthropic")
+        };
+        assert!(create_ai_provider(&anthropic).is_err());
+    }
+}
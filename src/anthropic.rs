@@ -0,0 +1,187 @@
+use anyhow::{Context, Result};
+
+use crate::ai_provider::{AiProvider, ChatRequest, ResponseFormat};
+use crate::config::RetryConfig;
+use crate::retry;
+use crate::usage::{TokenUsage, UsageMeter};
+
+const API_VERSION: &str = "2023-06-01";
+/// Longest answer asked for; enough for several notes from a long memo.
+const MAX_TOKENS: u32 = 8192;
+/// Tool the model is made to call to answer in JSON.
+const RESPONSE_TOOL: &str = "respond";
+
+/// Claude models through Anthropic's Messages API (`anthropic`).
+pub struct AnthropicProvider {
+    /// API root, `https://api.anthropic.com` unless proxied.
+    base_url: String,
+    model: String,
+    api_key: String,
+    client: reqwest::Client,
+    retry: RetryConfig,
+    usage: UsageMeter,
+}
+
+impl AnthropicProvider {
+    pub fn new(base_url: &str, model: String, api_key: String) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+            api_key,
+            client: reqwest::Client::new(),
+            retry: RetryConfig::default(),
+            usage: UsageMeter::default(),
+        }
+    }
+
+    /// Retry failed requests as configured in `retry`.
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        request
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+    }
+
+    /// Request body for `request`. JSON answers are requested as the input of
+    /// a tool the model has to call, which the API checks against the schema.
+    fn body(&self, request: ChatRequest) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "model": self.model,
+            "max_tokens": MAX_TOKENS,
+            "system": request.system_prompt,
+            "messages": [
+                { "role": "user", "content": request.user_prompt }
+            ],
+            // Claude takes temperature or top_p, not both
+            "temperature": request.temperature
+        });
+
+        let schema = match request.format {
+            ResponseFormat::Text => return body,
+            ResponseFormat::Json => serde_json::json!({ "type": "object" }),
+            ResponseFormat::Schema(schema) => schema,
+        };
+        body["tools"] = serde_json::json!([{
+            "name": RESPONSE_TOOL,
+            "description": "Answer with the JSON requested by the instructions.",
+            "input_schema": schema
+        }]);
+        body["tool_choice"] = serde_json::json!({ "type": "tool", "name": RESPONSE_TOOL });
+        body
+    }
+
+    /// The answer in a Messages API response: the tool input as JSON text,
+    /// or the text blocks joined.
+    fn content(response: &serde_json::Value) -> Result<String> {
+        let blocks = response["content"]
+            .as_array()
+            .context("No content in Anthropic response")?;
+        if let Some(tool_use) = blocks.iter().find(|block| block["type"] == "tool_use") {
+            return Ok(tool_use["input"].to_string());
+        }
+        let text: String = blocks
+            .iter()
+            .filter_map(|block| block["text"].as_str())
+            .collect();
+        anyhow::ensure!(!text.is_empty(), "No text in Anthropic response");
+        Ok(text)
+    }
+}
+
+#[async_trait::async_trait]
+impl AiProvider for AnthropicProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn describe(&self) -> String {
+        format!("the Anthropic API at {}", self.base_url)
+    }
+
+    /// Whether the API answers (and accepts the key), checked with its model
+    /// listing.
+    async fn is_available(&self) -> bool {
+        self.authorized(self.client.get(format!("{}/v1/models", self.base_url)))
+            .timeout(std::time::Duration::from_secs(5))
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success())
+    }
+
+    fn usage(&self) -> TokenUsage {
+        self.usage.total()
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<String> {
+        let body = self.body(request);
+        let url = format!("{}/v1/messages", self.base_url);
+        let response = retry::send_with_backoff(&self.retry, "Anthropic", || {
+            self.authorized(self.client.post(&url)).json(&body)
+        })
+        .await
+        .context("Failed to send request to Anthropic")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Anthropic API error ({}): {}", status, error_text);
+        }
+
+        let response_json: serde_json::Value = response
+            .json()
+            .await
+            .context("Failed to parse Anthropic response")?;
+        self.usage
+            .record_fields(&response_json["usage"], "input_tokens", "output_tokens");
+        Self::content(&response_json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_answers_go_through_a_forced_tool() {
+        let provider = AnthropicProvider::new(
+            "https://api.anthropic.com/",
+            "claude-sonnet-4-5".to_string(),
+            "key".to_string(),
+        );
+        let request = |format| ChatRequest {
+            system_prompt: "Sistema".to_string(),
+            user_prompt: "Utente".to_string(),
+            temperature: 0.7,
+            top_p: 0.9,
+            format,
+        };
+
+        let body = provider.body(request(ResponseFormat::Text));
+        assert_eq!(body["system"], "Sistema");
+        assert!(body.get("tools").is_none());
+
+        let schema = serde_json::json!({ "type": "object", "required": ["notes"] });
+        let body = provider.body(request(ResponseFormat::Schema(schema.clone())));
+        assert_eq!(body["tools"][0]["input_schema"], schema);
+        assert_eq!(body["tool_choice"]["name"], RESPONSE_TOOL);
+
+        let response = serde_json::json!({
+            "content": [{ "type": "tool_use", "name": "respond", "input": { "notes": [] } }]
+        });
+        assert_eq!(
+            AnthropicProvider::content(&response).unwrap(),
+            r#"{"notes":[]}"#
+        );
+        let response = serde_json::json!({
+            "content": [{ "type": "text", "text": "Testo corretto." }]
+        });
+        assert_eq!(
+            AnthropicProvider::content(&response).unwrap(),
+            "Testo corretto."
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! Replacing files so that readers (sync clients, Obsidian, the bot after a
+//! crash) see either the old content or the new, never half of it.
+
+use std::io::Write;
+use std::path::Path;
+
+/// Write `contents` to a temporary file next to `path` and rename it into
+/// place, so an interrupted write never leaves a truncated file behind. The
+/// temporary name is unique, so concurrent writers don't clobber each other's
+/// half-written file. With `fsync`, the data and the rename reach the disk
+/// before this returns.
+pub fn write_atomic(path: &Path, contents: &str, fsync: bool) -> std::io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    // Hidden, so Obsidian doesn't index it in the meantime
+    let tmp = dir.join(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4()));
+
+    let write = || -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(contents.as_bytes())?;
+        if fsync {
+            file.sync_all()?;
+        }
+        std::fs::rename(&tmp, path)
+    };
+    if let Err(e) = write() {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    #[cfg(unix)]
+    if fsync {
+        // The rename itself is only durable once the directory is synced
+        std::fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_replaces_the_file() {
+        let dir = std::env::temp_dir().join(format!("dot-atomic-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Nota.md");
+
+        write_atomic(&path, "prima", false).unwrap();
+        write_atomic(&path, "seconda", true).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "seconda");
+        // No temporary file is left next to the note
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
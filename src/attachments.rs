@@ -0,0 +1,174 @@
+use anyhow::{Context, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// Kind of binary asset stored next to the notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentKind {
+    Audio,
+    Document,
+}
+
+impl AttachmentKind {
+    fn subdir(&self) -> &'static str {
+        match self {
+            AttachmentKind::Audio => "audio",
+            AttachmentKind::Document => "documents",
+        }
+    }
+}
+
+/// Stores binary assets (audio, images, PDFs) under the configured
+/// attachments directory, one subfolder per kind.
+pub struct AttachmentStore {
+    dir: PathBuf,
+}
+
+impl AttachmentStore {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Copy `source` into the store under `file_name`, returning the stored path.
+    ///
+    /// An existing file with the same name is never overwritten: a numeric
+    /// suffix is added instead (`memo.ogg`, `memo-1.ogg`, ...).
+    pub fn store_file(
+        &self,
+        kind: AttachmentKind,
+        source: &Path,
+        file_name: &str,
+    ) -> Result<PathBuf> {
+        let target = self.target_path(kind, file_name)?;
+        std::fs::copy(source, &target)
+            .with_context(|| format!("Failed to store attachment: {}", target.display()))?;
+
+        log::info!("Attachments: stored {}", target.display());
+        Ok(target)
+    }
+
+    /// Like `store_file`, for content held in memory.
+    pub fn store_bytes(
+        &self,
+        kind: AttachmentKind,
+        bytes: &[u8],
+        file_name: &str,
+    ) -> Result<PathBuf> {
+        let target = self.target_path(kind, file_name)?;
+        std::fs::write(&target, bytes)
+            .with_context(|| format!("Failed to store attachment: {}", target.display()))?;
+
+        log::info!("Attachments: stored {}", target.display());
+        Ok(target)
+    }
+
+    /// Free path for `file_name` in the kind's subfolder, creating the folder.
+    fn target_path(&self, kind: AttachmentKind, file_name: &str) -> Result<PathBuf> {
+        let target_dir = self.dir.join(kind.subdir());
+        std::fs::create_dir_all(&target_dir).with_context(|| {
+            format!(
+                "Failed to create attachments directory: {}",
+                target_dir.display()
+            )
+        })?;
+        Ok(unique_path(&target_dir, file_name))
+    }
+}
+
+/// Return `dir/file_name`, adding `-1`, `-2`, ... before the extension if taken.
+fn unique_path(dir: &Path, file_name: &str) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let path = Path::new(file_name);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path.extension().map(|e| e.to_string_lossy().to_string());
+
+    (1..)
+        .map(|n| match &ext {
+            Some(ext) => dir.join(format!("{}-{}.{}", stem, n, ext)),
+            None => dir.join(format!("{}-{}", stem, n)),
+        })
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+/// Compute the path of `attachment` relative to the folder containing `note_path`,
+/// using forward slashes so the link works on every platform Obsidian runs on.
+pub fn relative_embed_path(note_path: &Path, attachment: &Path) -> String {
+    let note_dir = note_path.parent().unwrap_or_else(|| Path::new(""));
+    let from = normalize(note_dir);
+    let to = normalize(attachment);
+
+    let common = from
+        .iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts: Vec<String> = Vec::new();
+    parts.extend(std::iter::repeat_n("..".to_string(), from.len() - common));
+    parts.extend(to[common..].iter().cloned());
+    parts.join("/")
+}
+
+/// Markdown embed for an attachment, relative to the note that embeds it.
+pub fn embed_markdown(note_path: &Path, attachment: &Path) -> String {
+    let name = attachment.file_name().unwrap_or_default().to_string_lossy();
+    format!(
+        "![{}](<{}>)",
+        name,
+        relative_embed_path(note_path, attachment)
+    )
+}
+
+/// Split a path into normal components, resolving `.` and `..` lexically.
+fn normalize(path: &Path) -> Vec<String> {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(c) => parts.push(c.to_string_lossy().to_string()),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            Component::RootDir | Component::Prefix(_) => parts.clear(),
+            Component::CurDir => {}
+        }
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_embed_path_sibling_folder() {
+        let rel = relative_embed_path(
+            Path::new("./output/notes/My Note.md"),
+            Path::new("./output/notes/attachments/audio/memo.ogg"),
+        );
+        assert_eq!(rel, "attachments/audio/memo.ogg");
+    }
+
+    #[test]
+    fn test_relative_embed_path_from_subfolder() {
+        let rel = relative_embed_path(
+            Path::new("vault/Work/Meeting.md"),
+            Path::new("vault/attachments/images/board.png"),
+        );
+        assert_eq!(rel, "../attachments/images/board.png");
+    }
+
+    #[test]
+    fn test_embed_markdown_wraps_path_with_spaces() {
+        let md = embed_markdown(
+            Path::new("vault/Note.md"),
+            Path::new("vault/attachments/audio/voice memo.ogg"),
+        );
+        assert_eq!(md, "![voice memo.ogg](<attachments/audio/voice memo.ogg>)");
+    }
+}
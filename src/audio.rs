@@ -0,0 +1,776 @@
+use anyhow::{Context, Result};
+use rubato::{
+    FastFixedIn, PolynomialDegree, SincFixedIn, SincInterpolationParameters, SincInterpolationType,
+    VecResampler, WindowFunction,
+};
+use std::io::Write;
+
+use crate::config::ResampleQuality;
+use crate::transcription::AudioInput;
+
+/// Sample rate Whisper expects.
+pub const WHISPER_SAMPLE_RATE: usize = 16000;
+
+/// Turns downloaded audio into the 16 kHz samples Whisper expects.
+pub trait AudioConverter: Send + Sync {
+    /// Mono mixdown of the whole recording.
+    fn convert(&self, input: &AudioInput) -> Result<Vec<f32>>;
+
+    /// One signal per channel, for recordings with a speaker on each channel.
+    fn convert_channels(&self, input: &AudioInput) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Decodes in-process with Symphonia (and libopus, with the `opus` feature),
+/// handing formats it has no decoder for to [`FfmpegConverter`].
+#[derive(Debug, Clone, Default)]
+pub struct NativeConverter {
+    /// Suppress background noise before resampling.
+    pub denoise: bool,
+    /// Boost quiet recordings to a common speech level.
+    pub normalize: bool,
+    /// Resampler used for audio not decoded at 16 kHz.
+    pub resample_quality: ResampleQuality,
+}
+
+/// Decodes with the ffmpeg binary, reading from and writing to pipes so no
+/// intermediate file is created.
+pub struct FfmpegConverter;
+
+impl FfmpegConverter {
+    /// Run ffmpeg, returning `channels` interleaved channels at 16 kHz.
+    fn run(&self, input: &AudioInput, channels: usize) -> Result<Vec<f32>> {
+        use std::process::{Command, Stdio};
+
+        log::info!("Converting audio with ffmpeg...");
+
+        let mut command = Command::new("ffmpeg");
+        command.arg("-i");
+        match input {
+            AudioInput::File(path) => command.arg(path).stdin(Stdio::null()),
+            AudioInput::Memory { .. } => command.arg("pipe:0").stdin(Stdio::piped()),
+        };
+        let mut child = command
+            .arg("-ar")
+            .arg(WHISPER_SAMPLE_RATE.to_string())
+            .arg("-ac")
+            .arg(channels.to_string())
+            .arg("-f")
+            .arg("s16le") // raw 16-bit PCM on stdout
+            .arg("pipe:1")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context(if cfg!(feature = "opus") {
+                "Failed to run ffmpeg. Is ffmpeg installed?"
+            } else {
+                "Failed to run ffmpeg. Is ffmpeg installed? Voice notes can also be \
+                 decoded without it by building with --features opus"
+            })?;
+
+        // Feed in-memory audio from another thread so a full stdout pipe can't deadlock
+        let feeder = match (input, child.stdin.take()) {
+            (AudioInput::Memory { bytes, .. }, Some(mut stdin)) => {
+                let bytes = bytes.clone();
+                Some(std::thread::spawn(move || stdin.write_all(&bytes)))
+            }
+            _ => None,
+        };
+
+        let output = child.wait_with_output().context("Failed to run ffmpeg")?;
+        if let Some(feeder) = feeder {
+            // A write error only means ffmpeg stopped reading; its status says why
+            let _ = feeder.join();
+        }
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("ffmpeg conversion failed: {}", stderr);
+        }
+
+        log::info!("Audio converted successfully with ffmpeg");
+        Ok(output
+            .stdout
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / 32768.0)
+            .collect())
+    }
+}
+
+impl AudioConverter for FfmpegConverter {
+    fn convert(&self, input: &AudioInput) -> Result<Vec<f32>> {
+        self.run(input, 1)
+    }
+
+    /// Stereo at most; ffmpeg folds surround layouts into two channels.
+    fn convert_channels(&self, input: &AudioInput) -> Result<Vec<Vec<f32>>> {
+        let mut channels = deinterleave(&self.run(input, 2)?, 2);
+        // Mono sources come back upmixed into two identical channels
+        if channels[0] == channels[1] {
+            channels.truncate(1);
+        }
+        Ok(channels)
+    }
+}
+
+impl AudioConverter for NativeConverter {
+    fn convert(&self, input: &AudioInput) -> Result<Vec<f32>> {
+        let samples = match self.decode(input)? {
+            Some(channels) => mix_down(channels),
+            None => {
+                let samples = FfmpegConverter.convert(input)?;
+                self.clean(samples, WHISPER_SAMPLE_RATE as u32)
+            }
+        };
+        Ok(self.level(samples))
+    }
+
+    fn convert_channels(&self, input: &AudioInput) -> Result<Vec<Vec<f32>>> {
+        let channels = match self.decode(input)? {
+            Some(channels) => channels,
+            None => FfmpegConverter
+                .convert_channels(input)?
+                .into_iter()
+                .map(|channel| self.clean(channel, WHISPER_SAMPLE_RATE as u32))
+                .collect(),
+        };
+        Ok(channels.into_iter().map(|c| self.level(c)).collect())
+    }
+}
+
+impl NativeConverter {
+    /// Apply the optional preprocessing to one channel at its decoded rate.
+    fn clean(&self, samples: Vec<f32>, sample_rate: u32) -> Vec<f32> {
+        if self.denoise {
+            crate::denoise::denoise(&samples, sample_rate)
+        } else {
+            samples
+        }
+    }
+
+    /// Apply gain normalization, if enabled, to a finished 16 kHz signal.
+    fn level(&self, mut samples: Vec<f32>) -> Vec<f32> {
+        if self.normalize {
+            normalize_gain(&mut samples);
+        }
+        samples
+    }
+
+    /// Decode into one 16 kHz signal per channel, or `None` when there is no
+    /// native decoder for the codec and ffmpeg has to take over.
+    fn decode(&self, input: &AudioInput) -> Result<Option<Vec<Vec<f32>>>> {
+        use symphonia::core::audio::SampleBuffer;
+        use symphonia::core::codecs::DecoderOptions;
+        use symphonia::core::formats::FormatOptions;
+        use symphonia::core::io::{MediaSource, MediaSourceStream};
+        use symphonia::core::meta::MetadataOptions;
+        use symphonia::core::probe::Hint;
+
+        // Open the media source, checking it has content
+        let source: Box<dyn MediaSource> = match input {
+            AudioInput::File(path) => {
+                log::info!("Decoding audio file: {}", path.display());
+                let metadata =
+                    std::fs::metadata(path).context("Failed to read audio file metadata")?;
+                log::info!("Audio file size: {} bytes", metadata.len());
+                if metadata.len() == 0 {
+                    anyhow::bail!("Audio file is empty");
+                }
+                Box::new(std::fs::File::open(path).context("Failed to open audio file")?)
+            }
+            AudioInput::Memory { bytes, .. } => {
+                log::info!("Decoding in-memory audio: {} bytes", bytes.len());
+                if bytes.is_empty() {
+                    anyhow::bail!("Audio file is empty");
+                }
+                Box::new(std::io::Cursor::new(bytes.clone()))
+            }
+        };
+        let mss = MediaSourceStream::new(source, Default::default());
+
+        // Create a probe hint
+        let mut hint = Hint::new();
+        if let Some(extension) = input.extension() {
+            log::info!("Audio file extension: {}", extension);
+            hint.with_extension(&extension);
+        } else {
+            log::warn!("Audio file has no extension, probing without hint");
+        }
+
+        // Probe the media source
+        log::info!("Probing audio format...");
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                mss,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .context("Failed to probe audio file. The audio format may not be supported.")?;
+
+        log::info!("Audio format detected successfully");
+
+        let mut format = probed.format;
+
+        // Find the first audio track and extract codec params
+        log::info!("Finding audio track...");
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
+            .context("No audio track found in file")?;
+
+        let track_id = track.id;
+        let codec_params = track.codec_params.clone();
+        log::info!(
+            "Audio track found with codec type: {:?}",
+            codec_params.codec
+        );
+
+        // Get sample rate and channels before consuming format. MP4 only
+        // declares the channels of PCM tracks: for AAC the decoder tells
+        let sample_rate = codec_params.sample_rate.context("Sample rate not found")?;
+        let mut channels = codec_params.channels.map(|channels| channels.count());
+
+        // Symphonia demuxes OGG/Opus but has no Opus decoder: use libopus if built in
+        #[cfg(feature = "opus")]
+        if codec_params.codec == symphonia::core::codecs::CODEC_TYPE_OPUS {
+            let channels = channels.context("Channel info not found")?;
+            log::info!("Decoding Opus with libopus ({} channels)", channels);
+            let pre_skip = codec_params.delay.unwrap_or(0);
+            match crate::opus::decode_track(format.as_mut(), track_id, channels, pre_skip) {
+                Ok(decoded) if !decoded[0].is_empty() => {
+                    log::info!(
+                        "Audio decoded: {} samples at {} Hz",
+                        decoded[0].len(),
+                        crate::opus::SAMPLE_RATE
+                    );
+                    return Ok(Some(
+                        decoded
+                            .into_iter()
+                            .map(|channel| clamp(self.clean(channel, crate::opus::SAMPLE_RATE)))
+                            .collect(),
+                    ));
+                }
+                Ok(_) => anyhow::bail!("No audio samples decoded"),
+                Err(e) => {
+                    log::warn!("libopus decoding failed ({}). Falling back to ffmpeg...", e);
+                    return Ok(None);
+                }
+            }
+        }
+
+        // Create a decoder for the track
+        log::info!("Creating decoder for codec...");
+        let decoder_result =
+            symphonia::default::get_codecs().make(&codec_params, &DecoderOptions::default());
+
+        // If Symphonia can't decode (e.g., Opus codec), fall back to ffmpeg
+        if decoder_result.is_err() {
+            log::warn!("Symphonia can't decode this format. Falling back to ffmpeg...");
+            return Ok(None);
+        }
+
+        let mut decoder = decoder_result.context("Failed to create decoder")?;
+        log::info!("Decoder created successfully");
+
+        // Decode and collect samples
+        let mut samples = Vec::new();
+        let mut sample_buf = None;
+
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                Err(symphonia::core::errors::Error::IoError(e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    break
+                }
+                Err(e) => return Err(e).context("Failed to read packet")?,
+            };
+
+            if packet.track_id() != track_id {
+                continue;
+            }
+
+            let decoded = decoder.decode(&packet).context("Failed to decode packet")?;
+
+            if sample_buf.is_none() {
+                let spec = *decoded.spec();
+                channels = Some(spec.channels.count());
+                let duration = decoded.capacity() as u64;
+                sample_buf = Some(SampleBuffer::<f32>::new(duration, spec));
+            }
+
+            if let Some(ref mut buf) = sample_buf {
+                buf.copy_interleaved_ref(decoded);
+                samples.extend_from_slice(buf.samples());
+            }
+        }
+
+        if samples.is_empty() {
+            anyhow::bail!("No audio samples decoded");
+        }
+        let channels = channels.context("Channel info not found")?;
+
+        log::info!(
+            "Original audio: {} Hz, {} channels, {} samples",
+            sample_rate,
+            channels,
+            samples.len()
+        );
+
+        // Resample each channel to 16kHz if needed
+        let target_sample_rate = WHISPER_SAMPLE_RATE as u32;
+        let decoded: Vec<Vec<f32>> = deinterleave(&samples, channels)
+            .into_iter()
+            .map(|channel| self.clean(channel, sample_rate))
+            .map(|channel| {
+                resample_audio(
+                    &channel,
+                    sample_rate,
+                    target_sample_rate,
+                    self.resample_quality,
+                )
+                .map(clamp)
+            })
+            .collect::<Result<_>>()?;
+
+        log::info!(
+            "Audio decoded: {} samples at {} Hz",
+            decoded[0].len(),
+            target_sample_rate
+        );
+        Ok(Some(decoded))
+    }
+}
+
+/// Split interleaved samples into one signal per channel.
+fn deinterleave(samples: &[f32], channels: usize) -> Vec<Vec<f32>> {
+    (0..channels.max(1))
+        .map(|c| {
+            samples
+                .iter()
+                .skip(c)
+                .step_by(channels.max(1))
+                .copied()
+                .collect()
+        })
+        .collect()
+}
+
+/// Average the channels into one signal.
+fn mix_down(mut channels: Vec<Vec<f32>>) -> Vec<f32> {
+    if channels.len() == 1 {
+        return channels.pop().unwrap_or_default();
+    }
+    let len = channels.iter().map(Vec::len).min().unwrap_or(0);
+    (0..len)
+        .map(|i| channels.iter().map(|c| c[i]).sum::<f32>() / channels.len() as f32)
+        .collect()
+}
+
+fn clamp(samples: Vec<f32>) -> Vec<f32> {
+    samples.into_iter().map(|s| s.clamp(-1.0, 1.0)).collect()
+}
+
+/// Speech level quiet recordings are raised to (about -20 dBFS).
+const TARGET_RMS: f32 = 0.1;
+/// Largest boost applied (+26 dB), so near-silent noise isn't blown up.
+const MAX_GAIN: f32 = 20.0;
+/// Samples above this level are soft-limited instead of clipped.
+const LIMITER_THRESHOLD: f32 = 0.9;
+
+/// Raise quiet recordings (phone far from the mouth) to a common speech
+/// level. Loud recordings are left alone.
+///
+/// The level is the RMS of the loudest 20 ms frames, so long pauses don't
+/// inflate the gain; peaks pushed over [`LIMITER_THRESHOLD`] are bent
+/// smoothly towards full scale rather than clipped.
+fn normalize_gain(samples: &mut [f32]) {
+    let frame = WHISPER_SAMPLE_RATE / 50;
+    let mut levels: Vec<f32> = samples
+        .chunks(frame)
+        .map(|chunk| (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt())
+        .collect();
+    if levels.is_empty() {
+        return;
+    }
+    levels.sort_by(f32::total_cmp);
+    let speech_level = levels[levels.len() * 9 / 10];
+    if speech_level <= 0.0 {
+        return;
+    }
+
+    let gain = (TARGET_RMS / speech_level).min(MAX_GAIN);
+    if gain <= 1.0 {
+        return;
+    }
+    log::info!("Boosting quiet audio by {:.1} dB", 20.0 * gain.log10());
+
+    let headroom = 1.0 - LIMITER_THRESHOLD;
+    for sample in samples.iter_mut() {
+        let boosted = *sample * gain;
+        *sample = if boosted.abs() > LIMITER_THRESHOLD {
+            let over = (boosted.abs() - LIMITER_THRESHOLD) / headroom;
+            boosted.signum() * (LIMITER_THRESHOLD + headroom * over.tanh())
+        } else {
+            boosted
+        };
+    }
+}
+
+/// Input frames fed to the resampler per call.
+const RESAMPLE_CHUNK: usize = 1024;
+/// Edge samples repeated before and after the signal, so the filter's
+/// start-up and tail fall outside the kept output.
+const RESAMPLE_PAD: usize = 512;
+
+/// Band-limited resampling: content above the new Nyquist frequency is
+/// filtered out instead of folding back as aliasing (48 kHz Telegram OGGs
+/// would otherwise reach Whisper with hiss in the speech band).
+///
+/// The output has `samples.len() * to_rate / from_rate` samples, aligned
+/// with the input (the filter delay is compensated).
+pub(crate) fn resample_audio(
+    samples: &[f32],
+    from_rate: u32,
+    to_rate: u32,
+    quality: ResampleQuality,
+) -> Result<Vec<f32>> {
+    if from_rate == to_rate || samples.is_empty() {
+        return Ok(samples.to_vec());
+    }
+
+    let ratio = to_rate as f64 / from_rate as f64;
+    let output_len = (samples.len() as f64 * ratio) as usize;
+    let mut resampler: Box<dyn VecResampler<f32>> = match quality {
+        ResampleQuality::High => Box::new(SincFixedIn::<f32>::new(
+            ratio,
+            1.0,
+            SincInterpolationParameters {
+                sinc_len: 256,
+                f_cutoff: 0.95,
+                oversampling_factor: 128,
+                interpolation: SincInterpolationType::Cubic,
+                window: WindowFunction::BlackmanHarris2,
+            },
+            RESAMPLE_CHUNK,
+            1,
+        )?),
+        ResampleQuality::Fast => Box::new(FastFixedIn::<f32>::new(
+            ratio,
+            1.0,
+            PolynomialDegree::Cubic,
+            RESAMPLE_CHUNK,
+            1,
+        )?),
+    };
+
+    let first = samples[0];
+    let last = samples[samples.len() - 1];
+    let padded: Vec<f32> = std::iter::repeat_n(first, RESAMPLE_PAD)
+        .chain(samples.iter().copied())
+        .chain(std::iter::repeat_n(last, RESAMPLE_PAD))
+        .collect();
+
+    let skip = resampler.output_delay() + (RESAMPLE_PAD as f64 * ratio).round() as usize;
+    let mut output = Vec::with_capacity(skip + output_len + RESAMPLE_CHUNK);
+    let mut rest = padded.as_slice();
+    while rest.len() >= resampler.input_frames_next() {
+        let (chunk, tail) = rest.split_at(resampler.input_frames_next());
+        output.extend_from_slice(&resampler.process(&[chunk.to_vec()], None)?[0]);
+        rest = tail;
+    }
+    // rubato rejects an empty partial chunk
+    if !rest.is_empty() {
+        output.extend_from_slice(&resampler.process_partial(Some(&[rest.to_vec()]), None)?[0]);
+    }
+    while output.len() < skip + output_len {
+        let flushed = resampler.process_partial(None, None)?;
+        if flushed[0].is_empty() {
+            break;
+        }
+        output.extend_from_slice(&flushed[0]);
+    }
+
+    let mut output: Vec<f32> = output.into_iter().skip(skip).take(output_len).collect();
+    output.resize(output_len, last);
+    log::info!("Resampled from {} Hz to {} Hz", from_rate, to_rate);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Expected shape of a fixture once converted for Whisper.
+    struct Golden {
+        file: &'static str,
+        samples: usize,
+        /// Encoder delay and padding of lossy codecs, which ffmpeg trims and
+        /// the native decoders keep.
+        untrimmed: usize,
+        peak: f32,
+        /// RMS level; a half-scale sine is at 0.354.
+        rms: f32,
+        /// Dominant frequency of the test tone; `None` for silence.
+        frequency: Option<f32>,
+    }
+
+    /// Fixtures in `tests/fixtures/audio`: half-second 440 Hz tones at half
+    /// scale, and silent MPEG audio frames.
+    const GOLDEN: &[Golden] = &[
+        Golden {
+            file: "tone-44100-stereo.ogg",
+            samples: 8000,
+            untrimmed: 0,
+            peak: 0.5,
+            rms: 0.354,
+            frequency: Some(440.0),
+        },
+        Golden {
+            file: "tone-48000-mono.m4a",
+            samples: 8000,
+            untrimmed: 0,
+            peak: 0.5,
+            rms: 0.354,
+            frequency: Some(440.0),
+        },
+        Golden {
+            file: "silence-48000-mono.mp3",
+            samples: 9600,
+            untrimmed: 0,
+            peak: 0.0,
+            rms: 0.0,
+            frequency: None,
+        },
+        Golden {
+            file: "tone-48000-mono.opus",
+            samples: 8000,
+            untrimmed: 216,
+            peak: 0.54,
+            rms: 0.354,
+            frequency: Some(440.0),
+        },
+        Golden {
+            file: "tone-44100-mono-aac.m4a",
+            samples: 8000,
+            untrimmed: 1287,
+            peak: 0.51,
+            rms: 0.354,
+            frequency: Some(440.0),
+        },
+        Golden {
+            file: "tone-44100-mono.mp3",
+            samples: 8000,
+            untrimmed: 777,
+            peak: 0.5,
+            rms: 0.354,
+            frequency: Some(440.0),
+        },
+    ];
+
+    fn fixture(file: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/audio")
+            .join(file)
+    }
+
+    /// Frequency estimated from upward zero crossings.
+    fn frequency(samples: &[f32]) -> f32 {
+        let crossings = samples
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count();
+        crossings as f32 * WHISPER_SAMPLE_RATE as f32 / samples.len() as f32
+    }
+
+    /// Check converted samples against a golden, expecting `length` samples
+    /// give or take `length_slack` for resamplers that pad or trim the edges.
+    fn assert_matches(golden: &Golden, samples: &[f32], length: usize, length_slack: usize) {
+        assert!(
+            samples.len().abs_diff(length) <= length_slack,
+            "{}: {} samples, expected {}",
+            golden.file,
+            samples.len(),
+            length
+        );
+        let peak = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        assert!(
+            (peak - golden.peak).abs() < 0.02,
+            "{}: peak {}, expected {}",
+            golden.file,
+            peak,
+            golden.peak
+        );
+        // Level and tone of the middle half, away from where lossy codecs
+        // fade in and out
+        let middle = &samples[samples.len() / 4..samples.len() * 3 / 4];
+        let rms = (middle.iter().map(|s| s * s).sum::<f32>() / middle.len() as f32).sqrt();
+        assert!(
+            (rms - golden.rms).abs() < 0.03,
+            "{}: RMS {}, expected {}",
+            golden.file,
+            rms,
+            golden.rms
+        );
+        if let Some(expected) = golden.frequency {
+            let found = frequency(middle);
+            assert!(
+                (found - expected).abs() < 5.0,
+                "{}: {} Hz, expected {} Hz",
+                golden.file,
+                found,
+                expected
+            );
+        }
+    }
+
+    /// 16-bit PCM WAV with the given sample rate and channels.
+    fn wav_bytes(sample_rate: u32, channels: u16, samples: &[i16]) -> Vec<u8> {
+        let data_len = (samples.len() * 2) as u32;
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&channels.to_le_bytes());
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * channels as u32 * 2).to_le_bytes());
+        wav.extend_from_slice(&(channels * 2).to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        wav
+    }
+
+    #[test]
+    fn test_native_converter_matches_golden_fixtures() {
+        for golden in GOLDEN {
+            // Without libopus, Opus is handed over to ffmpeg
+            if golden.file.ends_with(".opus") && !cfg!(feature = "opus") {
+                continue;
+            }
+            let from_file = NativeConverter::default()
+                .convert(&AudioInput::File(fixture(golden.file)))
+                .unwrap();
+            assert_matches(golden, &from_file, golden.samples + golden.untrimmed, 0);
+
+            let in_memory = AudioInput::Memory {
+                bytes: std::fs::read(fixture(golden.file)).unwrap().into(),
+                file_name: golden.file.to_string(),
+            };
+            assert_eq!(
+                NativeConverter::default().convert(&in_memory).unwrap(),
+                from_file
+            );
+        }
+    }
+
+    #[test]
+    fn test_native_converter_in_memory() {
+        // 0.1 s of stereo 32 kHz audio → 1600 mono samples at 16 kHz
+        let audio = AudioInput::Memory {
+            bytes: wav_bytes(32000, 2, &[8192; 6400]).into(),
+            file_name: "memo.wav".to_string(),
+        };
+        let samples = NativeConverter::default().convert(&audio).unwrap();
+        assert_eq!(samples.len(), 1600);
+        assert!(samples.iter().all(|s| (s - 0.25).abs() < 1e-3));
+
+        // Left and right kept apart for per-channel transcription
+        let call = AudioInput::Memory {
+            bytes: wav_bytes(16000, 2, &[16384, -8192].repeat(800)).into(),
+            file_name: "call.wav".to_string(),
+        };
+        let channels = NativeConverter::default().convert_channels(&call).unwrap();
+        assert_eq!(channels.len(), 2);
+        assert!(channels[0].iter().all(|s| (s - 0.5).abs() < 1e-3));
+        assert!(channels[1].iter().all(|s| (s + 0.25).abs() < 1e-3));
+
+        let empty = AudioInput::Memory {
+            bytes: Vec::new().into(),
+            file_name: "memo.ogg".to_string(),
+        };
+        assert!(NativeConverter::default().convert(&empty).is_err());
+        assert_eq!(empty.extension().as_deref(), Some("ogg"));
+    }
+
+    #[test]
+    fn test_normalize_gain_boosts_quiet_audio_without_clipping() {
+        // A quiet tone with a pause and a loud click
+        let mut samples: Vec<f32> = (0..16000)
+            .map(|i| {
+                if i < 4000 {
+                    0.0
+                } else {
+                    (i as f32 * 0.05).sin() * 0.01
+                }
+            })
+            .collect();
+        samples[8000] = 0.2;
+        normalize_gain(&mut samples);
+
+        let rms = (samples[4000..].iter().map(|s| s * s).sum::<f32>() / 12000.0).sqrt();
+        assert!((rms - TARGET_RMS).abs() < 0.01, "rms {}", rms);
+        assert!(samples.iter().all(|s| s.abs() <= 1.0));
+        assert!(samples[8000] > LIMITER_THRESHOLD);
+        assert_eq!(samples[0], 0.0);
+
+        // Already loud enough: untouched
+        let mut loud = vec![0.5, -0.5, 0.25];
+        normalize_gain(&mut loud);
+        assert_eq!(loud, vec![0.5, -0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_resample_filters_content_above_nyquist() {
+        let tone = |hz: f32| -> Vec<f32> {
+            (0..48000)
+                .map(|i| (i as f32 * hz * std::f32::consts::TAU / 48000.0).sin() * 0.5)
+                .collect()
+        };
+        let peak = |samples: &[f32]| samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+
+        for quality in [ResampleQuality::High, ResampleQuality::Fast] {
+            let speech = resample_audio(&tone(440.0), 48000, 16000, quality).unwrap();
+            assert_eq!(speech.len(), 16000);
+            assert!((peak(&speech) - 0.5).abs() < 0.02, "{:?}", quality);
+            assert!((frequency(&speech) - 440.0).abs() < 5.0, "{:?}", quality);
+
+            // Padded to whole chunks, nothing is left for a partial one
+            let chunks = resample_audio(&tone(440.0)[..RESAMPLE_CHUNK * 3], 48000, 16000, quality);
+            assert_eq!(chunks.unwrap().len(), RESAMPLE_CHUNK);
+        }
+
+        // 12 kHz can't be represented at 16 kHz: decimating would fold it
+        // back as a loud 4 kHz tone
+        let hiss = resample_audio(&tone(12000.0), 48000, 16000, ResampleQuality::High).unwrap();
+        assert!(peak(&hiss[100..15900]) < 0.01, "peak {}", peak(&hiss));
+    }
+
+    /// Needs the ffmpeg binary: `cargo test --features ffmpeg-tests`.
+    #[cfg(feature = "ffmpeg-tests")]
+    #[test]
+    fn test_ffmpeg_converter_matches_golden_fixtures() {
+        for golden in GOLDEN {
+            let from_file = FfmpegConverter
+                .convert(&AudioInput::File(fixture(golden.file)))
+                .unwrap();
+            assert_matches(golden, &from_file, golden.samples, 64);
+
+            let piped = FfmpegConverter
+                .convert(&AudioInput::Memory {
+                    bytes: std::fs::read(fixture(golden.file)).unwrap().into(),
+                    file_name: golden.file.to_string(),
+                })
+                .unwrap();
+            assert_matches(golden, &piped, golden.samples, 64);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One transcribed memo, as stored in the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptRecord {
+    /// When the memo was sent.
+    pub timestamp: DateTime<Utc>,
+    pub chat_id: i64,
+    pub message_id: i32,
+    /// Transcription provider that produced the text.
+    pub provider: String,
+    pub raw_transcript: String,
+    /// Vault the memo was captured into (`None` for the default vault).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault: Option<String>,
+}
+
+/// Append-only log of raw transcripts in `<data_dir>/transcripts.jsonl`.
+///
+/// Keeps every memo's transcript independently of the notes generated from
+/// it, so notes can be regenerated after prompt or model upgrades.
+pub struct TranscriptLog {
+    path: PathBuf,
+}
+
+impl TranscriptLog {
+    pub fn new<P: AsRef<Path>>(data_dir: P) -> Self {
+        Self {
+            path: data_dir.as_ref().join("transcripts.jsonl"),
+        }
+    }
+
+    /// Path of the append-only transcript log.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, record: &TranscriptRecord) -> Result<()> {
+        append_line(&self.path, record)
+    }
+
+    /// Load all records, oldest first, skipping malformed lines.
+    pub fn load_records(path: &Path) -> Result<Vec<TranscriptRecord>> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read transcript log: {}", path.display()))?;
+
+        let mut records = Vec::new();
+        for (n, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(record) => records.push(record),
+                Err(e) => log::warn!("Transcripts: skipping malformed line {}: {}", n + 1, e),
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// A note generation attempt that failed, or succeeded after failures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationAttempt {
+    pub timestamp: DateTime<Utc>,
+    /// Memo the notes were generated for, when it came from Telegram.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<i32>,
+    pub model: String,
+    /// 1 for the first try.
+    pub attempt: u32,
+    pub temperature: f32,
+    pub top_p: f32,
+    /// Why the answer was rejected; `None` for the attempt that succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Append-only log of retried note generations in
+/// `<data_dir>/generations.jsonl`, to see how often a model needs retries.
+pub struct GenerationLog {
+    path: PathBuf,
+}
+
+impl GenerationLog {
+    pub fn new<P: AsRef<Path>>(data_dir: P) -> Self {
+        Self {
+            path: data_dir.as_ref().join("generations.jsonl"),
+        }
+    }
+
+    pub fn append(&self, attempt: &GenerationAttempt) -> Result<()> {
+        append_line(&self.path, attempt)
+    }
+}
+
+/// Append `record` to the JSON Lines file at `path`.
+fn append_line<T: Serialize>(path: &Path, record: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create data directory")?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open audit log: {}", path.display()))?;
+    let line = serde_json::to_string(record).context("Failed to serialize audit record")?;
+    writeln!(file, "{}", line).context("Failed to write audit record")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_load_records() {
+        let dir = std::env::temp_dir().join(format!("dot-audit-{}", uuid::Uuid::new_v4()));
+        let log = TranscriptLog::new(&dir);
+
+        for (message_id, text) in [(1, "primo memo"), (2, "secondo memo")] {
+            log.append(&TranscriptRecord {
+                timestamp: Utc::now(),
+                chat_id: 42,
+                message_id,
+                provider: "groq".to_string(),
+                raw_transcript: text.to_string(),
+                vault: None,
+            })
+            .unwrap();
+        }
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(log.path())
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+
+        let records = TranscriptLog::load_records(log.path()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].raw_transcript, "secondo memo");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
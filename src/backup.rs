@@ -0,0 +1,246 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use teloxide::prelude::*;
+
+use crate::config::Config;
+use crate::i18n::Msg;
+use crate::telegram::{Bot, SendRetrying};
+use crate::vault_lock;
+
+const ARCHIVE_PREFIX: &str = "dot-vault-";
+const ARCHIVE_SUFFIX: &str = ".tar.zst";
+/// zstd level: fast, and Markdown still compresses well.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// A backup that was written.
+#[derive(Debug)]
+pub struct BackupReport {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// Where the archive was uploaded, if S3 is configured.
+    pub uploaded_to: Option<String>,
+}
+
+/// Archive the vault into `backup.dir`, prune old archives and upload the new
+/// one to S3 if configured. Writes to the vault wait until the archive is done.
+pub async fn run(config: &Config) -> Result<BackupReport> {
+    let backup = config.backup.clone();
+    let dir = PathBuf::from(
+        backup
+            .dir
+            .as_deref()
+            .context("Backups are not configured")?,
+    );
+    let vault = config.vault_dir();
+
+    let lock = vault_lock::lock(&vault).await?;
+    let keep = backup.keep;
+    let mut report = tokio::task::spawn_blocking(move || write_backup(&vault, &dir, keep))
+        .await
+        .context("Backup task failed")??;
+    // The archive is complete; don't block note writes during the upload
+    drop(lock);
+
+    if let Some(uri) = backup.s3_uri {
+        let path = report.path.clone();
+        let target = tokio::task::spawn_blocking(move || upload(&path, &uri))
+            .await
+            .context("Backup upload task failed")??;
+        report.uploaded_to = Some(target);
+    }
+    log::info!(
+        "Backup written to {} ({} bytes)",
+        report.path.display(),
+        report.size_bytes
+    );
+    Ok(report)
+}
+
+/// Back up the vault every `backup.every_hours`, alerting the admin on failure.
+pub async fn run_scheduled(bot: Bot, config: Config) {
+    if config.backup.dir.is_none() || config.backup.every_hours == 0 {
+        return;
+    }
+    let every = Duration::from_secs(config.backup.every_hours * 3600);
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + every, every);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        if let Err(e) = run(&config).await {
+            log::error!("Scheduled backup failed: {:#}", e);
+            if let Some(admin) = config.telegram.admin_chat_id {
+                let text = Msg::ScheduledBackupFailed
+                    .fill(config.general.language, &[("error", &format!("{:#}", e))]);
+                let _ = bot.send_message(ChatId(admin), text).send_retrying().await;
+            }
+        }
+    }
+}
+
+fn write_backup(vault: &Path, dir: &Path, keep: usize) -> Result<BackupReport> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create backup dir: {}", dir.display()))?;
+    let name = format!(
+        "{}{}{}",
+        ARCHIVE_PREFIX,
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        ARCHIVE_SUFFIX
+    );
+    let path = dir.join(&name);
+
+    // Write under a temporary name so a partial archive is never kept
+    let tmp = dir.join(format!(".{}.partial", name));
+    create_archive(vault, &tmp, &[dir.to_path_buf()])?;
+    std::fs::rename(&tmp, &path).context("Failed to finalize backup")?;
+    let size_bytes = std::fs::metadata(&path)?.len();
+
+    let removed = prune(dir, keep)?;
+    if removed > 0 {
+        log::info!("Removed {} old backup(s)", removed);
+    }
+
+    Ok(BackupReport {
+        path,
+        size_bytes,
+        uploaded_to: None,
+    })
+}
+
+/// Write `vault` as a zstd-compressed tarball, skipping the lock file and the
+/// `exclude`d folders (the backup dir may live inside the vault).
+fn create_archive(vault: &Path, target: &Path, exclude: &[PathBuf]) -> Result<()> {
+    let file = std::fs::File::create(target)
+        .with_context(|| format!("Failed to create {}", target.display()))?;
+    let encoder = zstd::Encoder::new(file, COMPRESSION_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+
+    let exclude: Vec<PathBuf> = exclude
+        .iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .collect();
+    append_tree(&mut builder, vault, vault, &exclude)?;
+
+    builder.into_inner()?.finish()?.sync_all()?;
+    Ok(())
+}
+
+fn append_tree<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    root: &Path,
+    dir: &Path,
+    exclude: &[PathBuf],
+) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.file_name().is_some_and(|n| n == vault_lock::LOCK_FILE)
+            || path.canonicalize().is_ok_and(|p| exclude.contains(&p))
+        {
+            continue;
+        }
+        let relative = path.strip_prefix(root)?;
+        if path.is_dir() {
+            builder.append_dir(relative, &path)?;
+            append_tree(builder, root, &path, exclude)?;
+        } else {
+            builder
+                .append_path_with_name(&path, relative)
+                .with_context(|| format!("Failed to archive {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete all but the newest `keep` archives, returning how many were removed.
+fn prune(dir: &Path, keep: usize) -> Result<usize> {
+    let mut archives: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(ARCHIVE_PREFIX) && n.ends_with(ARCHIVE_SUFFIX))
+        })
+        .collect();
+    // Names embed the timestamp, so they sort oldest first
+    archives.sort();
+
+    let excess = archives.len().saturating_sub(keep.max(1));
+    for old in &archives[..excess] {
+        std::fs::remove_file(old)
+            .with_context(|| format!("Failed to remove old backup {}", old.display()))?;
+    }
+    Ok(excess)
+}
+
+/// Copy the archive under `s3_uri` with the `aws` CLI, returning its S3 URI.
+fn upload(path: &Path, s3_uri: &str) -> Result<String> {
+    let name = path.file_name().context("Backup has no file name")?;
+    let target = format!(
+        "{}/{}",
+        s3_uri.trim_end_matches('/'),
+        name.to_string_lossy()
+    );
+    let output = Command::new("aws")
+        .args(["s3", "cp", "--only-show-errors"])
+        .arg(path)
+        .arg(&target)
+        .output()
+        .context("Failed to run the aws CLI (is it installed?)")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Upload to {} failed: {}",
+            target,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_archives_vault_and_keeps_newest() {
+        let root = std::env::temp_dir().join(format!("dot-backup-{}", uuid::Uuid::new_v4()));
+        let vault = root.join("vault");
+        std::fs::create_dir_all(vault.join("Work")).unwrap();
+        std::fs::write(vault.join("Idea.md"), "# Idea").unwrap();
+        std::fs::write(vault.join("Work/Riunione.md"), "# Riunione").unwrap();
+        std::fs::write(vault.join(vault_lock::LOCK_FILE), "").unwrap();
+        // Backups inside the vault must not archive themselves
+        let dir = vault.join("backups");
+        std::fs::create_dir_all(&dir).unwrap();
+        for old in ["20240101-000000", "20240102-000000"] {
+            std::fs::write(
+                dir.join(format!("{ARCHIVE_PREFIX}{old}{ARCHIVE_SUFFIX}")),
+                "",
+            )
+            .unwrap();
+        }
+
+        let report = write_backup(&vault, &dir, 2).unwrap();
+        assert!(report.size_bytes > 0);
+        assert!(report.uploaded_to.is_none());
+
+        let mut kept: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        kept.sort();
+        assert_eq!(kept.len(), 2);
+        assert!(kept[0].contains("20240102"));
+
+        let decoder = zstd::Decoder::new(std::fs::File::open(&report.path).unwrap()).unwrap();
+        let mut entries: Vec<String> = tar::Archive::new(decoder)
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect();
+        entries.sort();
+        assert_eq!(entries, vec!["Idea.md", "Work", "Work/Riunione.md"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
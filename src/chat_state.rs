@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::atomic_write::write_atomic;
+
+/// Per-chat preferences chosen through commands or `/start` deep links.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatState {
+    /// Selected entry of `[profiles]`, if any.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Selected entry of `[vaults]`; `None` is the default vault.
+    #[serde(default)]
+    pub vault: Option<String>,
+}
+
+/// Persists per-chat preferences to `<data_dir>/chats.json`.
+pub struct ChatStateStore {
+    path: PathBuf,
+    chats: Mutex<HashMap<i64, ChatState>>,
+}
+
+impl ChatStateStore {
+    /// Load the store, starting empty if the file does not exist yet.
+    pub fn load<P: AsRef<Path>>(data_dir: P) -> Result<Self> {
+        let path = data_dir.as_ref().join("chats.json");
+        let chats = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse chat state: {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read chat state: {}", path.display()))
+            }
+        };
+
+        Ok(Self {
+            path,
+            chats: Mutex::new(chats),
+        })
+    }
+
+    /// Current state of a chat (default if never configured).
+    pub fn get(&self, chat_id: i64) -> ChatState {
+        self.chats
+            .lock()
+            .unwrap()
+            .get(&chat_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Modify a chat's state and persist the whole store.
+    pub fn update(&self, chat_id: i64, change: impl FnOnce(&mut ChatState)) -> Result<()> {
+        let mut chats = self.chats.lock().unwrap();
+        change(chats.entry(chat_id).or_default());
+
+        let json =
+            serde_json::to_string_pretty(&*chats).context("Failed to serialize chat state")?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create data directory")?;
+        }
+        // Write to a temp file and rename, so a crash never leaves half a file
+        write_atomic(&self.path, &json, false).context("Failed to write chat state")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_persists_across_loads() {
+        let dir = std::env::temp_dir().join(format!("dot-chats-{}", uuid::Uuid::new_v4()));
+        let store = ChatStateStore::load(&dir).unwrap();
+        assert!(store.get(7).profile.is_none());
+
+        store
+            .update(7, |state| state.profile = Some("work".to_string()))
+            .unwrap();
+
+        let reloaded = ChatStateStore::load(&dir).unwrap();
+        assert_eq!(reloaded.get(7).profile.as_deref(), Some("work"));
+        assert!(reloaded.get(8).profile.is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,12 @@
 use anyhow::{Context, Result};
+use chrono_tz::Tz;
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::i18n::Language;
+use crate::tools::notes_reader::DEFAULT_IGNORE;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
@@ -12,13 +17,77 @@ pub struct Config {
     pub ai_model: AiModelConfig,
     pub output: OutputConfig,
     pub features: FeaturesConfig,
+    #[serde(default)]
+    pub general: GeneralConfig,
+    #[serde(default)]
+    pub locale: LocaleConfig,
+    /// Named targets a chat can select with `/profile` or a `/start` deep link.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    #[serde(default)]
+    pub groups: GroupsConfig,
+    #[serde(default)]
+    pub prompts: PromptsConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub backup: BackupConfig,
+    #[serde(default)]
+    pub quota: QuotaConfig,
+    #[serde(default)]
+    pub access: AccessConfig,
+    #[serde(default)]
+    pub expenses: ExpensesConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub frontmatter: FrontmatterConfig,
+    /// Other vaults a chat can switch to with `/vault`; `[output]` is the
+    /// default one.
+    #[serde(default)]
+    pub vaults: BTreeMap<String, VaultConfig>,
+    /// Entry of `vaults` this configuration was derived for (`None` for the
+    /// default vault).
+    #[serde(skip)]
+    pub vault: Option<String>,
+    #[allow(dead_code)]
     pub logging: LoggingConfig,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct TelegramConfig {
     pub bot_token: String,
+    #[allow(dead_code)] // reserved: teloxide manages its own polling interval
     pub poll_interval: u64,
+    /// Chat that receives operational alerts (low disk space, write failures).
+    #[serde(default)]
+    pub admin_chat_id: Option<i64>,
+    #[serde(default)]
+    pub reply_verbosity: ReplyVerbosity,
+    /// Append how long each stage took to the reply.
+    #[serde(default)]
+    pub show_timings: bool,
+    /// Longest transcript shown in full replies; longer ones get a button
+    /// sending the rest. 0 always shows the whole transcript.
+    #[serde(default = "default_transcript_preview_chars")]
+    pub transcript_preview_chars: usize,
+}
+
+fn default_transcript_preview_chars() -> usize {
+    600
+}
+
+/// How much the bot writes back after saving notes.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplyVerbosity {
+    /// Notes, tags, files and the transcript.
+    #[default]
+    Full,
+    /// Just the number of saved notes.
+    Summary,
+    /// No message, only a reaction on the memo.
+    Silent,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -31,6 +100,116 @@ pub struct TranscriptionConfig {
     pub api_key_env: Option<String>,
     #[serde(default)]
     pub model: Option<String>,
+    /// URL of the OpenAI-compatible transcription endpoint, for `whisper_server`.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Unload the local Whisper model after this many idle seconds to free
+    /// RAM. Unset keeps it loaded for the lifetime of the bot.
+    #[serde(default)]
+    pub unload_after_idle_secs: Option<u64>,
+    /// CPU threads for local Whisper (default: whisper.cpp's choice).
+    #[serde(default)]
+    pub threads: Option<i32>,
+    /// Use the GPU when built with the `metal` or `cuda` feature.
+    #[serde(default = "default_true")]
+    pub use_gpu: bool,
+    #[serde(default)]
+    pub gpu_device: i32,
+    #[serde(default)]
+    pub task: WhisperTask,
+    /// Long recordings are transcribed locally in chunks of this many seconds.
+    #[serde(default = "default_chunk_secs")]
+    pub chunk_secs: u32,
+    /// Folder where the chunks of a long local transcription are saved as
+    /// they finish, so a crash or failure resumes from the last good chunk.
+    /// Unset disables checkpoints.
+    #[serde(default)]
+    pub checkpoint_dir: Option<String>,
+    /// Audio up to this size is downloaded and decoded in memory, without
+    /// temporary files. Unset always uses `output.temp_dir`.
+    #[serde(default)]
+    pub in_memory_max_mb: Option<u64>,
+    /// Suppress background noise (wind, traffic) with RNNoise before Whisper
+    /// hears the audio; helps with memos recorded outdoors.
+    #[serde(default)]
+    pub denoise: bool,
+    /// Boost quiet recordings (phone far from the mouth) before transcribing.
+    #[serde(default = "default_true")]
+    pub normalize_gain: bool,
+    /// Resampler used when decoded audio is not already at 16 kHz.
+    #[serde(default)]
+    pub resample_quality: ResampleQuality,
+    /// `[mm:ss]` markers in the transcript, per segment or per word.
+    #[serde(default)]
+    pub timestamps: TimestampMode,
+    /// How recordings with more than one channel are transcribed.
+    #[serde(default)]
+    pub channels: ChannelMode,
+    /// Speaker names for `channels = "separate"`, in channel order.
+    /// Missing names default to "Canale N".
+    #[serde(default)]
+    pub channel_labels: Vec<String>,
+    /// Text Whisper continues from, setting style and vocabulary.
+    #[serde(default)]
+    pub initial_prompt: Option<String>,
+    /// Terms Whisper should spell right (product names, jargon), added to
+    /// the initial prompt; Deepgram gets them as keywords.
+    #[serde(default)]
+    pub glossary: Vec<String>,
+    /// Retries of hosted transcription requests (rate limits, outages).
+    #[serde(default)]
+    pub retry: RetryConfig,
+}
+
+fn default_chunk_secs() -> u32 {
+    60
+}
+
+/// How multichannel audio is turned into a transcript.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelMode {
+    /// Mix all channels down to mono.
+    #[default]
+    Mix,
+    /// Transcribe each channel on its own and interleave the segments by
+    /// time, labelled with the channel's speaker (e.g. call recordings).
+    Separate,
+}
+
+/// Where transcripts get `[mm:ss]` markers pointing back into the recording.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampMode {
+    #[default]
+    Off,
+    /// One line per segment (a phrase or sentence), starting with its time.
+    Segments,
+    /// A marker whenever the time changes between words, for providers that
+    /// report word times (OpenAI, Groq, Deepgram); per segment otherwise.
+    Words,
+}
+
+/// Trade-off between speed and fidelity when resampling to 16 kHz.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ResampleQuality {
+    /// Polynomial interpolation: cheap, but lets some aliasing through.
+    Fast,
+    /// Windowed sinc interpolation with an anti-aliasing low-pass.
+    #[default]
+    High,
+}
+
+/// What Whisper produces from the audio (locally or through an API).
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WhisperTask {
+    /// Text in the spoken language.
+    #[default]
+    Transcribe,
+    /// English translation of the audio.
+    Translate,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -41,6 +220,23 @@ pub struct CorrectionConfig {
     pub temperature: f32,
     #[serde(default = "default_top_p")]
     pub top_p: f32,
+    /// Remember the phrases the corrector changes and pass the recurring
+    /// ones to later corrections as known fixes.
+    #[serde(default)]
+    pub learn_fixes: bool,
+    /// Times a fix must be seen before it is passed on.
+    #[serde(default = "default_min_fix_count")]
+    pub min_fix_count: u32,
+    /// Most known fixes in one correction prompt, most frequent first.
+    #[serde(default = "default_max_known_fixes")]
+    pub max_known_fixes: usize,
+}
+
+fn default_min_fix_count() -> u32 {
+    2
+}
+fn default_max_known_fixes() -> usize {
+    30
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -49,6 +245,90 @@ pub struct NotesGenerationConfig {
     pub temperature: f32,
     #[serde(default = "default_top_p")]
     pub top_p: f32,
+    /// Second model run concurrently with `ai_model.model` for best-of selection.
+    #[serde(default)]
+    pub compare_model: Option<String>,
+    /// How the better of the two results is chosen.
+    #[serde(default)]
+    pub compare_selection: CompareSelection,
+    /// Transcripts longer than this (after correction) are split into topics
+    /// and each topic gets its own generation prompt. Unset disables it.
+    #[serde(default)]
+    pub segment_above_chars: Option<usize>,
+    /// Ask the model for an outline first (one section per note, with the
+    /// existing notes it relates to), then write each note with its own
+    /// prompt. Applies above `segment_above_chars`, or to every memo if unset.
+    #[serde(default)]
+    pub two_phase: bool,
+    /// Most notes per memo: the model is told so, and notes beyond it are
+    /// merged into the longest one. Unset leaves the count to the model.
+    #[serde(default)]
+    pub max_notes: Option<usize>,
+    /// Pass transcript timestamps to the model, which keeps them next to
+    /// the points they belong to. Otherwise they stay in the raw transcript.
+    #[serde(default)]
+    pub keep_timestamps: bool,
+    /// Longest transcript (or topic section) sent in one generation prompt,
+    /// in characters. Unset sends it whole, leaving overflow to the model.
+    #[serde(default)]
+    pub max_transcript_chars: Option<usize>,
+    /// How transcripts over `max_transcript_chars` are shortened.
+    #[serde(default)]
+    pub overflow: OverflowStrategy,
+    /// Further attempts when the model answers with no notes or with JSON
+    /// that doesn't fit the notes format, before falling back to the
+    /// transcript-only note. 0 gives up at once.
+    #[serde(default)]
+    pub retries: u32,
+    /// Added to `temperature` on each retry, so the model doesn't repeat
+    /// the same answer.
+    #[serde(default = "default_retry_temperature_step")]
+    pub retry_temperature_step: f32,
+    /// Have the model check the generated notes against the transcript
+    /// (missing points, made-up facts, links to no note) and fix them before
+    /// saving. Doubles the generation time.
+    #[serde(default)]
+    pub review_pass: bool,
+    /// Tokens (about 4 characters each) the list of existing notes may take
+    /// in the generation prompt: related and recent notes are listed first.
+    /// 0 lists every note.
+    #[serde(default = "default_existing_notes_tokens")]
+    pub existing_notes_tokens: usize,
+    /// Ollama embedding model (e.g. `nomic-embed-text`) used to list only
+    /// the existing notes most similar to the memo in the prompt. Unset
+    /// lists them by `existing_notes_tokens` alone.
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    /// Ollama server of `embedding_model`; defaults to `ai_model.endpoint`.
+    #[serde(default)]
+    pub embedding_endpoint: Option<String>,
+    /// Most similar notes listed with `embedding_model`.
+    #[serde(default = "default_embedding_top_k")]
+    pub embedding_top_k: usize,
+}
+
+/// Shortening of transcripts that don't fit the model's context.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowStrategy {
+    /// Drop the beginning, keeping the end of the memo.
+    TruncateHead,
+    /// Drop the end, keeping the beginning of the memo.
+    #[default]
+    TruncateTail,
+    /// Summarize it with the model, in pieces that fit.
+    Summarize,
+}
+
+/// Selection strategy when `compare_model` is set.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CompareSelection {
+    /// A judge prompt on the primary model picks the winner.
+    #[default]
+    Judge,
+    /// Both results are shown in chat and the user picks one.
+    Ask,
 }
 
 fn default_true() -> bool {
@@ -60,6 +340,15 @@ fn default_correction_temperature() -> f32 {
 fn default_notes_temperature() -> f32 {
     0.7
 }
+fn default_retry_temperature_step() -> f32 {
+    0.15
+}
+fn default_existing_notes_tokens() -> usize {
+    4000
+}
+fn default_embedding_top_k() -> usize {
+    30
+}
 fn default_top_p() -> f32 {
     0.9
 }
@@ -68,14 +357,202 @@ fn default_top_p() -> f32 {
 pub struct AiModelConfig {
     pub provider: String,
     pub model: String,
+    /// Ollama server, or the base URL of an `openai_compatible` or
+    /// `anthropic` API.
     pub endpoint: String,
+    /// Environment variable with the API key: unset sends none with
+    /// `openai_compatible` (as local servers expect) and reads
+    /// `ANTHROPIC_API_KEY` with `anthropic`.
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    /// Retries of Ollama requests (connection resets, overloaded server).
+    #[serde(default)]
+    pub retry: RetryConfig,
+    /// Pass the JSON schema of the notes to Ollama (0.5+), which then only
+    /// produces matching answers; off sends the plain `"json"` hint.
+    #[serde(default = "default_true")]
+    pub structured_output: bool,
+    /// Reuse the answer to an identical request (same model and prompts)
+    /// for this many seconds; unset disables the cache.
+    #[serde(default)]
+    pub cache_ttl_secs: Option<u64>,
+    /// Ollama model options (`num_ctx`, `num_predict`, `seed`...), sent
+    /// verbatim in each request's `options` next to temperature and top_p.
+    #[serde(default)]
+    pub options: serde_json::Map<String, serde_json::Value>,
+    /// How long Ollama keeps the model loaded after a request: a duration
+    /// like "30m", or -1 to keep it loaded.
+    #[serde(default)]
+    pub keep_alive: Option<serde_json::Value>,
+    /// Give up on a model request after this many seconds, retries
+    /// included, so a hung server doesn't block the memo; 0 waits forever.
+    #[serde(default = "default_ai_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Price per million prompt and completion tokens, to show the cost of
+    /// each memo next to its token count; unset shows only the tokens.
+    #[serde(default)]
+    pub input_cost_per_mtok: Option<f64>,
+    #[serde(default)]
+    pub output_cost_per_mtok: Option<f64>,
+    /// Provider taking over correction and note generation when this one
+    /// fails or times out, e.g. a hosted API behind a local Ollama. It may
+    /// have a fallback of its own.
+    #[serde(default)]
+    pub fallback: Option<Box<AiModelConfig>>,
+    /// Ollama only: download the model at startup if the server doesn't
+    /// have it; otherwise the bot refuses to start.
+    #[serde(default)]
+    pub pull_model: bool,
+}
+
+fn default_ai_timeout_secs() -> u64 {
+    300
+}
+
+impl AiModelConfig {
+    /// Whether notes are generated by a model; `provider = "none"` writes
+    /// the transcript as a note without one.
+    pub fn enabled(&self) -> bool {
+        self.provider != "none"
+    }
+}
+
+/// Retries of transient HTTP failures (connection errors, 429 rate limits,
+/// 5xx answers) with exponential backoff.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// Tries in total; 1 disables retries.
+    #[serde(default = "default_retry_attempts")]
+    pub attempts: u32,
+    /// Wait before the first retry in milliseconds, doubled for each further one.
+    #[serde(default = "default_retry_backoff_ms")]
+    pub backoff_ms: u64,
+    /// Longest wait between tries, also when a server asks for more with
+    /// `Retry-After`.
+    #[serde(default = "default_retry_max_backoff_secs")]
+    pub max_backoff_secs: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            attempts: default_retry_attempts(),
+            backoff_ms: default_retry_backoff_ms(),
+            max_backoff_secs: default_retry_max_backoff_secs(),
+        }
+    }
+}
+
+fn default_retry_attempts() -> u32 {
+    3
+}
+fn default_retry_backoff_ms() -> u64 {
+    1000
+}
+fn default_retry_max_backoff_secs() -> u64 {
+    30
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct OutputConfig {
     pub notes_dir: String,
     pub tasks_dir: String,
+    /// Append every extracted task to this file in `tasks_dir` instead of
+    /// one file per day.
+    #[serde(default)]
+    pub tasks_file: Option<String>,
     pub temp_dir: String,
+    /// How note filenames are built: plain title or Zettelkasten ID prefix.
+    #[serde(default)]
+    pub filename_mode: FilenameMode,
+    /// Which moment a note is dated with.
+    #[serde(default)]
+    pub note_date: NoteDateSource,
+    /// Where binary assets (audio, images, PDFs) are stored.
+    /// Defaults to `<notes_dir>/attachments`.
+    #[serde(default)]
+    pub attachments_dir: Option<String>,
+    /// Warn (log + admin alert) when free space in `notes_dir` drops below this.
+    #[serde(default = "default_min_free_space_mb")]
+    pub min_free_space_mb: u64,
+    /// Bot state: feedback and other stores.
+    #[serde(default = "default_data_dir")]
+    pub data_dir: String,
+    /// Obsidian vault name; when set, replies link each note with `obsidian://open`.
+    #[serde(default)]
+    pub obsidian_vault: Option<String>,
+    /// Root folder of the vault, if `notes_dir` is a folder inside it.
+    /// Defaults to `notes_dir`.
+    #[serde(default)]
+    pub vault_dir: Option<String>,
+    /// Markdown template for the note body (after the frontmatter).
+    #[serde(default)]
+    pub note_template: Option<String>,
+    /// Append a note whose title matches an existing note to that note, as a
+    /// dated section, instead of overwriting it.
+    #[serde(default)]
+    pub merge_existing: bool,
+    /// Link each new note from the existing notes in its `related_notes`,
+    /// under their "Note correlate" section.
+    #[serde(default)]
+    pub backlinks: bool,
+    /// Flush every note to disk before it replaces the previous file, at the
+    /// cost of slower saves.
+    #[serde(default)]
+    pub fsync: bool,
+    /// Subfolders of `notes_dir` by tag pattern or title keyword, e.g.
+    /// `"work/*" = "Work"`; the longest matching pattern wins.
+    #[serde(default)]
+    pub routing: BTreeMap<String, String>,
+    /// Folder of note type templates (`meeting.md`, `idea.md`…); memos are
+    /// classified into one of them and laid out in its sections.
+    #[serde(default)]
+    pub templates_dir: Option<String>,
+    /// Globs of paths below `notes_dir` that are not notes (`*` matches any
+    /// characters, `/` included). Hidden folders are always skipped.
+    #[serde(default = "default_ignore")]
+    pub ignore: Vec<String>,
+    /// Keep the metadata of the notes in `<data_dir>/notes-index.json` and
+    /// read again only the notes changed since the last memo.
+    #[serde(default)]
+    pub notes_index: bool,
+    /// With `notes_index`, also watch `notes_dir` for changes, so an
+    /// unchanged vault isn't even listed.
+    #[serde(default)]
+    pub watch_notes: bool,
+}
+
+fn default_ignore() -> Vec<String> {
+    DEFAULT_IGNORE.iter().map(|glob| glob.to_string()).collect()
+}
+
+fn default_min_free_space_mb() -> u64 {
+    200
+}
+fn default_data_dir() -> String {
+    "./data".to_string()
+}
+
+/// Filename scheme for generated notes.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FilenameMode {
+    /// `Title.md`
+    #[default]
+    Title,
+    /// `202405141230 Title.md`, with the ID also recorded in frontmatter
+    Zettel,
+}
+
+/// Date written to a note's frontmatter (and Zettelkasten ID).
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteDateSource {
+    /// When the memo was sent; for forwarded memos, when the original was.
+    #[default]
+    Message,
+    /// When the bot generated the note.
+    Processing,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -83,8 +560,476 @@ pub struct FeaturesConfig {
     pub enable_task_extraction: bool,
     pub enable_auto_tags: bool,
     pub max_audio_size_mb: u64,
+    /// Keep the original audio in the attachments folder and embed it in the notes.
+    #[serde(default)]
+    pub archive_audio: bool,
+    /// Keep the original of shared documents in the attachments folder and
+    /// embed it in the notes.
+    #[serde(default)]
+    pub archive_documents: bool,
+    /// Add a validated Mermaid flowchart to notes describing a process.
+    #[serde(default)]
+    pub enable_mermaid: bool,
+    /// Align generated tags with the vault: drop tags it never uses and add
+    /// tags that usually appear together with the chosen ones.
+    #[serde(default)]
+    pub tags_from_vault: bool,
+    /// Also relate each note to existing notes close to it in the link
+    /// graph (linked to or from the notes it links, or sharing their links).
+    #[serde(default)]
+    pub related_from_graph: bool,
+    /// Have the model pick an emoji per note, written as `icon:` in the
+    /// frontmatter (Obsidian Iconize) and shown in the reply.
+    #[serde(default)]
+    pub enable_icons: bool,
+    /// Extract books, articles, podcasts and other media mentioned in a memo
+    /// into a `references:` frontmatter list.
+    #[serde(default)]
+    pub extract_references: bool,
+    /// Folder of the vault with one note per referenced media, each linking
+    /// the memos that mention it. Unset keeps references in the frontmatter only.
+    #[serde(default)]
+    pub library_folder: Option<String>,
+    /// Write a `telegram_link:` frontmatter field linking each note back to
+    /// the message it came from.
+    #[serde(default)]
+    pub telegram_link: bool,
+    /// Remember the message each note came from and reply there when the
+    /// note is deleted from the vault.
+    #[serde(default)]
+    pub report_deleted_notes: bool,
+    /// How often failed memos are retried once their dependency recovers,
+    /// in seconds. 0 leaves them to `/retry`.
+    #[serde(default = "default_pending_retry_secs")]
+    pub pending_retry_secs: u64,
+    /// Tag of the transcript-only notes saved when a memo's notes could not
+    /// be generated.
+    #[serde(default = "default_fallback_tag")]
+    pub fallback_tag: String,
 }
 
+fn default_pending_retry_secs() -> u64 {
+    300
+}
+
+fn default_fallback_tag() -> String {
+    "needs-processing".to_string()
+}
+
+/// What a Telegram user may do with the bot. Roles are ordered: each one
+/// includes the permissions of those before it.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Status, help and feedback; cannot create or change notes.
+    Reader,
+    /// Captures memos and documents into notes.
+    Writer,
+    /// Maintenance and backups.
+    Admin,
+}
+
+/// User IDs per role. With every list empty the bot is open: everyone is a
+/// writer. The user whose ID is `admin_chat_id` is always admin.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AccessConfig {
+    #[serde(default)]
+    pub admins: Vec<u64>,
+    #[serde(default)]
+    pub writers: Vec<u64>,
+    #[serde(default)]
+    pub readers: Vec<u64>,
+    /// Role of users not listed above. Unset refuses them.
+    #[serde(default)]
+    pub default_role: Option<Role>,
+}
+
+/// Extra properties of the notes' frontmatter, and other names for the
+/// built-in ones, to match a vault's property schema.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct FrontmatterConfig {
+    /// Added to every note. Values may use `{{title}}`, `{{date}}`,
+    /// `{{source}}`, `{{language}}` and `{{detected_project}}` (the `#project`
+    /// of `[prompts.overrides]` the memo mentions); empty ones are left out.
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+    /// Built-in keys written under another name, e.g. `date = "created"`.
+    #[serde(default)]
+    pub rename: BTreeMap<String, String>,
+}
+
+/// Frontmatter keys the notes are written with, which `rename` applies to.
+const FRONTMATTER_KEYS: &[&str] = &[
+    "title",
+    "id",
+    "date",
+    "source",
+    "language",
+    "icon",
+    "telegram_link",
+    "tags",
+    "references",
+    "related",
+    "modified",
+];
+
+impl FrontmatterConfig {
+    /// Reject settings that would write two fields under the same name.
+    fn validate(&self) -> Result<()> {
+        let unknown = self
+            .rename
+            .keys()
+            .find(|key| !FRONTMATTER_KEYS.contains(&key.as_str()));
+        if let Some(key) = unknown {
+            anyhow::bail!("frontmatter.rename: unknown frontmatter key '{}'", key);
+        }
+        let mut names: BTreeMap<&str, &str> = BTreeMap::new();
+        for key in FRONTMATTER_KEYS {
+            let name = self.rename.get(*key).map_or(*key, String::as_str);
+            if let Some(other) = names.insert(name, key) {
+                anyhow::bail!(
+                    "frontmatter.rename: '{}' and '{}' would both be written as '{}'",
+                    other,
+                    key,
+                    name
+                );
+            }
+        }
+        if let Some((name, key)) = self
+            .properties
+            .keys()
+            .find_map(|name| names.get_key_value(name.as_str()))
+        {
+            anyhow::bail!(
+                "frontmatter.properties: '{}' is already the name of the '{}' field",
+                name,
+                key
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Expenses mentioned in memos, appended to a table in the vault.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExpensesConfig {
+    /// Markdown table (or `.csv`) relative to the vault. Unset disables it.
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Only notes with one of these tags are recorded; empty records all.
+    #[serde(default = "default_expense_tags")]
+    pub tags: Vec<String>,
+    /// Currency for amounts said without one.
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+impl Default for ExpensesConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            tags: default_expense_tags(),
+            currency: default_currency(),
+        }
+    }
+}
+
+fn default_expense_tags() -> Vec<String> {
+    vec!["spese".to_string()]
+}
+
+fn default_currency() -> String {
+    "EUR".to_string()
+}
+
+/// Log mode: memos starting with a trigger word ("log, corso 5 km") are
+/// recorded as metrics instead of notes.
+#[derive(Debug, Deserialize, Clone)]
+pub struct MetricsConfig {
+    /// Metric names with a description for the model, e.g.
+    /// `corsa_km = "chilometri corsi"`. Empty disables log mode.
+    #[serde(default)]
+    pub names: BTreeMap<String, String>,
+    /// Words that start a log memo.
+    #[serde(default = "default_metric_triggers")]
+    pub triggers: Vec<String>,
+    /// Daily note relative to the vault (`{date}` is the memo's day), or a
+    /// `.csv` file that gets one row per value.
+    #[serde(default = "default_metrics_file")]
+    pub file: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            names: BTreeMap::new(),
+            triggers: default_metric_triggers(),
+            file: default_metrics_file(),
+        }
+    }
+}
+
+fn default_metric_triggers() -> Vec<String> {
+    vec!["log".to_string(), "registra".to_string()]
+}
+
+fn default_metrics_file() -> String {
+    "Metrics/{date}.md".to_string()
+}
+
+/// Scheduled tar.zst archives of the vault.
+#[derive(Debug, Deserialize, Clone)]
+pub struct BackupConfig {
+    /// Where archives are written. Unset disables backups.
+    #[serde(default)]
+    pub dir: Option<String>,
+    #[serde(default = "default_backup_every_hours")]
+    pub every_hours: u64,
+    /// Archives kept in `dir`; older ones are deleted.
+    #[serde(default = "default_backup_keep")]
+    pub keep: usize,
+    /// Also upload each archive here (`s3://bucket/prefix`) with the `aws` CLI.
+    #[serde(default)]
+    pub s3_uri: Option<String>,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            every_hours: default_backup_every_hours(),
+            keep: default_backup_keep(),
+            s3_uri: None,
+        }
+    }
+}
+
+/// Size caps of the folders the bot fills on its own.
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuotaConfig {
+    /// Cap of `output.temp_dir` in MB. Unset disables it.
+    #[serde(default)]
+    pub temp_max_mb: Option<u64>,
+    /// Cap of the attachments folder (archived audio) in MB. Unset disables it.
+    #[serde(default)]
+    pub attachments_max_mb: Option<u64>,
+    /// Warn the admin once a folder reaches this share of its cap; eviction
+    /// also frees space down to it.
+    #[serde(default = "default_quota_warn_percent")]
+    pub warn_percent: u8,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        Self {
+            temp_max_mb: None,
+            attachments_max_mb: None,
+            warn_percent: default_quota_warn_percent(),
+        }
+    }
+}
+
+fn default_quota_warn_percent() -> u8 {
+    90
+}
+
+fn default_backup_every_hours() -> u64 {
+    24
+}
+fn default_backup_keep() -> usize {
+    7
+}
+
+/// Tracing spans for each pipeline stage, exported over OTLP/HTTP.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector base URL (e.g. Jaeger on `http://localhost:4318`).
+    /// Unset disables tracing.
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: default_service_name(),
+        }
+    }
+}
+
+fn default_service_name() -> String {
+    "dot-transcriber".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct GeneralConfig {
+    /// Language of the built-in prompts and of the bot's replies.
+    #[serde(default)]
+    pub language: Language,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LocaleConfig {
+    /// IANA timezone used to resolve relative dates ("domani", "next Tuesday").
+    #[serde(default)]
+    pub timezone: Tz,
+}
+
+/// A vault selectable with `/vault`, overriding the vault paths of `[output]`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct VaultConfig {
+    pub notes_dir: String,
+    /// Root folder of the vault, if `notes_dir` is a folder inside it.
+    #[serde(default)]
+    pub vault_dir: Option<String>,
+    /// Defaults to `<notes_dir>/attachments`.
+    #[serde(default)]
+    pub attachments_dir: Option<String>,
+    /// Obsidian vault name, for `obsidian://open` links.
+    #[serde(default)]
+    pub obsidian_vault: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ProfileConfig {
+    /// Subfolder of `output.notes_dir` where this profile's notes are saved.
+    pub folder: String,
+    /// Shown in `/profile` listings.
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Group chats: only listed groups are served, and only audio addressed to
+/// the bot (mention, reply or trigger hashtag) is processed.
+#[derive(Debug, Deserialize, Clone)]
+pub struct GroupsConfig {
+    /// Hashtags in an audio caption that trigger processing.
+    #[serde(default = "default_trigger_hashtags")]
+    pub trigger_hashtags: Vec<String>,
+    #[serde(default)]
+    pub chats: Vec<GroupChatConfig>,
+}
+
+impl Default for GroupsConfig {
+    fn default() -> Self {
+        Self {
+            trigger_hashtags: default_trigger_hashtags(),
+            chats: Vec::new(),
+        }
+    }
+}
+
+impl GroupsConfig {
+    /// Settings of an enabled group, if it is served at all.
+    pub fn enabled_chat(&self, chat_id: i64) -> Option<&GroupChatConfig> {
+        self.chats.iter().find(|c| c.id == chat_id && c.enabled)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GroupChatConfig {
+    pub id: i64,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Subfolder of `output.notes_dir` for this group's notes.
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// Overrides `telegram.reply_verbosity` in this group.
+    #[serde(default)]
+    pub reply_verbosity: Option<ReplyVerbosity>,
+}
+
+fn default_trigger_hashtags() -> Vec<String> {
+    vec!["#dot".to_string()]
+}
+
+/// System prompt templates, and extra instructions appended to the note
+/// generation prompt.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PromptsConfig {
+    /// Keyed by target folder relative to the vault (`"work"` also covers
+    /// `"work/meetings"`) or by project (`"#acme"`, matched when the
+    /// transcript mentions `acme` or `#acme`).
+    #[serde(default)]
+    pub overrides: BTreeMap<String, String>,
+    /// Template file of the note generation system prompt, with
+    /// `{{existing_notes}}`, `{{instructions}}` and `{{transcript}}`
+    /// placeholders; unset uses the built-in `prompts/notes_system.md`.
+    #[serde(default)]
+    pub notes_system: Option<String>,
+    /// Template file of the correction system prompt, with a
+    /// `{{transcript}}` placeholder; unset uses the built-in
+    /// `prompts/corrector_system.md`.
+    #[serde(default)]
+    pub corrector_system: Option<String>,
+}
+
+/// The template at `path`, read for every memo so edits apply without a
+/// restart; `None` (with a warning if unreadable) falls back to the built-in.
+fn prompt_template(path: Option<&str>) -> Option<String> {
+    let path = path?;
+    match std::fs::read_to_string(path) {
+        Ok(template) => Some(template),
+        Err(e) => {
+            log::warn!("Failed to read prompt template {}: {}", path, e);
+            None
+        }
+    }
+}
+
+impl PromptsConfig {
+    pub fn notes_system(&self) -> Option<String> {
+        prompt_template(self.notes_system.as_deref())
+    }
+
+    pub fn corrector_system(&self) -> Option<String> {
+        prompt_template(self.corrector_system.as_deref())
+    }
+
+    /// Instructions that apply to notes saved in `folder` from `transcript`.
+    pub fn instructions_for(&self, folder: &str, transcript: &str) -> Vec<&str> {
+        let words = project_words(transcript);
+
+        self.overrides
+            .iter()
+            .filter(|(key, _)| match key.strip_prefix('#') {
+                Some(project) => words.contains(&project.to_lowercase()),
+                None => {
+                    let key = key.trim_matches('/');
+                    folder == key
+                        || folder
+                            .strip_prefix(key)
+                            .is_some_and(|rest| rest.starts_with('/'))
+                }
+            })
+            .map(|(_, instructions)| instructions.as_str())
+            .collect()
+    }
+
+    /// The first `#project` of `overrides` that `transcript` mentions.
+    pub fn detected_project(&self, transcript: &str) -> Option<&str> {
+        let words = project_words(transcript);
+        self.overrides
+            .keys()
+            .filter_map(|key| key.strip_prefix('#'))
+            .find(|project| words.contains(&project.to_lowercase()))
+    }
+}
+
+/// Lowercase words of `transcript`, as `#project` keys are matched.
+fn project_words(transcript: &str) -> Vec<String> {
+    transcript
+        .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// Reserved: logging is configured through `RUST_LOG` for now.
+#[allow(dead_code)]
 #[derive(Debug, Deserialize, Clone)]
 pub struct LoggingConfig {
     pub level: String,
@@ -104,6 +1049,7 @@ impl Config {
             config.telegram.bot_token = token;
         }
 
+        config.frontmatter.validate()?;
         Ok(config)
     }
 
@@ -111,9 +1057,117 @@ impl Config {
     pub fn ensure_directories(&self) -> Result<()> {
         fs::create_dir_all(&self.output.notes_dir).context("Failed to create notes directory")?;
         fs::create_dir_all(&self.output.tasks_dir).context("Failed to create tasks directory")?;
-        fs::create_dir_all(&self.output.temp_dir).context("Failed to create temp directory")?;
+        // Not needed (and possibly read-only) when every allowed audio fits in memory
+        if self
+            .transcription
+            .in_memory_max_mb
+            .is_none_or(|mb| mb < self.features.max_audio_size_mb)
+        {
+            fs::create_dir_all(&self.output.temp_dir).context("Failed to create temp directory")?;
+        }
+        fs::create_dir_all(&self.output.data_dir).context("Failed to create data directory")?;
+        fs::create_dir_all(self.attachments_dir())
+            .context("Failed to create attachments directory")?;
         Ok(())
     }
+
+    /// Configuration for the vault called `name`: notes, attachments and
+    /// links go to that vault. Unknown or no vault leaves it unchanged.
+    pub fn for_vault(&self, name: Option<&str>) -> Config {
+        let Some((name, vault)) = name.and_then(|name| self.vaults.get_key_value(name)) else {
+            return self.clone();
+        };
+        let mut config = self.clone();
+        config.output.notes_dir = vault.notes_dir.clone();
+        config.output.vault_dir = vault.vault_dir.clone();
+        config.output.attachments_dir = vault.attachments_dir.clone();
+        config.output.obsidian_vault = vault.obsidian_vault.clone();
+        config.vault = Some(name.clone());
+        config
+    }
+
+    /// Configuration as seen by a chat using `profile`: notes go to the
+    /// profile's subfolder. Unknown or no profile leaves it unchanged.
+    pub fn for_profile(&self, profile: Option<&str>) -> Config {
+        match profile.and_then(|name| self.profiles.get(name)) {
+            Some(profile) => self.with_notes_subfolder(&profile.folder),
+            None => self.clone(),
+        }
+    }
+
+    /// Configuration saving notes to `folder` inside `output.notes_dir`.
+    pub fn with_notes_subfolder(&self, folder: &str) -> Config {
+        let notes_dir = Path::new(&self.output.notes_dir).join(folder);
+        self.with_notes_dir(&notes_dir.to_string_lossy())
+    }
+
+    /// Configuration saving notes to `notes_dir`, a folder of the same vault.
+    pub fn with_notes_dir(&self, notes_dir: &str) -> Config {
+        let mut config = self.clone();
+        // Attachments and links stay relative to the vault root
+        config.output.attachments_dir = Some(self.attachments_dir().to_string_lossy().to_string());
+        config.output.vault_dir = Some(self.vault_dir().to_string_lossy().to_string());
+        config.output.notes_dir = notes_dir.to_string();
+        config
+    }
+
+    /// Notes folder relative to the vault root, `/`-separated (empty at the root).
+    pub fn notes_folder(&self) -> String {
+        Path::new(&self.output.notes_dir)
+            .strip_prefix(self.vault_dir())
+            .map(|relative| {
+                relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .unwrap_or_default()
+    }
+
+    /// Root of the Obsidian vault (`output.vault_dir` or `notes_dir`).
+    pub fn vault_dir(&self) -> PathBuf {
+        PathBuf::from(
+            self.output
+                .vault_dir
+                .as_deref()
+                .unwrap_or(&self.output.notes_dir),
+        )
+    }
+
+    /// Folder of per-media notes, when references are extracted into a library.
+    pub fn library_dir(&self) -> Option<PathBuf> {
+        let folder = self.features.library_folder.as_ref()?;
+        self.features
+            .extract_references
+            .then(|| self.vault_dir().join(folder))
+    }
+
+    /// File that expenses are appended to, if enabled.
+    pub fn expenses_file(&self) -> Option<PathBuf> {
+        let file = self.expenses.file.as_ref()?;
+        Some(self.vault_dir().join(file))
+    }
+
+    /// Contents of `output.note_template`, if set and readable.
+    pub fn note_template(&self) -> Option<String> {
+        let path = self.output.note_template.as_ref()?;
+        match std::fs::read_to_string(path) {
+            Ok(template) => Some(template),
+            Err(e) => {
+                log::warn!("Failed to read note template {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Resolved attachments directory (`output.attachments_dir` or `<notes_dir>/attachments`).
+    pub fn attachments_dir(&self) -> PathBuf {
+        match &self.output.attachments_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => Path::new(&self.output.notes_dir).join("attachments"),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -169,9 +1223,47 @@ mod tests {
             config.transcription.model_path.as_deref(),
             Some("./models/ggml-large-v3.bin")
         );
-        assert_eq!(config.correction.enabled, true);
+        assert!(config.transcription.use_gpu);
+        assert_eq!(config.transcription.threads, None);
+        assert_eq!(config.transcription.task, WhisperTask::Transcribe);
+        assert_eq!(config.transcription.channels, ChannelMode::Mix);
+        assert!(!config.transcription.denoise);
+        assert!(config.transcription.normalize_gain);
+        assert_eq!(config.transcription.resample_quality, ResampleQuality::High);
+        assert_eq!(config.transcription.timestamps, TimestampMode::Off);
+        assert!(config.correction.enabled);
         assert_eq!(config.correction.temperature, 0.3);
+        assert!(!config.correction.learn_fixes);
+        assert_eq!(config.correction.min_fix_count, 2);
         assert_eq!(config.notes_generation.temperature, 0.7);
+        assert_eq!(config.output.note_date, NoteDateSource::Message);
+        assert_eq!(config.transcription.retry.attempts, 3);
+        assert!(config.transcription.glossary.is_empty());
+        assert_eq!(config.ai_model.retry, RetryConfig::default());
+        assert!(config.ai_model.enabled());
+        assert!(config.ai_model.structured_output);
+        assert_eq!(config.notes_generation.max_transcript_chars, None);
+        assert_eq!(
+            config.notes_generation.overflow,
+            OverflowStrategy::TruncateTail
+        );
+        assert_eq!(config.notes_generation.retries, 0);
+        assert_eq!(
+            config.attachments_dir(),
+            PathBuf::from("./output/notes/attachments")
+        );
+        assert!(!config.features.archive_audio);
+        assert!(!config.features.archive_documents);
+        assert_eq!(config.features.pending_retry_secs, 300);
+        assert_eq!(config.features.fallback_tag, "needs-processing");
+        assert!(!config.features.tags_from_vault);
+        assert!(!config.features.related_from_graph);
+        assert!(!config.features.enable_icons);
+        assert!(!config.features.telegram_link);
+        assert!(config.backup.dir.is_none());
+        assert_eq!(config.backup.keep, 7);
+        assert!(config.access.admins.is_empty());
+        assert_eq!(config.locale.timezone, Tz::UTC);
     }
 
     #[test]
@@ -186,6 +1278,8 @@ mod tests {
             language = "it"
             api_key_env = "DEEPGRAM_API_KEY"
             model = "nova-2"
+            channels = "separate"
+            channel_labels = ["Io", "Cliente"]
 
             [correction]
             enabled = true
@@ -208,6 +1302,28 @@ mod tests {
             enable_auto_tags = true
             max_audio_size_mb = 20
 
+            [profiles.work]
+            folder = "Work"
+            description = "Note di lavoro"
+
+            [vaults.lavoro]
+            notes_dir = "./work-vault/Inbox"
+            vault_dir = "./work-vault"
+            obsidian_vault = "Work"
+
+            [[groups.chats]]
+            id = -1001234
+            folder = "Team"
+            reply_verbosity = "silent"
+
+            [[groups.chats]]
+            id = -1005678
+            enabled = false
+
+            [prompts.overrides]
+            "Work" = "Aggiungi sempre una sezione 'Stakeholder'."
+            '#acme' = "Usa la terminologia del progetto Acme."
+
             [logging]
             level = "info"
             log_file = "./dot.log"
@@ -220,8 +1336,75 @@ mod tests {
             Some("DEEPGRAM_API_KEY")
         );
         assert_eq!(config.transcription.model.as_deref(), Some("nova-2"));
+        assert_eq!(config.transcription.channels, ChannelMode::Separate);
+        assert_eq!(config.transcription.channel_labels, vec!["Io", "Cliente"]);
         assert_eq!(config.transcription.model_path, None);
         assert_eq!(config.ai_model.endpoint, "http://localhost:11434");
+        assert_eq!(
+            config.for_profile(Some("work")).output.notes_dir,
+            Path::new("./output/notes").join("Work").to_string_lossy()
+        );
+        assert_eq!(
+            config.for_profile(Some("work")).vault_dir(),
+            PathBuf::from("./output/notes")
+        );
+        assert_eq!(
+            config.for_profile(Some("unknown")).output.notes_dir,
+            "./output/notes"
+        );
+        assert_eq!(config.groups.trigger_hashtags, vec!["#dot"]);
+        assert_eq!(
+            config
+                .groups
+                .enabled_chat(-1001234)
+                .unwrap()
+                .folder
+                .as_deref(),
+            Some("Team")
+        );
+        assert_eq!(
+            config
+                .groups
+                .enabled_chat(-1001234)
+                .unwrap()
+                .reply_verbosity,
+            Some(ReplyVerbosity::Silent)
+        );
+        assert_eq!(config.telegram.reply_verbosity, ReplyVerbosity::Full);
+        assert_eq!(config.telegram.transcript_preview_chars, 600);
+        assert!(config.groups.enabled_chat(-1005678).is_none());
+
+        let work = config.for_profile(Some("work"));
+        assert_eq!(work.notes_folder(), "Work");
+        assert_eq!(config.notes_folder(), "");
+
+        let work_vault = config.for_vault(Some("lavoro"));
+        assert_eq!(work_vault.vault.as_deref(), Some("lavoro"));
+        assert_eq!(work_vault.vault_dir(), PathBuf::from("./work-vault"));
+        assert_eq!(work_vault.notes_folder(), "Inbox");
+        assert_eq!(
+            work_vault.attachments_dir(),
+            Path::new("./work-vault/Inbox").join("attachments")
+        );
+        assert_eq!(work_vault.output.obsidian_vault.as_deref(), Some("Work"));
+        assert_eq!(
+            work_vault.for_profile(Some("work")).vault_dir(),
+            PathBuf::from("./work-vault")
+        );
+        assert!(config.for_vault(Some("unknown")).vault.is_none());
+        assert_eq!(
+            config
+                .prompts
+                .instructions_for("Work/Meeting", "Riunione con #ACME oggi"),
+            vec![
+                "Usa la terminologia del progetto Acme.",
+                "Aggiungi sempre una sezione 'Stakeholder'."
+            ]
+        );
+        assert!(config
+            .prompts
+            .instructions_for("Workshop", "acmed")
+            .is_empty());
     }
 
     #[test]
@@ -247,6 +1430,10 @@ mod tests {
             provider = "ollama_local"
             model = "llama3.2:3b"
             endpoint = "http://localhost:11434"
+            keep_alive = -1
+
+            [ai_model.options]
+            num_ctx = 8192
 
             [output]
             notes_dir = "./output/notes"
@@ -264,6 +1451,8 @@ mod tests {
         "#;
 
         let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.ai_model.options["num_ctx"], 8192);
+        assert_eq!(config.ai_model.keep_alive, Some(serde_json::json!(-1)));
         assert_eq!(config.transcription.provider, "groq");
         assert_eq!(
             config.transcription.api_key_env.as_deref(),
@@ -274,11 +1463,44 @@ mod tests {
             Some("whisper-large-v3-turbo")
         );
         assert_eq!(config.transcription.model_path, None);
-        assert_eq!(config.correction.enabled, false);
+        assert!(!config.correction.enabled);
         // Check defaults applied
         assert_eq!(config.correction.temperature, 0.3);
         assert_eq!(config.correction.top_p, 0.9);
         assert_eq!(config.notes_generation.temperature, 0.5);
         assert_eq!(config.notes_generation.top_p, 0.9);
     }
+
+    #[test]
+    fn test_frontmatter_names_must_not_collide() {
+        let frontmatter =
+            |toml_str: &str| -> FrontmatterConfig { toml::from_str(toml_str).unwrap() };
+        assert!(
+            frontmatter("rename.date = \"created\"\nproperties.type = \"voice\"")
+                .validate()
+                .is_ok()
+        );
+        // Swapping two names is fine
+        assert!(
+            frontmatter("rename.date = \"source\"\nrename.source = \"date\"")
+                .validate()
+                .is_ok()
+        );
+        assert!(frontmatter("rename.title = \"date\"").validate().is_err());
+        assert!(frontmatter("rename.titel = \"name\"").validate().is_err());
+        assert!(frontmatter("properties.tags = \"voice\"")
+            .validate()
+            .is_err());
+        assert!(
+            frontmatter("rename.date = \"created\"\nproperties.created = \"x\"")
+                .validate()
+                .is_err()
+        );
+        // The built-in name is free once renamed
+        assert!(
+            frontmatter("rename.source = \"origin\"\nproperties.source = \"voice\"")
+                .validate()
+                .is_ok()
+        );
+    }
 }
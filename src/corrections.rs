@@ -0,0 +1,202 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Serializes updates of the dictionary within the process.
+static DICTIONARY_LOCK: Mutex<()> = Mutex::new(());
+
+/// Longest phrase (in words) on either side of a learned fix; longer
+/// rewrites are rephrasing rather than mis-heard words.
+const MAX_PHRASE_WORDS: usize = 3;
+/// Transcripts longer than this are not compared word by word.
+const MAX_DIFF_WORDS: usize = 2000;
+
+/// A phrase the corrector replaced, and how often it did so.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KnownFix {
+    /// The phrase as transcribed, lowercase and without punctuation.
+    pub raw: String,
+    pub corrected: String,
+    pub count: u32,
+}
+
+/// Corrections seen across memos, kept in `<data_dir>/corrections.json`.
+///
+/// Recurring ones (a colleague's name Whisper always mishears) are given to
+/// the corrector as known fixes, so they are fixed the same way every time.
+pub struct CorrectionDictionary {
+    path: PathBuf,
+}
+
+impl CorrectionDictionary {
+    pub fn new<P: AsRef<Path>>(data_dir: P) -> Self {
+        Self {
+            path: data_dir.as_ref().join("corrections.json"),
+        }
+    }
+
+    fn load(&self) -> Result<Vec<KnownFix>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", self.path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", self.path.display())),
+        }
+    }
+
+    /// Fixes seen at least `min_count` times, the most frequent `limit` first.
+    pub fn known_fixes(&self, min_count: u32, limit: usize) -> Result<Vec<KnownFix>> {
+        let mut fixes: Vec<KnownFix> = self
+            .load()?
+            .into_iter()
+            .filter(|fix| fix.count >= min_count)
+            .collect();
+        fixes.sort_by_key(|fix| std::cmp::Reverse(fix.count));
+        fixes.truncate(limit);
+        Ok(fixes)
+    }
+
+    /// Record the phrases changed between `raw` and `corrected`. Returns how
+    /// many were found.
+    pub fn learn(&self, raw: &str, corrected: &str) -> Result<usize> {
+        let pairs = changed_phrases(raw, corrected);
+        if pairs.is_empty() {
+            return Ok(0);
+        }
+
+        let _guard = DICTIONARY_LOCK.lock().unwrap();
+        let mut fixes = self.load()?;
+        for (raw, corrected) in &pairs {
+            match fixes
+                .iter_mut()
+                .find(|fix| &fix.raw == raw && &fix.corrected == corrected)
+            {
+                Some(fix) => fix.count += 1,
+                None => fixes.push(KnownFix {
+                    raw: raw.clone(),
+                    corrected: corrected.clone(),
+                    count: 1,
+                }),
+            }
+        }
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create data directory")?;
+        }
+        let content =
+            serde_json::to_string_pretty(&fixes).context("Failed to serialize corrections")?;
+        std::fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        Ok(pairs.len())
+    }
+}
+
+/// Comparison form of a word: lowercase, without surrounding punctuation.
+fn word_key(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+/// Short phrases of `raw` replaced by different words in `corrected`, as
+/// `(raw key, corrected phrase)` pairs. Changes of case or punctuation only,
+/// insertions and deletions are ignored.
+fn changed_phrases(raw: &str, corrected: &str) -> Vec<(String, String)> {
+    let a: Vec<&str> = raw.split_whitespace().collect();
+    let b: Vec<&str> = corrected.split_whitespace().collect();
+    if a.len() > MAX_DIFF_WORDS || b.len() > MAX_DIFF_WORDS {
+        return Vec::new();
+    }
+    let ka: Vec<String> = a.iter().map(|w| word_key(w)).collect();
+    let kb: Vec<String> = b.iter().map(|w| word_key(w)).collect();
+
+    // Longest common subsequence of the word keys, filled from the end
+    let mut lcs = vec![vec![0u16; kb.len() + 1]; ka.len() + 1];
+    for i in (0..ka.len()).rev() {
+        for j in (0..kb.len()).rev() {
+            lcs[i][j] = if ka[i] == kb[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut gap_a, mut gap_b) = (0, 0);
+    let mut flush = |i: usize, j: usize, gap_a: usize, gap_b: usize| {
+        if gap_a == 0 || gap_b == 0 || gap_a > MAX_PHRASE_WORDS || gap_b > MAX_PHRASE_WORDS {
+            return;
+        }
+        let raw_key = ka[i - gap_a..i].join(" ");
+        let phrase = b[j - gap_b..j]
+            .iter()
+            .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !raw_key.trim().is_empty() && raw_key != phrase.to_lowercase() {
+            pairs.push((raw_key, phrase));
+        }
+    };
+    while i < ka.len() || j < kb.len() {
+        if i < ka.len() && j < kb.len() && ka[i] == kb[j] {
+            flush(i, j, gap_a, gap_b);
+            (gap_a, gap_b) = (0, 0);
+            i += 1;
+            j += 1;
+        } else if j < kb.len() && (i == ka.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            gap_b += 1;
+            j += 1;
+        } else {
+            gap_a += 1;
+            i += 1;
+        }
+    }
+    flush(i, j, gap_a, gap_b);
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learns_recurring_fixes() {
+        assert_eq!(
+            changed_phrases(
+                "ho sentito marco della akme corporation ieri",
+                "Ho sentito Marco, della Acme Corporation, ieri."
+            ),
+            vec![("akme".to_string(), "Acme".to_string())]
+        );
+        assert_eq!(
+            changed_phrases(
+                "chiama giulia rossi domani",
+                "Chiama Giulia Russo domani, e basta"
+            ),
+            vec![("rossi".to_string(), "Russo".to_string())]
+        );
+
+        let dir = std::env::temp_dir().join(format!("dot-corrections-{}", uuid::Uuid::new_v4()));
+        let dictionary = CorrectionDictionary::new(&dir);
+        assert!(dictionary.known_fixes(1, 10).unwrap().is_empty());
+        dictionary
+            .learn("riunione con akme", "Riunione con Acme.")
+            .unwrap();
+        assert!(dictionary.known_fixes(2, 10).unwrap().is_empty());
+        dictionary
+            .learn("la akme ha chiamato", "La Acme ha chiamato.")
+            .unwrap();
+        assert_eq!(
+            dictionary.known_fixes(2, 10).unwrap(),
+            vec![KnownFix {
+                raw: "akme".to_string(),
+                corrected: "Acme".to_string(),
+                count: 2,
+            }]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
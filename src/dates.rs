@@ -0,0 +1,224 @@
+use chrono::{DateTime, Datelike, Days, NaiveDate, Utc, Weekday};
+use chrono_tz::Tz;
+
+/// Marker of a due date in the Obsidian Tasks plugin format.
+const DUE_MARKER: &str = "📅";
+
+/// Resolves relative date phrases ("domani", "venerdì prossimo",
+/// "next Tuesday") into calendar dates.
+///
+/// Phrases are resolved against the day the memo was sent in the configured
+/// timezone, so "domani" said at 23:30 in Rome is not off by one when the
+/// server runs in UTC. Italian and English phrases are both recognized.
+#[derive(Debug, Clone, Copy)]
+pub struct DateResolver {
+    timezone: Tz,
+}
+
+impl DateResolver {
+    pub fn new(timezone: Tz) -> Self {
+        Self { timezone }
+    }
+
+    /// Calendar day of `at` in the configured timezone.
+    pub fn local_date(&self, at: DateTime<Utc>) -> NaiveDate {
+        at.with_timezone(&self.timezone).date_naive()
+    }
+
+    /// Append an Obsidian Tasks due date (`📅 YYYY-MM-DD`) to every open
+    /// checkbox line whose text contains a date phrase and has no due date yet.
+    pub fn annotate_tasks(&self, content: &str, at: DateTime<Utc>) -> String {
+        let today = self.local_date(at);
+        let mut annotated: Vec<String> = Vec::new();
+
+        for line in content.lines() {
+            let is_open_task = line.trim_start().starts_with("- [ ]");
+            match resolve_phrase(line, today) {
+                Some(date) if is_open_task && !line.contains(DUE_MARKER) => {
+                    annotated.push(format!(
+                        "{} {} {}",
+                        line.trim_end(),
+                        DUE_MARKER,
+                        date.format("%Y-%m-%d")
+                    ))
+                }
+                _ => annotated.push(line.to_string()),
+            }
+        }
+
+        let mut result = annotated.join("\n");
+        if content.ends_with('\n') {
+            result.push('\n');
+        }
+        result
+    }
+}
+
+/// Find the first relative date phrase in `text` and resolve it from `today`.
+///
+/// Weekdays ("venerdì", "next Tuesday") resolve to their next occurrence
+/// after today; "next week" / "la prossima settimana" to the next Monday.
+pub fn resolve_phrase(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let normalized = fold_accents(&text.to_lowercase());
+    let words: Vec<&str> = normalized
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    for i in 0..words.len() {
+        let rest = &words[i..];
+        let next = |n: usize| rest.get(n).copied().unwrap_or("");
+
+        // Multi-word phrases first, so "dopo domani" is not read as "domani"
+        match (next(0), next(1), next(2)) {
+            ("dopo", "domani", _) => return add_days(today, 2),
+            ("day", "after", "tomorrow") => return add_days(today, 2),
+            ("prossima", "settimana", _) | ("settimana", "prossima", _) | ("next", "week", _) => {
+                return Some(next_weekday(today, Weekday::Mon));
+            }
+            ("tra" | "fra" | "in", amount, unit) => {
+                if let (Some(n), Some(days_per_unit)) = (parse_number(amount), unit_days(unit)) {
+                    return add_days(today, n * days_per_unit);
+                }
+            }
+            _ => {}
+        }
+
+        match next(0) {
+            "oggi" | "today" | "stasera" | "tonight" => return Some(today),
+            "domani" | "tomorrow" => return add_days(today, 1),
+            "dopodomani" => return add_days(today, 2),
+            word => {
+                if let Some(weekday) = parse_weekday(word) {
+                    return Some(next_weekday(today, weekday));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Replace accented vowels so "venerdì" and "venerdi" match alike.
+fn fold_accents(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'à' | 'á' => 'a',
+            'è' | 'é' => 'e',
+            'ì' | 'í' => 'i',
+            'ò' | 'ó' => 'o',
+            'ù' | 'ú' => 'u',
+            other => other,
+        })
+        .collect()
+}
+
+fn add_days(date: NaiveDate, days: u64) -> Option<NaiveDate> {
+    date.checked_add_days(Days::new(days))
+}
+
+/// Next occurrence of `weekday` strictly after `today`.
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 { 7 } else { ahead };
+    today + Days::new(u64::from(ahead))
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    Some(match word {
+        "lunedi" | "monday" => Weekday::Mon,
+        "martedi" | "tuesday" => Weekday::Tue,
+        "mercoledi" | "wednesday" => Weekday::Wed,
+        "giovedi" | "thursday" => Weekday::Thu,
+        "venerdi" | "friday" => Weekday::Fri,
+        "sabato" | "saturday" => Weekday::Sat,
+        "domenica" | "sunday" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+fn parse_number(word: &str) -> Option<u64> {
+    if let Ok(n) = word.parse() {
+        return Some(n);
+    }
+    Some(match word {
+        "un" | "uno" | "una" | "a" | "one" => 1,
+        "due" | "two" => 2,
+        "tre" | "three" => 3,
+        "quattro" | "four" => 4,
+        "cinque" | "five" => 5,
+        "sei" | "six" => 6,
+        "sette" | "seven" => 7,
+        "otto" | "eight" => 8,
+        "nove" | "nine" => 9,
+        "dieci" | "ten" => 10,
+        _ => return None,
+    })
+}
+
+fn unit_days(word: &str) -> Option<u64> {
+    match word {
+        "giorno" | "giorni" | "day" | "days" => Some(1),
+        "settimana" | "settimane" | "week" | "weeks" => Some(7),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // Wednesday
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, 15).unwrap()
+    }
+
+    fn date(d: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(2024, 5, d)
+    }
+
+    #[test]
+    fn test_italian_phrases() {
+        assert_eq!(resolve_phrase("Chiamare Marco domani", today()), date(16));
+        assert_eq!(resolve_phrase("pagare dopodomani", today()), date(17));
+        assert_eq!(resolve_phrase("pagare dopo domani", today()), date(17));
+        assert_eq!(
+            resolve_phrase("consegna venerdì prossimo", today()),
+            date(17)
+        );
+        assert_eq!(resolve_phrase("riunione mercoledì", today()), date(22));
+        assert_eq!(resolve_phrase("tra tre giorni", today()), date(18));
+        assert_eq!(resolve_phrase("fra 2 settimane", today()), date(29));
+        assert_eq!(resolve_phrase("la prossima settimana", today()), date(20));
+        assert_eq!(resolve_phrase("comprare il latte", today()), None);
+    }
+
+    #[test]
+    fn test_english_phrases() {
+        assert_eq!(resolve_phrase("Call Marco tomorrow", today()), date(16));
+        assert_eq!(resolve_phrase("the day after tomorrow", today()), date(17));
+        assert_eq!(resolve_phrase("ship it next Tuesday", today()), date(21));
+        assert_eq!(resolve_phrase("in 5 days", today()), date(20));
+        assert_eq!(resolve_phrase("review next week", today()), date(20));
+        assert_eq!(resolve_phrase("buy milk", today()), None);
+    }
+
+    #[test]
+    fn test_annotate_tasks_uses_local_day() {
+        // 23:30 UTC on Wednesday is already Thursday in Rome
+        let at = Utc.with_ymd_and_hms(2024, 5, 15, 23, 30, 0).unwrap();
+        let resolver = DateResolver::new(chrono_tz::Europe::Rome);
+
+        let content = "## Da fare\n- [ ] Chiamare Marco domani\n- [ ] Pagare 📅 2024-06-01 domani\n- [x] Fatto oggi\nDomani piove\n";
+        let annotated = resolver.annotate_tasks(content, at);
+
+        assert_eq!(
+            annotated,
+            "## Da fare\n- [ ] Chiamare Marco domani 📅 2024-05-17\n- [ ] Pagare 📅 2024-06-01 domani\n- [x] Fatto oggi\nDomani piove\n"
+        );
+        assert_eq!(
+            DateResolver::new(Tz::UTC).annotate_tasks("- [ ] domani", at),
+            "- [ ] domani 📅 2024-05-16"
+        );
+    }
+}
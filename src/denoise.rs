@@ -0,0 +1,117 @@
+use nnnoiseless::DenoiseState;
+
+use crate::config::ResampleQuality;
+
+/// Sample rate RNNoise works at.
+const RNNOISE_RATE: u32 = 48000;
+/// RNNoise expects samples in the 16-bit integer range.
+const I16_SCALE: f32 = i16::MAX as f32;
+
+/// Suppress background noise (wind, traffic, hum) with RNNoise.
+///
+/// The recording is brought to 48 kHz if needed and denoised frame by frame,
+/// so memory grows with the output only, whatever the memo's length.
+pub fn denoise(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    if samples.is_empty() {
+        return Vec::new();
+    }
+    let resample = |samples: &[f32], from, to| {
+        crate::audio::resample_audio(samples, from, to, ResampleQuality::High)
+    };
+    let input = match resample(samples, sample_rate, RNNOISE_RATE) {
+        Ok(input) => input,
+        Err(e) => {
+            log::warn!(
+                "Denoise: resampling failed, skipping noise suppression: {:#}",
+                e
+            );
+            return samples.to_vec();
+        }
+    };
+
+    let denoised = denoise_48k(&input);
+    match resample(&denoised, RNNOISE_RATE, sample_rate) {
+        Ok(mut output) => {
+            output.resize(samples.len(), 0.0);
+            output
+        }
+        Err(e) => {
+            log::warn!(
+                "Denoise: resampling failed, skipping noise suppression: {:#}",
+                e
+            );
+            samples.to_vec()
+        }
+    }
+}
+
+/// Run RNNoise over a 48 kHz signal, compensating its one-frame delay.
+fn denoise_48k(samples: &[f32]) -> Vec<f32> {
+    let frame = DenoiseState::FRAME_SIZE;
+    let mut state = DenoiseState::new();
+    let mut output = Vec::with_capacity(samples.len() + frame);
+    let mut input = [0.0; DenoiseState::FRAME_SIZE];
+    let mut denoised = [0.0; DenoiseState::FRAME_SIZE];
+
+    // One frame of silence at the end flushes the delayed last samples
+    let frames = samples.len().div_ceil(frame) + 1;
+    for n in 0..frames {
+        let start = (n * frame).min(samples.len());
+        let chunk = &samples[start..(start + frame).min(samples.len())];
+        input.fill(0.0);
+        for (to, from) in input.iter_mut().zip(chunk) {
+            *to = from * I16_SCALE;
+        }
+        state.process_frame(&mut denoised, &input);
+        output.extend(denoised.iter().map(|s| s / I16_SCALE));
+    }
+
+    output.drain(..frame);
+    output.truncate(samples.len());
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_denoise_reduces_noise_and_keeps_speech() {
+        let rate = 16000;
+        // A tone with pauses (like speech), buried in deterministic white noise
+        let speaking = |i: usize| (i / 4000) % 2 == 1;
+        let clean: Vec<f32> = (0..rate * 4)
+            .map(|i| {
+                let t = i as f32 / rate as f32;
+                if speaking(i) {
+                    (t * 300.0 * std::f32::consts::TAU).sin() * 0.4
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let mut seed = 1u32;
+        let noisy: Vec<f32> = clean
+            .iter()
+            .map(|s| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                s + (seed as f32 / u32::MAX as f32 - 0.5) * 0.1
+            })
+            .collect();
+
+        let denoised = denoise(&noisy, rate as u32);
+        assert_eq!(denoised.len(), noisy.len());
+
+        // Energy in the pauses (noise only) and while speaking, skipping the
+        // first second while the model adapts and the 30 ms around each
+        // onset, which RNNoise's 10 ms frames smear
+        let energy = |signal: &[f32], in_speech: bool| -> f32 {
+            (rate..signal.len())
+                .filter(|&i| speaking(i) == in_speech && (i + 480) % 4000 >= 960)
+                .map(|i| signal[i].powi(2))
+                .sum()
+        };
+        assert!(energy(&denoised, false) < energy(&noisy, false) / 4.0);
+        assert!(energy(&denoised, true) > energy(&clean, true) / 4.0);
+    }
+}
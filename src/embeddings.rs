@@ -0,0 +1,191 @@
+//! Semantic retrieval of the existing notes related to a memo, with an
+//! Ollama embedding model (`notes_generation.embedding_model`).
+//!
+//! Note embeddings are kept in `<data_dir>/embeddings.json` next to the text
+//! they were computed from, so only new and changed notes are embedded again.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::ollama::OllamaProvider;
+use crate::tools::NoteMeta;
+
+/// An embedded note and the text the vector was computed from.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    text: String,
+    vector: Vec<f32>,
+}
+
+/// `<data_dir>/embeddings.json`: the vectors of one model, by note filename.
+#[derive(Default, Serialize, Deserialize)]
+struct Store {
+    model: String,
+    notes: HashMap<String, Entry>,
+}
+
+/// Embedding index of the vault's notes.
+pub struct EmbeddingIndex {
+    ollama: OllamaProvider,
+    model: String,
+    path: PathBuf,
+    top_k: usize,
+}
+
+impl EmbeddingIndex {
+    /// The index of `config`, if an embedding model is configured. It is
+    /// served by `embedding_endpoint`, or by the Ollama of `ai_model`.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let generation = &config.notes_generation;
+        let model = generation.embedding_model.clone()?;
+        let endpoint = generation
+            .embedding_endpoint
+            .clone()
+            .unwrap_or_else(|| config.ai_model.endpoint.clone());
+        Some(Self {
+            ollama: OllamaProvider::new(endpoint, model.clone()).with_retry(config.ai_model.retry),
+            model,
+            path: Path::new(&config.output.data_dir).join("embeddings.json"),
+            top_k: generation.embedding_top_k,
+        })
+    }
+
+    /// The `top_k` notes most similar to `transcript`, most similar first.
+    pub async fn most_similar(
+        &self,
+        notes: &[NoteMeta],
+        transcript: &str,
+    ) -> Result<Vec<NoteMeta>> {
+        let mut store = self.load();
+        if store.model != self.model {
+            store = Store {
+                model: self.model.clone(),
+                notes: HashMap::new(),
+            };
+        }
+
+        let mut embedded = 0;
+        let mut vectors = HashMap::with_capacity(notes.len());
+        for note in notes {
+            if vectors.contains_key(&note.filename) {
+                continue;
+            }
+            let text = embedding_text(note);
+            let entry = match store.notes.remove(&note.filename) {
+                Some(entry) if entry.text == text => entry,
+                _ => {
+                    embedded += 1;
+                    let vector = self.ollama.embed(&text).await?;
+                    Entry { text, vector }
+                }
+            };
+            vectors.insert(note.filename.clone(), entry);
+        }
+        // Notes gone from the vault drop out of the store here
+        store.notes = vectors;
+        if embedded > 0 {
+            log::info!("Agent: embedded {} new or changed note(s)", embedded);
+            if let Err(e) = self.save(&store) {
+                log::warn!("Agent: failed to store note embeddings: {}", e);
+            }
+        }
+
+        let query = self.ollama.embed(transcript).await?;
+        let mut scored: Vec<(f32, &NoteMeta)> = notes
+            .iter()
+            .map(|note| {
+                let vector = &store.notes[&note.filename].vector;
+                (cosine_similarity(&query, vector), note)
+            })
+            .collect();
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        Ok(scored
+            .into_iter()
+            .take(self.top_k)
+            .map(|(_, note)| note.clone())
+            .collect())
+    }
+
+    /// The stored embeddings; an unreadable store is rebuilt.
+    fn load(&self) -> Store {
+        let Ok(content) = std::fs::read_to_string(&self.path) else {
+            return Store::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!(
+                "Agent: invalid {}, rebuilding it: {}",
+                self.path.display(),
+                e
+            );
+            Store::default()
+        })
+    }
+
+    fn save(&self, store: &Store) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create data directory")?;
+        }
+        let content = serde_json::to_string(store).context("Failed to serialize embeddings")?;
+        std::fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// What a note is embedded from: its title, aliases and tags.
+fn embedding_text(note: &NoteMeta) -> String {
+    let mut text = note.title.clone();
+    if !note.aliases.is_empty() {
+        text.push_str(&format!(" ({})", note.aliases.join(", ")));
+    }
+    if !note.tags.is_empty() {
+        text.push_str(&format!(" [{}]", note.tags.join(", ")));
+    }
+    text
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity_and_embedding_text() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]).abs() < 1e-6);
+        assert!(
+            cosine_similarity(&[1.0, 1.0], &[1.0, 0.9])
+                > cosine_similarity(&[1.0, 1.0], &[1.0, -0.5])
+        );
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+
+        let note = NoteMeta {
+            title: "Kubernetes".to_string(),
+            date: String::new(),
+            tags: vec!["devops".to_string()],
+            filename: "Kubernetes.md".to_string(),
+            source: String::new(),
+            id: None,
+            aliases: vec!["K8s".to_string()],
+            links: Vec::new(),
+            folder: String::new(),
+        };
+        assert_eq!(embedding_text(&note), "Kubernetes (K8s) [devops]");
+    }
+}
@@ -0,0 +1,152 @@
+use crate::ai_provider::AiTimeout;
+use crate::i18n::{Language, Msg};
+use crate::tools::PreflightError;
+
+/// Failure of one stage of the voice-memo pipeline.
+///
+/// Stages keep `anyhow` internally for context; this enum is the boundary the
+/// handlers see, so each failure maps to a stable code for logs/metrics and a
+/// user-facing message with a concrete suggestion.
+#[derive(Debug, thiserror::Error)]
+pub enum PipelineError {
+    #[error("download failed: {0:#}")]
+    Download(anyhow::Error),
+    #[error("audio conversion failed: {0:#}")]
+    Convert(anyhow::Error),
+    #[error("transcription with '{provider}' failed: {source:#}")]
+    Transcribe {
+        provider: String,
+        source: anyhow::Error,
+    },
+    #[error("AI backend failed: {0:#}")]
+    Llm(anyhow::Error),
+    #[error("saving notes failed: {0:#}")]
+    Save(anyhow::Error),
+}
+
+impl PipelineError {
+    /// Classify an error returned by a transcription provider.
+    ///
+    /// Providers may already tag a failure (e.g. `Convert`); anything else is
+    /// attributed to the provider itself.
+    pub fn from_transcription(error: anyhow::Error, provider: &str) -> Self {
+        match error.downcast::<PipelineError>() {
+            Ok(tagged) => tagged,
+            Err(source) => PipelineError::Transcribe {
+                provider: provider.to_string(),
+                source,
+            },
+        }
+    }
+
+    /// Stable machine-readable code, used in logs and shown to the user.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PipelineError::Download(_) => "E_DOWNLOAD",
+            PipelineError::Convert(_) => "E_CONVERT",
+            PipelineError::Transcribe { .. } => "E_TRANSCRIBE",
+            PipelineError::Llm(_) if self.is_timeout() => "E_LLM_TIMEOUT",
+            PipelineError::Llm(_) => "E_LLM",
+            PipelineError::Save(_) => "E_SAVE",
+        }
+    }
+
+    /// Whether the AI backend didn't answer in time (`ai_model.timeout_secs`).
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            PipelineError::Llm(source) => source.chain().any(|e| e.is::<AiTimeout>()),
+            _ => false,
+        }
+    }
+
+    /// Whether the failure is operational and worth an admin alert.
+    pub fn needs_admin(&self) -> bool {
+        matches!(self, PipelineError::Save(_))
+    }
+
+    /// Localized message with a suggestion for the user.
+    pub fn user_message(&self, language: Language) -> String {
+        let (title, hint) = match self {
+            PipelineError::Download(_) => (Msg::ErrorDownload, Msg::ErrorDownloadHint),
+            PipelineError::Convert(_) => (Msg::ErrorConvert, Msg::ErrorConvertHint),
+            PipelineError::Transcribe { .. } => (Msg::ErrorTranscribe, Msg::ErrorTranscribeHint),
+            PipelineError::Llm(_) if self.is_timeout() => {
+                (Msg::ErrorLlmTimeout, Msg::ErrorLlmTimeoutHint)
+            }
+            PipelineError::Llm(_) => (Msg::ErrorLlm, Msg::ErrorLlmHint),
+            PipelineError::Save(source) => (
+                Msg::ErrorSave,
+                match source.downcast_ref::<PreflightError>() {
+                    Some(PreflightError::InsufficientSpace { .. }) => Msg::ErrorSaveSpaceHint,
+                    _ => Msg::ErrorSavePermissionsHint,
+                },
+            ),
+        };
+        let provider = match self {
+            PipelineError::Transcribe { provider, .. } => provider.as_str(),
+            _ => "",
+        };
+
+        Msg::ErrorMessage.fill(
+            language,
+            &[
+                ("title", &title.text(language)),
+                ("details", self),
+                ("hint", &hint.fill(language, &[("provider", &provider)])),
+                ("code", &self.code()),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_transcription_keeps_tagged_stage() {
+        let tagged = anyhow::Error::new(PipelineError::Convert(anyhow::anyhow!("bad codec")));
+        let err = PipelineError::from_transcription(tagged, "whisper_local");
+        assert_eq!(err.code(), "E_CONVERT");
+
+        let untagged = anyhow::anyhow!("HTTP 500");
+        let err = PipelineError::from_transcription(untagged, "groq");
+        assert_eq!(err.code(), "E_TRANSCRIBE");
+        assert!(err.user_message(Language::It).contains("'groq'"));
+    }
+
+    #[test]
+    fn test_llm_timeout_has_its_own_message() {
+        let timeout = anyhow::Error::new(AiTimeout {
+            service: "Ollama at http://localhost:11434".to_string(),
+            secs: 300,
+        })
+        .context("Agent: LLM note generation failed");
+        let err = PipelineError::Llm(timeout);
+        assert_eq!(err.code(), "E_LLM_TIMEOUT");
+        assert!(err
+            .user_message(Language::It)
+            .contains("non ha risposto in tempo"));
+        assert!(err
+            .user_message(Language::En)
+            .contains("didn't answer in time"));
+        assert_eq!(
+            PipelineError::Llm(anyhow::anyhow!("HTTP 500")).code(),
+            "E_LLM"
+        );
+    }
+
+    #[test]
+    fn test_save_error_hint_for_disk_space() {
+        let err = PipelineError::Save(
+            PreflightError::InsufficientSpace {
+                dir: "./notes".to_string(),
+                available_mb: 0,
+            }
+            .into(),
+        );
+        assert!(err.needs_admin());
+        assert!(err.user_message(Language::It).contains("Libera spazio"));
+        assert!(err.user_message(Language::It).contains("E_SAVE"));
+    }
+}
@@ -0,0 +1,238 @@
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::feedback::{FeedbackStore, Rating, ResultSnapshot};
+use crate::note_generator::NoteGeneratorAgent;
+
+/// Options for the `eval` subcommand.
+#[derive(Debug, Default)]
+pub struct EvalOptions {
+    /// Feedback file to replay (defaults to `<data_dir>/feedback.jsonl`).
+    pub feedback_path: Option<PathBuf>,
+    /// Only replay memos that were rated 👎.
+    pub only_negative: bool,
+    /// Stop after this many memos.
+    pub limit: Option<usize>,
+}
+
+impl EvalOptions {
+    /// Parse `eval [--only-negative] [--limit N] [FEEDBACK_FILE]`.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut options = EvalOptions::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--only-negative" => options.only_negative = true,
+                "--limit" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("--limit requires a value"))?;
+                    options.limit = Some(value.parse()?);
+                }
+                other if other.starts_with("--") => anyhow::bail!("Unknown eval option: {}", other),
+                path => options.feedback_path = Some(PathBuf::from(path)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Differences between the notes saved at the time and a fresh generation.
+#[derive(Debug, Default, PartialEq)]
+pub struct EvalDiff {
+    pub notes_before: usize,
+    pub notes_after: usize,
+    pub titles_removed: Vec<String>,
+    pub titles_added: Vec<String>,
+    pub tags_removed: Vec<String>,
+    pub tags_added: Vec<String>,
+    pub links_before: usize,
+    pub links_after: usize,
+}
+
+impl EvalDiff {
+    pub fn compute(before: &ResultSnapshot, after: &ResultSnapshot) -> Self {
+        let titles = |s: &ResultSnapshot| -> BTreeSet<String> {
+            s.notes.iter().map(|n| n.title.clone()).collect()
+        };
+        let tags = |s: &ResultSnapshot| -> BTreeSet<String> {
+            s.notes
+                .iter()
+                .flat_map(|n| n.tags.iter().cloned())
+                .collect()
+        };
+        let links = |s: &ResultSnapshot| -> usize { s.notes.iter().map(|n| n.link_count).sum() };
+
+        let (titles_before, titles_after) = (titles(before), titles(after));
+        let (tags_before, tags_after) = (tags(before), tags(after));
+
+        Self {
+            notes_before: before.notes.len(),
+            notes_after: after.notes.len(),
+            titles_removed: titles_before.difference(&titles_after).cloned().collect(),
+            titles_added: titles_after.difference(&titles_before).cloned().collect(),
+            tags_removed: tags_before.difference(&tags_after).cloned().collect(),
+            tags_added: tags_after.difference(&tags_before).cloned().collect(),
+            links_before: links(before),
+            links_after: links(after),
+        }
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        self.notes_before == self.notes_after
+            && self.titles_removed.is_empty()
+            && self.titles_added.is_empty()
+            && self.tags_removed.is_empty()
+            && self.tags_added.is_empty()
+            && self.links_before == self.links_after
+    }
+}
+
+/// Replay stored transcripts through the current prompts and print how the
+/// generated notes differ from what was saved at the time. Nothing is written
+/// to the vault.
+pub async fn run(config: &Config, options: EvalOptions) -> Result<()> {
+    let path = options.feedback_path.clone().unwrap_or_else(|| {
+        FeedbackStore::new(&config.output.data_dir)
+            .path()
+            .to_path_buf()
+    });
+
+    let mut records = FeedbackStore::load_records(&path)?;
+    if options.only_negative {
+        records.retain(|r| r.rating == Rating::Down);
+    }
+    if let Some(limit) = options.limit {
+        records.truncate(limit);
+    }
+
+    println!(
+        "📊 Evaluating {} memo(s) from {}\n",
+        records.len(),
+        path.display()
+    );
+
+    let agent = NoteGeneratorAgent::new(config)?;
+    let (mut changed, mut failed) = (0, 0);
+    let (mut links_before, mut links_after) = (0, 0);
+
+    for (i, record) in records.iter().enumerate() {
+        let rating = match record.rating {
+            Rating::Up => "👍",
+            Rating::Down => "👎",
+        };
+        print!(
+            "#{} {} chat {} msg {}",
+            i + 1,
+            rating,
+            record.chat_id,
+            record.message_id
+        );
+        if let Some(comment) = &record.comment {
+            print!(" — \"{}\"", comment);
+        }
+        println!();
+
+        let result = match agent
+            .generate(record.result.raw_transcript.clone(), &[])
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                println!("   ❌ [{}] {}\n", e.code(), e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        let diff = EvalDiff::compute(&record.result, &ResultSnapshot::from_agent_result(&result));
+        links_before += diff.links_before;
+        links_after += diff.links_after;
+
+        if diff.is_unchanged() {
+            println!("   = unchanged\n");
+            continue;
+        }
+        changed += 1;
+
+        println!("   notes: {} → {}", diff.notes_before, diff.notes_after);
+        for title in &diff.titles_removed {
+            println!("   - {}", title);
+        }
+        for title in &diff.titles_added {
+            println!("   + {}", title);
+        }
+        if !diff.tags_removed.is_empty() || !diff.tags_added.is_empty() {
+            println!(
+                "   tags: -[{}] +[{}]",
+                diff.tags_removed.join(", "),
+                diff.tags_added.join(", ")
+            );
+        }
+        println!("   links: {} → {}\n", diff.links_before, diff.links_after);
+    }
+
+    println!(
+        "Summary: {} memo(s), {} changed, {} failed, links {} → {}",
+        records.len(),
+        changed,
+        failed,
+        links_before,
+        links_after
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feedback::NoteSummary;
+
+    fn snapshot(notes: &[(&str, &[&str], usize)]) -> ResultSnapshot {
+        ResultSnapshot {
+            raw_transcript: String::new(),
+            cleaned_transcript: String::new(),
+            notes: notes
+                .iter()
+                .map(|(title, tags, links)| NoteSummary {
+                    title: title.to_string(),
+                    tags: tags.iter().map(|t| t.to_string()).collect(),
+                    filename: format!("{}.md", title),
+                    link_count: *links,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_eval_diff_reports_titles_tags_and_links() {
+        let before = snapshot(&[("Rust", &["rust"], 1), ("Idee", &["misc"], 0)]);
+        let after = snapshot(&[("Rust", &["rust", "coding"], 3)]);
+
+        let diff = EvalDiff::compute(&before, &after);
+        assert_eq!(diff.notes_before, 2);
+        assert_eq!(diff.notes_after, 1);
+        assert_eq!(diff.titles_removed, vec!["Idee"]);
+        assert!(diff.titles_added.is_empty());
+        assert_eq!(diff.tags_removed, vec!["misc"]);
+        assert_eq!(diff.tags_added, vec!["coding"]);
+        assert_eq!((diff.links_before, diff.links_after), (1, 3));
+        assert!(!diff.is_unchanged());
+        assert!(EvalDiff::compute(&before, &before).is_unchanged());
+    }
+
+    #[test]
+    fn test_eval_options_parse() {
+        let args: Vec<String> = ["--only-negative", "--limit", "5", "fb.jsonl"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let options = EvalOptions::parse(&args).unwrap();
+        assert!(options.only_negative);
+        assert_eq!(options.limit, Some(5));
+        assert_eq!(options.feedback_path, Some(PathBuf::from("fb.jsonl")));
+        assert!(EvalOptions::parse(&["--bogus".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::atomic_write::write_atomic;
+use crate::note_generator::Note;
+
+const TABLE_HEADER: &str =
+    "| Data | Importo | Valuta | Esercente | Descrizione | Nota |\n|---|---:|---|---|---|---|\n";
+const CSV_HEADER: &str = "date,amount,currency,merchant,description,note\n";
+
+/// A payment mentioned in a memo.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct Expense {
+    pub amount: f64,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub merchant: Option<String>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl Expense {
+    /// Normalize an expense from the model, dropping it if the amount is not
+    /// a positive number. Unknown currencies become `default_currency`.
+    pub fn sanitize(self, default_currency: &str) -> Option<Expense> {
+        if !self.amount.is_finite() || self.amount <= 0.0 {
+            return None;
+        }
+        let currency = self
+            .currency
+            .map(|c| c.trim().to_uppercase())
+            .filter(|c| c.len() == 3 && c.chars().all(|ch| ch.is_ascii_alphabetic()))
+            .unwrap_or_else(|| default_currency.to_string());
+        let merchant = self
+            .merchant
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty());
+        Some(Expense {
+            amount: self.amount,
+            currency: Some(currency),
+            merchant,
+            description: self.description.trim().to_string(),
+        })
+    }
+}
+
+/// Append the expenses of the `notes` tagged with one of `tags` (any note,
+/// if `tags` is empty) to `path`: a Markdown table, or CSV for `.csv` files.
+///
+/// The caller must hold the vault lock.
+pub fn record(path: &Path, notes: &[Note], tags: &[String], fsync: bool) -> Result<usize> {
+    let csv = path.extension().is_some_and(|ext| ext == "csv");
+    let rows: Vec<String> = notes
+        .iter()
+        .filter(|note| tags.is_empty() || note.tags.iter().any(|t| tags.contains(t)))
+        .flat_map(|note| {
+            note.expenses.iter().map(move |expense| {
+                if csv {
+                    csv_row(note, expense)
+                } else {
+                    table_row(note, expense)
+                }
+            })
+        })
+        .collect();
+    if rows.is_empty() {
+        return Ok(0);
+    }
+
+    let mut content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).context("Failed to create expenses folder")?;
+            }
+            if csv {
+                CSV_HEADER.to_string()
+            } else {
+                format!("# Spese\n\n{}", TABLE_HEADER)
+            }
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    for row in &rows {
+        content.push_str(row);
+    }
+    write_atomic(path, &content, fsync)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    log::info!(
+        "Expenses: recorded {} row(s) in {}",
+        rows.len(),
+        path.display()
+    );
+    Ok(rows.len())
+}
+
+fn table_row(note: &Note, expense: &Expense) -> String {
+    let cell = |text: &str| text.replace('|', "\\|").replace('\n', " ");
+    format!(
+        "| {} | {:.2} | {} | {} | {} | [[{}]] |\n",
+        note.date.format("%Y-%m-%d"),
+        expense.amount,
+        expense.currency.as_deref().unwrap_or_default(),
+        cell(expense.merchant.as_deref().unwrap_or_default()),
+        cell(&expense.description),
+        note.filename_stem()
+    )
+}
+
+fn csv_row(note: &Note, expense: &Expense) -> String {
+    let field = |text: &str| {
+        if text.contains([',', '"', '\n']) {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text.to_string()
+        }
+    };
+    format!(
+        "{},{:.2},{},{},{},{}\n",
+        note.date.format("%Y-%m-%d"),
+        expense.amount,
+        expense.currency.as_deref().unwrap_or_default(),
+        field(expense.merchant.as_deref().unwrap_or_default()),
+        field(&expense.description),
+        field(&note.filename_stem())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_record_appends_tagged_expenses() {
+        let dir = std::env::temp_dir().join(format!("dot-expenses-{}", uuid::Uuid::new_v4()));
+        let expense = Expense {
+            amount: 42.0,
+            currency: Some("eur".to_string()),
+            merchant: Some(" meccanico ".to_string()),
+            description: "Cambio olio | filtro".to_string(),
+        }
+        .sanitize("EUR")
+        .unwrap();
+        assert_eq!(expense.currency.as_deref(), Some("EUR"));
+        assert_eq!(expense.merchant.as_deref(), Some("meccanico"));
+
+        let note = |title: &str, tags: &[&str]| Note {
+            title: title.to_string(),
+            content: String::new(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            date: Utc.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap(),
+            source: "voice-memo".to_string(),
+            related_notes: vec![],
+            id: None,
+            language: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![expense.clone()],
+            telegram_link: None,
+            properties: Default::default(),
+        };
+        let notes = [note("Auto", &["spese"]), note("Idea", &["lavoro"])];
+        let tags = vec!["spese".to_string()];
+
+        let table = dir.join("Finance/expenses.md");
+        assert_eq!(record(&table, &notes, &tags, false).unwrap(), 1);
+        record(&table, &notes[..1], &tags, false).unwrap();
+        let content = std::fs::read_to_string(&table).unwrap();
+        let row = "| 2024-05-01 | 42.00 | EUR | meccanico | Cambio olio \\| filtro | [[Auto]] |\n";
+        assert!(content.starts_with("# Spese\n\n| Data |"));
+        assert_eq!(content.matches(row).count(), 2);
+
+        let csv = dir.join("expenses.csv");
+        record(&csv, &notes, &[], false).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&csv).unwrap(),
+            format!(
+                "{}2024-05-01,42.00,EUR,meccanico,Cambio olio | filtro,Auto\n\
+                2024-05-01,42.00,EUR,meccanico,Cambio olio | filtro,Idea\n",
+                CSV_HEADER
+            )
+        );
+
+        assert!(Expense {
+            amount: 0.0,
+            currency: None,
+            merchant: None,
+            description: String::new(),
+        }
+        .sanitize("EUR")
+        .is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::{Context, Result};
+use pulldown_cmark::{html, Event, Options, Parser};
+
+use crate::search;
+use crate::tools::document_converter::run_external;
+
+/// Format of a shared note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Html,
+    /// HTML printed by `wkhtmltopdf`.
+    Pdf,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Html => "html",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+const STYLE: &str = "body{font-family:-apple-system,'Segoe UI',Helvetica,Arial,sans-serif;\
+max-width:46em;margin:2em auto;padding:0 1em;line-height:1.6;color:#222}\
+h1,h2,h3{line-height:1.25}code{background:#f4f4f4;padding:.1em .3em;border-radius:3px}\
+pre{background:#f4f4f4;padding:1em;overflow-x:auto}pre code{padding:0}\
+blockquote{border-left:4px solid #ddd;margin:0;padding-left:1em;color:#555}\
+table{border-collapse:collapse}td,th{border:1px solid #ddd;padding:.3em .6em}";
+
+/// Render a note file's contents for people outside the vault.
+pub fn export(title: &str, content: &str, format: ExportFormat) -> Result<Vec<u8>> {
+    let html = render_html(title, content);
+    match format {
+        ExportFormat::Html => Ok(html.into_bytes()),
+        ExportFormat::Pdf => run_external(
+            "wkhtmltopdf",
+            &["--quiet", "--disable-local-file-access", "-", "-"],
+            html.as_bytes(),
+        )
+        .context("Failed to print the note to PDF"),
+    }
+}
+
+/// A standalone HTML page with the note body: no frontmatter, wiki-links as
+/// plain text, embeds dropped and raw HTML shown as text, so a note can't
+/// run scripts or load files in the reader's browser or in `wkhtmltopdf`.
+pub fn render_html(title: &str, content: &str) -> String {
+    let markdown = unlink_wiki(search::strip_frontmatter(content));
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let mut body = String::new();
+    let events = Parser::new_ext(&markdown, options).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        event => event,
+    });
+    html::push_html(&mut body, events);
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+        <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+/// Replace `[[note]]` and `[[note|label]]` with their text and drop
+/// `![[embeds]]`, which mean nothing outside Obsidian.
+fn unlink_wiki(markdown: &str) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+    while let Some(start) = rest.find("[[") {
+        let Some(len) = rest[start..].find("]]") else {
+            break;
+        };
+        let target = &rest[start + 2..start + len];
+        let embed = rest[..start].ends_with('!');
+        out.push_str(&rest[..start - usize::from(embed)]);
+        if !embed {
+            // `[[note#heading|label]]`: prefer the label, else the note name
+            let text = match target.split_once('|') {
+                Some((_, label)) => label,
+                None => target.split('#').next().unwrap_or(target),
+            };
+            out.push_str(text.trim());
+        }
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_html_without_frontmatter_and_wiki_links() {
+        let note = "---\ntitle: \"Riunione <Q3>\"\ntags:\n  - lavoro\n---\n\n\
+            # Riunione\n\nVedi [[Budget 2024]] e [[Roadmap#Q3|la roadmap]].\n\n\
+            ![[lavagna.png]]\n\n- [ ] Inviare il verbale\n";
+        let html = render_html("Riunione <Q3>", note);
+
+        assert!(html.contains("<title>Riunione &lt;Q3&gt;</title>"));
+        assert!(!html.contains("tags:"));
+        assert!(html.contains("<h1>Riunione</h1>"));
+        assert!(html.contains("Vedi Budget 2024 e la roadmap."));
+        assert!(!html.contains("lavagna"));
+        assert!(html.contains("type=\"checkbox\""));
+    }
+
+    #[test]
+    fn test_render_html_escapes_raw_html() {
+        let note = "<script>alert(1)</script>\n\nFoto <img src=\"file:///etc/passwd\"> qui\n";
+        let html = render_html("Nota", note);
+
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!html.contains("<img"));
+        assert!(html.contains("Foto &lt;img"));
+    }
+}
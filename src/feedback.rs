@@ -0,0 +1,299 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::note_generator::AgentResult;
+
+/// How many recent result messages can still receive reactions.
+const MAX_TRACKED_RESULTS: usize = 500;
+
+/// Quick rating given by reacting to a result message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+impl Rating {
+    pub fn from_emoji(emoji: &str) -> Option<Self> {
+        match emoji {
+            "👍" => Some(Rating::Up),
+            "👎" => Some(Rating::Down),
+            _ => None,
+        }
+    }
+}
+
+/// Summary of one generated note, as stored alongside feedback.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteSummary {
+    pub title: String,
+    pub tags: Vec<String>,
+    pub filename: String,
+    /// Number of `[[wiki-links]]` in the content plus related notes.
+    pub link_count: usize,
+}
+
+/// What the bot produced for a memo, kept so a rating can be tied to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultSnapshot {
+    pub raw_transcript: String,
+    pub cleaned_transcript: String,
+    pub notes: Vec<NoteSummary>,
+}
+
+impl ResultSnapshot {
+    pub fn from_agent_result(result: &AgentResult) -> Self {
+        let notes = result
+            .notes
+            .iter()
+            .enumerate()
+            .map(|(i, note)| NoteSummary {
+                title: note.title.clone(),
+                tags: note.tags.clone(),
+                filename: result
+                    .saved_paths
+                    .get(i)
+                    .and_then(|p| p.file_name())
+                    .map(|f| f.to_string_lossy().to_string())
+                    .unwrap_or_else(|| note.generate_filename()),
+                link_count: note.content.matches("[[").count() + note.related_notes.len(),
+            })
+            .collect();
+
+        Self {
+            raw_transcript: result.raw_transcript.clone(),
+            cleaned_transcript: result.cleaned_transcript.clone(),
+            notes,
+        }
+    }
+}
+
+/// One line of the feedback store.
+///
+/// Records are append-only: a later record for the same message (e.g. the
+/// comment following a 👎) supersedes earlier ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackRecord {
+    pub timestamp: DateTime<Utc>,
+    pub chat_id: i64,
+    pub message_id: i32,
+    pub rating: Rating,
+    #[serde(default)]
+    pub comment: Option<String>,
+    pub result: ResultSnapshot,
+}
+
+/// Collects reaction feedback on result messages into `feedback.jsonl`.
+pub struct FeedbackStore {
+    path: PathBuf,
+    /// Recent result messages that can be rated, keyed by (chat, message).
+    results: Mutex<TrackedResults>,
+    /// "What was wrong?" prompts awaiting a reply: prompt message → record.
+    awaiting_comment: Mutex<HashMap<(i64, i32), FeedbackRecord>>,
+}
+
+#[derive(Default)]
+struct TrackedResults {
+    by_message: HashMap<(i64, i32), ResultSnapshot>,
+    order: VecDeque<(i64, i32)>,
+}
+
+impl FeedbackStore {
+    pub fn new<P: AsRef<Path>>(data_dir: P) -> Self {
+        Self {
+            path: data_dir.as_ref().join("feedback.jsonl"),
+            results: Mutex::new(TrackedResults::default()),
+            awaiting_comment: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Path of the append-only feedback file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the latest record for each rated message, oldest first.
+    pub fn load_records(path: &Path) -> Result<Vec<FeedbackRecord>> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read feedback store: {}", path.display()))?;
+
+        let mut latest: Vec<FeedbackRecord> = Vec::new();
+        for (n, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: FeedbackRecord = match serde_json::from_str(line) {
+                Ok(r) => r,
+                Err(e) => {
+                    log::warn!("Feedback: skipping malformed line {}: {}", n + 1, e);
+                    continue;
+                }
+            };
+            latest.retain(|r| (r.chat_id, r.message_id) != (record.chat_id, record.message_id));
+            latest.push(record);
+        }
+        Ok(latest)
+    }
+
+    /// Remember a result message so reactions to it can be recorded.
+    pub fn track_result(&self, chat_id: i64, message_id: i32, snapshot: ResultSnapshot) {
+        let mut tracked = self.results.lock().unwrap();
+        let key = (chat_id, message_id);
+        if tracked.by_message.insert(key, snapshot).is_none() {
+            tracked.order.push_back(key);
+        }
+        while tracked.order.len() > MAX_TRACKED_RESULTS {
+            if let Some(oldest) = tracked.order.pop_front() {
+                tracked.by_message.remove(&oldest);
+            }
+        }
+    }
+
+    /// The result shown in a tracked message, if it is still remembered.
+    pub fn tracked_result(&self, chat_id: i64, message_id: i32) -> Option<ResultSnapshot> {
+        self.results
+            .lock()
+            .unwrap()
+            .by_message
+            .get(&(chat_id, message_id))
+            .cloned()
+    }
+
+    /// Record a rating for a tracked result message.
+    ///
+    /// Returns the stored record, or `None` if the message is not a known result.
+    pub fn record_rating(
+        &self,
+        chat_id: i64,
+        message_id: i32,
+        rating: Rating,
+    ) -> Result<Option<FeedbackRecord>> {
+        let snapshot = match self
+            .results
+            .lock()
+            .unwrap()
+            .by_message
+            .get(&(chat_id, message_id))
+        {
+            Some(s) => s.clone(),
+            None => return Ok(None),
+        };
+
+        let record = FeedbackRecord {
+            timestamp: Utc::now(),
+            chat_id,
+            message_id,
+            rating,
+            comment: None,
+            result: snapshot,
+        };
+        self.append(&record)?;
+        Ok(Some(record))
+    }
+
+    /// Wait for a reply to `prompt_message_id` explaining a negative rating.
+    pub fn expect_comment(&self, prompt_message_id: i32, record: FeedbackRecord) {
+        self.awaiting_comment
+            .lock()
+            .unwrap()
+            .insert((record.chat_id, prompt_message_id), record);
+    }
+
+    /// Attach a comment if `reply_to_message_id` is a pending feedback prompt.
+    ///
+    /// Returns `true` when the message was consumed as feedback.
+    pub fn record_comment(
+        &self,
+        chat_id: i64,
+        reply_to_message_id: i32,
+        comment: &str,
+    ) -> Result<bool> {
+        let pending = self
+            .awaiting_comment
+            .lock()
+            .unwrap()
+            .remove(&(chat_id, reply_to_message_id));
+
+        match pending {
+            Some(mut record) => {
+                record.timestamp = Utc::now();
+                record.comment = Some(comment.to_string());
+                self.append(&record)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn append(&self, record: &FeedbackRecord) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create data directory")?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open feedback store: {}", self.path.display()))?;
+        let line = serde_json::to_string(record).context("Failed to serialize feedback")?;
+        writeln!(file, "{}", line).context("Failed to write feedback")?;
+
+        log::info!(
+            "Feedback: {:?} for message {} in chat {}",
+            record.rating,
+            record.message_id,
+            record.chat_id
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> ResultSnapshot {
+        ResultSnapshot {
+            raw_transcript: "ciao mondo".to_string(),
+            cleaned_transcript: "Ciao mondo.".to_string(),
+            notes: vec![],
+        }
+    }
+
+    #[test]
+    fn test_rating_and_comment_are_appended() {
+        let dir = std::env::temp_dir().join(format!("dot-feedback-{}", uuid::Uuid::new_v4()));
+        let store = FeedbackStore::new(&dir);
+
+        // Unknown messages are ignored
+        assert!(store.record_rating(1, 10, Rating::Up).unwrap().is_none());
+
+        store.track_result(1, 10, snapshot());
+        let record = store.record_rating(1, 10, Rating::Down).unwrap().unwrap();
+        store.expect_comment(11, record);
+
+        assert!(!store.record_comment(1, 99, "not a prompt").unwrap());
+        assert!(store.record_comment(1, 11, "titolo sbagliato").unwrap());
+
+        let content = std::fs::read_to_string(dir.join("feedback.jsonl")).unwrap();
+        let records: Vec<FeedbackRecord> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].comment.as_deref(), Some("titolo sbagliato"));
+        assert_eq!(records[1].rating, Rating::Down);
+
+        // The comment supersedes the bare rating for the same message
+        let latest = FeedbackStore::load_records(store.path()).unwrap();
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[0].comment.as_deref(), Some("titolo sbagliato"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
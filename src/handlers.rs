@@ -1,228 +1,2783 @@
-use crate::config::Config;
-use crate::note_generator::NoteGeneratorAgent;
-use crate::transcription;
-use teloxide::{prelude::*, types::Me};
-
-/// Handler for /start command
-pub async fn start_handler(bot: Bot, msg: Message, me: Me) -> ResponseResult<()> {
-    let text = format!(
-        "👋 Ciao! Sono {}, il tuo assistente per la trascrizione vocale.\n\n\
-        Inviami un messaggio vocale e lo trasformerò in note strutturate!\n\n\
-        Comandi disponibili:\n\
-        /start - Mostra questo messaggio\n\
-        /help - Aiuto e istruzioni\n\
-        /status - Stato del bot",
-        me.username()
-    );
+use crate::ai_provider::{self, TokenUpdates};
+use crate::attachments::{AttachmentKind, AttachmentStore};
+use crate::audit::{TranscriptLog, TranscriptRecord};
+use crate::backup;
+use crate::chat_state::ChatStateStore;
+use crate::config::{Config, ReplyVerbosity, WhisperTask};
+use crate::errors::PipelineError;
+use crate::export::{self, ExportFormat};
+use crate::feedback::{FeedbackStore, Rating, ResultSnapshot};
+use crate::history::{CaptureStatus, CapturedNote, HistoryEntry, HistoryLog};
+use crate::i18n::{Language, Msg};
+use crate::maintenance::{Maintenance, MaintenanceMode};
+use crate::metrics;
+use crate::note_generator::{self, AgentResult, NoteGeneratorAgent};
+use crate::obsidian;
+use crate::ollama::{self, ModelStatus};
+use crate::pending::{self, FailedStage, PendingMemo, PendingStore};
+use crate::quota::Quota;
+use crate::retag;
+use crate::search::NoteSearch;
+use crate::selection::PendingSelections;
+use crate::source_links::{SourceLinkLog, SourceMessage};
+use crate::telegram::{self, Bot, SendRetrying};
+use crate::tools::{Document, DocumentConverter, NoteWriter, Tool};
+use crate::transcription::{self, AudioInput, Transcript};
+use crate::usage::UsageStats;
+use anyhow::Context;
+use std::sync::Arc;
+use teloxide::{
+    prelude::*,
+    types::{
+        ForceReply, InlineKeyboardButton, InlineKeyboardMarkup, InlineQueryResult,
+        InlineQueryResultArticle, InputFile, InputMessageContent, InputMessageContentText, Me,
+        MessageId, MessageReactionUpdated, ReactionType, ReplyParameters,
+    },
+};
+use tracing::Instrument;
+
+/// Seconds Telegram may reuse an inline search answer for the same user.
+const INLINE_CACHE_SECS: u32 = 30;
+/// How often the vault is checked for deleted notes, with `report_deleted_notes`.
+const DELETED_NOTES_CHECK_SECS: u64 = 600;
+
+/// Handler for /start command, including `t.me/<bot>?start=<profile>` deep links
+pub async fn start_handler(
+    bot: Bot,
+    msg: Message,
+    me: Me,
+    payload: String,
+    config: Config,
+    chats: Arc<ChatStateStore>,
+) -> ResponseResult<()> {
+    let payload = payload.trim();
+    if !payload.is_empty() {
+        let notice = select_profile(&config, &chats, msg.chat.id.0, payload);
+        bot.send_message(msg.chat.id, notice)
+            .send_retrying()
+            .await?;
+    }
+
+    let text = Msg::Welcome.fill(config.general.language, &[("bot", &me.username())]);
+
+    bot.send_message(msg.chat.id, text).send_retrying().await?;
+    Ok(())
+}
+
+/// Handler for /profile command: without argument lists profiles, otherwise selects one
+pub async fn profile_handler(
+    bot: Bot,
+    msg: Message,
+    me: Me,
+    name: String,
+    config: Config,
+    chats: Arc<ChatStateStore>,
+) -> ResponseResult<()> {
+    let name = name.trim();
+    if !name.is_empty() {
+        let notice = select_profile(&config, &chats, msg.chat.id.0, name);
+        bot.send_message(msg.chat.id, notice)
+            .send_retrying()
+            .await?;
+        return Ok(());
+    }
+
+    let language = config.general.language;
+    let current = chats.get(msg.chat.id.0).profile;
+    let current = current
+        .as_deref()
+        .unwrap_or(Msg::ProfileNone.text(language));
+    let mut text = Msg::ProfileCurrent.fill(language, &[("profile", &current)]);
+    if config.profiles.is_empty() {
+        text.push_str(Msg::ProfilesNotConfigured.text(language));
+    } else {
+        text.push_str(Msg::ProfilesAvailable.text(language));
+        for (profile, profile_config) in &config.profiles {
+            text.push_str(&format!(
+                "\n• {} → {}/\n  https://t.me/{}?start={}\n",
+                profile,
+                profile_config.folder,
+                me.username(),
+                profile
+            ));
+            if let Some(description) = &profile_config.description {
+                text.push_str(&format!("  {}\n", description));
+            }
+        }
+        text.push_str(Msg::ProfileUsage.text(language));
+    }
+
+    bot.send_message(msg.chat.id, text).send_retrying().await?;
+    Ok(())
+}
+
+/// Store the chat's profile choice and describe the outcome.
+fn select_profile(config: &Config, chats: &ChatStateStore, chat_id: i64, name: &str) -> String {
+    let language = config.general.language;
+    let profile = match name {
+        "nessuno" | "none" => None,
+        name if config.profiles.contains_key(name) => Some(name.to_string()),
+        name => return Msg::ProfileUnknown.fill(language, &[("name", &name)]),
+    };
+
+    let notice = match &profile {
+        Some(name) => Msg::ProfileActivated.fill(
+            language,
+            &[("name", name), ("folder", &config.profiles[name].folder)],
+        ),
+        None => Msg::ProfileDeactivated.text(language).to_string(),
+    };
+    match chats.update(chat_id, |state| state.profile = profile) {
+        Ok(()) => notice,
+        Err(e) => {
+            log::error!("Failed to store profile for chat {}: {}", chat_id, e);
+            Msg::ProfileSaveFailed.text(language).to_string()
+        }
+    }
+}
+
+/// Names `/vault` accepts for the `[output]` vault, whatever the language.
+const DEFAULT_VAULT_NAMES: &[&str] = &["principale", "main"];
+
+/// Handler for /vault command: without argument lists vaults, otherwise
+/// switches the chat to one.
+pub async fn vault_handler(
+    bot: Bot,
+    msg: Message,
+    name: String,
+    config: Config,
+    chats: Arc<ChatStateStore>,
+) -> ResponseResult<()> {
+    let name = name.trim();
+    if !name.is_empty() {
+        let notice = select_vault(&config, &chats, msg.chat.id.0, name);
+        bot.send_message(msg.chat.id, notice)
+            .send_retrying()
+            .await?;
+        return Ok(());
+    }
+
+    let language = config.general.language;
+    let default_vault = Msg::DefaultVault.text(language);
+    let current = chats.get(msg.chat.id.0).vault;
+    let current = current.as_deref().unwrap_or(default_vault);
+    let mut text = Msg::VaultCurrent.fill(language, &[("vault", &current)]);
+    if config.vaults.is_empty() {
+        text.push_str(Msg::VaultsNotConfigured.text(language));
+    } else {
+        text.push_str(Msg::VaultsAvailable.text(language));
+        text.push_str(&format!(
+            "• {} → {}\n",
+            default_vault, config.output.notes_dir
+        ));
+        for (vault, vault_config) in &config.vaults {
+            text.push_str(&format!("• {} → {}\n", vault, vault_config.notes_dir));
+        }
+        text.push_str(Msg::VaultUsage.text(language));
+    }
 
-    bot.send_message(msg.chat.id, text).await?;
+    bot.send_message(msg.chat.id, text).send_retrying().await?;
     Ok(())
 }
 
+/// Store the chat's vault choice and describe the outcome.
+fn select_vault(config: &Config, chats: &ChatStateStore, chat_id: i64, name: &str) -> String {
+    let language = config.general.language;
+    let vault = match name {
+        name if config.vaults.contains_key(name) => Some(name.to_string()),
+        name if DEFAULT_VAULT_NAMES.contains(&name) => None,
+        name => return Msg::VaultUnknown.fill(language, &[("name", &name)]),
+    };
+
+    let notice = Msg::VaultActivated.fill(
+        language,
+        &[
+            (
+                "vault",
+                &vault.as_deref().unwrap_or(Msg::DefaultVault.text(language)),
+            ),
+            ("dir", &config.for_vault(vault.as_deref()).output.notes_dir),
+        ],
+    );
+    match chats.update(chat_id, |state| state.vault = vault) {
+        Ok(()) => notice,
+        Err(e) => {
+            log::error!("Failed to store vault for chat {}: {}", chat_id, e);
+            Msg::VaultSaveFailed.text(language).to_string()
+        }
+    }
+}
+
 /// Handler for /help command
-pub async fn help_handler(bot: Bot, msg: Message) -> ResponseResult<()> {
-    let text = "📖 Come usare Dot:\n\n\
-        1️⃣ Registra un messaggio vocale\n\
-        2️⃣ Inviamelo qui in chat\n\
-        3️⃣ Aspetta mentre lo trascrivo\n\
-        4️⃣ Riceverai note strutturate in formato Markdown\n\n\
-        💡 Funzionalità:\n\
-        - Trascrizione automatica (italiano)\n\
-        - Generazione di note strutturate\n\
-        - Estrazione di task (per progetti di sviluppo)\n\
-        - Formato compatibile con Obsidian\n\n\
-        ⚙️ Configurazione:\n\
-        - Lingua: Italiano\n\
-        - Dimensione max audio: 20MB\n\
-        - Formato output: Markdown (.md)\n\n\
-        Problemi? Contatta il tuo amministratore.";
-
-    bot.send_message(msg.chat.id, text).await?;
+pub async fn help_handler(bot: Bot, msg: Message, config: Config) -> ResponseResult<()> {
+    let text = Msg::Help.text(config.general.language);
+
+    bot.send_message(msg.chat.id, text).send_retrying().await?;
     Ok(())
 }
 
 /// Handler for /status command
-pub async fn status_handler(bot: Bot, msg: Message, config: Config) -> ResponseResult<()> {
-    let text = format!(
-        "🤖 Stato Bot\n\n\
-        ✅ Online e funzionante\n\
-        📝 Servizio trascrizione: {}\n\
-        🤖 AI Provider: {}\n\
-        📁 Directory note: {}\n\
-        🔧 Task extraction: {}\n\n\
-        Pronto a ricevere messaggi vocali!",
-        config.transcription.provider,
-        config.ai_model.provider,
-        config.output.notes_dir,
-        if config.features.enable_task_extraction {
-            "Abilitata"
-        } else {
-            "Disabilitata"
+pub async fn status_handler(
+    bot: Bot,
+    msg: Message,
+    config: Config,
+    chats: Arc<ChatStateStore>,
+) -> ResponseResult<()> {
+    let config = config.for_vault(chats.get(msg.chat.id.0).vault.as_deref());
+    let language = config.general.language;
+    let usage = match UsageStats::new(&config.output.data_dir).totals() {
+        Ok(totals) => match totals.since {
+            Some(since) => Msg::UsageTotals.fill(
+                language,
+                &[
+                    ("usage", &totals.usage.summary(&config.ai_model)),
+                    ("requests", &totals.usage.requests),
+                    ("since", &since.format(Msg::DateFormat.text(language))),
+                ],
+            ),
+            None => Msg::UsageNone.text(language).to_string(),
+        },
+        Err(e) => {
+            log::warn!("Failed to read token usage: {}", e);
+            Msg::UsageUnavailable.text(language).to_string()
+        }
+    };
+    let ai = match ollama::model_status(&config.ai_model).await {
+        Some(status) => match status {
+            ModelStatus::Ready => Msg::ModelReady,
+            ModelStatus::Missing => Msg::ModelMissing,
+            ModelStatus::Unreachable => Msg::ModelUnreachable,
         }
+        .fill(
+            language,
+            &[
+                ("provider", &config.ai_model.provider),
+                ("model", &config.ai_model.model),
+            ],
+        ),
+        None => config.ai_model.provider.clone(),
+    };
+    let tasks = if config.features.enable_task_extraction {
+        Msg::Enabled
+    } else {
+        Msg::Disabled
+    };
+    let text = Msg::Status.fill(
+        language,
+        &[
+            (
+                "transcription",
+                &transcription::describe(&config.transcription, language),
+            ),
+            ("ai", &ai),
+            ("usage", &usage),
+            (
+                "vault",
+                &config
+                    .vault
+                    .as_deref()
+                    .unwrap_or(Msg::DefaultVault.text(language)),
+            ),
+            ("notes_dir", &config.output.notes_dir),
+            ("tasks", &tasks.text(language)),
+        ],
     );
 
-    bot.send_message(msg.chat.id, text).await?;
+    bot.send_message(msg.chat.id, text).send_retrying().await?;
     Ok(())
 }
 
-/// Handler for audio/voice messages
-pub async fn audio_handler(bot: Bot, msg: Message, config: Config) -> ResponseResult<()> {
-    log::info!("Received audio message from user {}", msg.chat.id);
+/// Handler for inline queries (`@bot rust ownership`): search the notes and
+/// offer each match's title, excerpt and Obsidian link to paste in any chat.
+pub async fn inline_query_handler(
+    bot: Bot,
+    query: InlineQuery,
+    config: Config,
+    chats: Arc<ChatStateStore>,
+    search: Arc<NoteSearch>,
+) -> ResponseResult<()> {
+    // The private chat with the bot has the user's ID
+    let config = config.for_vault(chats.get(query.from.id.0 as i64).vault.as_deref());
+    let hits = match search.search(&config.output.notes_dir, &query.query).await {
+        Ok(hits) => hits,
+        Err(e) => {
+            log::error!("Inline search failed: {:#}", e);
+            Vec::new()
+        }
+    };
 
-    // Send acknowledgment
-    let ack_msg = bot
-        .send_message(
-            msg.chat.id,
-            "🎤 Messaggio vocale ricevuto! Sto trascrivendo...",
-        )
+    let results: Vec<InlineQueryResult> =
+        hits.into_iter()
+            .enumerate()
+            .map(|(i, hit)| {
+                let mut text = format!("📝 {}", hit.title);
+                if !hit.excerpt.is_empty() {
+                    text.push_str(&format!("\n\n{}", hit.excerpt));
+                }
+                let uri =
+                    config.output.obsidian_vault.as_deref().and_then(|vault| {
+                        obsidian::open_uri(vault, &config.vault_dir(), &hit.path)
+                    });
+                if let Some(uri) = uri {
+                    text.push_str(&format!("\n\n{}", uri));
+                }
+                let content = InputMessageContent::Text(InputMessageContentText::new(text));
+                InlineQueryResultArticle::new(i.to_string(), hit.title, content)
+                    .description(hit.excerpt)
+                    .into()
+            })
+            .collect();
+
+    bot.answer_inline_query(query.id, results)
+        .cache_time(INLINE_CACHE_SECS)
+        .is_personal(true)
+        .send_retrying()
         .await?;
+    Ok(())
+}
 
-    // Get the file info from the message
-    let file_info = if let Some(voice) = msg.voice() {
-        Some(voice.file.clone())
-    } else if let Some(audio) = msg.audio() {
-        Some(audio.file.clone())
-    } else {
-        None
+/// Handler for /share: render the best match for the query as HTML (or PDF
+/// with `/share pdf <nota>`) and send it as a document.
+pub async fn share_handler(
+    bot: Bot,
+    msg: Message,
+    arg: String,
+    config: Config,
+    chats: Arc<ChatStateStore>,
+) -> ResponseResult<()> {
+    let config = config.for_vault(chats.get(msg.chat.id.0).vault.as_deref());
+    let language = config.general.language;
+    let arg = arg.trim();
+    let (format, query) = match arg.split_once(' ') {
+        Some((word, rest)) if word.eq_ignore_ascii_case("pdf") => (ExportFormat::Pdf, rest),
+        Some((word, rest)) if word.eq_ignore_ascii_case("html") => (ExportFormat::Html, rest),
+        _ => (ExportFormat::Html, arg),
     };
+    if query.trim().is_empty() {
+        bot.send_message(msg.chat.id, Msg::ShareUsage.text(language))
+            .send_retrying()
+            .await?;
+        return Ok(());
+    }
 
-    if file_info.is_none() {
+    let hit = match NoteSearch::new()
+        .search(&config.output.notes_dir, query)
+        .await
+    {
+        Ok(hits) => hits.into_iter().next(),
+        Err(e) => {
+            log::error!("Note search failed: {:#}", e);
+            None
+        }
+    };
+    let Some(hit) = hit else {
         bot.send_message(
             msg.chat.id,
-            "❌ Errore: Nessun file audio trovato nel messaggio.",
+            Msg::ShareNotFound.fill(language, &[("query", &query.trim())]),
         )
+        .send_retrying()
+        .await?;
+        return Ok(());
+    };
+
+    let exported = tokio::fs::read_to_string(&hit.path)
+        .await
+        .context("Failed to read the note")
+        .and_then(|content| export::export(&hit.title, &content, format));
+    let bytes = match exported {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::error!("Failed to export {}: {:#}", hit.path.display(), e);
+            bot.send_message(
+                msg.chat.id,
+                Msg::ShareFailed.fill(
+                    language,
+                    &[("title", &hit.title), ("error", &format!("{:#}", e))],
+                ),
+            )
+            .send_retrying()
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let stem = hit
+        .path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| hit.title.clone());
+    let file = InputFile::memory(bytes).file_name(format!("{}.{}", stem, format.extension()));
+    bot.send_document(msg.chat.id, file)
+        .caption(format!("📝 {}", hit.title))
+        .send_retrying()
         .await?;
+    Ok(())
+}
+
+/// Handler for /maintenance (admin only): `on` holds generated notes in a
+/// queue, `reject` only transcribes, `off` writes the held notes.
+pub async fn maintenance_handler(
+    bot: Bot,
+    msg: Message,
+    arg: String,
+    config: Config,
+    maintenance: Arc<Maintenance>,
+) -> ResponseResult<()> {
+    let language = config.general.language;
+    let mode = match arg.trim().to_lowercase().as_str() {
+        "on" | "hold" => MaintenanceMode::Hold,
+        "reject" => MaintenanceMode::Reject,
+        "off" => MaintenanceMode::Off,
+        _ => {
+            let mode = match maintenance.mode() {
+                MaintenanceMode::Off => Msg::MaintenanceOff,
+                MaintenanceMode::Hold => Msg::MaintenanceHold,
+                MaintenanceMode::Reject => Msg::MaintenanceReject,
+            };
+            let text = Msg::MaintenanceStatus.fill(
+                language,
+                &[
+                    ("mode", &mode.text(language)),
+                    ("held", &maintenance.held_count()),
+                ],
+            );
+            bot.send_message(msg.chat.id, text).send_retrying().await?;
+            return Ok(());
+        }
+    };
+
+    if let Err(e) = maintenance.set_mode(mode) {
+        log::error!("Failed to change maintenance mode: {:#}", e);
+        bot.send_message(msg.chat.id, Msg::MaintenanceSaveFailed.text(language))
+            .send_retrying()
+            .await?;
         return Ok(());
     }
+    log::info!("Maintenance mode set to {:?}", mode);
 
-    // Get full file information
-    let file_meta = file_info.unwrap();
-    let file = match bot.get_file(&file_meta.id).await {
-        Ok(f) => f,
+    let text = match mode {
+        MaintenanceMode::Hold => Msg::MaintenanceHoldOn.text(language).to_string(),
+        MaintenanceMode::Reject => Msg::MaintenanceRejectOn.text(language).to_string(),
+        MaintenanceMode::Off => {
+            let (saved, failed) = flush_held_notes(&bot, &config, &maintenance).await;
+            let mut text = Msg::MaintenanceEnded.fill(language, &[("saved", &saved)]);
+            if failed > 0 {
+                text.push_str(&Msg::MaintenanceStillHeld.fill(language, &[("failed", &failed)]));
+            }
+            text
+        }
+    };
+    bot.send_message(msg.chat.id, text).send_retrying().await?;
+    Ok(())
+}
+
+/// Handler for /backup (admin only): `now` archives the vault immediately and
+/// reports where the archive went.
+pub async fn backup_handler(
+    bot: Bot,
+    msg: Message,
+    arg: String,
+    config: Config,
+) -> ResponseResult<()> {
+    let language = config.general.language;
+    if config.backup.dir.is_none() {
+        bot.send_message(msg.chat.id, Msg::BackupNotConfigured.text(language))
+            .send_retrying()
+            .await?;
+        return Ok(());
+    }
+    if arg.trim().to_lowercase() != "now" {
+        let text = Msg::BackupStatus.fill(
+            language,
+            &[
+                ("hours", &config.backup.every_hours),
+                ("keep", &config.backup.keep),
+            ],
+        );
+        bot.send_message(msg.chat.id, text).send_retrying().await?;
+        return Ok(());
+    }
+
+    let status = bot
+        .send_message(msg.chat.id, Msg::BackupRunning.text(language))
+        .send_retrying()
+        .await?;
+    let text = match backup::run(&config).await {
+        Ok(report) => {
+            let mut text = Msg::BackupCreated.fill(
+                language,
+                &[
+                    ("path", &report.path.display()),
+                    (
+                        "size",
+                        &format!("{:.1}", report.size_bytes as f64 / 1_048_576.0),
+                    ),
+                ],
+            );
+            if let Some(uploaded) = report.uploaded_to {
+                text.push_str(&Msg::BackupUploaded.fill(language, &[("target", &uploaded)]));
+            }
+            text
+        }
         Err(e) => {
-            log::error!("Failed to get file info: {}", e);
-            bot.send_message(msg.chat.id, "❌ Errore nel recupero del file audio.")
+            log::error!("Manual backup failed: {:#}", e);
+            Msg::BackupFailed.fill(language, &[("error", &format!("{:#}", e))])
+        }
+    };
+    bot.edit_message_text(msg.chat.id, status.id, text)
+        .send_retrying()
+        .await?;
+    Ok(())
+}
+
+/// Handler for /retag (admin only): rename a tag in every note of the chat's
+/// vault, frontmatter and inline `#tags` alike.
+pub async fn retag_handler(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    config: Config,
+    chats: Arc<ChatStateStore>,
+    maintenance: Arc<Maintenance>,
+    search: Arc<NoteSearch>,
+) -> ResponseResult<()> {
+    let config = config.for_vault(chats.get(msg.chat.id.0).vault.as_deref());
+    let language = config.general.language;
+    let tags: Vec<Option<String>> = args.split_whitespace().map(retag::parse_tag).collect();
+    let (old, new) = match tags.as_slice() {
+        [Some(old), Some(new)] => (old, new),
+        _ => {
+            bot.send_message(msg.chat.id, Msg::RetagUsage.text(language))
+                .send_retrying()
                 .await?;
             return Ok(());
         }
     };
+    if maintenance.mode() != MaintenanceMode::Off {
+        bot.send_message(msg.chat.id, Msg::RetagMaintenance.text(language))
+            .send_retrying()
+            .await?;
+        return Ok(());
+    }
 
-    // Create transcription provider
-    let provider = match transcription::create_transcription_provider(&config.transcription) {
-        Ok(p) => p,
-        Err(e) => {
-            log::error!("Failed to create transcription provider: {}", e);
-            let _ = bot.delete_message(msg.chat.id, ack_msg.id).await;
-            bot.send_message(
-                msg.chat.id,
-                format!("❌ Errore configurazione trascrizione: {}", e),
+    let text = match retag::retag_vault(&config, old, new).await {
+        Ok(changed) if changed.is_empty() => Msg::RetagNoNotes.fill(language, &[("tag", old)]),
+        Ok(changed) => {
+            log::info!("Retagged #{} as #{} in {} note(s)", old, new, changed.len());
+            search.invalidate().await;
+            Msg::RetagDone.fill(
+                language,
+                &[("old", old), ("new", new), ("count", &changed.len())],
             )
-            .await?;
-            return Ok(());
+        }
+        Err(e) => {
+            log::error!("Retag failed: {:#}", e);
+            // Notes rewritten before the error keep the new tag
+            search.invalidate().await;
+            Msg::RetagFailed.fill(language, &[("error", &format!("{:#}", e))])
         }
     };
+    bot.send_message(msg.chat.id, text).send_retrying().await?;
+    Ok(())
+}
+
+/// Write the notes held during maintenance and tell each chat. Batches that
+/// fail to write go back in the queue. Returns (notes saved, batches failed).
+async fn flush_held_notes(bot: &Bot, config: &Config, maintenance: &Maintenance) -> (usize, usize) {
+    let held = match maintenance.take_held() {
+        Ok(held) => held,
+        Err(e) => {
+            log::error!("Failed to read held notes: {:#}", e);
+            return (0, maintenance.held_count());
+        }
+    };
+
+    let (mut saved, mut failed) = (0, 0);
+    for batch in held {
+        // The same agent as a live memo of the batch's vault, so the notes
+        // also reach the history, the sources log and the tasks file
+        let vault_config = config.for_vault(batch.vault.as_deref());
+        let written = match NoteGeneratorAgent::new(&vault_config) {
+            Ok(agent) => {
+                agent
+                    .for_held(&batch)
+                    .save_to(AgentResult::held(batch.clone()), &batch.notes_dir)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        match written {
+            Ok(result) => {
+                let count = result.saved_paths.len();
+                saved += count;
+                let _ = bot
+                    .send_message(
+                        ChatId(batch.chat_id),
+                        Msg::MaintenanceHeldSaved
+                            .fill(config.general.language, &[("count", &count)]),
+                    )
+                    .send_retrying()
+                    .await;
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to write held notes for chat {}: {:#}",
+                    batch.chat_id,
+                    e
+                );
+                failed += 1;
+                if let Err(e) = maintenance.hold(batch) {
+                    log::error!("Failed to re-queue held notes: {:#}", e);
+                }
+            }
+        }
+    }
+    (saved, failed)
+}
+
+/// Handler for /retry: reprocess the chat's latest pending memo from the
+/// stage it failed at.
+pub async fn retry_handler(
+    bot: Bot,
+    msg: Message,
+    config: Config,
+    feedback: Arc<FeedbackStore>,
+    maintenance: Arc<Maintenance>,
+    pending: Arc<PendingStore>,
+) -> ResponseResult<()> {
+    let language = config.general.language;
+    let Some(mut memo) = pending.latest(msg.chat.id.0) else {
+        bot.send_message(msg.chat.id, Msg::RetryNothing.text(language))
+            .send_retrying()
+            .await?;
+        return Ok(());
+    };
+    if maintenance.mode() != MaintenanceMode::Off {
+        bot.send_message(msg.chat.id, Msg::RetryMaintenance.text(language))
+            .send_retrying()
+            .await?;
+        return Ok(());
+    }
+
+    let status = bot
+        .send_message(msg.chat.id, Msg::RetryRunning.text(language))
+        .send_retrying()
+        .await?;
+    let config = config
+        .for_vault(memo.vault.as_deref())
+        .with_notes_dir(&memo.notes_dir);
+    let result = reprocess_pending(&bot, &config, &mut memo).await;
+    settle_pending(&config, &pending, &mut memo, &result).await;
+    finish(&bot, &msg, status.id, &config, &feedback, result).await
+}
+
+/// Retry pending memos every `pending_retry_secs` once the dependency they
+/// failed on is healthy again, telling each chat when its memo completes.
+pub async fn retry_pending_memos(
+    bot: Bot,
+    config: Config,
+    feedback: Arc<FeedbackStore>,
+    maintenance: Arc<Maintenance>,
+    pending: Arc<PendingStore>,
+) {
+    let every = config.features.pending_retry_secs;
+    if every == 0 {
+        return;
+    }
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(every));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        if maintenance.mode() != MaintenanceMode::Off {
+            continue;
+        }
+
+        // Check each dependency once per round and vault: the Save check
+        // depends on the notes folder, Generation on the vault's model
+        let mut healthy = std::collections::HashMap::new();
+        for mut memo in pending.all() {
+            if memo.attempts >= pending::MAX_AUTO_RETRIES {
+                continue;
+            }
+            let memo_config = config
+                .for_vault(memo.vault.as_deref())
+                .with_notes_dir(&memo.notes_dir);
+            let key = (memo.stage, memo.vault.clone(), memo.notes_dir.clone());
+            let is_healthy = match healthy.get(&key) {
+                Some(&is_healthy) => is_healthy,
+                None => {
+                    let is_healthy = dependency_healthy(&bot, &memo_config, memo.stage).await;
+                    healthy.insert(key, is_healthy);
+                    is_healthy
+                }
+            };
+            if !is_healthy {
+                continue;
+            }
 
-    // Transcribe the audio
-    match provider
-        .transcribe(&bot, &file, &config.output.temp_dir)
-        .await
-    {
-        Ok(raw_transcript) => {
             log::info!(
-                "Transcription successful for user {}: {} chars",
-                msg.chat.id,
-                raw_transcript.len()
+                "Retrying memo {} of chat {} (failed at {:?})",
+                memo.message_id,
+                memo.chat_id,
+                memo.stage
             );
+            memo.attempts += 1;
+            let result = reprocess_pending(&bot, &memo_config, &mut memo).await;
+            settle_pending(&memo_config, &pending, &mut memo, &result).await;
+            match result {
+                Ok(result) => {
+                    if let Err(e) =
+                        notify_completed(&bot, &memo_config, &feedback, &memo, &result).await
+                    {
+                        log::warn!("Failed to notify completed memo: {}", e);
+                    }
+                }
+                Err(e) => log::warn!("[{}] retry of memo {}: {}", e.code(), memo.message_id, e),
+            }
+        }
+    }
+}
 
-            // Update status message
-            let _ = bot
-                .edit_message_text(msg.chat.id, ack_msg.id, "✅ Trascritto! Genero le note...")
+/// Reply to the memo of every note deleted from the vault, checking
+/// periodically (no-op unless `report_deleted_notes` is set).
+pub async fn report_deleted_notes(bot: Bot, config: Config) {
+    if !config.features.report_deleted_notes {
+        return;
+    }
+    let links = SourceLinkLog::new(&config.output.data_dir);
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(DELETED_NOTES_CHECK_SECS));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let deleted = match links.take_missing() {
+            Ok(deleted) => deleted,
+            Err(e) => {
+                log::warn!("Failed to check for deleted notes: {:#}", e);
+                continue;
+            }
+        };
+        for note in deleted {
+            log::info!("Note {} was deleted from the vault", note.path.display());
+            let result = bot
+                .send_message(
+                    ChatId(note.chat_id),
+                    Msg::NoteDeleted.fill(config.general.language, &[("title", &note.title)]),
+                )
+                .reply_parameters(
+                    ReplyParameters::new(MessageId(note.message_id)).allow_sending_without_reply(),
+                )
+                .send_retrying()
                 .await;
+            if let Err(e) = result {
+                log::warn!("Failed to report deleted note: {}", e);
+            }
+        }
+    }
+}
 
-            // Delegate to agent
-            let agent = NoteGeneratorAgent::new(&config);
-            match agent.process_transcript(raw_transcript).await {
-                Ok(result) => {
-                    // Delete status message
-                    let _ = bot.delete_message(msg.chat.id, ack_msg.id).await;
-
-                    // Build response
-                    let mut response = format!(
-                        "🎉 Completato!\n\n📝 {} nota/e generata/e:\n\n",
-                        result.notes.len()
-                    );
-
-                    for (i, note) in result.notes.iter().enumerate() {
-                        response.push_str(&format!("{}. **{}**\n", i + 1, note.title));
-                        response.push_str(&format!("   Tags: {}\n", note.tags.join(", ")));
-                        response.push_str(&format!(
-                            "   File: {}\n\n",
-                            result
-                                .saved_paths
-                                .get(i)
-                                .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
-                                .unwrap_or_else(|| "errore".to_string())
-                        ));
-                    }
+/// Whether the dependency of a failed stage is reachable again.
+async fn dependency_healthy(bot: &Bot, config: &Config, stage: FailedStage) -> bool {
+    match stage {
+        // Telegram answering means the network is back
+        FailedStage::Download | FailedStage::Transcription => bot.get_me().await.is_ok(),
+        FailedStage::Generation if !config.ai_model.enabled() => true,
+        FailedStage::Generation => match ai_provider::create_ai_provider(&config.ai_model) {
+            Ok(ai) => ai.is_available().await,
+            Err(_) => false,
+        },
+        FailedStage::Save => NoteWriter::preflight(&config.output.notes_dir, 0).is_ok(),
+    }
+}
 
-                    if result.cleaned_transcript != result.raw_transcript {
-                        response.push_str("\n📊 Trascrizione (corretta):\n");
-                        response.push_str(&result.cleaned_transcript);
-                        response.push_str(&format!(
-                            "\n\n🔍 Originale (Whisper):\n{}",
-                            result.raw_transcript
-                        ));
-                    } else {
-                        response.push_str(&format!(
-                            "\n📊 Trascrizione:\n{}",
-                            result.cleaned_transcript
-                        ));
-                    }
+/// Run the stages a pending memo has not completed yet and save its notes.
+/// The memo keeps the progress made (e.g. the transcript) if a later stage
+/// fails again.
+async fn reprocess_pending(
+    bot: &Bot,
+    config: &Config,
+    memo: &mut PendingMemo,
+) -> Result<AgentResult, PipelineError> {
+    let raw_transcript = match memo.raw_transcript.clone() {
+        Some(transcript) => transcript,
+        None => {
+            let file_id = memo.file_id.clone().ok_or_else(|| {
+                PipelineError::Download(anyhow::anyhow!("Pending memo has no audio file"))
+            })?;
+            NoteWriter::preflight(&config.output.notes_dir, 0)
+                .map_err(|e| PipelineError::Save(e.into()))?;
+            let file = bot.get_file(&file_id).send_retrying().await.map_err(|e| {
+                PipelineError::Download(anyhow::Error::new(e).context("Failed to get file info"))
+            })?;
+            let in_memory_max_bytes = config
+                .transcription
+                .in_memory_max_mb
+                .map(|mb| mb * 1024 * 1024);
+            let audio = transcription::download_audio(
+                bot,
+                &file,
+                &config.output.temp_dir,
+                in_memory_max_bytes,
+            )
+            .await
+            .map_err(PipelineError::Download)?;
 
-                    bot.send_message(msg.chat.id, response).await?;
-                    log::info!("Notes generated and saved for user {}", msg.chat.id);
-                }
-                Err(e) => {
-                    log::error!("Agent failed: {}", e);
-                    let _ = bot.delete_message(msg.chat.id, ack_msg.id).await;
+            let provider = &config.transcription.provider;
+            let mut transcription_config = config.transcription.clone();
+            if memo.translate {
+                transcription_config.task = WhisperTask::Translate;
+            }
+            let transcript =
+                match transcription::create_transcription_provider(&transcription_config) {
+                    Ok(transcriber) => transcriber.transcribe(&audio).await,
+                    Err(e) => Err(e),
+                };
+            audio.cleanup();
+            let transcript =
+                transcript.map_err(|e| PipelineError::from_transcription(e, provider))?;
+            memo.raw_transcript = Some(transcript.text.clone());
+            memo.language = transcript.language;
+            transcript.text
+        }
+    };
+
+    let agent = NoteGeneratorAgent::new(config)?
+        .with_received_at(memo.received_at)
+        .with_language(memo.language.clone())
+        .with_source_message(SourceMessage {
+            chat_id: memo.chat_id,
+            message_id: memo.message_id,
+            link: memo.telegram_link.clone(),
+        });
+    let result = agent.generate(raw_transcript, &[]).await?;
+    agent.save(result).await
+}
 
-                    let error_msg = format!(
-                        "❌ Errore nella generazione delle note.\n\n\
-                        Dettagli: {}\n\n\
-                        💡 Verifica che Ollama sia in esecuzione: ollama list",
-                        e
-                    );
-                    bot.send_message(msg.chat.id, error_msg).await?;
+/// Record the outcome of reprocessing a pending memo: forget it and its
+/// fallback note once done, or keep it at the stage that failed.
+async fn settle_pending(
+    config: &Config,
+    pending: &PendingStore,
+    memo: &mut PendingMemo,
+    result: &Result<AgentResult, PipelineError>,
+) {
+    match result {
+        Ok(_) => {
+            log::info!(
+                "Completed pending memo {} of chat {}",
+                memo.message_id,
+                memo.chat_id
+            );
+            if let Some(path) = &memo.fallback_note {
+                if let Err(e) = std::fs::remove_file(path) {
+                    log::warn!("Failed to remove fallback note {}: {}", path.display(), e);
                 }
             }
+            if let Err(e) = pending.remove(memo.chat_id, memo.message_id) {
+                log::warn!("Failed to update pending memos: {:#}", e);
+            }
         }
         Err(e) => {
-            log::error!("Transcription failed: {}", e);
-            let _ = bot.delete_message(msg.chat.id, ack_msg.id).await;
-
-            let error_msg = format!(
-                "❌ Errore nella trascrizione.\n\n\
-                Dettagli: {}\n\n\
-                💡 Suggerimenti:\n\
-                - Controlla la configurazione del provider '{}'\n\
-                - Controlla i log per maggiori dettagli\n\
-                - Usa /status per verificare la configurazione",
-                e, config.transcription.provider
-            );
-            bot.send_message(msg.chat.id, error_msg).await?;
+            memo.stage = FailedStage::of(e);
+            // Keep a transcript obtained on retry in the vault as well
+            if let (Some(transcript), None) = (&memo.raw_transcript, &memo.fallback_note) {
+                match save_fallback_note(config, transcript, memo.received_at).await {
+                    Ok((_, path)) => memo.fallback_note = Some(path),
+                    Err(e) => log::warn!("Failed to save fallback note: {:#}", e),
+                }
+            }
+            if let Err(e) = pending.add(memo.clone()) {
+                log::warn!("Failed to update pending memos: {:#}", e);
+            }
         }
     }
+}
 
+/// Reply to a memo that completed on an automatic retry.
+async fn notify_completed(
+    bot: &Bot,
+    config: &Config,
+    feedback: &FeedbackStore,
+    memo: &PendingMemo,
+    result: &AgentResult,
+) -> ResponseResult<()> {
+    let chat_id = ChatId(memo.chat_id);
+    let memo_id = MessageId(memo.message_id);
+    let language = config.general.language;
+    let (text, truncated) = match config.telegram.reply_verbosity {
+        ReplyVerbosity::Full => {
+            let (text, truncated) = format_result_message(result, config);
+            (
+                Msg::PendingCompleted.fill(language, &[("result", &text)]),
+                truncated,
+            )
+        }
+        ReplyVerbosity::Summary => (
+            Msg::PendingCompletedSummary.fill(language, &[("count", &result.saved_paths.len())]),
+            false,
+        ),
+        ReplyVerbosity::Silent => {
+            let reaction = ReactionType::Emoji {
+                emoji: SILENT_REACTION.to_string(),
+            };
+            bot.set_message_reaction(chat_id, memo_id)
+                .reaction(vec![reaction])
+                .send_retrying()
+                .await?;
+            return Ok(());
+        }
+    };
+    let mut request = bot
+        .send_message(chat_id, text)
+        .reply_parameters(ReplyParameters::new(memo_id).allow_sending_without_reply());
+    if truncated {
+        request = request.reply_markup(transcript_button(memo_id, language));
+    }
+    let sent = request.send_retrying().await?;
+    feedback.track_result(
+        memo.chat_id,
+        sent.id.0,
+        ResultSnapshot::from_agent_result(result),
+    );
     Ok(())
 }
 
-/// Handler for text messages (fallback)
-pub async fn text_handler(bot: Bot, msg: Message) -> ResponseResult<()> {
-    let text = "📝 Ho ricevuto il tuo messaggio di testo.\n\n\
-        Per ora, sono specializzato solo in messaggi vocali! 🎤\n\
-        Inviami un messaggio vocale e lo trasformerò in note strutturate.\n\n\
-        Usa /help per maggiori informazioni.";
+/// Handler for audio/voice messages
+#[allow(clippy::too_many_arguments)]
+pub async fn audio_handler(
+    bot: Bot,
+    msg: Message,
+    config: Config,
+    feedback: Arc<FeedbackStore>,
+    selections: Arc<PendingSelections>,
+    chats: Arc<ChatStateStore>,
+    me: Me,
+    maintenance: Arc<Maintenance>,
+    pending: Arc<PendingStore>,
+) -> ResponseResult<()> {
+    let Some(config) = memo_config(&msg, &config, &chats, &me) else {
+        return Ok(());
+    };
+    log::info!("Received audio message from user {}", msg.chat.id);
 
-    bot.send_message(msg.chat.id, text).await?;
-    Ok(())
+    let span = tracing::info_span!("memo", chat_id = msg.chat.id.0, message_id = msg.id.0);
+    process_audio(bot, msg, config, feedback, selections, maintenance, pending)
+        .instrument(span)
+        .await
+}
+
+/// Configuration for a memo or document sent in `msg`: the chat's vault and
+/// profile folder, or the group's folder and reply verbosity. `None` if the bot
+/// should ignore it: in groups, only enabled groups and messages addressed
+/// to the bot are processed.
+fn memo_config(msg: &Message, config: &Config, chats: &ChatStateStore, me: &Me) -> Option<Config> {
+    let group = if msg.chat.is_group() || msg.chat.is_supergroup() {
+        let Some(group) = config.groups.enabled_chat(msg.chat.id.0) else {
+            log::debug!("Ignoring message in group {} (not enabled)", msg.chat.id);
+            return None;
+        };
+        let reply_to_bot = msg
+            .reply_to_message()
+            .and_then(|reply| reply.from.as_ref())
+            .is_some_and(|user| user.id == me.id);
+        if !is_addressed_to_bot(
+            msg.caption(),
+            reply_to_bot,
+            me.username(),
+            &config.groups.trigger_hashtags,
+        ) {
+            return None;
+        }
+        Some(group.clone())
+    } else {
+        None
+    };
+
+    let state = chats.get(msg.chat.id.0);
+    let config = config.for_vault(state.vault.as_deref());
+    let mut config = match group.as_ref().and_then(|g| g.folder.as_deref()) {
+        Some(folder) => config.with_notes_subfolder(folder),
+        None => config.for_profile(state.profile.as_deref()),
+    };
+    if let Some(verbosity) = group.and_then(|g| g.reply_verbosity) {
+        config.telegram.reply_verbosity = verbosity;
+    }
+    if asks_for_translation(msg.caption()) {
+        config.transcription.task = WhisperTask::Translate;
+    }
+    Some(config)
+}
+
+/// Caption hashtags asking for a memo to be translated to English.
+const TRANSLATE_HASHTAGS: &[&str] = &["#en", "#english", "#inglese", "#translate"];
+
+/// Whether the caption of a memo asks for an English translation instead of
+/// a transcript in the spoken language.
+fn asks_for_translation(caption: Option<&str>) -> bool {
+    caption.is_some_and(|caption| {
+        caption.split_whitespace().any(|word| {
+            let word = word
+                .trim_end_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase();
+            TRANSLATE_HASHTAGS.contains(&word.as_str())
+        })
+    })
+}
+
+/// When a memo was sent: for forwarded memos, when the original was.
+fn sent_at(msg: &Message) -> chrono::DateTime<chrono::Utc> {
+    msg.forward_date().unwrap_or(msg.date)
+}
+
+/// Extensions of recordings exported from other apps, recognized in
+/// documents sent without an audio MIME type.
+const AUDIO_DOCUMENT_EXTENSIONS: &[&str] =
+    &["mp3", "m4a", "aac", "flac", "wav", "ogg", "oga", "opus"];
+
+/// Whether a document is a recording (mp3, m4a, flac, wav...), to be
+/// transcribed like a voice message rather than converted to Markdown.
+pub fn is_audio_document(msg: &Message) -> bool {
+    let Some(document) = msg.document() else {
+        return false;
+    };
+    if document
+        .mime_type
+        .as_ref()
+        .is_some_and(|mime| mime.type_() == "audio")
+    {
+        return true;
+    }
+    document
+        .file_name
+        .as_deref()
+        .and_then(|name| std::path::Path::new(name).extension())
+        .is_some_and(|ext| {
+            AUDIO_DOCUMENT_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+        })
+}
+
+/// Handler for documents (HTML, reStructuredText, Word, PDF, text): convert
+/// them to Markdown and turn them into notes like a transcript.
+#[allow(clippy::too_many_arguments)]
+pub async fn document_handler(
+    bot: Bot,
+    msg: Message,
+    config: Config,
+    feedback: Arc<FeedbackStore>,
+    chats: Arc<ChatStateStore>,
+    me: Me,
+    maintenance: Arc<Maintenance>,
+) -> ResponseResult<()> {
+    let Some(document) = msg.document() else {
+        return Ok(());
+    };
+    let Some(config) = memo_config(&msg, &config, &chats, &me) else {
+        return Ok(());
+    };
+    let file_name = document.file_name.clone().unwrap_or_default();
+    let mime = document
+        .mime_type
+        .as_ref()
+        .map(|m| m.essence_str().to_string());
+    log::info!("Received document {} from user {}", file_name, msg.chat.id);
+    let language = config.general.language;
+
+    let status = bot
+        .send_message(msg.chat.id, Msg::DocumentReceived.text(language))
+        .send_retrying()
+        .await?;
+
+    let max_bytes = config.features.max_audio_size_mb * 1024 * 1024;
+    if u64::from(document.file.size) > max_bytes {
+        let _ = bot
+            .delete_message(msg.chat.id, status.id)
+            .send_retrying()
+            .await;
+        bot.send_message(
+            msg.chat.id,
+            Msg::DocumentTooLarge.fill(language, &[("max", &config.features.max_audio_size_mb)]),
+        )
+        .send_retrying()
+        .await?;
+        return Ok(());
+    }
+
+    let bytes = match download_bytes(&bot, &document.file.id).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let err = PipelineError::Download(e);
+            return report_pipeline_error(&bot, &msg, status.id, &config, err).await;
+        }
+    };
+
+    // Archive the original document so notes can embed it
+    let mut attachments = Vec::new();
+    if config.features.archive_documents {
+        let name = if file_name.is_empty() {
+            "document"
+        } else {
+            &file_name
+        };
+        let store = AttachmentStore::new(config.attachments_dir());
+        match store.store_bytes(AttachmentKind::Document, &bytes, name) {
+            Ok(archived) => attachments.push(archived),
+            Err(e) => log::warn!("Failed to archive document: {}", e),
+        }
+        enforce_quota(
+            &bot,
+            &config,
+            &config.attachments_dir(),
+            config.quota.attachments_max_mb,
+            &attachments,
+        )
+        .await;
+    }
+
+    let converted = DocumentConverter::new()
+        .run(Document {
+            file_name: file_name.clone(),
+            mime,
+            bytes,
+        })
+        .await;
+    let markdown = match converted {
+        Ok(markdown) => markdown,
+        Err(e) => {
+            log::warn!("Failed to convert document {}: {:#}", file_name, e);
+            for archived in &attachments {
+                let _ = std::fs::remove_file(archived);
+            }
+            let _ = bot
+                .delete_message(msg.chat.id, status.id)
+                .send_retrying()
+                .await;
+            bot.send_message(msg.chat.id, Msg::DocumentUnsupported.text(language))
+                .send_retrying()
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if maintenance.mode() == MaintenanceMode::Reject {
+        return reject_for_maintenance(&bot, &msg, status.id, &config, &markdown).await;
+    }
+    let _ = bot
+        .edit_message_text(
+            msg.chat.id,
+            status.id,
+            Msg::DocumentConverted.text(language),
+        )
+        .send_retrying()
+        .await;
+
+    let agent = match NoteGeneratorAgent::new(&config) {
+        Ok(agent) => agent
+            .with_received_at(sent_at(&msg))
+            .with_source_message(SourceMessage::of(&bot, &msg))
+            .for_document(),
+        Err(e) => return report_pipeline_error(&bot, &msg, status.id, &config, e).await,
+    };
+    match agent.generate(markdown, &attachments).await {
+        Ok(result) => {
+            save_or_hold(
+                &bot,
+                &msg,
+                status.id,
+                &config,
+                &feedback,
+                &maintenance,
+                &agent,
+                result,
+            )
+            .await
+        }
+        Err(e) => report_pipeline_error(&bot, &msg, status.id, &config, e).await,
+    }
+}
+
+/// Download a Telegram file into memory.
+async fn download_bytes(bot: &Bot, file_id: &str) -> anyhow::Result<Vec<u8>> {
+    use futures_util::StreamExt;
+    use teloxide::net::Download;
+
+    let file = bot
+        .get_file(file_id)
+        .send_retrying()
+        .await
+        .context("Failed to get file info")?;
+    let mut bytes = Vec::with_capacity(file.size as usize);
+    let mut stream = bot.inner().download_file_stream(&file.path);
+    while let Some(chunk) = stream.next().await {
+        bytes.extend_from_slice(&chunk.context("Failed to download file chunk")?);
+    }
+    Ok(bytes)
+}
+
+/// Transcribe an accepted memo and turn it into notes.
+async fn process_audio(
+    bot: Bot,
+    msg: Message,
+    config: Config,
+    feedback: Arc<FeedbackStore>,
+    selections: Arc<PendingSelections>,
+    maintenance: Arc<Maintenance>,
+    pending: Arc<PendingStore>,
+) -> ResponseResult<()> {
+    // Send acknowledgment
+    let language = config.general.language;
+    let ack_msg = bot
+        .send_message(msg.chat.id, Msg::AudioReceived.text(language))
+        .send_retrying()
+        .await?;
+
+    // Get the file info from the message
+    let file_info = if let Some(voice) = msg.voice() {
+        Some(voice.file.clone())
+    } else if let Some(audio) = msg.audio() {
+        Some(audio.file.clone())
+    } else {
+        msg.document().map(|document| document.file.clone())
+    };
+
+    if file_info.is_none() {
+        bot.send_message(msg.chat.id, Msg::AudioMissing.text(language))
+            .send_retrying()
+            .await?;
+        return Ok(());
+    }
+
+    // Get full file information
+    let file_meta = file_info.unwrap();
+    let max_bytes = config.features.max_audio_size_mb * 1024 * 1024;
+    if u64::from(file_meta.size) > max_bytes {
+        let _ = bot
+            .delete_message(msg.chat.id, ack_msg.id)
+            .send_retrying()
+            .await;
+        bot.send_message(
+            msg.chat.id,
+            Msg::AudioTooLarge.fill(language, &[("max", &config.features.max_audio_size_mb)]),
+        )
+        .send_retrying()
+        .await?;
+        return Ok(());
+    }
+
+    let file = match bot.get_file(&file_meta.id).send_retrying().await {
+        Ok(f) => f,
+        Err(e) => {
+            let err =
+                PipelineError::Download(anyhow::Error::new(e).context("Failed to get file info"));
+            return queue_failed_memo(
+                &bot,
+                &msg,
+                ack_msg.id,
+                &config,
+                &pending,
+                &file_meta.id,
+                err,
+            )
+            .await;
+        }
+    };
+
+    // Make sure the vault can take new notes before doing any expensive work
+    match NoteWriter::preflight(&config.output.notes_dir, config.output.min_free_space_mb) {
+        Ok(report) if report.low_space => {
+            notify_admin(
+                &bot,
+                &config,
+                Msg::LowDiskSpace.fill(
+                    language,
+                    &[
+                        ("mb", &report.available_mb),
+                        ("dir", &config.output.notes_dir),
+                    ],
+                ),
+            )
+            .await;
+        }
+        Ok(_) => {}
+        Err(e) => {
+            let err = PipelineError::Save(e.into());
+            return queue_failed_memo(
+                &bot,
+                &msg,
+                ack_msg.id,
+                &config,
+                &pending,
+                &file_meta.id,
+                err,
+            )
+            .await;
+        }
+    }
+
+    // Create transcription provider
+    let provider = match transcription::create_transcription_provider(&config.transcription) {
+        Ok(p) => p,
+        Err(e) => {
+            log::error!("Failed to create transcription provider: {}", e);
+            let _ = bot
+                .delete_message(msg.chat.id, ack_msg.id)
+                .send_retrying()
+                .await;
+            bot.send_message(
+                msg.chat.id,
+                Msg::TranscriptionConfigError.fill(language, &[("error", &e)]),
+            )
+            .send_retrying()
+            .await?;
+            return Ok(());
+        }
+    };
+
+    enforce_quota(
+        &bot,
+        &config,
+        std::path::Path::new(&config.output.temp_dir),
+        config.quota.temp_max_mb,
+        &[],
+    )
+    .await;
+
+    // Download the audio from Telegram
+    let in_memory_max_bytes = config
+        .transcription
+        .in_memory_max_mb
+        .map(|mb| mb * 1024 * 1024);
+    let audio = match transcription::download_audio(
+        &bot,
+        &file,
+        &config.output.temp_dir,
+        in_memory_max_bytes,
+    )
+    .instrument(tracing::info_span!("download", bytes = file.size))
+    .await
+    {
+        Ok(audio) => audio,
+        Err(e) => {
+            let err = PipelineError::Download(e);
+            return queue_failed_memo(
+                &bot,
+                &msg,
+                ack_msg.id,
+                &config,
+                &pending,
+                &file_meta.id,
+                err,
+            )
+            .await;
+        }
+    };
+
+    // Archive the original audio so notes can embed it
+    let mut attachments = Vec::new();
+    if config.features.archive_audio {
+        let store = AttachmentStore::new(config.attachments_dir());
+        let file_name = audio.file_name();
+        let stored = match &audio {
+            AudioInput::File(path) => store.store_file(AttachmentKind::Audio, path, &file_name),
+            AudioInput::Memory { bytes, .. } => {
+                store.store_bytes(AttachmentKind::Audio, bytes, &file_name)
+            }
+        };
+        match stored {
+            Ok(archived) => attachments.push(archived),
+            Err(e) => log::warn!("Failed to archive audio: {}", e),
+        }
+        enforce_quota(
+            &bot,
+            &config,
+            &config.attachments_dir(),
+            config.quota.attachments_max_mb,
+            &attachments,
+        )
+        .await;
+    }
+
+    // Transcribe the audio, showing progress and the transcript-so-far
+    let (progress_tx, mut progress_rx) =
+        tokio::sync::mpsc::unbounded_channel::<transcription::Progress>();
+    let status_updates = {
+        let bot = bot.clone();
+        let chat_id = msg.chat.id;
+        let status_id = ack_msg.id;
+        // Partial transcripts are only shown when full replies are wanted
+        let show_text = config.telegram.reply_verbosity == ReplyVerbosity::Full;
+        tokio::spawn(async move {
+            let mut last_edit: Option<tokio::time::Instant> = None;
+            let mut last_status = String::new();
+            while let Some(mut progress) = progress_rx.recv().await {
+                // Coalesce fast segments into at most one edit per interval
+                if let Some(at) = last_edit {
+                    tokio::time::sleep_until(at + telegram::EDIT_INTERVAL).await;
+                }
+                while let Ok(newer) = progress_rx.try_recv() {
+                    progress = newer;
+                }
+                let status = format_progress_status(&progress, show_text, language);
+                // Telegram rejects edits that change nothing
+                if status == last_status {
+                    continue;
+                }
+                let _ = bot
+                    .edit_message_text(chat_id, status_id, status.as_str())
+                    .send_retrying()
+                    .await;
+                last_status = status;
+                last_edit = Some(tokio::time::Instant::now());
+            }
+        })
+    };
+    let transcription_started = std::time::Instant::now();
+    let transcription_result = provider
+        .transcribe_streaming(&audio, progress_tx)
+        .instrument(tracing::info_span!(
+            "transcription",
+            provider = %config.transcription.provider
+        ))
+        .await;
+    let transcription_time = transcription_started.elapsed();
+    let _ = status_updates.await;
+    audio.cleanup();
+
+    match transcription_result {
+        Ok(transcript) => {
+            let raw_transcript = &transcript.text;
+            log::info!(
+                "Transcription successful for user {}: {} chars",
+                msg.chat.id,
+                raw_transcript.len()
+            );
+
+            // Keep the raw transcript so notes can be regenerated later
+            let record = TranscriptRecord {
+                timestamp: sent_at(&msg),
+                chat_id: msg.chat.id.0,
+                message_id: msg.id.0,
+                provider: config.transcription.provider.clone(),
+                raw_transcript: raw_transcript.clone(),
+                vault: config.vault.clone(),
+            };
+            if let Err(e) = TranscriptLog::new(&config.output.data_dir).append(&record) {
+                log::warn!("Failed to record transcript: {}", e);
+            }
+
+            // During a vault reorganization, skip generation entirely
+            if maintenance.mode() == MaintenanceMode::Reject {
+                return reject_for_maintenance(&bot, &msg, ack_msg.id, &config, raw_transcript)
+                    .await;
+            }
+
+            // Log memos record metrics instead of notes
+            let log_body = metrics::log_body(&config.metrics, raw_transcript)
+                .filter(|_| config.ai_model.enabled());
+            if let Some(body) = log_body {
+                return log_metrics(&bot, &msg, ack_msg.id, &config, body).await;
+            }
+
+            // Update status message
+            let _ = bot
+                .edit_message_text(msg.chat.id, ack_msg.id, Msg::Transcribed.text(language))
+                .send_retrying()
+                .await;
+
+            // Delegate to agent
+            let agent = match NoteGeneratorAgent::new(&config) {
+                Ok(agent) => agent
+                    .with_received_at(sent_at(&msg))
+                    .with_language(transcript.language.clone())
+                    .with_source_message(SourceMessage::of(&bot, &msg)),
+                Err(e) => {
+                    return report_pipeline_error(&bot, &msg, ack_msg.id, &config, e).await;
+                }
+            };
+            if agent.asks_user_to_choose() {
+                match agent
+                    .generate_candidates(raw_transcript.clone(), &attachments)
+                    .await
+                {
+                    Ok(mut candidates) if candidates.len() > 1 => {
+                        for candidate in &mut candidates {
+                            candidate.timings.prepend("transcribe", transcription_time);
+                        }
+                        let _ = bot
+                            .delete_message(msg.chat.id, ack_msg.id)
+                            .send_retrying()
+                            .await;
+                        send_candidates(&bot, &msg, &selections, candidates, language).await?;
+                    }
+                    Ok(mut candidates) => {
+                        let mut result = candidates.remove(0);
+                        result.timings.prepend("transcribe", transcription_time);
+                        save_or_hold(
+                            &bot,
+                            &msg,
+                            ack_msg.id,
+                            &config,
+                            &feedback,
+                            &maintenance,
+                            &agent,
+                            result,
+                        )
+                        .await?;
+                    }
+                    Err(e) => {
+                        save_fallback(
+                            &bot,
+                            &msg,
+                            ack_msg.id,
+                            &config,
+                            &maintenance,
+                            &pending,
+                            &transcript,
+                            e,
+                        )
+                        .await?;
+                    }
+                }
+            } else {
+                // Long generations show their token count instead of a still status
+                let status = GenerationStatus::start(&bot, msg.chat.id, ack_msg.id, language);
+                let agent = agent.with_token_updates(status.tokens.clone());
+                let generated = agent.generate(raw_transcript.clone(), &attachments).await;
+                status.finish().await;
+                match generated {
+                    Ok(mut result) => {
+                        result.timings.prepend("transcribe", transcription_time);
+                        save_or_hold(
+                            &bot,
+                            &msg,
+                            ack_msg.id,
+                            &config,
+                            &feedback,
+                            &maintenance,
+                            &agent,
+                            result,
+                        )
+                        .await?;
+                    }
+                    Err(e) => {
+                        save_fallback(
+                            &bot,
+                            &msg,
+                            ack_msg.id,
+                            &config,
+                            &maintenance,
+                            &pending,
+                            &transcript,
+                            e,
+                        )
+                        .await?;
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            let err = PipelineError::from_transcription(e, &config.transcription.provider);
+            queue_failed_memo(
+                &bot,
+                &msg,
+                ack_msg.id,
+                &config,
+                &pending,
+                &file_meta.id,
+                err,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Record the metrics of a log memo and report them in the status message.
+async fn log_metrics(
+    bot: &Bot,
+    msg: &Message,
+    status_id: MessageId,
+    config: &Config,
+    body: &str,
+) -> ResponseResult<()> {
+    let language = config.general.language;
+    let at = sent_at(msg).with_timezone(&config.locale.timezone);
+    let text = match metrics::log(config, body, at).await {
+        Ok(values) if values.is_empty() => {
+            let names: Vec<&str> = config.metrics.names.keys().map(String::as_str).collect();
+            Msg::MetricsNone.fill(language, &[("names", &names.join(", "))])
+        }
+        Ok(values) => {
+            Msg::MetricsLogged.fill(language, &[("values", &metrics::format_values(&values))])
+        }
+        Err(e) => {
+            log::error!("Failed to log metrics: {:#}", e);
+            Msg::MetricsFailed.fill(language, &[("error", &e)])
+        }
+    };
+    bot.edit_message_text(msg.chat.id, status_id, text)
+        .send_retrying()
+        .await?;
+    Ok(())
+}
+
+/// Whether a group audio message asks for the bot: its caption mentions
+/// `@bot_username` or contains a trigger hashtag, or it replies to the bot.
+fn is_addressed_to_bot(
+    caption: Option<&str>,
+    reply_to_bot: bool,
+    bot_username: &str,
+    trigger_hashtags: &[String],
+) -> bool {
+    if reply_to_bot {
+        return true;
+    }
+    let Some(caption) = caption else {
+        return false;
+    };
+    let mention = format!("@{}", bot_username.to_lowercase());
+    caption.split_whitespace().any(|word| {
+        let word = word
+            .trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_')
+            .to_lowercase();
+        word == mention
+            || trigger_hashtags
+                .iter()
+                .any(|tag| tag.to_lowercase() == word)
+    })
+}
+
+/// Longest transcript tail shown in the status message (Telegram caps
+/// messages at [`telegram::MAX_MESSAGE_CHARS`]).
+const MAX_PARTIAL_CHARS: usize = 3500;
+
+/// Status text with the transcription progress and, if `show_text`, the end
+/// of the transcript-so-far.
+fn format_progress_status(
+    progress: &transcription::Progress,
+    show_text: bool,
+    language: Language,
+) -> String {
+    let header = Msg::TranscriptionProgress.fill(language, &[("percent", &progress.percent)]);
+    if !show_text || progress.text.is_empty() {
+        return header;
+    }
+    let chars = progress.text.chars().count();
+    let tail: String = if chars > MAX_PARTIAL_CHARS {
+        let skipped: String = progress
+            .text
+            .chars()
+            .skip(chars - MAX_PARTIAL_CHARS)
+            .collect();
+        format!("…{}", skipped)
+    } else {
+        progress.text.clone()
+    };
+    format!("{}\n\n{}", header, tail)
+}
+
+/// Token count of the notes being generated, kept up to date in the status
+/// message while the model streams them.
+struct GenerationStatus {
+    tokens: TokenUpdates,
+    stop: tokio::sync::oneshot::Sender<()>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl GenerationStatus {
+    fn start(bot: &Bot, chat_id: ChatId, status_id: MessageId, language: Language) -> Self {
+        let (tokens, mut updates) = tokio::sync::mpsc::unbounded_channel::<usize>();
+        let (stop, mut stopped) = tokio::sync::oneshot::channel::<()>();
+        let bot = bot.clone();
+        let task = tokio::spawn(async move {
+            let mut last_edit: Option<tokio::time::Instant> = None;
+            loop {
+                let mut count = tokio::select! {
+                    _ = &mut stopped => return,
+                    count = updates.recv() => match count {
+                        Some(count) => count,
+                        None => return,
+                    },
+                };
+                // Coalesce tokens into at most one edit per interval
+                if let Some(at) = last_edit {
+                    tokio::select! {
+                        _ = &mut stopped => return,
+                        _ = tokio::time::sleep_until(at + telegram::EDIT_INTERVAL) => {}
+                    }
+                }
+                while let Ok(newer) = updates.try_recv() {
+                    count = newer;
+                }
+                let status = Msg::GeneratingTokens.fill(language, &[("count", &count)]);
+                let _ = bot
+                    .edit_message_text(chat_id, status_id, status)
+                    .send_retrying()
+                    .await;
+                last_edit = Some(tokio::time::Instant::now());
+            }
+        });
+        Self { tokens, stop, task }
+    }
+
+    /// Stop updating the status message, after any edit in flight, so later
+    /// edits aren't overwritten.
+    async fn finish(self) {
+        let _ = self.stop.send(());
+        let _ = self.task.await;
+    }
+}
+
+/// Save generated notes, or queue them while maintenance mode holds writes.
+#[allow(clippy::too_many_arguments)]
+async fn save_or_hold(
+    bot: &Bot,
+    msg: &Message,
+    status_msg_id: MessageId,
+    config: &Config,
+    feedback: &FeedbackStore,
+    maintenance: &Maintenance,
+    agent: &NoteGeneratorAgent,
+    result: AgentResult,
+) -> ResponseResult<()> {
+    match maintenance.mode() {
+        MaintenanceMode::Off => {
+            let result = agent.save(result).await;
+            finish(bot, msg, status_msg_id, config, feedback, result).await
+        }
+        MaintenanceMode::Hold => {
+            let count = result.notes.len();
+            let held = agent.hold(result, msg.chat.id.0, config.vault.clone());
+            if let Err(e) = maintenance.hold(held) {
+                let err = PipelineError::Save(e.context("Failed to queue notes"));
+                return report_pipeline_error(bot, msg, status_msg_id, config, err).await;
+            }
+            log::info!(
+                "Maintenance: holding {} note(s) for chat {}",
+                count,
+                msg.chat.id
+            );
+            let _ = bot
+                .edit_message_text(
+                    msg.chat.id,
+                    status_msg_id,
+                    Msg::NotesHeld.fill(config.general.language, &[("count", &count)]),
+                )
+                .send_retrying()
+                .await;
+            Ok(())
+        }
+        MaintenanceMode::Reject => {
+            reject_for_maintenance(bot, msg, status_msg_id, config, &result.raw_transcript).await
+        }
+    }
+}
+
+/// Keep the transcript of a memo whose notes could not be generated as a
+/// note tagged `features.fallback_tag`, and queue the memo for another try.
+/// During maintenance, the memo is only queued.
+#[allow(clippy::too_many_arguments)]
+async fn save_fallback(
+    bot: &Bot,
+    msg: &Message,
+    status_msg_id: MessageId,
+    config: &Config,
+    maintenance: &Maintenance,
+    pending: &PendingStore,
+    transcript: &Transcript,
+    err: PipelineError,
+) -> ResponseResult<()> {
+    let mut memo = PendingMemo {
+        chat_id: msg.chat.id.0,
+        message_id: msg.id.0,
+        received_at: sent_at(msg),
+        stage: FailedStage::of(&err),
+        file_id: None,
+        raw_transcript: Some(transcript.text.clone()),
+        language: transcript.language.clone(),
+        notes_dir: config.output.notes_dir.clone(),
+        vault: config.vault.clone(),
+        telegram_link: SourceMessage::of(bot, msg).link,
+        translate: config.transcription.task == WhisperTask::Translate,
+        fallback_note: None,
+        attempts: 0,
+    };
+
+    // The vault must not be written during maintenance: only queue the memo,
+    // it is retried once maintenance ends
+    if maintenance.mode() != MaintenanceMode::Off {
+        if let Err(e) = pending.add(memo) {
+            log::warn!("Failed to record pending memo: {:#}", e);
+            return report_pipeline_error(bot, msg, status_msg_id, config, err).await;
+        }
+        record_capture(config, msg, CaptureStatus::Failed, Vec::new());
+        let hint = retry_hint(config);
+        return send_pipeline_error(bot, msg, status_msg_id, config, err, Some(hint)).await;
+    }
+
+    let (title, path) = match save_fallback_note(config, &transcript.text, sent_at(msg)).await {
+        Ok(saved) => saved,
+        Err(e) => {
+            log::error!("Failed to save fallback note: {:#}", e);
+            return report_pipeline_error(bot, msg, status_msg_id, config, err).await;
+        }
+    };
+
+    memo.fallback_note = Some(path.clone());
+    if let Err(e) = pending.add(memo) {
+        log::warn!("Failed to record pending memo: {:#}", e);
+    }
+    record_capture(
+        config,
+        msg,
+        CaptureStatus::Unprocessed,
+        vec![CapturedNote {
+            title: title.clone(),
+            path,
+        }],
+    );
+
+    let extra = Msg::FallbackSaved.fill(
+        config.general.language,
+        &[
+            ("title", &title),
+            ("tag", &config.features.fallback_tag),
+            ("hint", &retry_hint(config)),
+        ],
+    );
+    send_pipeline_error(bot, msg, status_msg_id, config, err, Some(&extra)).await
+}
+
+/// Write the transcript-only note of a failed memo, returning its title and path.
+async fn save_fallback_note(
+    config: &Config,
+    raw_transcript: &str,
+    date: chrono::DateTime<chrono::Utc>,
+) -> anyhow::Result<(String, std::path::PathBuf)> {
+    let note = pending::fallback_note(raw_transcript, date, &config.features.fallback_tag);
+    let title = note.title.clone();
+    let path = NoteWriter::new()
+        .with_vault(config.vault_dir())
+        .run((vec![note], config.output.notes_dir.clone()))
+        .await?
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("No fallback note written"))?;
+    Ok((title, path))
+}
+
+/// Queue a memo that failed before it was transcribed, so it is downloaded
+/// and transcribed again later.
+async fn queue_failed_memo(
+    bot: &Bot,
+    msg: &Message,
+    status_msg_id: MessageId,
+    config: &Config,
+    pending: &PendingStore,
+    file_id: &str,
+    err: PipelineError,
+) -> ResponseResult<()> {
+    let memo = PendingMemo {
+        chat_id: msg.chat.id.0,
+        message_id: msg.id.0,
+        received_at: sent_at(msg),
+        stage: FailedStage::of(&err),
+        file_id: Some(file_id.to_string()),
+        raw_transcript: None,
+        language: None,
+        notes_dir: config.output.notes_dir.clone(),
+        vault: config.vault.clone(),
+        telegram_link: SourceMessage::of(bot, msg).link,
+        translate: config.transcription.task == WhisperTask::Translate,
+        fallback_note: None,
+        attempts: 0,
+    };
+    if let Err(e) = pending.add(memo) {
+        log::warn!("Failed to record pending memo: {:#}", e);
+        return report_pipeline_error(bot, msg, status_msg_id, config, err).await;
+    }
+    record_capture(config, msg, CaptureStatus::Failed, Vec::new());
+    send_pipeline_error(
+        bot,
+        msg,
+        status_msg_id,
+        config,
+        err,
+        Some(retry_hint(config)),
+    )
+    .await
+}
+
+/// How a queued memo will be retried.
+fn retry_hint(config: &Config) -> &'static str {
+    if config.features.pending_retry_secs > 0 {
+        Msg::RetryHintAutomatic
+    } else {
+        Msg::RetryHintManual
+    }
+    .text(config.general.language)
+}
+
+/// Tell the user no notes are created during maintenance, returning the
+/// transcript so nothing is lost.
+async fn reject_for_maintenance(
+    bot: &Bot,
+    msg: &Message,
+    status_msg_id: MessageId,
+    config: &Config,
+    raw_transcript: &str,
+) -> ResponseResult<()> {
+    log::info!("Maintenance: rejecting memo from chat {}", msg.chat.id);
+    let _ = bot
+        .delete_message(msg.chat.id, status_msg_id)
+        .send_retrying()
+        .await;
+    let text =
+        Msg::MaintenanceRejected.fill(config.general.language, &[("transcript", &raw_transcript)]);
+    bot.send_message(msg.chat.id, text).send_retrying().await?;
+    Ok(())
+}
+
+/// Reaction put on the memo when `reply_verbosity = "silent"`.
+const SILENT_REACTION: &str = "👌";
+
+/// Report the outcome of note generation, replacing the status message.
+async fn finish(
+    bot: &Bot,
+    msg: &Message,
+    status_msg_id: MessageId,
+    config: &Config,
+    feedback: &FeedbackStore,
+    result: Result<AgentResult, PipelineError>,
+) -> ResponseResult<()> {
+    let language = config.general.language;
+    match result {
+        Ok(result) => {
+            let _ = bot
+                .delete_message(msg.chat.id, status_msg_id)
+                .send_retrying()
+                .await;
+            let (mut text, truncated) = match config.telegram.reply_verbosity {
+                ReplyVerbosity::Full => format_result_message(&result, config),
+                ReplyVerbosity::Summary => (
+                    Msg::NotesSaved.fill(language, &[("count", &result.saved_paths.len())]),
+                    false,
+                ),
+                ReplyVerbosity::Silent => {
+                    // Just acknowledge the memo with a reaction
+                    let reaction = ReactionType::Emoji {
+                        emoji: SILENT_REACTION.to_string(),
+                    };
+                    if let Err(e) = bot
+                        .set_message_reaction(msg.chat.id, msg.id)
+                        .reaction(vec![reaction])
+                        .send_retrying()
+                        .await
+                    {
+                        log::warn!("Failed to react to message: {}", e);
+                    }
+                    log::info!("Notes generated and saved for user {}", msg.chat.id);
+                    return Ok(());
+                }
+            };
+            if let Some(truncation) = &result.truncation {
+                text.push_str("\n\n");
+                text.push_str(&truncation.notice(language));
+            }
+            if let Some(primary) = &result.fallback_from {
+                text.push_str("\n\n");
+                text.push_str(
+                    &Msg::FallbackUsed
+                        .fill(language, &[("primary", primary), ("model", &result.model)]),
+                );
+            }
+            if !result.tasks.is_empty() {
+                let tasks: Vec<String> = result
+                    .tasks
+                    .iter()
+                    .map(|task| format!("• {}", task))
+                    .collect();
+                text.push_str("\n\n");
+                text.push_str(&Msg::TasksExtracted.fill(language, &[("tasks", &tasks.join("\n"))]));
+            }
+            if config.telegram.show_timings {
+                if let Some(footer) = result.timings.footer() {
+                    text.push_str("\n\n");
+                    text.push_str(&footer);
+                }
+            }
+            if !result.usage.is_empty() {
+                text.push_str(&format!(
+                    "\n\n🔢 {}",
+                    result.usage.summary(&config.ai_model)
+                ));
+            }
+            let mut request = bot.send_message(msg.chat.id, text);
+            if truncated {
+                request = request.reply_markup(transcript_button(msg.id, language));
+            }
+            let sent = request.send_retrying().await?;
+            feedback.track_result(
+                msg.chat.id.0,
+                sent.id.0,
+                ResultSnapshot::from_agent_result(&result),
+            );
+            log::info!("Notes generated and saved for user {}", msg.chat.id);
+            Ok(())
+        }
+        Err(e) => report_pipeline_error(bot, msg, status_msg_id, config, e).await,
+    }
+}
+
+/// Build the "Completato" message listing saved notes and the transcript,
+/// also telling whether the transcript was cut to the preview length.
+fn format_result_message(result: &AgentResult, config: &Config) -> (String, bool) {
+    let language = config.general.language;
+    let mut response = Msg::ResultHeader.fill(language, &[("count", &result.notes.len())]);
+
+    for (i, note) in result.notes.iter().enumerate() {
+        match &note.icon {
+            Some(icon) => response.push_str(&format!("{}. {} **{}**\n", i + 1, icon, note.title)),
+            None => response.push_str(&format!("{}. **{}**\n", i + 1, note.title)),
+        }
+        response.push_str(&format!("   Tags: {}\n", note.tags.join(", ")));
+        response.push_str(&format!(
+            "   File: {}\n\n",
+            result
+                .saved_paths
+                .get(i)
+                .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+                .unwrap_or_else(|| Msg::ResultFileMissing.text(language).to_string())
+        ));
+        let uri = config.output.obsidian_vault.as_deref().and_then(|vault| {
+            obsidian::open_uri(vault, &config.vault_dir(), result.saved_paths.get(i)?)
+        });
+        if let Some(uri) = uri {
+            response.push_str(&format!("   🔗 {}\n\n", uri));
+        }
+    }
+
+    let max_chars = config.telegram.transcript_preview_chars;
+    let (cleaned, cut_cleaned) = transcript_preview(&result.cleaned_transcript, max_chars);
+    if result.cleaned_transcript != result.raw_transcript {
+        let (raw, cut_raw) = transcript_preview(&result.raw_transcript, max_chars);
+        response.push_str(&format!(
+            "\n{}\n{}\n\n{}\n{}",
+            Msg::TranscriptCorrected.text(language),
+            cleaned,
+            Msg::TranscriptOriginal.text(language),
+            raw
+        ));
+        (response, cut_cleaned || cut_raw)
+    } else {
+        response.push_str(&format!(
+            "\n{}\n{}",
+            Msg::Transcript.text(language),
+            cleaned
+        ));
+        (response, cut_cleaned)
+    }
+}
+
+/// The first `max_chars` characters of a transcript, cut at a word boundary,
+/// and whether anything was left out. `0` keeps the whole transcript.
+fn transcript_preview(transcript: &str, max_chars: usize) -> (String, bool) {
+    if max_chars == 0 || transcript.chars().count() <= max_chars {
+        return (transcript.to_string(), false);
+    }
+    let cut: String = transcript.chars().take(max_chars).collect();
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(space) if space > cut.len() / 2 => &cut[..space],
+        _ => &cut,
+    };
+    (format!("{}…", cut.trim_end()), true)
+}
+
+/// Prefix of the callback data of the "full transcript" button.
+const TRANSCRIPT_CALLBACK: &str = "transcript";
+
+/// Button under a result message sending the full transcript of `memo_id`.
+fn transcript_button(memo_id: MessageId, language: Language) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([[InlineKeyboardButton::callback(
+        Msg::FullTranscriptButton.text(language),
+        format!("{}:{}", TRANSCRIPT_CALLBACK, memo_id.0),
+    )]])
+}
+
+/// Whether a button press asks for a full transcript.
+pub fn is_transcript_request(query: &CallbackQuery) -> bool {
+    query
+        .data
+        .as_deref()
+        .is_some_and(|data| data.starts_with(TRANSCRIPT_CALLBACK))
+}
+
+/// Handler for the "📄 Full transcript" button: send the transcript a
+/// result message cut short, as a file when it exceeds a Telegram message.
+pub async fn transcript_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    config: Config,
+    feedback: Arc<FeedbackStore>,
+) -> ResponseResult<()> {
+    let Some(message) = query.regular_message().cloned() else {
+        return Ok(());
+    };
+    let language = config.general.language;
+    let memo_id = query.data.as_deref().and_then(|data| {
+        data.strip_prefix(TRANSCRIPT_CALLBACK)?
+            .strip_prefix(':')?
+            .parse()
+            .ok()
+    });
+
+    // The result message remembers both transcripts; older ones only have
+    // the raw transcript in the audit log
+    let transcript = match feedback.tracked_result(message.chat.id.0, message.id.0) {
+        Some(result) if result.cleaned_transcript != result.raw_transcript => Some(format!(
+            "{}\n{}\n\n{}\n{}",
+            Msg::TranscriptCorrected.text(language),
+            result.cleaned_transcript,
+            Msg::TranscriptOriginal.text(language),
+            result.raw_transcript
+        )),
+        Some(result) => Some(result.cleaned_transcript),
+        None => memo_id
+            .and_then(|memo_id| logged_transcript(&config, message.chat.id.0, memo_id))
+            .map(|r| r.raw_transcript),
+    };
+    let Some(transcript) = transcript else {
+        bot.answer_callback_query(query.id)
+            .text(Msg::TranscriptGone.text(language))
+            .send_retrying()
+            .await?;
+        return Ok(());
+    };
+    bot.answer_callback_query(query.id).send_retrying().await?;
+
+    let reply = ReplyParameters::new(message.id).allow_sending_without_reply();
+    if transcript.chars().count() <= telegram::MAX_MESSAGE_CHARS {
+        bot.send_message(message.chat.id, transcript)
+            .reply_parameters(reply)
+            .send_retrying()
+            .await?;
+    } else {
+        let file = InputFile::memory(transcript.into_bytes())
+            .file_name(Msg::TranscriptFileName.text(language));
+        bot.send_document(message.chat.id, file)
+            .reply_parameters(reply)
+            .send_retrying()
+            .await?;
+    }
+    Ok(())
+}
+
+/// The audit log's transcript of memo `message_id` in `chat_id`.
+fn logged_transcript(config: &Config, chat_id: i64, message_id: i32) -> Option<TranscriptRecord> {
+    let log = TranscriptLog::new(&config.output.data_dir);
+    TranscriptLog::load_records(log.path())
+        .ok()?
+        .into_iter()
+        .rev()
+        .find(|r| r.chat_id == chat_id && r.message_id == message_id)
+}
+
+/// Prefix of the callback data of the `/history` buttons.
+const HISTORY_CALLBACK: &str = "history";
+/// Captures listed per `/history` page.
+const HISTORY_PAGE_SIZE: usize = 5;
+
+/// Record the outcome of a memo that was not fully processed in `/history`
+/// (fully processed ones are recorded when their notes are saved).
+fn record_capture(config: &Config, msg: &Message, status: CaptureStatus, notes: Vec<CapturedNote>) {
+    let entry = HistoryEntry {
+        timestamp: sent_at(msg),
+        chat_id: msg.chat.id.0,
+        message_id: msg.id.0,
+        status,
+        provider: config.transcription.provider.clone(),
+        model: None,
+        notes,
+    };
+    if let Err(e) = HistoryLog::new(&config.output.data_dir).append(&entry) {
+        log::warn!("Failed to record the capture history: {:#}", e);
+    }
+}
+
+/// Handler for /history: the chat's captures, newest first, with buttons to
+/// page through them and act on each.
+pub async fn history_handler(bot: Bot, msg: Message, config: Config) -> ResponseResult<()> {
+    let (text, keyboard) = history_page(&config, msg.chat.id.0, 0);
+    let mut request = bot.send_message(msg.chat.id, text);
+    if let Some(keyboard) = keyboard {
+        request = request.reply_markup(keyboard);
+    }
+    request.send_retrying().await?;
+    Ok(())
+}
+
+/// Text and buttons of page `page` of the chat's capture history.
+fn history_page(
+    config: &Config,
+    chat_id: i64,
+    page: usize,
+) -> (String, Option<InlineKeyboardMarkup>) {
+    let language = config.general.language;
+    let captures = match HistoryLog::new(&config.output.data_dir).captures(chat_id) {
+        Ok(captures) => captures,
+        Err(e) => {
+            log::warn!("Failed to read the capture history: {:#}", e);
+            return (Msg::HistoryUnavailable.text(language).to_string(), None);
+        }
+    };
+    if captures.is_empty() {
+        return (Msg::HistoryEmpty.text(language).to_string(), None);
+    }
+
+    let pages = captures.len().div_ceil(HISTORY_PAGE_SIZE);
+    let page = page.min(pages - 1);
+    let mut text = Msg::HistoryHeader.fill(language, &[("page", &(page + 1)), ("pages", &pages)]);
+    let mut rows = Vec::new();
+    for (i, entry) in captures
+        .iter()
+        .enumerate()
+        .skip(page * HISTORY_PAGE_SIZE)
+        .take(HISTORY_PAGE_SIZE)
+    {
+        let n = i + 1;
+        let when = entry.timestamp.with_timezone(&config.locale.timezone);
+        let via = match &entry.model {
+            Some(model) => format!("{} / {}", entry.provider, model),
+            None => entry.provider.clone(),
+        };
+        let titles = match entry.notes.is_empty() {
+            true => "—".to_string(),
+            false => entry
+                .notes
+                .iter()
+                .map(|note| format!("«{}»", note.title))
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        text.push_str(&format!(
+            "\n{}. {} · {} · {}\n   {}\n",
+            n,
+            when.format(Msg::DateTimeFormat.text(language)),
+            entry.status.label(language),
+            via,
+            titles
+        ));
+        let button = |label: Msg, action: &str| {
+            InlineKeyboardButton::callback(
+                format!("{} {}", n, label.text(language)),
+                format!("{}:{}:{}", HISTORY_CALLBACK, action, entry.message_id),
+            )
+        };
+        rows.push(vec![
+            button(Msg::HistoryOpen, "open"),
+            button(Msg::HistoryText, "text"),
+            button(Msg::HistoryRedo, "redo"),
+        ]);
+    }
+
+    let mut navigation = Vec::new();
+    if page > 0 {
+        navigation.push(InlineKeyboardButton::callback(
+            Msg::HistoryNewer.text(language),
+            format!("{}:page:{}", HISTORY_CALLBACK, page - 1),
+        ));
+    }
+    if page + 1 < pages {
+        navigation.push(InlineKeyboardButton::callback(
+            Msg::HistoryOlder.text(language),
+            format!("{}:page:{}", HISTORY_CALLBACK, page + 1),
+        ));
+    }
+    if !navigation.is_empty() {
+        rows.push(navigation);
+    }
+    (text, Some(InlineKeyboardMarkup::new(rows)))
+}
+
+/// Whether a button press comes from a `/history` list.
+pub fn is_history_request(query: &CallbackQuery) -> bool {
+    query
+        .data
+        .as_deref()
+        .is_some_and(|data| data.starts_with(HISTORY_CALLBACK))
+}
+
+/// Handler for the `/history` buttons: turn the page, send a capture's notes
+/// or transcript, or generate its notes again from the transcript.
+pub async fn history_callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    config: Config,
+    feedback: Arc<FeedbackStore>,
+    maintenance: Arc<Maintenance>,
+) -> ResponseResult<()> {
+    let Some(message) = query.regular_message().cloned() else {
+        return Ok(());
+    };
+    let chat_id = message.chat.id;
+    let language = config.general.language;
+    let Some((action, arg)) = query.data.as_deref().and_then(|data| {
+        let (action, arg) = data
+            .strip_prefix(HISTORY_CALLBACK)?
+            .strip_prefix(':')?
+            .split_once(':')?;
+        Some((action.to_string(), arg.parse::<i32>().ok()?))
+    }) else {
+        bot.answer_callback_query(query.id).send_retrying().await?;
+        return Ok(());
+    };
+
+    match action.as_str() {
+        "page" => {
+            bot.answer_callback_query(query.id).send_retrying().await?;
+            let (text, keyboard) = history_page(&config, chat_id.0, arg.max(0) as usize);
+            let mut request = bot.edit_message_text(chat_id, message.id, text);
+            if let Some(keyboard) = keyboard {
+                request = request.reply_markup(keyboard);
+            }
+            let _ = request.send_retrying().await;
+        }
+        "open" => {
+            let entry = HistoryLog::new(&config.output.data_dir)
+                .find(chat_id.0, arg)
+                .ok()
+                .flatten();
+            let paths: Vec<_> = entry
+                .iter()
+                .flat_map(|entry| &entry.notes)
+                .map(|note| note.path.clone())
+                .filter(|path| path.exists())
+                .collect();
+            if paths.is_empty() {
+                bot.answer_callback_query(query.id)
+                    .text(Msg::HistoryNotesGone.text(language))
+                    .send_retrying()
+                    .await?;
+                return Ok(());
+            }
+            bot.answer_callback_query(query.id).send_retrying().await?;
+            for path in paths {
+                bot.send_document(chat_id, InputFile::file(path))
+                    .send_retrying()
+                    .await?;
+            }
+        }
+        "text" | "redo" => {
+            let Some(record) = logged_transcript(&config, chat_id.0, arg) else {
+                bot.answer_callback_query(query.id)
+                    .text(Msg::TranscriptUnavailable.text(language))
+                    .send_retrying()
+                    .await?;
+                return Ok(());
+            };
+            bot.answer_callback_query(query.id).send_retrying().await?;
+            if action == "text" {
+                let transcript = record.raw_transcript;
+                if transcript.chars().count() <= telegram::MAX_MESSAGE_CHARS {
+                    bot.send_message(chat_id, transcript)
+                        .send_retrying()
+                        .await?;
+                } else {
+                    let file = InputFile::memory(transcript.into_bytes())
+                        .file_name(Msg::TranscriptFileName.text(language));
+                    bot.send_document(chat_id, file).send_retrying().await?;
+                }
+                return Ok(());
+            }
+
+            let status = bot
+                .send_message(chat_id, Msg::HistoryReprocessing.text(language))
+                .send_retrying()
+                .await?;
+            let config = config.for_vault(record.vault.as_deref());
+            let agent = match NoteGeneratorAgent::new(&config) {
+                Ok(agent) => agent
+                    .with_received_at(record.timestamp)
+                    .with_source_message(SourceMessage {
+                        chat_id: chat_id.0,
+                        message_id: arg,
+                        link: None,
+                    }),
+                Err(e) => {
+                    report_pipeline_error(&bot, &message, status.id, &config, e).await?;
+                    return Ok(());
+                }
+            };
+            match agent.generate(record.raw_transcript, &[]).await {
+                Ok(result) => {
+                    save_or_hold(
+                        &bot,
+                        &message,
+                        status.id,
+                        &config,
+                        &feedback,
+                        &maintenance,
+                        &agent,
+                        result,
+                    )
+                    .await?
+                }
+                Err(e) => report_pipeline_error(&bot, &message, status.id, &config, e).await?,
+            }
+        }
+        _ => {
+            bot.answer_callback_query(query.id).send_retrying().await?;
+        }
+    }
+    Ok(())
+}
+
+/// Present the candidates of each model side by side with a button to pick one.
+async fn send_candidates(
+    bot: &Bot,
+    msg: &Message,
+    selections: &PendingSelections,
+    candidates: Vec<AgentResult>,
+    language: Language,
+) -> ResponseResult<()> {
+    let mut text = Msg::CandidatesHeader.text(language).to_string();
+    for (i, candidate) in candidates.iter().enumerate() {
+        text.push_str(&format!(
+            "\n{} — {}\n",
+            note_generator::candidate_label(i),
+            candidate.model
+        ));
+        for note in &candidate.notes {
+            text.push_str(&format!(
+                "• {}**{}** ({})\n",
+                note.icon
+                    .as_deref()
+                    .map(|i| format!("{} ", i))
+                    .unwrap_or_default(),
+                note.title,
+                note.tags.join(", ")
+            ));
+        }
+    }
+
+    let labels: Vec<char> = (0..candidates.len())
+        .map(note_generator::candidate_label)
+        .collect();
+    let key = selections.insert(msg.clone(), candidates);
+    let buttons = labels.iter().enumerate().map(|(i, label)| {
+        InlineKeyboardButton::callback(
+            Msg::UseCandidate.fill(language, &[("label", label)]),
+            PendingSelections::callback_data(key, i),
+        )
+    });
+
+    bot.send_message(msg.chat.id, text)
+        .reply_markup(InlineKeyboardMarkup::new([buttons]))
+        .send_retrying()
+        .await?;
+    Ok(())
+}
+
+/// Handler for the "Use A"/"Use B" buttons: saves the chosen candidate
+#[allow(clippy::too_many_arguments)]
+pub async fn selection_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    config: Config,
+    feedback: Arc<FeedbackStore>,
+    selections: Arc<PendingSelections>,
+    chats: Arc<ChatStateStore>,
+    maintenance: Arc<Maintenance>,
+    me: Me,
+) -> ResponseResult<()> {
+    let Some((key, index)) = query
+        .data
+        .as_deref()
+        .and_then(PendingSelections::parse_callback_data)
+    else {
+        return Ok(());
+    };
+    let Some(message) = query.regular_message().cloned() else {
+        return Ok(());
+    };
+
+    // Save with the config the memo was processed with, group folder included
+    let picked = selections.take(key, index).and_then(|(memo, chosen)| {
+        let config = memo_config(&memo, &config, &chats, &me)?;
+        Some((config, memo, chosen))
+    });
+    let Some((config, memo, chosen)) = picked else {
+        bot.answer_callback_query(query.id)
+            .text(Msg::SelectionExpired.text(config.general.language))
+            .send_retrying()
+            .await?;
+        return Ok(());
+    };
+    bot.answer_callback_query(query.id).send_retrying().await?;
+    log::info!("User picked candidate from {}", chosen.model);
+
+    let _ = bot
+        .edit_message_text(
+            message.chat.id,
+            message.id,
+            Msg::SavingChoice.text(config.general.language),
+        )
+        .send_retrying()
+        .await;
+    let agent = match NoteGeneratorAgent::new(&config) {
+        Ok(agent) => agent
+            .with_received_at(sent_at(&memo))
+            .with_source_message(SourceMessage::of(&bot, &memo)),
+        Err(e) => return report_pipeline_error(&bot, &message, message.id, &config, e).await,
+    };
+    save_or_hold(
+        &bot,
+        &message,
+        message.id,
+        &config,
+        &feedback,
+        &maintenance,
+        &agent,
+        chosen,
+    )
+    .await
+}
+
+/// Replace the status message with the user-facing description of a pipeline
+/// failure, logging its code and alerting the admin for operational failures.
+async fn report_pipeline_error(
+    bot: &Bot,
+    msg: &Message,
+    status_msg_id: MessageId,
+    config: &Config,
+    err: PipelineError,
+) -> ResponseResult<()> {
+    send_pipeline_error(bot, msg, status_msg_id, config, err, None).await
+}
+
+/// Like [`report_pipeline_error`], appending `extra` to the user message.
+async fn send_pipeline_error(
+    bot: &Bot,
+    msg: &Message,
+    status_msg_id: MessageId,
+    config: &Config,
+    err: PipelineError,
+    extra: Option<&str>,
+) -> ResponseResult<()> {
+    log::error!("[{}] chat {}: {}", err.code(), msg.chat.id, err);
+    let _ = bot
+        .delete_message(msg.chat.id, status_msg_id)
+        .send_retrying()
+        .await;
+    let mut text = err.user_message(config.general.language);
+    if let Some(extra) = extra {
+        text.push_str("\n\n");
+        text.push_str(extra);
+    }
+    bot.send_message(msg.chat.id, text).send_retrying().await?;
+
+    if err.needs_admin() {
+        notify_admin(
+            bot,
+            config,
+            format!("🚨 [{}] chat {}: {}", err.code(), msg.chat.id, err),
+        )
+        .await;
+    }
+    Ok(())
+}
+
+/// Keep `dir` under its `[quota]` cap of `max_mb` (if set), telling the admin
+/// about evicted files and a folder close to its cap. `keep` is never evicted.
+async fn enforce_quota(
+    bot: &Bot,
+    config: &Config,
+    dir: &std::path::Path,
+    max_mb: Option<u64>,
+    keep: &[std::path::PathBuf],
+) {
+    let Some(max_mb) = max_mb else {
+        return;
+    };
+    let language = config.general.language;
+    let mb = |bytes: u64| bytes / (1024 * 1024);
+    match Quota::new(dir, max_mb, config.quota.warn_percent).enforce(keep) {
+        Ok(report) if report.evicted_files > 0 => {
+            notify_admin(
+                bot,
+                config,
+                Msg::QuotaEvicted.fill(
+                    language,
+                    &[
+                        ("dir", &dir.display()),
+                        ("files", &report.evicted_files),
+                        ("freed", &mb(report.freed_bytes)),
+                        ("used", &mb(report.used_bytes)),
+                        ("max", &max_mb),
+                    ],
+                ),
+            )
+            .await;
+        }
+        Ok(report) if report.approaching => {
+            notify_admin(
+                bot,
+                config,
+                Msg::QuotaApproaching.fill(
+                    language,
+                    &[
+                        ("dir", &dir.display()),
+                        ("used", &mb(report.used_bytes)),
+                        ("max", &max_mb),
+                    ],
+                ),
+            )
+            .await;
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to check the quota of {}: {}", dir.display(), e),
+    }
+}
+
+/// Send an operational alert to the configured admin chat, if any.
+async fn notify_admin(bot: &Bot, config: &Config, text: String) {
+    if let Some(admin_chat_id) = config.telegram.admin_chat_id {
+        if let Err(e) = bot
+            .send_message(ChatId(admin_chat_id), text)
+            .send_retrying()
+            .await
+        {
+            log::warn!("Failed to send admin alert: {}", e);
+        }
+    }
+}
+
+/// Handler for 👍/👎 reactions on result messages
+pub async fn reaction_handler(
+    bot: Bot,
+    reaction: MessageReactionUpdated,
+    config: Config,
+    feedback: Arc<FeedbackStore>,
+) -> ResponseResult<()> {
+    let rating = reaction.new_reaction.iter().find_map(|r| match r {
+        ReactionType::Emoji { emoji } => Rating::from_emoji(emoji),
+        ReactionType::CustomEmoji { .. } => None,
+    });
+    let Some(rating) = rating else {
+        return Ok(());
+    };
+
+    let record = match feedback.record_rating(reaction.chat.id.0, reaction.message_id.0, rating) {
+        Ok(Some(record)) => record,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            log::error!("Failed to store feedback: {}", e);
+            return Ok(());
+        }
+    };
+
+    if rating == Rating::Down {
+        let prompt = bot
+            .send_message(
+                reaction.chat.id,
+                Msg::FeedbackAsk.text(config.general.language),
+            )
+            .reply_parameters(ReplyParameters::new(reaction.message_id))
+            .reply_markup(ForceReply::new())
+            .send_retrying()
+            .await?;
+        feedback.expect_comment(prompt.id.0, record);
+    }
+
+    Ok(())
+}
+
+/// Handler for text messages (fallback)
+pub async fn text_handler(
+    bot: Bot,
+    msg: Message,
+    config: Config,
+    feedback: Arc<FeedbackStore>,
+) -> ResponseResult<()> {
+    let language = config.general.language;
+    // Replies to a "what was wrong?" prompt are feedback comments
+    if let (Some(reply_to), Some(text)) = (msg.reply_to_message(), msg.text()) {
+        match feedback.record_comment(msg.chat.id.0, reply_to.id.0, text) {
+            Ok(true) => {
+                bot.send_message(msg.chat.id, Msg::FeedbackThanks.text(language))
+                    .send_retrying()
+                    .await?;
+                return Ok(());
+            }
+            Ok(false) => {}
+            Err(e) => log::error!("Failed to store feedback comment: {}", e),
+        }
+    }
+
+    // Stay quiet about ordinary conversation in groups
+    if !msg.chat.is_private() {
+        return Ok(());
+    }
+
+    bot.send_message(msg.chat.id, Msg::TextOnly.text(language))
+        .send_retrying()
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_addressed_to_bot() {
+        let triggers = vec!["#dot".to_string()];
+        let addressed = |caption, reply| is_addressed_to_bot(caption, reply, "DotBot", &triggers);
+
+        assert!(addressed(Some("@dotbot per favore"), false));
+        assert!(addressed(Some("idea veloce #Dot."), false));
+        assert!(addressed(None, true));
+        assert!(!addressed(Some("#dotnet e @dotbotter"), false));
+        assert!(!addressed(None, false));
+
+        assert!(asks_for_translation(Some("riunione con Tom #EN.")));
+        assert!(!asks_for_translation(Some("#entrate di maggio")));
+        assert!(!asks_for_translation(None));
+    }
+
+    #[test]
+    fn test_format_progress_status_keeps_tail() {
+        let progress = |text: &str| transcription::Progress {
+            percent: 40,
+            text: text.to_string(),
+        };
+        assert_eq!(
+            format_progress_status(&progress("ciao"), true, Language::It),
+            "🎤 Trascrizione in corso… 40%\n\nciao"
+        );
+        assert_eq!(
+            format_progress_status(&progress("ciao"), false, Language::It),
+            "🎤 Trascrizione in corso… 40%"
+        );
+
+        let long = format!("{}fine", "à".repeat(MAX_PARTIAL_CHARS));
+        let status = format_progress_status(&progress(&long), true, Language::En);
+        assert!(status.contains("…"));
+        assert!(status.ends_with("fine"));
+        assert!(status.chars().count() < MAX_PARTIAL_CHARS + 50);
+    }
+
+    #[test]
+    fn test_transcript_preview_cuts_at_word() {
+        assert_eq!(
+            transcript_preview("breve", 10),
+            ("breve".to_string(), false)
+        );
+        assert_eq!(
+            transcript_preview("ciao a tutti quanti", 14),
+            ("ciao a tutti…".to_string(), true)
+        );
+        let long = "è".repeat(50);
+        assert_eq!(transcript_preview(&long, 0), (long.clone(), false));
+        assert_eq!(transcript_preview(&long, 10).0.chars().count(), 11);
+    }
 }
@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::i18n::{Language, Msg};
+
+/// Serializes appends to the history within the process.
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+/// Outcome of a capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureStatus {
+    /// Notes were generated and saved.
+    Saved,
+    /// Only the transcript was saved, as a `features.fallback_tag` note.
+    Unprocessed,
+    /// Nothing was saved; the memo is queued for another try.
+    Failed,
+}
+
+impl CaptureStatus {
+    pub fn label(&self, language: Language) -> &'static str {
+        match self {
+            CaptureStatus::Saved => Msg::CaptureSaved,
+            CaptureStatus::Unprocessed => Msg::CaptureUnprocessed,
+            CaptureStatus::Failed => Msg::CaptureFailed,
+        }
+        .text(language)
+    }
+}
+
+/// A note written for a capture.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapturedNote {
+    pub title: String,
+    pub path: PathBuf,
+}
+
+/// One outcome of processing a memo or document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the memo was sent.
+    pub timestamp: DateTime<Utc>,
+    pub chat_id: i64,
+    pub message_id: i32,
+    pub status: CaptureStatus,
+    /// Transcription provider, or "document" for documents.
+    pub provider: String,
+    /// Model that wrote the notes, if one did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub notes: Vec<CapturedNote>,
+}
+
+/// Log of captures in `<data_dir>/history.jsonl`, browsed with `/history`.
+///
+/// A memo processed again (retried, re-processed) gets a new entry; the
+/// latest one is its current state.
+pub struct HistoryLog {
+    path: PathBuf,
+}
+
+impl HistoryLog {
+    pub fn new<P: AsRef<Path>>(data_dir: P) -> Self {
+        Self {
+            path: data_dir.as_ref().join("history.jsonl"),
+        }
+    }
+
+    pub fn append(&self, entry: &HistoryEntry) -> Result<()> {
+        let _guard = HISTORY_LOCK.lock().unwrap();
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create data directory")?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open history: {}", self.path.display()))?;
+        let line = serde_json::to_string(entry).context("Failed to serialize history entry")?;
+        writeln!(file, "{}", line).context("Failed to write history entry")?;
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Vec<HistoryEntry>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read history: {}", self.path.display()))
+            }
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Current state of each capture of `chat_id`, newest memo first.
+    pub fn captures(&self, chat_id: i64) -> Result<Vec<HistoryEntry>> {
+        Ok(latest_per_memo(
+            self.load()?
+                .into_iter()
+                .filter(|entry| entry.chat_id == chat_id),
+        ))
+    }
+
+    /// Current state of one capture.
+    pub fn find(&self, chat_id: i64, message_id: i32) -> Result<Option<HistoryEntry>> {
+        Ok(self
+            .load()?
+            .into_iter()
+            .rev()
+            .find(|entry| entry.chat_id == chat_id && entry.message_id == message_id))
+    }
+}
+
+/// The last entry of each memo in `entries` (oldest first), newest memo first.
+fn latest_per_memo(entries: impl Iterator<Item = HistoryEntry>) -> Vec<HistoryEntry> {
+    let mut latest: Vec<HistoryEntry> = Vec::new();
+    for entry in entries {
+        latest.retain(|e| (e.chat_id, e.message_id) != (entry.chat_id, entry.message_id));
+        latest.push(entry);
+    }
+    latest.sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
+    latest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message_id: i32, minutes: i64, status: CaptureStatus) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: DateTime::from_timestamp(1_700_000_000 + minutes * 60, 0).unwrap(),
+            chat_id: 42,
+            message_id,
+            status,
+            provider: "groq".to_string(),
+            model: None,
+            notes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_captures_keep_the_latest_state_newest_first() {
+        let dir = std::env::temp_dir().join(format!("dot-history-{}", uuid::Uuid::new_v4()));
+        let history = HistoryLog::new(&dir);
+        assert!(history.captures(42).unwrap().is_empty());
+
+        history.append(&entry(1, 0, CaptureStatus::Failed)).unwrap();
+        history.append(&entry(2, 5, CaptureStatus::Saved)).unwrap();
+        // Memo 1 completes on retry, after memo 2 was saved
+        history.append(&entry(1, 0, CaptureStatus::Saved)).unwrap();
+        history
+            .append(&HistoryEntry {
+                chat_id: 7,
+                ..entry(3, 10, CaptureStatus::Saved)
+            })
+            .unwrap();
+
+        let captures = history.captures(42).unwrap();
+        let states: Vec<_> = captures.iter().map(|e| (e.message_id, e.status)).collect();
+        assert_eq!(
+            states,
+            [(2, CaptureStatus::Saved), (1, CaptureStatus::Saved)]
+        );
+        assert_eq!(
+            history.find(42, 1).unwrap().map(|e| e.status),
+            Some(CaptureStatus::Saved)
+        );
+        assert!(history.find(42, 3).unwrap().is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
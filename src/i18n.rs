@@ -0,0 +1,1069 @@
+//! Strings table for the prompts and the bot's replies, in the language set
+//! with `[general] language`.
+//!
+//! Each message has a text per language; `{{name}}` placeholders are filled
+//! with [`Msg::fill`] (see [`template::render`]).
+
+use serde::Deserialize;
+use std::fmt::Display;
+
+use crate::template;
+
+/// Language of the prompts and the bot's replies.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    It,
+    En,
+}
+
+impl Language {
+    /// ISO 639-1 code, as transcribers report the language of a memo.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::It => "it",
+            Language::En => "en",
+        }
+    }
+}
+
+macro_rules! messages {
+    ($($(#[$doc:meta])* $name:ident { it: $it:expr, en: $en:expr $(,)? })*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Msg {
+            $($(#[$doc])* $name,)*
+        }
+
+        impl Msg {
+            #[cfg(test)]
+            const ALL: &'static [Msg] = &[$(Msg::$name),*];
+
+            pub fn text(self, language: Language) -> &'static str {
+                match (self, language) {
+                    $(
+                        (Msg::$name, Language::It) => $it,
+                        (Msg::$name, Language::En) => $en,
+                    )*
+                }
+            }
+        }
+    };
+}
+
+impl Msg {
+    /// The text with its `{{name}}` placeholders filled from `values`.
+    pub fn fill(self, language: Language, values: &[(&str, &(dyn Display + Sync))]) -> String {
+        let values: Vec<(&str, String)> = values
+            .iter()
+            .map(|(name, value)| (*name, value.to_string()))
+            .collect();
+        let values: Vec<(&str, &str)> = values
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect();
+        template::render(self.text(language), &values)
+    }
+}
+
+messages! {
+    // Prompts
+
+    /// System prompt of note generation, unless `prompts.notes_system` is set.
+    NotesSystemPrompt {
+        it: include_str!("../prompts/notes_system.md"),
+        en: include_str!("../prompts/en/notes_system.md"),
+    }
+    /// System prompt of the corrector, unless `prompts.corrector_system` is set.
+    CorrectorSystemPrompt {
+        it: include_str!("../prompts/corrector_system.md"),
+        en: include_str!("../prompts/en/corrector_system.md"),
+    }
+    CorrectorKnownFixes {
+        it: "\n\nCorrezioni note (errori che la trascrizione automatica ripete spesso, correggili sempre così):",
+        en: "\n\nKnown fixes (mistakes the automatic transcription often repeats, always correct them like this):",
+    }
+    CorrectorUserPrompt {
+        it: "Trascrizione automatica da correggere:\n\n---\n{{transcript}}\n---\n\nCorreggi eventuali errori mantenendo il significato originale.",
+        en: "Automatic transcript to correct:\n\n---\n{{transcript}}\n---\n\nFix any errors while keeping the original meaning.",
+    }
+    ExistingNotesHeader {
+        it: "## NOTE ESISTENTI NEL SISTEMA\n\nQueste sono le note già presenti nel vault. DEVI consultare questa lista per i link interni e i related_notes.\n\n",
+        en: "## EXISTING NOTES IN THE SYSTEM\n\nThese are the notes already in the vault. You MUST check this list for internal links and related_notes.\n\n",
+    }
+    InstructionsHeader {
+        it: "\n\n## ISTRUZIONI AGGIUNTIVE\n",
+        en: "\n\n## ADDITIONAL INSTRUCTIONS\n",
+    }
+    UserPrompt {
+        it: "Trascrizione del messaggio vocale:\n\n---\n{{transcript}}\n---\n\nCrea note strutturate da questa trascrizione.",
+        en: "Transcript of the voice message:\n\n---\n{{transcript}}\n---\n\nCreate structured notes from this transcript.",
+    }
+    SegmentPrompt {
+        it: "Sezione {{index}}/{{total}} di un messaggio vocale lungo — argomento: {{topic}}\n\n---\n{{text}}\n---\n\nCrea note strutturate solo per questa sezione.",
+        en: "Section {{index}}/{{total}} of a long voice message — topic: {{topic}}\n\n---\n{{text}}\n---\n\nCreate structured notes for this section only.",
+    }
+    OutlinedNotePrompt {
+        it: "Nota {{index}}/{{total}} di un messaggio vocale lungo — titolo: {{title}}\n\n---\n{{text}}\n---\n\nScrivi una sola nota con questo titolo, solo per questa parte.",
+        en: "Note {{index}}/{{total}} of a long voice message — title: {{title}}\n\n---\n{{text}}\n---\n\nWrite a single note with this title, for this part only.",
+    }
+    IconInstruction {
+        it: "Aggiungi a ogni nota un campo \"icon\" con una sola emoji che ne rappresenti l'argomento (es. \"💡\" per un'idea, \"🛒\" per la spesa, \"🐛\" per un bug)",
+        en: "Add to every note an \"icon\" field with a single emoji representing its topic (e.g. \"💡\" for an idea, \"🛒\" for groceries, \"🐛\" for a bug)",
+    }
+    TimestampInstruction {
+        it: "La trascrizione contiene riferimenti temporali come [01:23]: riportali nella nota accanto ai punti a cui si riferiscono, così si può tornare a quel momento della registrazione",
+        en: "The transcript contains time references like [01:23]: keep them in the note next to the points they refer to, so one can go back to that moment of the recording",
+    }
+    MermaidInstruction {
+        it: "Se la trascrizione descrive un processo, una procedura o un flusso di passaggi, aggiungi alla nota un diagramma Mermaid in un blocco ```mermaid``` con `flowchart TD`. Usa id semplici (A, B, C...) ed etichette tra virgolette, es. A[\"Ricevi ordine\"] --> B{\"Disponibile?\"}. Non aggiungere diagrammi per contenuti che non sono processi.",
+        en: "If the transcript describes a process, a procedure or a flow of steps, add to the note a Mermaid diagram in a ```mermaid``` block with `flowchart TD`. Use simple ids (A, B, C...) and quoted labels, e.g. A[\"Receive order\"] --> B{\"Available?\"}. Don't add diagrams for content that isn't a process.",
+    }
+    MaxNotesInstruction {
+        it: "Crea al massimo {{max}} note per questa trascrizione: raggruppa gli argomenti minori in una nota invece di dedicare a ognuno una nota a sé.",
+        en: "Create at most {{max}} notes for this transcript: group minor topics into one note instead of giving each a note of its own.",
+    }
+    NoteTypeInstruction {
+        it: "Questo messaggio è di tipo \"{{name}}\": organizza il contenuto di ogni nota nelle sezioni {{sections}} (titoli `##` con questi nomi, in quest'ordine), senza inventare informazioni per le sezioni che la trascrizione non copre.",
+        en: "This message is of type \"{{name}}\": organize the content of each note in the sections {{sections}} (`##` headings with these names, in this order), without making up information for the sections the transcript doesn't cover.",
+    }
+    ReferencesInstruction {
+        it: "Se la trascrizione cita libri, articoli, podcast, video o film, aggiungi alla nota un campo \"references\": una lista di oggetti {\"kind\": \"book\" | \"article\" | \"podcast\" | \"video\" | \"film\", \"title\": titolo originale, \"author\": autore o conduttore se noto, altrimenti null}. Non inventare titoli: includi solo opere citate esplicitamente, altrimenti usa una lista vuota.",
+        en: "If the transcript mentions books, articles, podcasts, videos or films, add to the note a \"references\" field: a list of objects {\"kind\": \"book\" | \"article\" | \"podcast\" | \"video\" | \"film\", \"title\": original title, \"author\": author or host if known, otherwise null}. Don't make up titles: include only works mentioned explicitly, otherwise use an empty list.",
+    }
+    ExpensesInstruction {
+        it: "Se la trascrizione parla di soldi spesi, aggiungi alla nota il tag \"{{tag}}\" e un campo \"expenses\": una lista di oggetti {\"amount\": importo numerico (es. 42.5), \"currency\": codice ISO 4217 se detto (es. \"EUR\", \"USD\"), altrimenti null, \"merchant\": negozio o persona pagata se detto, altrimenti null, \"description\": cosa è stato pagato}. Per esempio \"spesa 42 euro dal meccanico\" diventa {\"amount\": 42, \"currency\": \"EUR\", \"merchant\": \"meccanico\", \"description\": \"riparazione auto\"}. Senza spese usa una lista vuota.",
+        en: "If the transcript talks about money spent, add to the note the tag \"{{tag}}\" and an \"expenses\" field: a list of objects {\"amount\": numeric amount (e.g. 42.5), \"currency\": ISO 4217 code if said (e.g. \"EUR\", \"USD\"), otherwise null, \"merchant\": shop or person paid if said, otherwise null, \"description\": what was paid for}. For example \"spent 42 dollars at the mechanic\" becomes {\"amount\": 42, \"currency\": \"USD\", \"merchant\": \"mechanic\", \"description\": \"car repair\"}. Without expenses use an empty list.",
+    }
+    LanguageInstruction {
+        it: "La trascrizione è in {{name}} ({{code}}): scrivi titolo, contenuto e tag delle note in questa lingua, non in italiano",
+        en: "The transcript is in {{name}} ({{code}}): write the title, content and tags of the notes in this language, not in English",
+    }
+    RetryInstruction {
+        it: "IMPORTANTE: la risposta precedente non era utilizzabile. Rispondi SOLO con un oggetto JSON valido nel formato {\"notes\": [...]}, con almeno una nota e tutti i campi richiesti, senza testo prima o dopo.",
+        en: "IMPORTANT: the previous answer was not usable. Reply ONLY with a valid JSON object in the format {\"notes\": [...]}, with at least one note and all the required fields, without text before or after.",
+    }
+    JsonRepairPrompt {
+        it: "Il JSON che segue non è valido. Correggilo mantenendo invariati struttura e contenuti: rispondi SOLO con il JSON corretto, senza blocchi di codice né commenti.",
+        en: "The following JSON is not valid. Fix it keeping its structure and content unchanged: reply ONLY with the corrected JSON, without code blocks or comments.",
+    }
+    JsonRepairInput {
+        it: "Errore: {{error}}\n\nJSON da correggere:\n{{json}}",
+        en: "Error: {{error}}\n\nJSON to fix:\n{{json}}",
+    }
+    JudgeSystemPrompt {
+        it: "Sei un revisore esperto di note per un sistema di gestione della conoscenza personale in Obsidian.\n\n\
+            Ricevi la trascrizione di un messaggio vocale e più candidati, ciascuno un insieme di note generate dalla stessa trascrizione.\n\n\
+            Scegli il candidato migliore valutando:\n\
+            - Fedeltà alla trascrizione (nessuna informazione inventata o persa)\n\
+            - Chiarezza e struttura delle note\n\
+            - Qualità della suddivisione in note e dei tag\n\
+            - Uso corretto dei link interni [[...]]\n\n\
+            Rispondi SOLO con JSON valido: {\"winner\": \"A\", \"reason\": \"breve motivazione\"}",
+        en: "You are an expert reviewer of notes for a personal knowledge management system in Obsidian.\n\n\
+            You receive the transcript of a voice message and several candidates, each a set of notes generated from the same transcript.\n\n\
+            Pick the best candidate judging:\n\
+            - Faithfulness to the transcript (no information made up or lost)\n\
+            - Clarity and structure of the notes\n\
+            - Quality of the split into notes and of the tags\n\
+            - Correct use of internal links [[...]]\n\n\
+            Reply ONLY with valid JSON: {\"winner\": \"A\", \"reason\": \"short explanation\"}",
+    }
+    JudgeTranscript {
+        it: "Trascrizione:\n\n---\n{{transcript}}\n---\n",
+        en: "Transcript:\n\n---\n{{transcript}}\n---\n",
+    }
+    JudgeCandidate {
+        it: "\n## Candidato {{label}}\n\n",
+        en: "\n## Candidate {{label}}\n\n",
+    }
+    /// System prompt of the `review_pass` over the generated notes.
+    ReviewSystemPrompt {
+        it: "Sei un revisore esperto di note per un sistema di gestione della conoscenza personale in Obsidian.\n\n\
+            Ricevi la trascrizione di un messaggio vocale e le note generate da essa, in JSON. Controlla le note rispetto alla trascrizione:\n\
+            - Punti mancanti: aggiungi idee, decisioni, task e dettagli della trascrizione che le note non riportano\n\
+            - Allucinazioni: togli fatti, nomi, numeri e date che la trascrizione non contiene\n\
+            - Link interni: togli le parentesi [[...]] dai link elencati come senza nota, lasciando il testo\n\n\
+            Non cambiare ciò che è già corretto: stesso numero di note, stessi titoli, stesso stile e stessi tag, salvo errori.\n\n\
+            Rispondi SOLO con le note corrette, nello stesso formato JSON ricevuto.",
+        en: "You are an expert reviewer of notes for a personal knowledge management system in Obsidian.\n\n\
+            You receive the transcript of a voice message and the notes generated from it, as JSON. Check the notes against the transcript:\n\
+            - Missing points: add ideas, decisions, tasks and details of the transcript that the notes leave out\n\
+            - Hallucinations: remove facts, names, numbers and dates the transcript doesn't contain\n\
+            - Internal links: remove the [[...]] brackets from the links listed as having no note, keeping the text\n\n\
+            Don't change what is already right: same number of notes, same titles, same style and same tags, unless wrong.\n\n\
+            Reply ONLY with the corrected notes, in the same JSON format you received.",
+    }
+    ReviewUserPrompt {
+        it: "Trascrizione:\n\n---\n{{transcript}}\n---\n\nNote generate:\n\n{{notes}}\n",
+        en: "Transcript:\n\n---\n{{transcript}}\n---\n\nGenerated notes:\n\n{{notes}}\n",
+    }
+    ReviewBrokenLinks {
+        it: "\nLink senza nota nel vault: {{links}}\n",
+        en: "\nLinks without a note in the vault: {{links}}\n",
+    }
+    /// System prompt of the segmenter, which splits long memos by topic.
+    SegmenterSystemPrompt {
+        it: "Sei un esperto nell'analisi di trascrizioni di messaggi vocali lunghi.\n\n\
+            Ricevi una trascrizione divisa in frasi numerate. Individua i cambi di argomento e dividi la trascrizione in sezioni tematiche.\n\n\
+            Regole:\n\
+            - Ogni sezione deve trattare un argomento coerente\n\
+            - Non creare sezioni troppo brevi: accorpa le digressioni all'argomento vicino\n\
+            - La prima sezione inizia sempre dalla frase 1\n\
+            - Le sezioni sono contigue e in ordine\n\n\
+            Rispondi SOLO con JSON valido: {\"segments\": [{\"topic\": \"breve titolo dell'argomento\", \"start\": 1}, ...]}",
+        en: "You are an expert in analyzing transcripts of long voice messages.\n\n\
+            You receive a transcript split into numbered sentences. Find where the topic changes and split the transcript into topical sections.\n\n\
+            Rules:\n\
+            - Each section must cover one coherent topic\n\
+            - Don't make sections too short: merge digressions into the nearby topic\n\
+            - The first section always starts at sentence 1\n\
+            - The sections are contiguous and in order\n\n\
+            Reply ONLY with valid JSON: {\"segments\": [{\"topic\": \"short title of the topic\", \"start\": 1}, ...]}",
+    }
+    SegmenterUserPrompt {
+        it: "Trascrizione:\n\n---\n{{sentences}}\n---\n\nDividi la trascrizione in sezioni tematiche.",
+        en: "Transcript:\n\n---\n{{sentences}}\n---\n\nSplit the transcript into topical sections.",
+    }
+    /// System prompt of the outliner of two-phase generation.
+    OutlinerSystemPrompt {
+        it: "Sei un esperto nell'organizzare trascrizioni di messaggi vocali in note per Obsidian.\n\n\
+            Ricevi una trascrizione divisa in frasi numerate e l'elenco delle note già presenti nel vault. Prepara la scaletta delle note da scrivere: una nota per ogni argomento distinto.\n\n\
+            Regole:\n\
+            - Ogni nota copre un gruppo contiguo di frasi, indicato dalla frase da cui inizia\n\
+            - La prima nota inizia sempre dalla frase 1; le note sono in ordine\n\
+            - Non creare note troppo brevi: accorpa le digressioni all'argomento vicino\n\
+            - In \"related\" indica i file delle note esistenti legate all'argomento, solo dall'elenco\n\n\
+            Rispondi SOLO con JSON valido: {\"notes\": [{\"topic\": \"titolo della nota\", \"start\": 1, \"related\": [\"file nota esistente\"]}, ...]}",
+        en: "You are an expert in organizing transcripts of voice messages into notes for Obsidian.\n\n\
+            You receive a transcript split into numbered sentences and the list of the notes already in the vault. Outline the notes to write: one note for each distinct topic.\n\n\
+            Rules:\n\
+            - Each note covers a contiguous group of sentences, given by the sentence it starts at\n\
+            - The first note always starts at sentence 1; the notes are in order\n\
+            - Don't make notes too short: merge digressions into the nearby topic\n\
+            - In \"related\" list the files of the existing notes linked to the topic, only from the list\n\n\
+            Reply ONLY with valid JSON: {\"notes\": [{\"topic\": \"title of the note\", \"start\": 1, \"related\": [\"existing note file\"]}, ...]}",
+    }
+    OutlinerUserPrompt {
+        it: "Note esistenti:\n{{existing}}\n\nTrascrizione:\n\n---\n{{sentences}}\n---\n\nPrepara la scaletta delle note.",
+        en: "Existing notes:\n{{existing}}\n\nTranscript:\n\n---\n{{sentences}}\n---\n\nOutline the notes.",
+    }
+    OutlinerNoExistingNotes {
+        it: "(nessuna)",
+        en: "(none)",
+    }
+    /// System prompt of the task extractor of `enable_task_extraction`.
+    TaskExtractorSystemPrompt {
+        it: "Estrai le cose da fare da una trascrizione di un messaggio vocale.\n\n\
+            Regole:\n\
+            - Solo azioni concrete che chi parla deve fare (telefonate, acquisti, scadenze, invii), non idee o riflessioni\n\
+            - Ogni azione è una frase breve che inizia con un verbo, nella lingua della trascrizione\n\
+            - Mantieni le indicazioni di tempo così come sono dette (es. \"domani\", \"entro venerdì\")\n\
+            - Se non ci sono azioni rispondi {\"tasks\": []}\n\n\
+            Rispondi SOLO con JSON valido: {\"tasks\": [\"Chiamare Marco domani per il preventivo\", ...]}",
+        en: "Extract the things to do from the transcript of a voice message.\n\n\
+            Rules:\n\
+            - Only concrete actions the speaker has to take (calls, purchases, deadlines, things to send), not ideas or thoughts\n\
+            - Each action is a short sentence starting with a verb, in the language of the transcript\n\
+            - Keep the time references as they are said (e.g. \"tomorrow\", \"by Friday\")\n\
+            - If there are no actions reply {\"tasks\": []}\n\n\
+            Reply ONLY with valid JSON: {\"tasks\": [\"Call Mark tomorrow about the quote\", ...]}",
+    }
+    TaskExtractorUserPrompt {
+        it: "Trascrizione:\n\n---\n{{transcript}}\n---",
+        en: "Transcript:\n\n---\n{{transcript}}\n---",
+    }
+    /// System prompt of the classifier picking the note type.
+    ClassifierSystemPrompt {
+        it: "Classifica la trascrizione di un messaggio vocale in uno dei tipi di nota indicati.\n\n\
+            Regole:\n\
+            - Scegli il tipo che descrive meglio il messaggio nel suo insieme\n\
+            - Usa solo i nomi dei tipi dell'elenco\n\
+            - Se nessun tipo è adatto rispondi {\"type\": null}\n\n\
+            Rispondi SOLO con JSON valido: {\"type\": \"nome del tipo\"}",
+        en: "Classify the transcript of a voice message as one of the given note types.\n\n\
+            Rules:\n\
+            - Pick the type that best describes the message as a whole\n\
+            - Use only the names of the types in the list\n\
+            - If no type fits reply {\"type\": null}\n\n\
+            Reply ONLY with valid JSON: {\"type\": \"name of the type\"}",
+    }
+    ClassifierUserPrompt {
+        it: "Tipi di nota:\n{{types}}\n\nTrascrizione:\n\n---\n{{transcript}}\n---",
+        en: "Note types:\n{{types}}\n\nTranscript:\n\n---\n{{transcript}}\n---",
+    }
+    /// System prompt of the summarizer, which shortens memos too long for the model.
+    SummarizerSystemPrompt {
+        it: "Sei un esperto nel riassumere trascrizioni di messaggi vocali.\n\n\
+            Riassumi la trascrizione conservando tutti i fatti, le idee, i nomi, le date, i numeri e le cose da fare. Elimina ripetizioni, esitazioni e divagazioni. Scrivi in prosa, nella lingua della trascrizione.\n\n\
+            Rispondi SOLO con il riassunto, senza commenti o spiegazioni.",
+        en: "You are an expert in summarizing transcripts of voice messages.\n\n\
+            Summarize the transcript keeping all the facts, ideas, names, dates, numbers and things to do. Drop repetitions, hesitations and digressions. Write in prose, in the language of the transcript.\n\n\
+            Reply ONLY with the summary, without comments or explanations.",
+    }
+    SummarizerUserPrompt {
+        it: "Trascrizione da riassumere in al massimo {{chars}} caratteri:\n\n---\n{{piece}}\n---",
+        en: "Transcript to summarize in at most {{chars}} characters:\n\n---\n{{piece}}\n---",
+    }
+    /// System prompt of the metric extractor of log memos (`[metrics] triggers`).
+    MetricsSystemPrompt {
+        it: "Estrai misure numeriche da un breve messaggio vocale di diario.\n\n\
+            Metriche disponibili (nome: significato):\n\
+            {{metrics}}\n\
+            Rispondi SOLO con JSON nel formato {\"metrics\": {\"nome\": valore}}.\n\
+            - Usa solo i nomi elencati, con valori numerici (es. 7.5, non \"7 ore e mezza\")\n\
+            - Converti le unità al significato della metrica (es. \"mezz'ora\" → 30 se sono minuti)\n\
+            - Ometti le metriche non menzionate; se nessuna è menzionata rispondi {\"metrics\": {}}",
+        en: "Extract numeric measures from a short voice diary message.\n\n\
+            Available metrics (name: meaning):\n\
+            {{metrics}}\n\
+            Reply ONLY with JSON in the format {\"metrics\": {\"name\": value}}.\n\
+            - Use only the listed names, with numeric values (e.g. 7.5, not \"seven and a half hours\")\n\
+            - Convert units to the meaning of the metric (e.g. \"half an hour\" → 30 if it is minutes)\n\
+            - Leave out the metrics not mentioned; if none is mentioned reply {\"metrics\": {}}",
+    }
+
+    // Command menu
+
+    MenuStart {
+        it: "Avvia il bot",
+        en: "Start the bot",
+    }
+    MenuHelp {
+        it: "Mostra l'aiuto",
+        en: "Show the help",
+    }
+    MenuStatus {
+        it: "Mostra lo stato del bot",
+        en: "Show the bot status",
+    }
+    MenuProfile {
+        it: "Mostra o cambia il profilo delle note",
+        en: "Show or change the notes profile",
+    }
+    MenuVault {
+        it: "Mostra o cambia il vault delle note",
+        en: "Show or change the notes vault",
+    }
+    MenuMaintenance {
+        it: "Modalità manutenzione del vault (admin): on, reject, off",
+        en: "Vault maintenance mode (admin): on, reject, off",
+    }
+    MenuRetry {
+        it: "Rigenera le note dell'ultimo memo non elaborato",
+        en: "Generate again the notes of the last unprocessed memo",
+    }
+    MenuBackup {
+        it: "Backup del vault (admin): now",
+        en: "Vault backup (admin): now",
+    }
+    MenuShare {
+        it: "Esporta una nota in HTML o PDF: /share [pdf] <nota>",
+        en: "Export a note as HTML or PDF: /share [pdf] <note>",
+    }
+    MenuHistory {
+        it: "Sfoglia i memo elaborati",
+        en: "Browse the processed memos",
+    }
+    MenuRetag {
+        it: "Rinomina un tag in tutte le note: /retag <vecchio> <nuovo>",
+        en: "Rename a tag in all the notes: /retag <old> <new>",
+    }
+
+    // Commands
+
+    Welcome {
+        it: "👋 Ciao! Sono {{bot}}, il tuo assistente per la trascrizione vocale.\n\n\
+            Inviami un messaggio vocale e lo trasformerò in note strutturate!\n\n\
+            Comandi disponibili:\n\
+            /start - Mostra questo messaggio\n\
+            /help - Aiuto e istruzioni\n\
+            /status - Stato del bot\n\
+            /profile - Mostra o cambia il profilo delle note\n\
+            /vault - Mostra o cambia il vault",
+        en: "👋 Hi! I'm {{bot}}, your voice transcription assistant.\n\n\
+            Send me a voice message and I'll turn it into structured notes!\n\n\
+            Available commands:\n\
+            /start - Show this message\n\
+            /help - Help and instructions\n\
+            /status - Bot status\n\
+            /profile - Show or change the notes profile\n\
+            /vault - Show or change the vault",
+    }
+    Help {
+        it: "📖 Come usare Dot:\n\n\
+            1️⃣ Registra un messaggio vocale\n\
+            2️⃣ Inviamelo qui in chat\n\
+            3️⃣ Aspetta mentre lo trascrivo\n\
+            4️⃣ Riceverai note strutturate in formato Markdown\n\n\
+            💡 Funzionalità:\n\
+            - Trascrizione automatica (italiano)\n\
+            - Generazione di note strutturate\n\
+            - Estrazione di task (per progetti di sviluppo)\n\
+            - Documenti (testo, HTML, Word, PDF) convertiti in note\n\
+            - Cerca le note da qualsiasi chat scrivendo @nomebot seguito dalle parole\n\
+            - Più vault (es. personale e lavoro): scegli con /vault\n\
+            - Rivedi i memo elaborati con /history\n\
+            - Rinomina un tag in tutte le note con /retag (admin)\n\
+            - Formato compatibile con Obsidian\n\n\
+            ⚙️ Configurazione:\n\
+            - Lingua: Italiano\n\
+            - Dimensione max audio: 20MB\n\
+            - Formato output: Markdown (.md)\n\n\
+            Problemi? Contatta il tuo amministratore.",
+        en: "📖 How to use Dot:\n\n\
+            1️⃣ Record a voice message\n\
+            2️⃣ Send it to me here in the chat\n\
+            3️⃣ Wait while I transcribe it\n\
+            4️⃣ You'll get structured notes in Markdown\n\n\
+            💡 Features:\n\
+            - Automatic transcription\n\
+            - Structured note generation\n\
+            - Task extraction (for development projects)\n\
+            - Documents (text, HTML, Word, PDF) converted to notes\n\
+            - Search your notes from any chat by typing @botname followed by the words\n\
+            - Several vaults (e.g. personal and work): pick one with /vault\n\
+            - Review the processed memos with /history\n\
+            - Rename a tag in every note with /retag (admin)\n\
+            - Obsidian-compatible format\n\n\
+            ⚙️ Configuration:\n\
+            - Language: English\n\
+            - Max audio size: 20MB\n\
+            - Output format: Markdown (.md)\n\n\
+            Problems? Contact your administrator.",
+    }
+    /// Date in `/status`, as a chrono format.
+    DateFormat {
+        it: "%d/%m/%Y",
+        en: "%Y-%m-%d",
+    }
+    /// Date and time in `/history`, as a chrono format.
+    DateTimeFormat {
+        it: "%d/%m/%Y %H:%M",
+        en: "%Y-%m-%d %H:%M",
+    }
+    Status {
+        it: "🤖 Stato Bot\n\n\
+            ✅ Online e funzionante\n\
+            📝 Servizio trascrizione: {{transcription}}\n\
+            🤖 AI Provider: {{ai}}\n\
+            🔢 Token usati: {{usage}}\n\
+            🗄 Vault: {{vault}}\n\
+            📁 Directory note: {{notes_dir}}\n\
+            🔧 Task extraction: {{tasks}}\n\n\
+            Pronto a ricevere messaggi vocali!",
+        en: "🤖 Bot status\n\n\
+            ✅ Online and working\n\
+            📝 Transcription service: {{transcription}}\n\
+            🤖 AI provider: {{ai}}\n\
+            🔢 Tokens used: {{usage}}\n\
+            🗄 Vault: {{vault}}\n\
+            📁 Notes directory: {{notes_dir}}\n\
+            🔧 Task extraction: {{tasks}}\n\n\
+            Ready for voice messages!",
+    }
+    UsageTotals {
+        it: "{{usage}} in {{requests}} richieste dal {{since}}",
+        en: "{{usage}} in {{requests}} requests since {{since}}",
+    }
+    UsageNone {
+        it: "nessuno",
+        en: "none",
+    }
+    UsageUnavailable {
+        it: "non disponibili",
+        en: "unavailable",
+    }
+    ModelReady {
+        it: "{{provider}} · {{model}} ✅",
+        en: "{{provider}} · {{model}} ✅",
+    }
+    ModelMissing {
+        it: "{{provider}} · {{model}} ⚠️ modello non installato (ollama pull {{model}})",
+        en: "{{provider}} · {{model}} ⚠️ model not installed (ollama pull {{model}})",
+    }
+    ModelUnreachable {
+        it: "{{provider}} · {{model}} ⚠️ server non raggiungibile",
+        en: "{{provider}} · {{model}} ⚠️ server not reachable",
+    }
+    Enabled {
+        it: "Abilitata",
+        en: "Enabled",
+    }
+    Disabled {
+        it: "Disabilitata",
+        en: "Disabled",
+    }
+    LocalWhisperMissing {
+        it: "whisper_local (non incluso in questa build)",
+        en: "whisper_local (not included in this build)",
+    }
+    LocalWhisperFallback {
+        it: "{{provider}} (whisper_local non incluso in questa build)",
+        en: "{{provider}} (whisper_local not included in this build)",
+    }
+    /// Reply to an action the sender's role doesn't allow.
+    AccessDenied {
+        it: "⛔ Non hai i permessi per questa azione.",
+        en: "⛔ You don't have permission for this action.",
+    }
+    ProfileCurrent {
+        it: "🗂 Profilo attuale: {{profile}}\n",
+        en: "🗂 Current profile: {{profile}}\n",
+    }
+    /// No profile, in `/profile`.
+    ProfileNone {
+        it: "nessuno",
+        en: "none",
+    }
+    ProfilesNotConfigured {
+        it: "\nNessun profilo configurato ([profiles] in config.toml).",
+        en: "\nNo profiles configured ([profiles] in config.toml).",
+    }
+    ProfilesAvailable {
+        it: "\nProfili disponibili (tocca il link per condividerlo):\n",
+        en: "\nAvailable profiles (tap the link to share it):\n",
+    }
+    ProfileUsage {
+        it: "\nUsa /profile <nome> per cambiare, /profile nessuno per tornare alla cartella principale.",
+        en: "\nUse /profile <name> to switch, /profile none to go back to the main folder.",
+    }
+    ProfileUnknown {
+        it: "⚠️ Profilo «{{name}}» sconosciuto. Usa /profile per l'elenco.",
+        en: "⚠️ Unknown profile «{{name}}». Use /profile for the list.",
+    }
+    ProfileActivated {
+        it: "✅ Profilo «{{name}}» attivato: le note andranno in {{folder}}/",
+        en: "✅ Profile «{{name}}» activated: notes will go to {{folder}}/",
+    }
+    ProfileDeactivated {
+        it: "✅ Profilo disattivato: le note andranno nella cartella principale.",
+        en: "✅ Profile deactivated: notes will go to the main folder.",
+    }
+    ProfileSaveFailed {
+        it: "❌ Impossibile salvare il profilo, riprova più tardi.",
+        en: "❌ Couldn't save the profile, try again later.",
+    }
+    /// Name of the `[output]` vault in `/vault` and `/status`.
+    DefaultVault {
+        it: "principale",
+        en: "main",
+    }
+    VaultCurrent {
+        it: "🗄 Vault attuale: {{vault}}\n",
+        en: "🗄 Current vault: {{vault}}\n",
+    }
+    VaultsNotConfigured {
+        it: "\nNessun altro vault configurato ([vaults] in config.toml).",
+        en: "\nNo other vaults configured ([vaults] in config.toml).",
+    }
+    VaultsAvailable {
+        it: "\nVault disponibili:\n\n",
+        en: "\nAvailable vaults:\n\n",
+    }
+    VaultUsage {
+        it: "\nUsa /vault <nome> per cambiare.",
+        en: "\nUse /vault <name> to switch.",
+    }
+    VaultUnknown {
+        it: "⚠️ Vault «{{name}}» sconosciuto. Usa /vault per l'elenco.",
+        en: "⚠️ Unknown vault «{{name}}». Use /vault for the list.",
+    }
+    VaultActivated {
+        it: "✅ Vault «{{vault}}» attivato: le note andranno in {{dir}}",
+        en: "✅ Vault «{{vault}}» activated: notes will go to {{dir}}",
+    }
+    VaultSaveFailed {
+        it: "❌ Impossibile salvare il vault, riprova più tardi.",
+        en: "❌ Couldn't save the vault, try again later.",
+    }
+    ShareUsage {
+        it: "Uso: /share [pdf] <titolo della nota>",
+        en: "Usage: /share [pdf] <note title>",
+    }
+    ShareNotFound {
+        it: "🔎 Nessuna nota trovata per «{{query}}».",
+        en: "🔎 No note found for «{{query}}».",
+    }
+    ShareFailed {
+        it: "❌ Impossibile esportare «{{title}}»: {{error}}",
+        en: "❌ Couldn't export «{{title}}»: {{error}}",
+    }
+    MaintenanceStatus {
+        it: "🛠️ Manutenzione: {{mode}}\n📥 Memo in attesa: {{held}}\n\nUso: /maintenance on | reject | off",
+        en: "🛠️ Maintenance: {{mode}}\n📥 Memos on hold: {{held}}\n\nUsage: /maintenance on | reject | off",
+    }
+    MaintenanceOff {
+        it: "disattivata",
+        en: "off",
+    }
+    MaintenanceHold {
+        it: "attiva (note in attesa)",
+        en: "on (notes on hold)",
+    }
+    MaintenanceReject {
+        it: "attiva (solo trascrizione)",
+        en: "on (transcription only)",
+    }
+    MaintenanceSaveFailed {
+        it: "❌ Impossibile salvare lo stato di manutenzione.",
+        en: "❌ Couldn't save the maintenance state.",
+    }
+    MaintenanceHoldOn {
+        it: "🛠️ Manutenzione attiva: le note vengono generate ma messe in attesa.",
+        en: "🛠️ Maintenance on: notes are generated but put on hold.",
+    }
+    MaintenanceRejectOn {
+        it: "🛠️ Manutenzione attiva: i memo vengono solo trascritti, senza creare note.",
+        en: "🛠️ Maintenance on: memos are only transcribed, without creating notes.",
+    }
+    MaintenanceEnded {
+        it: "✅ Manutenzione terminata: {{saved}} nota/e salvata/e",
+        en: "✅ Maintenance over: {{saved}} note(s) saved",
+    }
+    MaintenanceStillHeld {
+        it: "\n⚠️ {{failed}} memo ancora in attesa per errori di scrittura",
+        en: "\n⚠️ {{failed}} memo(s) still on hold because of write errors",
+    }
+    MaintenanceHeldSaved {
+        it: "✅ Manutenzione terminata: {{count}} nota/e in attesa salvata/e",
+        en: "✅ Maintenance over: {{count}} held note(s) saved",
+    }
+    BackupNotConfigured {
+        it: "💾 Backup non configurati: imposta dir nella sezione [backup].",
+        en: "💾 Backups not configured: set dir in the [backup] section.",
+    }
+    BackupStatus {
+        it: "💾 Backup ogni {{hours}} ore, ultimi {{keep}} conservati\n\nUso: /backup now",
+        en: "💾 Backup every {{hours}} hours, last {{keep}} kept\n\nUsage: /backup now",
+    }
+    BackupRunning {
+        it: "💾 Backup in corso...",
+        en: "💾 Backing up...",
+    }
+    BackupCreated {
+        it: "💾 Backup creato: {{path}} ({{size}} MB)",
+        en: "💾 Backup created: {{path}} ({{size}} MB)",
+    }
+    BackupUploaded {
+        it: "\n☁️ Caricato su {{target}}",
+        en: "\n☁️ Uploaded to {{target}}",
+    }
+    BackupFailed {
+        it: "❌ Backup fallito: {{error}}",
+        en: "❌ Backup failed: {{error}}",
+    }
+    ScheduledBackupFailed {
+        it: "🚨 Backup del vault fallito: {{error}}",
+        en: "🚨 Vault backup failed: {{error}}",
+    }
+    RetagUsage {
+        it: "Uso: /retag <vecchio> <nuovo>\n\n\
+            I tag possono contenere lettere, cifre, -, _ e / (es. /retag rust programmazione/rust).",
+        en: "Usage: /retag <old> <new>\n\n\
+            Tags may contain letters, digits, -, _ and / (e.g. /retag rust programming/rust).",
+    }
+    RetagMaintenance {
+        it: "🛠 Vault in manutenzione: riprova /retag dopo /maintenance off.",
+        en: "🛠 Vault under maintenance: try /retag again after /maintenance off.",
+    }
+    RetagNoNotes {
+        it: "🏷 Nessuna nota con il tag #{{tag}}.",
+        en: "🏷 No notes with the tag #{{tag}}.",
+    }
+    RetagDone {
+        it: "🏷 #{{old}} → #{{new}}: {{count}} note aggiornate.",
+        en: "🏷 #{{old}} → #{{new}}: {{count}} notes updated.",
+    }
+    RetagFailed {
+        it: "❌ Rinomina del tag fallita: {{error}}",
+        en: "❌ Tag rename failed: {{error}}",
+    }
+    RetryNothing {
+        it: "Nessun memo da rielaborare.",
+        en: "No memo to process again.",
+    }
+    RetryMaintenance {
+        it: "🛠️ Vault in manutenzione: riprova /retry al termine.",
+        en: "🛠️ Vault under maintenance: try /retry again when it's over.",
+    }
+    RetryRunning {
+        it: "🔄 Rielaboro il memo in sospeso...",
+        en: "🔄 Processing the pending memo again...",
+    }
+    NoteDeleted {
+        it: "🗑️ La nota «{{title}}» non è più nel vault.",
+        en: "🗑️ The note «{{title}}» is no longer in the vault.",
+    }
+    PendingCompleted {
+        it: "🔁 Memo in sospeso completato!\n\n{{result}}",
+        en: "🔁 Pending memo completed!\n\n{{result}}",
+    }
+    PendingCompletedSummary {
+        it: "🔁 Memo in sospeso completato: {{count}} nota/e salvata/e",
+        en: "🔁 Pending memo completed: {{count}} note(s) saved",
+    }
+
+    // Memos and documents
+
+    DocumentReceived {
+        it: "📄 Documento ricevuto! Lo converto in nota...",
+        en: "📄 Document received! Converting it to a note...",
+    }
+    DocumentTooLarge {
+        it: "❌ Documento troppo grande (max {{max}}MB).",
+        en: "❌ Document too large (max {{max}}MB).",
+    }
+    DocumentUnsupported {
+        it: "❌ Non riesco a convertire questo documento.\n\n\
+            💡 Formati supportati: testo, Markdown, HTML, reStructuredText, Word (.docx, richiede pandoc) e PDF (richiede pdftotext).",
+        en: "❌ I can't convert this document.\n\n\
+            💡 Supported formats: text, Markdown, HTML, reStructuredText, Word (.docx, needs pandoc) and PDF (needs pdftotext).",
+    }
+    DocumentConverted {
+        it: "✅ Convertito! Genero le note...",
+        en: "✅ Converted! Generating the notes...",
+    }
+    AudioReceived {
+        it: "🎤 Messaggio vocale ricevuto! Sto trascrivendo...",
+        en: "🎤 Voice message received! Transcribing...",
+    }
+    AudioMissing {
+        it: "❌ Errore: Nessun file audio trovato nel messaggio.",
+        en: "❌ Error: no audio file found in the message.",
+    }
+    AudioTooLarge {
+        it: "❌ File audio troppo grande (max {{max}}MB).",
+        en: "❌ Audio file too large (max {{max}}MB).",
+    }
+    TranscriptionConfigError {
+        it: "❌ Errore configurazione trascrizione: {{error}}",
+        en: "❌ Transcription configuration error: {{error}}",
+    }
+    TranscriptionProgress {
+        it: "🎤 Trascrizione in corso… {{percent}}%",
+        en: "🎤 Transcribing… {{percent}}%",
+    }
+    Transcribed {
+        it: "✅ Trascritto! Genero le note...",
+        en: "✅ Transcribed! Generating the notes...",
+    }
+    GeneratingTokens {
+        it: "✅ Trascritto! Genero le note… {{count}} token",
+        en: "✅ Transcribed! Generating the notes… {{count}} tokens",
+    }
+    MetricsNone {
+        it: "🤷 Nessuna metrica riconosciuta. Metriche configurate: {{names}}",
+        en: "🤷 No metric recognized. Configured metrics: {{names}}",
+    }
+    MetricsLogged {
+        it: "📊 Registrato: {{values}}",
+        en: "📊 Logged: {{values}}",
+    }
+    MetricsFailed {
+        it: "❌ Registrazione delle metriche fallita: {{error}}",
+        en: "❌ Logging the metrics failed: {{error}}",
+    }
+    NotesHeld {
+        it: "🛠️ Vault in manutenzione: {{count}} nota/e in attesa, verranno salvate al termine.",
+        en: "🛠️ Vault under maintenance: {{count}} note(s) on hold, they'll be saved when it's over.",
+    }
+    FallbackSaved {
+        it: "📝 Trascrizione salvata in «{{title}}» con tag #{{tag}}.\n{{hint}}",
+        en: "📝 Transcript saved in «{{title}}» with tag #{{tag}}.\n{{hint}}",
+    }
+    RetryHintAutomatic {
+        it: "🔁 Il memo è in coda: lo rielaborerò automaticamente appena il servizio torna disponibile (oppure usa /retry).",
+        en: "🔁 The memo is queued: I'll process it again as soon as the service is back (or use /retry).",
+    }
+    RetryHintManual {
+        it: "🔁 Il memo è in coda: usa /retry per rielaborarlo quando il problema è risolto.",
+        en: "🔁 The memo is queued: use /retry to process it again once the problem is fixed.",
+    }
+    MaintenanceRejected {
+        it: "🛠️ Vault in manutenzione: le note non sono state create.\n\n📝 Trascrizione:\n{{transcript}}",
+        en: "🛠️ Vault under maintenance: no notes were created.\n\n📝 Transcript:\n{{transcript}}",
+    }
+    NotesSaved {
+        it: "✅ {{count}} nota/e salvata/e",
+        en: "✅ {{count}} note(s) saved",
+    }
+    ResultHeader {
+        it: "🎉 Completato!\n\n📝 {{count}} nota/e generata/e:\n\n",
+        en: "🎉 Done!\n\n📝 {{count}} note(s) generated:\n\n",
+    }
+    /// In place of the file name of a note that wasn't saved.
+    ResultFileMissing {
+        it: "errore",
+        en: "error",
+    }
+    TranscriptCorrected {
+        it: "📊 Trascrizione (corretta):",
+        en: "📊 Transcript (corrected):",
+    }
+    TranscriptOriginal {
+        it: "🔍 Originale (Whisper):",
+        en: "🔍 Original (Whisper):",
+    }
+    Transcript {
+        it: "📊 Trascrizione:",
+        en: "📊 Transcript:",
+    }
+    TranscriptTruncatedHead {
+        it: "le note coprono solo gli ultimi {{kept}} di {{original}} caratteri",
+        en: "the notes only cover the last {{kept}} of {{original}} characters",
+    }
+    TranscriptTruncatedTail {
+        it: "le note coprono solo i primi {{kept}} di {{original}} caratteri",
+        en: "the notes only cover the first {{kept}} of {{original}} characters",
+    }
+    TranscriptSummarized {
+        it: "è stata riassunta da {{original}} a {{kept}} caratteri prima di generare le note",
+        en: "it was summarized from {{original}} to {{kept}} characters before generating the notes",
+    }
+    FallbackUsed {
+        it: "↪️ {{primary}} non ha risposto: note generate con {{model}}.",
+        en: "↪️ {{primary}} did not answer: notes generated with {{model}}.",
+    }
+    TasksExtracted {
+        it: "📋 Task salvati:\n{{tasks}}",
+        en: "📋 Tasks saved:\n{{tasks}}",
+    }
+    TranscriptTooLong {
+        it: "✂️ Trascrizione troppo lunga per il modello: {{what}}.",
+        en: "✂️ Transcript too long for the model: {{what}}.",
+    }
+    FullTranscriptButton {
+        it: "📄 Trascrizione completa",
+        en: "📄 Full transcript",
+    }
+    TranscriptGone {
+        it: "Trascrizione non più disponibile.",
+        en: "Transcript no longer available.",
+    }
+    TranscriptUnavailable {
+        it: "Trascrizione non disponibile.",
+        en: "Transcript not available.",
+    }
+    /// File name of a transcript too long for a message.
+    TranscriptFileName {
+        it: "trascrizione.txt",
+        en: "transcript.txt",
+    }
+
+    // History
+
+    HistoryUnavailable {
+        it: "❌ Cronologia non disponibile.",
+        en: "❌ History not available.",
+    }
+    HistoryEmpty {
+        it: "🗂 Nessun memo elaborato in questa chat.",
+        en: "🗂 No memos processed in this chat.",
+    }
+    HistoryHeader {
+        it: "🗂 Cronologia (pagina {{page}}/{{pages}}):\n",
+        en: "🗂 History (page {{page}}/{{pages}}):\n",
+    }
+    HistoryOpen {
+        it: "📂 Note",
+        en: "📂 Notes",
+    }
+    HistoryText {
+        it: "📄 Testo",
+        en: "📄 Text",
+    }
+    HistoryRedo {
+        it: "🔄 Rielabora",
+        en: "🔄 Redo",
+    }
+    HistoryNewer {
+        it: "◀️ Più recenti",
+        en: "◀️ Newer",
+    }
+    HistoryOlder {
+        it: "Meno recenti ▶️",
+        en: "Older ▶️",
+    }
+    HistoryNotesGone {
+        it: "Le note non sono più nel vault.",
+        en: "The notes are no longer in the vault.",
+    }
+    HistoryReprocessing {
+        it: "🔄 Rielaboro il memo...",
+        en: "🔄 Processing the memo again...",
+    }
+    CaptureSaved {
+        it: "✅ salvato",
+        en: "✅ saved",
+    }
+    CaptureUnprocessed {
+        it: "🛟 non elaborato",
+        en: "🛟 unprocessed",
+    }
+    CaptureFailed {
+        it: "❌ fallito",
+        en: "❌ failed",
+    }
+
+    // Candidates and feedback
+
+    CandidatesHeader {
+        it: "⚖️ Due proposte a confronto, scegli quella da salvare:\n",
+        en: "⚖️ Two proposals side by side, pick the one to save:\n",
+    }
+    UseCandidate {
+        it: "Usa {{label}}",
+        en: "Use {{label}}",
+    }
+    SelectionExpired {
+        it: "Scelta già effettuata o scaduta.",
+        en: "Already chosen or expired.",
+    }
+    SavingChoice {
+        it: "💾 Salvo la proposta scelta...",
+        en: "💾 Saving the chosen proposal...",
+    }
+    FeedbackAsk {
+        it: "👎 Grazie per il feedback! Cosa non andava?\n\
+            Rispondi a questo messaggio con una breve descrizione.",
+        en: "👎 Thanks for the feedback! What was wrong?\n\
+            Reply to this message with a short description.",
+    }
+    FeedbackThanks {
+        it: "🙏 Feedback registrato, grazie!",
+        en: "🙏 Feedback recorded, thanks!",
+    }
+    TextOnly {
+        it: "📝 Ho ricevuto il tuo messaggio di testo.\n\n\
+            Per ora, sono specializzato in messaggi vocali e documenti! 🎤📄\n\
+            Inviami un messaggio vocale o un file e lo trasformerò in note strutturate.\n\n\
+            Usa /help per maggiori informazioni.",
+        en: "📝 I got your text message.\n\n\
+            For now, I specialize in voice messages and documents! 🎤📄\n\
+            Send me a voice message or a file and I'll turn it into structured notes.\n\n\
+            Use /help for more information.",
+    }
+
+    // Admin alerts
+
+    LowDiskSpace {
+        it: "⚠️ Spazio su disco in esaurimento: {{mb}} MB liberi in {{dir}}",
+        en: "⚠️ Running out of disk space: {{mb}} MB free in {{dir}}",
+    }
+    QuotaEvicted {
+        it: "🧹 Quota superata in {{dir}}: rimossi {{files}} file meno usati ({{freed}} MB), ora {{used}} / {{max}} MB",
+        en: "🧹 Quota exceeded in {{dir}}: removed {{files}} least used file(s) ({{freed}} MB), now {{used}} / {{max}} MB",
+    }
+    QuotaApproaching {
+        it: "⚠️ {{dir}} quasi pieno: {{used}} / {{max}} MB (oltre il limite i file meno usati vengono rimossi)",
+        en: "⚠️ {{dir}} almost full: {{used}} / {{max}} MB (past the limit the least used files are removed)",
+    }
+
+    // Pipeline errors
+
+    ErrorMessage {
+        it: "{{title}}\n\nDettagli: {{details}}\n\n{{hint}}\n\nCodice: {{code}}",
+        en: "{{title}}\n\nDetails: {{details}}\n\n{{hint}}\n\nCode: {{code}}",
+    }
+    ErrorDownload {
+        it: "❌ Errore nel download del file audio.",
+        en: "❌ Error downloading the audio file.",
+    }
+    ErrorDownloadHint {
+        it: "💡 Riprova tra qualche istante: Telegram potrebbe non essere raggiungibile.",
+        en: "💡 Try again in a moment: Telegram may be unreachable.",
+    }
+    ErrorConvert {
+        it: "❌ Errore nella conversione dell'audio.",
+        en: "❌ Error converting the audio.",
+    }
+    ErrorConvertHint {
+        it: "💡 Il formato potrebbe non essere supportato. Verifica che ffmpeg sia installato.",
+        en: "💡 The format may not be supported. Check that ffmpeg is installed.",
+    }
+    ErrorTranscribe {
+        it: "❌ Errore nella trascrizione.",
+        en: "❌ Transcription error.",
+    }
+    ErrorTranscribeHint {
+        it: "💡 Suggerimenti:\n\
+            - Controlla la configurazione del provider '{{provider}}'\n\
+            - Controlla i log per maggiori dettagli\n\
+            - Usa /status per verificare la configurazione",
+        en: "💡 Suggestions:\n\
+            - Check the configuration of the provider '{{provider}}'\n\
+            - Check the logs for more details\n\
+            - Use /status to check the configuration",
+    }
+    ErrorLlmTimeout {
+        it: "⏱ Il backend AI non ha risposto in tempo.",
+        en: "⏱ The AI backend didn't answer in time.",
+    }
+    ErrorLlmTimeoutHint {
+        it: "💡 Il modello potrebbe essere bloccato o sovraccarico: controlla il server \
+            (ollama ps) o aumenta ai_model.timeout_secs.",
+        en: "💡 The model may be stuck or overloaded: check the server \
+            (ollama ps) or raise ai_model.timeout_secs.",
+    }
+    ErrorLlm {
+        it: "❌ Errore nella generazione delle note.",
+        en: "❌ Error generating the notes.",
+    }
+    ErrorLlmHint {
+        it: "💡 Verifica che Ollama sia in esecuzione: ollama list",
+        en: "💡 Check that Ollama is running: ollama list",
+    }
+    ErrorSave {
+        it: "❌ Impossibile salvare le note.",
+        en: "❌ Couldn't save the notes.",
+    }
+    ErrorSaveSpaceHint {
+        it: "💡 Libera spazio su disco e invia di nuovo il messaggio.",
+        en: "💡 Free up disk space and send the message again.",
+    }
+    ErrorSavePermissionsHint {
+        it: "💡 Controlla i permessi della cartella delle note (output.notes_dir).",
+        en: "💡 Check the permissions of the notes folder (output.notes_dir).",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The `{{name}}` placeholders of `text`, sorted.
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut names = Vec::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            rest = &rest[start + 2..];
+            let Some(end) = rest.find("}}") else {
+                break;
+            };
+            names.push(rest[..end].trim());
+            rest = &rest[end + 2..];
+        }
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn test_every_message_is_translated_with_the_same_placeholders() {
+        for msg in Msg::ALL {
+            let (it, en) = (msg.text(Language::It), msg.text(Language::En));
+            assert!(!it.trim().is_empty() && !en.trim().is_empty(), "{:?}", msg);
+            assert_eq!(placeholders(it), placeholders(en), "{:?}", msg);
+        }
+
+        assert_eq!(
+            Msg::RetagDone.fill(
+                Language::En,
+                &[("old", &"rust"), ("new", &"code"), ("count", &3)]
+            ),
+            "🏷 #rust → #code: 3 notes updated."
+        );
+        let language: Language = serde_json::from_str("\"en\"").unwrap();
+        assert_eq!(language.code(), "en");
+    }
+}
@@ -0,0 +1,72 @@
+//! Lenient parsing of the JSON answers of small models, which often wrap it
+//! in Markdown fences or commentary and leave trailing commas.
+
+use anyhow::{Context, Result};
+
+/// Parse `text` as JSON, first as is, then with the usual mistakes fixed.
+pub fn parse(text: &str) -> Result<serde_json::Value> {
+    if let Ok(value) = serde_json::from_str(text.trim()) {
+        return Ok(value);
+    }
+    let extracted = extract(text).context("No JSON object in the answer")?;
+    serde_json::from_str(&strip_trailing_commas(extracted)).context("Invalid JSON in the answer")
+}
+
+/// The outermost JSON object or array in `text`, dropping fences and
+/// commentary around it.
+fn extract(text: &str) -> Option<&str> {
+    let start = text.find(['{', '['])?;
+    let close = if text[start..].starts_with('{') {
+        '}'
+    } else {
+        ']'
+    };
+    let end = text.rfind(close)?;
+    (end > start).then(|| &text[start..=end])
+}
+
+/// `json` without commas right before a closing `}` or `]`, outside strings.
+fn strip_trailing_commas(json: &str) -> String {
+    let mut result = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in json.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = json[i + 1..].trim_start().chars().next();
+            if matches!(next, Some('}' | ']')) {
+                continue;
+            }
+        }
+        result.push(c);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repairs_fences_commentary_and_trailing_commas() {
+        let answer = "Ecco le note:\n```json\n{\"notes\": [{\"title\": \"Idea, nuova\", \"tags\": [\"a\", \"b\",],},]}\n```\nSpero vada bene!";
+        let value = parse(answer).unwrap();
+        assert_eq!(value["notes"][0]["title"], "Idea, nuova");
+        assert_eq!(value["notes"][0]["tags"][1], "b");
+
+        // Commas inside strings stay
+        let value = parse(r#"{"content": "a ,}"}"#).unwrap();
+        assert_eq!(value["content"], "a ,}");
+
+        assert!(parse("Non ho capito la richiesta.").is_err());
+        assert!(parse("{\"notes\": [").is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::atomic_write::write_atomic;
+use crate::note_generator::{sanitize_filename, Note};
+
+/// Media kinds a reference may have; anything else is recorded as `other`.
+const KINDS: &[&str] = &["book", "article", "podcast", "video", "film"];
+
+/// A book, article, podcast or other media mentioned in a memo.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct Reference {
+    pub kind: String,
+    pub title: String,
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+impl Reference {
+    /// Normalize a reference from the model, dropping it if it has no title.
+    pub fn sanitize(self) -> Option<Reference> {
+        let title = self.title.trim().trim_matches('"').trim();
+        if title.is_empty() {
+            return None;
+        }
+        let kind = self.kind.trim().to_lowercase();
+        let kind = if KINDS.contains(&kind.as_str()) {
+            kind
+        } else {
+            "other".to_string()
+        };
+        let author = self
+            .author
+            .map(|a| a.trim().to_string())
+            .filter(|a| !a.is_empty());
+        Some(Reference {
+            kind,
+            title: title.to_string(),
+            author,
+        })
+    }
+}
+
+/// Record the references of the saved `notes` in `library_dir`: one note per
+/// media, created on first mention, with a dated link to every memo citing it.
+///
+/// The caller must hold the vault lock.
+pub fn record(library_dir: &Path, notes: &[Note], fsync: bool) -> Result<Vec<PathBuf>> {
+    let mut touched = Vec::new();
+    for note in notes {
+        for reference in &note.references {
+            let path = library_dir.join(format!("{}.md", sanitize_filename(&reference.title)));
+            if append_mention(&path, reference, note, fsync)? {
+                touched.push(path);
+            }
+        }
+    }
+    Ok(touched)
+}
+
+/// Add a mention of `note` to the media note at `path`, creating it if
+/// needed. Returns false if the note was already linked there.
+fn append_mention(path: &Path, reference: &Reference, note: &Note, fsync: bool) -> Result<bool> {
+    let link = format!("[[{}]]", note.filename_stem());
+    let mut content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).context("Failed to create library folder")?;
+            }
+            new_media_note(reference)
+        }
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+    if content.contains(&link) {
+        return Ok(false);
+    }
+
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&format!("- {} {}\n", note.date.format("%Y-%m-%d"), link));
+    write_atomic(path, &content, fsync)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    log::info!("Library: recorded mention in {}", path.display());
+    Ok(true)
+}
+
+fn new_media_note(reference: &Reference) -> String {
+    let mut md = String::from("---\n");
+    md.push_str(&format!("title: \"{}\"\n", reference.title));
+    md.push_str(&format!("kind: {}\n", reference.kind));
+    if let Some(author) = &reference.author {
+        md.push_str(&format!("author: \"{}\"\n", author));
+    }
+    md.push_str(&format!("tags:\n  - library/{}\n", reference.kind));
+    md.push_str("---\n\n");
+    md.push_str(&format!("# {}\n\n", reference.title));
+    if let Some(author) = &reference.author {
+        md.push_str(&format!("Autore: {}\n\n", author));
+    }
+    md.push_str("## Menzioni\n\n");
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_record_creates_and_appends_media_notes() {
+        let dir = std::env::temp_dir().join(format!("dot-library-{}", uuid::Uuid::new_v4()));
+        let reference = Reference {
+            kind: " Book".to_string(),
+            title: "\"Thinking, Fast and Slow\"".to_string(),
+            author: Some("Daniel Kahneman".to_string()),
+        }
+        .sanitize()
+        .unwrap();
+        assert_eq!(reference.kind, "book");
+        assert_eq!(reference.title, "Thinking, Fast and Slow");
+
+        let note = |title: &str, day: u32| Note {
+            title: title.to_string(),
+            content: String::new(),
+            tags: vec![],
+            date: Utc.with_ymd_and_hms(2024, 5, day, 9, 0, 0).unwrap(),
+            source: "voice-memo".to_string(),
+            related_notes: vec![],
+            id: None,
+            language: None,
+            icon: None,
+            references: vec![reference.clone()],
+            expenses: vec![],
+            telegram_link: None,
+            properties: Default::default(),
+        };
+
+        let first = note("Bias cognitivi", 1);
+        record(&dir, std::slice::from_ref(&first), false).unwrap();
+        // The same memo saved twice is linked once
+        record(&dir, &[first, note("Decisioni", 3)], false).unwrap();
+
+        let content = std::fs::read_to_string(dir.join("Thinking, Fast and Slow.md")).unwrap();
+        assert!(content.contains("kind: book\n"));
+        assert!(content.contains("  - library/book\n"));
+        assert!(content.ends_with(
+            "## Menzioni\n\n- 2024-05-01 [[Bias cognitivi]]\n- 2024-05-03 [[Decisioni]]\n"
+        ));
+
+        assert!(Reference {
+            kind: "podcast".to_string(),
+            title: "  ".to_string(),
+            author: None,
+        }
+        .sanitize()
+        .is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::tools::NoteMeta;
+
+/// Weight of each tag a candidate shares with the new note.
+const TAG_WEIGHT: f32 = 1.0;
+/// Weight of each direct link between a candidate and a note the new note
+/// already links.
+const LINK_WEIGHT: f32 = 2.0;
+/// Weight of each neighbor a candidate shares with the linked notes.
+const NEIGHBOR_WEIGHT: f32 = 0.5;
+/// Score a candidate needs to be suggested: a single shared tag is not enough
+/// without some link-graph evidence.
+const MIN_SCORE: f32 = 2.0;
+/// Most related notes suggested per note.
+const MAX_SUGGESTED: usize = 3;
+
+/// Targets of the `[[wiki-links]]` in `content`, without `|alias`, `#heading`
+/// or `.md`.
+pub fn wiki_links(content: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+        let target = rest[..end]
+            .split(['|', '#'])
+            .next()
+            .unwrap_or_default()
+            .trim();
+        let target = target.strip_suffix(".md").unwrap_or(target);
+        if !target.is_empty() && !links.iter().any(|l| l == target) {
+            links.push(target.to_string());
+        }
+        rest = &rest[end + 2..];
+    }
+    links
+}
+
+/// Undirected graph of the links between the vault's notes, keyed by
+/// lowercase filename stem.
+#[derive(Debug, Default)]
+pub struct LinkGraph {
+    neighbors: HashMap<String, HashSet<String>>,
+}
+
+fn stem(note: &NoteMeta) -> &str {
+    note.filename.strip_suffix(".md").unwrap_or(&note.filename)
+}
+
+impl LinkGraph {
+    pub fn from_notes(notes: &[NoteMeta]) -> Self {
+        // Links may name a note by filename, path, title or alias
+        let mut names: HashMap<String, String> = HashMap::new();
+        for note in notes {
+            let key = stem(note).to_lowercase();
+            for name in std::iter::once(&note.title).chain(&note.aliases) {
+                names.entry(name.to_lowercase()).or_insert(key.clone());
+            }
+        }
+        for note in notes {
+            let key = stem(note).to_lowercase();
+            names.insert(key.clone(), key);
+        }
+
+        let mut graph = Self::default();
+        for note in notes {
+            let from = stem(note).to_lowercase();
+            for link in &note.links {
+                let name = link.rsplit('/').next().unwrap_or(link).to_lowercase();
+                if let Some(to) = names.get(&name).filter(|to| **to != from) {
+                    graph.connect(&from, to);
+                }
+            }
+        }
+        graph
+    }
+
+    fn connect(&mut self, a: &str, b: &str) {
+        self.neighbors
+            .entry(a.to_string())
+            .or_default()
+            .insert(b.to_string());
+        self.neighbors
+            .entry(b.to_string())
+            .or_default()
+            .insert(a.to_string());
+    }
+
+    fn neighbors(&self, stem: &str) -> Option<&HashSet<String>> {
+        self.neighbors.get(&stem.to_lowercase())
+    }
+
+    /// Existing notes worth relating to a new note with `tags` that already
+    /// links the notes in `linked` (filename stems), best first.
+    ///
+    /// Candidates score for shared tags, and more for sitting next to the
+    /// linked notes in the graph: linked to or from them, or sharing their
+    /// neighbors. Tag overlap alone has to be strong to qualify, so the new
+    /// note joins the clusters the vault already has. At most
+    /// [`MAX_SUGGESTED`] notes are returned.
+    pub fn suggest(&self, tags: &[String], linked: &[String], notes: &[NoteMeta]) -> Vec<String> {
+        let tags: HashSet<String> = tags.iter().map(|t| t.to_lowercase()).collect();
+        let linked: HashSet<String> = linked.iter().map(|l| l.to_lowercase()).collect();
+        let around: HashSet<&String> = linked
+            .iter()
+            .filter_map(|l| self.neighbors(l))
+            .flatten()
+            .filter(|n| !linked.contains(*n))
+            .collect();
+
+        let mut scored: Vec<(f32, &NoteMeta)> = notes
+            .iter()
+            .filter(|note| !linked.contains(&stem(note).to_lowercase()))
+            .filter_map(|note| {
+                let shared_tags = note
+                    .tags
+                    .iter()
+                    .filter(|t| tags.contains(&t.trim_start_matches('#').to_lowercase()))
+                    .count();
+                let (direct, shared_neighbors) = match self.neighbors(stem(note)) {
+                    Some(neighbors) => (
+                        neighbors.iter().filter(|n| linked.contains(*n)).count(),
+                        neighbors.iter().filter(|n| around.contains(n)).count(),
+                    ),
+                    None => (0, 0),
+                };
+                let score = shared_tags as f32 * TAG_WEIGHT
+                    + direct as f32 * LINK_WEIGHT
+                    + shared_neighbors as f32 * NEIGHBOR_WEIGHT;
+                (score >= MIN_SCORE).then_some((score, note))
+            })
+            .collect();
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        scored
+            .into_iter()
+            .take(MAX_SUGGESTED)
+            .map(|(_, note)| stem(note).to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(filename: &str, tags: &[&str], content: &str) -> NoteMeta {
+        NoteMeta {
+            title: filename.to_string(),
+            date: String::new(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            filename: format!("{}.md", filename),
+            source: String::new(),
+            id: None,
+            aliases: Vec::new(),
+            links: wiki_links(content),
+            folder: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_suggest_prefers_graph_neighbors_over_tag_matches() {
+        assert_eq!(
+            wiki_links("Vedi [[Ownership|possesso]], [[Lifetimes#Esempi]] e [[Ownership]]."),
+            ["Ownership", "Lifetimes"]
+        );
+
+        let notes = vec![
+            note(
+                "Ownership",
+                &["rust"],
+                "Collegata a [[Borrowing]] e [[Lifetimes]]",
+            ),
+            note(
+                "Borrowing",
+                &["rust"],
+                "Prestiti, vedi [[progetti/Lifetimes.md]]",
+            ),
+            note("Lifetimes", &["rust"], ""),
+            // Shares a tag with the new note, but sits in another cluster
+            note("Rust in cucina", &["rust", "ricette"], ""),
+            note("Unrelated", &["cucina"], "[[Rust in cucina]]"),
+        ];
+        let graph = LinkGraph::from_notes(&notes);
+
+        // A new note tagged #rust that links Ownership
+        let suggested = graph.suggest(&["rust".to_string()], &["Ownership".to_string()], &notes);
+        assert_eq!(suggested, ["Borrowing", "Lifetimes"]);
+    }
+}
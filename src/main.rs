@@ -1,15 +1,82 @@
+mod access;
+mod ai_provider;
+mod anthropic;
+mod atomic_write;
+mod attachments;
+mod audio;
+mod audit;
+mod backup;
+mod chat_state;
 mod config;
+mod corrections;
+mod dates;
+mod denoise;
+mod embeddings;
+mod errors;
+mod eval;
+mod expenses;
+mod export;
+mod feedback;
 mod handlers;
+mod history;
+mod i18n;
+mod json_repair;
+mod library;
+mod link_graph;
+mod maintenance;
+mod mermaid;
+mod metrics;
 mod note_generator;
+mod note_types;
+mod notes_index;
+mod obsidian;
 mod ollama;
+mod openai_compatible;
+#[cfg(feature = "opus")]
+mod opus;
+mod pending;
+mod plain_notes;
+mod prompt_notes;
+mod quota;
+mod reprocess;
+mod response_cache;
+mod retag;
+mod retry;
+mod routing;
+mod search;
+mod selection;
+mod service;
+mod source_links;
+mod tags;
+mod tasks;
+mod telegram;
+mod telemetry;
+mod template;
+mod timings;
 mod tools;
 mod transcription;
+mod usage;
+mod vault_lock;
 
 use anyhow::Result;
-use config::Config;
-use handlers::{audio_handler, help_handler, start_handler, status_handler, text_handler};
+use chat_state::ChatStateStore;
+use config::{Config, Role};
+use feedback::FeedbackStore;
+use handlers::{
+    audio_handler, backup_handler, document_handler, help_handler, history_callback_handler,
+    history_handler, inline_query_handler, maintenance_handler, profile_handler, reaction_handler,
+    retag_handler, retry_handler, selection_handler, share_handler, start_handler, status_handler,
+    text_handler, transcript_handler, vault_handler,
+};
+use i18n::{Language, Msg};
+use maintenance::Maintenance;
+use pending::PendingStore;
+use search::NoteSearch;
+use selection::PendingSelections;
+use std::sync::Arc;
+use telegram::Bot;
 use teloxide::prelude::*;
-use teloxide::types::Me;
+use teloxide::types::{BotCommand, Me};
 use teloxide::utils::command::BotCommands;
 
 #[tokio::main]
@@ -25,71 +92,268 @@ async fn main() -> Result<()> {
     let config = Config::from_file("config.toml")?;
     log::info!("Configuration loaded successfully");
 
+    // Fail now on an unknown ai_model.provider rather than on the first memo
+    ai_provider::create_ai_provider(&config.ai_model)?;
+
+    // Pipeline tracing (no-op unless an OTLP endpoint is configured)
+    telemetry::init(&config.telemetry)?;
+
+    // Offline subcommands run and exit without starting the bot
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("eval") => return eval::run(&config, eval::EvalOptions::parse(&args[1..])?).await,
+        Some("reprocess") => {
+            let options = reprocess::ReprocessOptions::parse(&args[1..])?;
+            return reprocess::run(&config, options).await;
+        }
+        _ => {}
+    }
+
     // Ensure output directories exist
     config.ensure_directories()?;
     log::info!("Output directories verified");
 
+    // Refuse to start (and to report readiness to systemd) if memos can't be
+    // processed
+    service::self_check(&config).await?;
+
     // Create bot instance
-    let bot = Bot::new(&config.telegram.bot_token);
+    let bot = telegram::bot(&config.telegram.bot_token);
     log::info!("Bot instance created");
 
     // Get bot info
     let me = bot.get_me().await?;
     log::info!("Bot started as @{}", me.username());
 
+    // Register the command menu shown by Telegram clients
+    if let Err(e) = bot
+        .set_my_commands(Command::menu(config.general.language))
+        .await
+    {
+        log::warn!("Failed to register bot commands: {}", e);
+    }
+
     // Print startup info
     println!("🤖 Dot Bot is running!");
     println!("   Username: @{}", me.username());
     println!("   Press Ctrl+C to stop");
 
-    // Clone config for use in closures
-    let config_voice = config.clone();
-    let config_audio = config.clone();
+    // Shared stores, injected into handlers by the dispatcher
+    let feedback = Arc::new(FeedbackStore::new(&config.output.data_dir));
+    let selections = Arc::new(PendingSelections::new());
+    let chats = Arc::new(ChatStateStore::load(&config.output.data_dir)?);
+    let maintenance = Arc::new(Maintenance::load(&config.output.data_dir)?);
+    let pending = Arc::new(PendingStore::load(&config.output.data_dir)?);
+    let search = Arc::new(NoteSearch::new());
+
+    // Retry failed memos in the background once their dependency recovers
+    tokio::spawn(handlers::retry_pending_memos(
+        bot.clone(),
+        config.clone(),
+        feedback.clone(),
+        maintenance.clone(),
+        pending.clone(),
+    ));
+
+    // Scheduled vault backups (no-op unless [backup] dir is set)
+    tokio::spawn(backup::run_scheduled(bot.clone(), config.clone()));
+
+    // Replies to memos whose notes were deleted (no-op unless enabled)
+    tokio::spawn(handlers::report_deleted_notes(bot.clone(), config.clone()));
 
     // Create dispatcher with command and message handlers
     let handler = dptree::entry()
-        // Handle commands
+        // Handle commands, each limited to the role it requires
         .branch(
             Update::filter_message()
                 .filter_command::<Command>()
-                .endpoint(command_handler),
+                .branch(
+                    dptree::filter(|upd: Update, cmd: Command, config: Config| {
+                        access::allows(&config, &upd, cmd.required_role())
+                    })
+                    .endpoint(command_handler),
+                )
+                .endpoint(access::deny_handler),
         )
         // Handle voice messages
         .branch(
             Update::filter_message()
                 .filter(|msg: Message| msg.voice().is_some())
-                .endpoint(move |bot, msg| audio_handler(bot, msg, config_voice.clone())),
+                .chain(access::guarded(
+                    Role::Writer,
+                    dptree::endpoint(audio_handler),
+                )),
         )
         // Handle audio files
         .branch(
             Update::filter_message()
                 .filter(|msg: Message| msg.audio().is_some())
-                .endpoint(move |bot, msg| audio_handler(bot, msg, config_audio.clone())),
+                .chain(access::guarded(
+                    Role::Writer,
+                    dptree::endpoint(audio_handler),
+                )),
+        )
+        // Handle recordings sent as files (mp3, m4a, flac, wav)
+        .branch(
+            Update::filter_message()
+                .filter(|msg: Message| handlers::is_audio_document(&msg))
+                .chain(access::guarded(
+                    Role::Writer,
+                    dptree::endpoint(audio_handler),
+                )),
+        )
+        // Handle documents (HTML, reStructuredText, Word, PDF, text)
+        .branch(
+            Update::filter_message()
+                .filter(|msg: Message| msg.document().is_some())
+                .chain(access::guarded(
+                    Role::Writer,
+                    dptree::endpoint(document_handler),
+                )),
         )
+        // Handle 👍/👎 reactions to result messages
+        .branch(
+            Update::filter_message_reaction_updated().chain(access::guarded(
+                Role::Reader,
+                dptree::endpoint(reaction_handler),
+            )),
+        )
+        // Handle the "full transcript" button under result messages
+        .branch(
+            Update::filter_callback_query()
+                .filter(|query: CallbackQuery| handlers::is_transcript_request(&query))
+                .chain(access::guarded(
+                    Role::Reader,
+                    dptree::endpoint(transcript_handler),
+                )),
+        )
+        // Handle the /history buttons
+        .branch(
+            Update::filter_callback_query()
+                .filter(|query: CallbackQuery| handlers::is_history_request(&query))
+                .chain(access::guarded(
+                    Role::Writer,
+                    dptree::endpoint(history_callback_handler),
+                )),
+        )
+        // Handle the choice between candidates of two models
+        .branch(Update::filter_callback_query().chain(access::guarded(
+            Role::Writer,
+            dptree::endpoint(selection_handler),
+        )))
+        // Search notes from any chat with `@bot <query>`; anyone can send
+        // these, so only listed users get results
+        .branch(Update::filter_inline_query().chain(access::guarded_listed(
+            Role::Reader,
+            dptree::endpoint(inline_query_handler),
+        )))
         // Handle all other text messages
-        .branch(Update::filter_message().endpoint(text_handler));
+        .branch(Update::filter_message().chain(access::guarded(
+            Role::Reader,
+            dptree::endpoint(text_handler),
+        )));
 
     // Start the dispatcher
-    Dispatcher::builder(bot, handler)
-        .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
+    let mut dispatcher = Dispatcher::builder(bot, handler)
+        .dependencies(dptree::deps![
+            CommandStores {
+                feedback: feedback.clone(),
+                chats: chats.clone(),
+                maintenance: maintenance.clone(),
+                pending: pending.clone(),
+                search: search.clone(),
+            },
+            config,
+            feedback,
+            selections,
+            chats,
+            maintenance,
+            pending,
+            search
+        ])
+        .build();
+
+    // On SIGTERM or Ctrl+C stop taking updates, but finish the memos in progress
+    let shutdown = dispatcher.shutdown_token();
+    tokio::spawn(async move {
+        service::terminated().await;
+        service::stopping();
+        match shutdown.shutdown() {
+            Ok(drained) => drained.await,
+            Err(e) => log::warn!("Failed to stop the dispatcher: {}", e),
+        }
+    });
+
+    service::spawn_watchdog();
+    service::ready("Waiting for memos");
+    dispatcher.dispatch().await;
 
+    telemetry::shutdown();
     log::info!("Bot stopped");
     Ok(())
 }
 
 /// Command enumeration
 #[derive(BotCommands, Clone)]
-#[command(rename_rule = "lowercase", description = "Comandi disponibili:")]
+#[command(rename_rule = "lowercase")]
 enum Command {
-    #[command(description = "Avvia il bot")]
-    Start,
-    #[command(description = "Mostra l'aiuto")]
+    Start(String),
     Help,
-    #[command(description = "Mostra lo stato del bot")]
     Status,
+    Profile(String),
+    Vault(String),
+    Maintenance(String),
+    Retry,
+    Backup(String),
+    Share(String),
+    History,
+    Retag(String),
+}
+
+impl Command {
+    /// The command menu shown by Telegram clients, described in `language`.
+    fn menu(language: Language) -> Vec<BotCommand> {
+        [
+            ("start", Msg::MenuStart),
+            ("help", Msg::MenuHelp),
+            ("status", Msg::MenuStatus),
+            ("profile", Msg::MenuProfile),
+            ("vault", Msg::MenuVault),
+            ("maintenance", Msg::MenuMaintenance),
+            ("retry", Msg::MenuRetry),
+            ("backup", Msg::MenuBackup),
+            ("share", Msg::MenuShare),
+            ("history", Msg::MenuHistory),
+            ("retag", Msg::MenuRetag),
+        ]
+        .into_iter()
+        .map(|(command, description)| BotCommand::new(command, description.text(language)))
+        .collect()
+    }
+
+    /// Minimum role needed to run the command.
+    fn required_role(&self) -> Role {
+        match self {
+            // A deep-link payload switches the chat's profile
+            Command::Start(payload) if !payload.trim().is_empty() => Role::Writer,
+            Command::Start(_) | Command::Help | Command::Status | Command::Share(_) => Role::Reader,
+            Command::Profile(_) | Command::Vault(_) | Command::Retry | Command::History => {
+                Role::Writer
+            }
+            Command::Maintenance(_) | Command::Backup(_) | Command::Retag(_) => Role::Admin,
+        }
+    }
+}
+
+/// The stores the commands work on, injected as one dependency since
+/// endpoints take at most nine.
+#[derive(Clone)]
+struct CommandStores {
+    feedback: Arc<FeedbackStore>,
+    chats: Arc<ChatStateStore>,
+    maintenance: Arc<Maintenance>,
+    pending: Arc<PendingStore>,
+    search: Arc<NoteSearch>,
 }
 
 /// Command handler that routes to specific command functions
@@ -98,15 +362,29 @@ async fn command_handler(
     msg: Message,
     cmd: Command,
     me: Me,
+    config: Config,
+    stores: CommandStores,
 ) -> ResponseResult<()> {
+    let CommandStores {
+        feedback,
+        chats,
+        maintenance,
+        pending,
+        search,
+    } = stores;
     match cmd {
-        Command::Start => start_handler(bot, msg, me).await,
-        Command::Help => help_handler(bot, msg).await,
-        Command::Status => {
-            // Load config for status display
-            let config = Config::from_file("config.toml")
-                .expect("Failed to load config");
-            status_handler(bot, msg, config).await
+        Command::Start(payload) => start_handler(bot, msg, me, payload, config, chats).await,
+        Command::Help => help_handler(bot, msg, config).await,
+        Command::Status => status_handler(bot, msg, config, chats).await,
+        Command::Profile(name) => profile_handler(bot, msg, me, name, config, chats).await,
+        Command::Vault(name) => vault_handler(bot, msg, name, config, chats).await,
+        Command::Maintenance(arg) => maintenance_handler(bot, msg, arg, config, maintenance).await,
+        Command::Retry => retry_handler(bot, msg, config, feedback, maintenance, pending).await,
+        Command::Backup(arg) => backup_handler(bot, msg, arg, config).await,
+        Command::Share(arg) => share_handler(bot, msg, arg, config, chats).await,
+        Command::History => history_handler(bot, msg, config).await,
+        Command::Retag(args) => {
+            retag_handler(bot, msg, args, config, chats, maintenance, search).await
         }
     }
 }
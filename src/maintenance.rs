@@ -0,0 +1,165 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::atomic_write::write_atomic;
+use crate::note_generator::Note;
+use crate::source_links::SourceMessage;
+
+/// What happens to generated notes while the vault is being reorganized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MaintenanceMode {
+    /// Notes are written normally.
+    #[default]
+    Off,
+    /// Notes are generated but kept in a queue until maintenance ends.
+    Hold,
+    /// No notes are generated; the user only gets the transcript back.
+    Reject,
+}
+
+/// Notes generated during maintenance, waiting to be written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeldNotes {
+    pub chat_id: i64,
+    /// Folder the notes would have been written to.
+    pub notes_dir: String,
+    /// Vault of `notes_dir` (`None` for the default vault).
+    #[serde(default)]
+    pub vault: Option<String>,
+    pub notes: Vec<Note>,
+    pub held_at: DateTime<Utc>,
+    /// Message the memo was sent in, for `/history` and the sources log.
+    #[serde(default)]
+    pub source_message: Option<SourceMessage>,
+    /// When the memo was sent; due dates of its tasks are relative to it.
+    #[serde(default)]
+    pub received_at: Option<DateTime<Utc>>,
+    /// Action items of the memo, recorded in the tasks file on save.
+    #[serde(default)]
+    pub tasks: Vec<String>,
+    /// Model that generated the notes.
+    #[serde(default)]
+    pub model: String,
+    /// The notes come from a document rather than a voice memo.
+    #[serde(default)]
+    pub document: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MaintenanceState {
+    #[serde(default)]
+    mode: MaintenanceMode,
+    #[serde(default)]
+    held: Vec<HeldNotes>,
+}
+
+/// Maintenance mode and held notes, persisted to `<data_dir>/maintenance.json`
+/// so a restart during a long reorganization loses nothing.
+pub struct Maintenance {
+    path: PathBuf,
+    state: Mutex<MaintenanceState>,
+}
+
+impl Maintenance {
+    /// Load the state, starting with maintenance off if the file does not exist yet.
+    pub fn load<P: AsRef<Path>>(data_dir: P) -> Result<Self> {
+        let path = data_dir.as_ref().join("maintenance.json");
+        let state = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).with_context(|| {
+                format!("Failed to parse maintenance state: {}", path.display())
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => MaintenanceState::default(),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Failed to read maintenance state: {}", path.display())
+                })
+            }
+        };
+
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn mode(&self) -> MaintenanceMode {
+        self.state.lock().unwrap().mode
+    }
+
+    pub fn set_mode(&self, mode: MaintenanceMode) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.mode = mode;
+        self.persist(&state)
+    }
+
+    /// Number of memos whose notes are waiting to be written.
+    pub fn held_count(&self) -> usize {
+        self.state.lock().unwrap().held.len()
+    }
+
+    pub fn hold(&self, held: HeldNotes) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.held.push(held);
+        self.persist(&state)
+    }
+
+    /// Remove and return all held notes, oldest first.
+    pub fn take_held(&self) -> Result<Vec<HeldNotes>> {
+        let mut state = self.state.lock().unwrap();
+        let held = std::mem::take(&mut state.held);
+        self.persist(&state)?;
+        Ok(held)
+    }
+
+    fn persist(&self, state: &MaintenanceState) -> Result<()> {
+        let json =
+            serde_json::to_string_pretty(state).context("Failed to serialize maintenance state")?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create data directory")?;
+        }
+        // Write to a temp file and rename, so a crash never loses held notes
+        write_atomic(&self.path, &json, false).context("Failed to write maintenance state")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_held_notes_survive_restart() {
+        let dir = std::env::temp_dir().join(format!("dot-maintenance-{}", uuid::Uuid::new_v4()));
+        let maintenance = Maintenance::load(&dir).unwrap();
+        assert_eq!(maintenance.mode(), MaintenanceMode::Off);
+
+        maintenance.set_mode(MaintenanceMode::Hold).unwrap();
+        maintenance
+            .hold(HeldNotes {
+                chat_id: 7,
+                notes_dir: "./notes/Work".to_string(),
+                vault: None,
+                notes: Vec::new(),
+                held_at: Utc::now(),
+                source_message: None,
+                received_at: None,
+                tasks: Vec::new(),
+                model: String::new(),
+                document: false,
+            })
+            .unwrap();
+
+        let reloaded = Maintenance::load(&dir).unwrap();
+        assert_eq!(reloaded.mode(), MaintenanceMode::Hold);
+        assert_eq!(reloaded.held_count(), 1);
+
+        let held = reloaded.take_held().unwrap();
+        assert_eq!(held[0].notes_dir, "./notes/Work");
+        assert_eq!(Maintenance::load(&dir).unwrap().held_count(), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+/// Opening fence of a Mermaid code block in a note.
+const FENCE_OPEN: &str = "```mermaid";
+const FENCE_CLOSE: &str = "```";
+
+/// Remove Mermaid blocks whose flowchart syntax does not parse, so a broken
+/// diagram never ends up in the vault. Other code blocks are left alone.
+pub fn drop_invalid_blocks(content: &str) -> String {
+    let mut kept: Vec<&str> = Vec::new();
+    let mut lines = content.lines();
+
+    while let Some(line) = lines.next() {
+        if line.trim() != FENCE_OPEN {
+            kept.push(line);
+            continue;
+        }
+
+        let mut block = vec![line];
+        let mut closed = false;
+        for inner in lines.by_ref() {
+            block.push(inner);
+            if inner.trim() == FENCE_CLOSE {
+                closed = true;
+                break;
+            }
+        }
+
+        let source = block[1..block.len() - usize::from(closed)].join("\n");
+        match validate_flowchart(&source) {
+            Ok(()) if closed => kept.extend(block),
+            Ok(()) => log::warn!("Mermaid: dropping unterminated diagram block"),
+            Err(e) => log::warn!("Mermaid: dropping invalid diagram: {}", e),
+        }
+    }
+
+    let mut result = kept.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Check that `source` is a syntactically valid Mermaid flowchart.
+///
+/// Covers the subset the generation prompt asks for: a `flowchart`/`graph`
+/// header, node shapes, links with optional labels, `&` chains, subgraphs
+/// and styling statements.
+pub fn validate_flowchart(source: &str) -> Result<(), String> {
+    let mut statements = source
+        .lines()
+        .enumerate()
+        .map(|(n, line)| (n + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with("%%"));
+
+    let (_, header) = statements.next().ok_or("empty diagram")?;
+    let mut header_words = header.split_whitespace();
+    match (
+        header_words.next(),
+        header_words.next(),
+        header_words.next(),
+    ) {
+        (Some("flowchart" | "graph"), None | Some("TB" | "TD" | "BT" | "RL" | "LR"), None) => {}
+        _ => return Err(format!("unsupported header '{}'", header)),
+    }
+
+    let mut subgraphs = 0usize;
+    let mut nodes = 0usize;
+    for (n, line) in statements {
+        let line = line.trim_end_matches(';');
+        let keyword = line.split_whitespace().next().unwrap_or("");
+        match keyword {
+            "subgraph" => subgraphs += 1,
+            "end" => {
+                subgraphs = subgraphs
+                    .checked_sub(1)
+                    .ok_or_else(|| format!("line {}: 'end' without subgraph", n))?;
+            }
+            "direction" | "classDef" | "class" | "style" | "linkStyle" | "click" => {}
+            _ => {
+                parse_statement(line).map_err(|e| format!("line {}: {}", n, e))?;
+                nodes += 1;
+            }
+        }
+    }
+
+    if subgraphs > 0 {
+        return Err("unclosed subgraph".to_string());
+    }
+    if nodes == 0 {
+        return Err("no nodes".to_string());
+    }
+    Ok(())
+}
+
+/// Parse `node (link node | & node)*`.
+fn parse_statement(line: &str) -> Result<(), String> {
+    let mut rest = parse_node(line)?;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok(());
+        }
+        rest = match rest.strip_prefix('&') {
+            Some(after) => after,
+            None => parse_link(rest)?,
+        };
+        rest = parse_node(rest.trim_start())?;
+    }
+}
+
+/// Parse a node id with an optional shape (`A`, `A[text]`, `B{"x?"}`, `C((o))`)
+/// and optional `:::class`; returns the unparsed remainder.
+fn parse_node(input: &str) -> Result<&str, String> {
+    let id_len = input
+        .char_indices()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+        .map_or(input.len(), |(i, _)| i);
+    if id_len == 0 {
+        return Err(format!("expected node id at '{}'", input));
+    }
+
+    let mut rest = parse_shape(&input[id_len..])?;
+    if let Some(class) = rest.strip_prefix(":::") {
+        let class_len = class
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(class.len());
+        rest = &class[class_len..];
+    }
+    Ok(rest)
+}
+
+/// Parse a bracketed node shape, checking that brackets balance. Text inside
+/// double quotes is skipped, so labels may contain any bracket.
+fn parse_shape(input: &str) -> Result<&str, String> {
+    let mut stack: Vec<char> = Vec::new();
+    match input.chars().next() {
+        Some('[' | '(' | '{') => {}
+        // Asymmetric shape: `>text]`
+        Some('>') => stack.push(']'),
+        _ => return Ok(input),
+    }
+
+    let mut in_quotes = false;
+    for (i, c) in input.char_indices() {
+        if in_quotes {
+            in_quotes = c != '"';
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            '[' => stack.push(']'),
+            '(' => stack.push(')'),
+            '{' => stack.push('}'),
+            ']' | ')' | '}' => {
+                if stack.pop() != Some(c) {
+                    return Err(format!("mismatched '{}' in node shape", c));
+                }
+                if stack.is_empty() {
+                    return Ok(&input[i + 1..]);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err("unclosed node shape".to_string())
+}
+
+/// Parse a link (`-->`, `---`, `-.->`, `==>`, `--o`, `<-->`, `-- text -->`)
+/// with an optional `|label|`; returns the unparsed remainder.
+fn parse_link(input: &str) -> Result<&str, String> {
+    let body = input.strip_prefix('<').unwrap_or(input);
+    let run = body
+        .find(|c: char| !matches!(c, '-' | '.' | '='))
+        .unwrap_or(body.len());
+    if run < 2 {
+        return Err(format!("expected link at '{}'", input));
+    }
+
+    let mut rest = &body[run..];
+    if let Some(after) = rest.strip_prefix('>') {
+        rest = after;
+    } else if rest.starts_with(['o', 'x'])
+        && rest[1..].starts_with(|c: char| c.is_whitespace() || c == '|')
+    {
+        rest = &rest[1..];
+    } else if rest.starts_with(char::is_whitespace) && matches!(&body[..run], "--" | "-." | "==") {
+        // Inline label: `-- text -->`, `-. text .->`, `== text ==>`
+        let closings: &[&str] = match &body[..run] {
+            "--" => &["-->", "---"],
+            "-." => &[".->", ".-"],
+            _ => &["==>", "==="],
+        };
+        let (at, closing) = closings
+            .iter()
+            .filter_map(|closing| rest.find(closing).map(|at| (at, *closing)))
+            .min_by_key(|(at, _)| *at)
+            .ok_or_else(|| format!("unterminated link label at '{}'", input))?;
+        rest = &rest[at + closing.len()..];
+    }
+
+    let trimmed = rest.trim_start();
+    if let Some(label) = trimmed.strip_prefix('|') {
+        let end = label
+            .find('|')
+            .ok_or_else(|| format!("unterminated link label at '{}'", input))?;
+        rest = &label[end + 1..];
+    }
+    Ok(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_flowchart() {
+        let valid = r#"flowchart TD
+    %% ordine
+    A["Ricevi ordine"] --> B{"Disponibile?"}
+    B -->|Sì| C([Spedisci])
+    B -- No --> D[(Magazzino)]
+    C & D --> E((Fine)):::done
+    subgraph Pagamento
+        F>Fattura] -.-> G[/Incasso/]
+    end
+    classDef done fill:#9f9"#;
+        assert_eq!(validate_flowchart(valid), Ok(()));
+        assert_eq!(validate_flowchart("graph\nA---B;"), Ok(()));
+
+        assert!(validate_flowchart("sequenceDiagram\nA->>B: ciao").is_err());
+        assert!(validate_flowchart("flowchart TD\nA[Inizio --> B").is_err());
+        assert!(validate_flowchart("flowchart TD\nA(Inizio] --> B").is_err());
+        assert!(validate_flowchart("flowchart TD\nA -> B").is_err());
+        assert!(validate_flowchart("flowchart TD\nA -->|sì B").is_err());
+        assert!(validate_flowchart("flowchart TD\nsubgraph X\nA --> B").is_err());
+        assert!(validate_flowchart("flowchart LR").is_err());
+    }
+
+    #[test]
+    fn test_drop_invalid_blocks() {
+        let content = "## Processo\n\n```mermaid\nflowchart TD\nA --> B\n```\n\n```mermaid\nflowchart TD\nA[rotto --> B\n```\n\nFine\n";
+        assert_eq!(
+            drop_invalid_blocks(content),
+            "## Processo\n\n```mermaid\nflowchart TD\nA --> B\n```\n\n\nFine\n"
+        );
+
+        let untouched = "```rust\nfn main() {\n```\n";
+        assert_eq!(drop_invalid_blocks(untouched), untouched);
+    }
+}
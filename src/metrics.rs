@@ -0,0 +1,203 @@
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::ai_provider;
+use crate::atomic_write::write_atomic;
+use crate::config::{Config, MetricsConfig};
+use crate::tools::{MetricExtractor, Tool};
+use crate::vault_lock;
+
+const CSV_HEADER: &str = "date,time,metric,value\n";
+
+/// The text after the trigger word if `transcript` is a log memo ("log,
+/// corso 5 km"), or `None` for ordinary memos and when log mode is off.
+pub fn log_body<'a>(config: &MetricsConfig, transcript: &'a str) -> Option<&'a str> {
+    if config.names.is_empty() {
+        return None;
+    }
+    let transcript = transcript.trim_start();
+    let word_end = transcript
+        .find(|c: char| !c.is_alphanumeric())
+        .unwrap_or(transcript.len());
+    let word = transcript[..word_end].to_lowercase();
+    config
+        .triggers
+        .iter()
+        .any(|trigger| trigger.to_lowercase() == word)
+        .then(|| transcript[word_end..].trim_start_matches(|c: char| !c.is_alphanumeric()))
+}
+
+/// Extract the configured metrics from a log memo sent at `at` and record
+/// them in the vault. Returns the values found (none are written if empty).
+pub async fn log(config: &Config, body: &str, at: DateTime<Tz>) -> Result<BTreeMap<String, f64>> {
+    let extractor = MetricExtractor::new(
+        ai_provider::create_ai_provider(&config.ai_model)?,
+        config.metrics.names.clone(),
+        config.notes_generation.top_p,
+        config.general.language,
+    );
+    let values = extractor.run(body.to_string()).await?;
+    if values.is_empty() {
+        return Ok(values);
+    }
+
+    let path = config.vault_dir().join(
+        config
+            .metrics
+            .file
+            .replace("{date}", &at.format("%Y-%m-%d").to_string()),
+    );
+    let _lock = vault_lock::lock(&config.vault_dir()).await?;
+    record(&path, at, &values, config.output.fsync)?;
+    Ok(values)
+}
+
+/// Write `values` to `path`: as frontmatter fields of a daily note (where
+/// Obsidian Tracker reads them) plus a timestamped line in its body, or as
+/// rows of a `.csv` file.
+///
+/// The caller must hold the vault lock.
+pub fn record(
+    path: &Path,
+    at: DateTime<Tz>,
+    values: &BTreeMap<String, f64>,
+    fsync: bool,
+) -> Result<()> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).context("Failed to create metrics folder")?;
+            }
+            None
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    let date = at.format("%Y-%m-%d").to_string();
+    let time = at.format("%H:%M").to_string();
+    let content = if path.extension().is_some_and(|ext| ext == "csv") {
+        let mut content = existing.unwrap_or_else(|| CSV_HEADER.to_string());
+        for (name, value) in values {
+            content.push_str(&format!("{},{},{},{}\n", date, time, name, value));
+        }
+        content
+    } else {
+        let content = existing
+            .unwrap_or_else(|| format!("---\ndate: {}\n---\n\n# Metriche {}\n\n", date, date));
+        let mut content = upsert_frontmatter(&content, values);
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        for (name, value) in values {
+            content.push_str(&format!("- {} {}: {}\n", time, name, value));
+        }
+        content
+    };
+
+    write_atomic(path, &content, fsync)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    log::info!(
+        "Metrics: recorded {} value(s) in {}",
+        values.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Set `values` as frontmatter fields, replacing earlier values of the same
+/// metric (the latest reading of the day wins).
+fn upsert_frontmatter(content: &str, values: &BTreeMap<String, f64>) -> String {
+    let (mut fields, body): (Vec<String>, &str) = match content
+        .strip_prefix("---\n")
+        .and_then(|rest| rest.split_once("\n---\n"))
+    {
+        Some((frontmatter, body)) => (frontmatter.lines().map(str::to_string).collect(), body),
+        None => (Vec::new(), content),
+    };
+
+    for (name, value) in values {
+        let field = format!("{}: {}", name, value);
+        let prefix = format!("{}:", name);
+        match fields.iter_mut().find(|line| line.starts_with(&prefix)) {
+            Some(line) => *line = field,
+            None => fields.push(field),
+        }
+    }
+    format!("---\n{}\n---\n{}", fields.join("\n"), body)
+}
+
+/// Values as shown in replies: "corsa_km 5 · sonno_ore 7".
+pub fn format_values(values: &BTreeMap<String, f64>) -> String {
+    values
+        .iter()
+        .map(|(name, value)| format!("{} {}", name, value))
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_log_body_and_daily_note() {
+        let config = MetricsConfig {
+            names: BTreeMap::from([("corsa_km".to_string(), "chilometri corsi".to_string())]),
+            ..MetricsConfig::default()
+        };
+        assert_eq!(log_body(&config, "Log, corso 5 km"), Some("corso 5 km"));
+        assert_eq!(log_body(&config, "Logistica del trasloco"), None);
+        assert_eq!(log_body(&MetricsConfig::default(), "log corso 5 km"), None);
+
+        let dir = std::env::temp_dir().join(format!("dot-metrics-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("Metrics/2024-05-01.md");
+        let at = |hour| {
+            Tz::Europe__Rome
+                .with_ymd_and_hms(2024, 5, 1, hour, 30, 0)
+                .unwrap()
+        };
+        record(
+            &path,
+            at(7),
+            &BTreeMap::from([
+                ("corsa_km".to_string(), 5.0),
+                ("sonno_ore".to_string(), 7.5),
+            ]),
+            false,
+        )
+        .unwrap();
+        record(
+            &path,
+            at(19),
+            &BTreeMap::from([("corsa_km".to_string(), 3.0)]),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "---\ndate: 2024-05-01\ncorsa_km: 3\nsonno_ore: 7.5\n---\n\n# Metriche 2024-05-01\n\n\
+            - 07:30 corsa_km: 5\n- 07:30 sonno_ore: 7.5\n- 19:30 corsa_km: 3\n"
+        );
+
+        let csv = dir.join("metrics.csv");
+        record(
+            &csv,
+            at(7),
+            &BTreeMap::from([("corsa_km".to_string(), 5.0)]),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&csv).unwrap(),
+            format!("{}2024-05-01,07:30,corsa_km,5\n", CSV_HEADER)
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,11 +1,50 @@
-use anyhow::{Context, Result};
+use anyhow::Context;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-
-use crate::config::Config;
-use crate::ollama::{ChatRequest, OllamaClient};
-use crate::tools::{Corrector, NoteMeta, NoteWriter, NotesReader, Tool};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::Instrument;
+
+use crate::ai_provider::{self, AiProvider, ChatRequest, ResponseFormat, TokenUpdates};
+use crate::attachments;
+use crate::audit::{GenerationAttempt, GenerationLog};
+use crate::config::{
+    AiModelConfig, CompareSelection, Config, FilenameMode, NoteDateSource, OverflowStrategy,
+    PromptsConfig,
+};
+use crate::corrections::{CorrectionDictionary, KnownFix};
+use crate::dates::DateResolver;
+use crate::embeddings::EmbeddingIndex;
+use crate::errors::PipelineError;
+use crate::expenses::{self, Expense};
+use crate::history::{CaptureStatus, CapturedNote, HistoryEntry, HistoryLog};
+use crate::i18n::{Language, Msg};
+use crate::json_repair;
+use crate::library::{self, Reference};
+use crate::link_graph::{self, LinkGraph};
+use crate::maintenance::HeldNotes;
+use crate::mermaid;
+use crate::note_types::{self, NoteType};
+use crate::notes_index::NotesIndex;
+use crate::pending;
+use crate::plain_notes;
+use crate::prompt_notes;
+use crate::response_cache;
+use crate::routing::Routing;
+use crate::source_links::{NoteSource, SourceLinkLog, SourceMessage};
+use crate::tags::TagModel;
+use crate::tasks;
+use crate::template;
+use crate::timings::StageTimings;
+use crate::tools::{
+    Classifier, Corrector, NoteMeta, NoteWriter, NotesReader, Outliner, Segment, Segmenter,
+    Summarizer, TaskExtractor, Tool,
+};
+use crate::transcription;
+use crate::usage::{TokenUsage, UsageStats};
+use crate::vault_lock;
 
 /// Represents a generated note.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,66 +56,159 @@ pub struct Note {
     pub source: String,
     #[serde(default)]
     pub related_notes: Vec<String>,
+    /// Zettelkasten ID (`YYYYMMDDHHMM`), set when `filename_mode = "zettel"`.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Language detected in the memo (ISO 639-1), with `language = "auto"`.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Emoji for the topic, with `enable_icons`.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Books, articles and other media mentioned, with `extract_references`.
+    #[serde(default)]
+    pub references: Vec<Reference>,
+    /// Payments mentioned, recorded in the expenses file rather than the note.
+    #[serde(default)]
+    pub expenses: Vec<Expense>,
+    /// Link back to the Telegram message of the memo, with `telegram_link`.
+    #[serde(default)]
+    pub telegram_link: Option<String>,
+    /// Further frontmatter fields, e.g. those of the memo's note type.
+    #[serde(default)]
+    pub properties: BTreeMap<String, serde_yaml::Value>,
 }
 
-impl Note {
-    /// Convert note to markdown with YAML frontmatter.
-    pub fn to_markdown(&self) -> String {
-        let mut md = String::new();
-
-        md.push_str("---\n");
-        md.push_str(&format!("title: \"{}\"\n", self.title));
-        md.push_str(&format!("date: {}\n", self.date.format("%Y-%m-%d")));
-        md.push_str(&format!("source: {}\n", self.source));
-
-        if !self.tags.is_empty() {
-            md.push_str("tags:\n");
-            for tag in &self.tags {
-                md.push_str(&format!("  - {}\n", tag));
-            }
-        }
+/// The YAML frontmatter of a [`Note`], in the order the fields are written.
+#[derive(Serialize)]
+struct Frontmatter<'a> {
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<&'a str>,
+    date: &'a str,
+    source: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    telegram_link: Option<&'a str>,
+    #[serde(flatten)]
+    properties: &'a BTreeMap<String, serde_yaml::Value>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    tags: &'a [String],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    references: &'a [Reference],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    related: &'a [String],
+}
 
-        if !self.related_notes.is_empty() {
-            md.push_str("related:\n");
-            for rel in &self.related_notes {
-                md.push_str(&format!("  - \"{}\"\n", rel));
+impl Note {
+    /// Convert note to markdown with YAML frontmatter: the body rendered from
+    /// `template` (see [`template::render`]) or in the default layout, and
+    /// the built-in frontmatter keys written under their names in
+    /// `property_names`.
+    pub fn to_markdown(
+        &self,
+        template: Option<&str>,
+        property_names: &BTreeMap<String, String>,
+    ) -> String {
+        let mut md = self.frontmatter(property_names);
+        let Some(template) = template else {
+            md.push_str(&self.content);
+
+            // Render related notes as Obsidian wiki-links (using filenames)
+            if !self.related_notes.is_empty() {
+                md.push_str("\n\n---\n\n## Note correlate\n\n");
+                md.push_str(&self.related_links());
             }
-        }
+            return md;
+        };
 
-        md.push_str("---\n\n");
-        md.push_str(&self.content);
+        let date = self.date.format("%Y-%m-%d").to_string();
+        let tags: Vec<String> = self.tags.iter().map(|t| format!("#{}", t)).collect();
+        let tags = tags.join(" ");
+        let related = self.related_links();
+        md.push_str(&template::render(
+            template,
+            &[
+                ("title", &self.title),
+                ("content", &self.content),
+                ("date", &date),
+                ("tags", &tags),
+                ("related", &related),
+                ("source", &self.source),
+                ("id", self.id.as_deref().unwrap_or_default()),
+                ("language", self.language.as_deref().unwrap_or_default()),
+                ("icon", self.icon.as_deref().unwrap_or_default()),
+            ],
+        ));
+        md
+    }
 
-        // Render related notes as Obsidian wiki-links (using filenames)
-        if !self.related_notes.is_empty() {
-            md.push_str("\n\n---\n\n## Note correlate\n\n");
-            for rel in &self.related_notes {
-                md.push_str(&format!("- [[{}]]\n", rel));
-            }
-        }
+    /// YAML frontmatter block, including the closing `---` and a blank line.
+    fn frontmatter(&self, property_names: &BTreeMap<String, String>) -> String {
+        let date = self.date.format("%Y-%m-%d").to_string();
+        let frontmatter = Frontmatter {
+            title: &self.title,
+            id: self.id.as_deref(),
+            date: &date,
+            source: &self.source,
+            language: self.language.as_deref(),
+            icon: self.icon.as_deref(),
+            telegram_link: self.telegram_link.as_deref(),
+            properties: &self.properties,
+            tags: &self.tags,
+            references: &self.references,
+            related: &self.related_notes,
+        };
+        // Plain strings and maps always serialize
+        let value = serde_yaml::to_value(&frontmatter).expect("frontmatter is valid YAML");
+        let fields: serde_yaml::Mapping = value
+            .as_mapping()
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| {
+                let name = key.as_str().and_then(|key| property_names.get(key));
+                (
+                    name.map_or_else(|| key.clone(), |name| name.as_str().into()),
+                    value.clone(),
+                )
+            })
+            .collect();
+        let yaml = serde_yaml::to_string(&fields).expect("frontmatter is valid YAML");
+        format!("---\n{}---\n\n", yaml)
+    }
 
-        md
+    /// Related notes as a list of `[[wiki-links]]`.
+    fn related_links(&self) -> String {
+        self.related_notes
+            .iter()
+            .map(|rel| format!("- [[{}]]\n", rel))
+            .collect()
     }
 
     /// Generate a sanitized filename for this note.
     ///
     /// The filename is the title with whitespaces preserved, only removing
-    /// characters that are unsafe for filenames.
+    /// characters that are unsafe for filenames. Notes with a Zettelkasten ID
+    /// get it as a prefix (`202405141230 Title.md`).
     pub fn generate_filename(&self) -> String {
-        let safe_title: String = self
-            .title
-            .chars()
-            .filter(|c| !['/', '\\', ':', '*', '?', '"', '<', '>', '|'].contains(c))
-            .collect::<String>()
-            .replace("  ", " ");
-        let safe_title = safe_title.trim();
+        let safe_title = sanitize_filename(&self.title);
 
-        format!("{}.md", safe_title)
+        match &self.id {
+            Some(id) => format!("{} {}.md", id, safe_title),
+            None => format!("{}.md", safe_title),
+        }
     }
 
     /// Return the filename stem (filename without .md extension), used for Obsidian wiki-links.
     pub fn filename_stem(&self) -> String {
         let filename = self.generate_filename();
-        filename.strip_suffix(".md").unwrap_or(&filename).to_string()
+        filename
+            .strip_suffix(".md")
+            .unwrap_or(&filename)
+            .to_string()
     }
 
     /// Sanitize a tag for Obsidian: replace spaces with hyphens, keep only
@@ -87,6 +219,28 @@ impl Note {
             .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == '/')
             .collect()
     }
+
+    /// Keep the model's icon only if it looks like a single emoji: a short
+    /// run of symbols without letters, digits or quotes.
+    fn sanitize_icon(icon: &str) -> Option<String> {
+        let icon = icon.split_whitespace().next()?;
+        let plausible = icon.chars().count() <= 8
+            && !icon
+                .chars()
+                .any(|c| c.is_alphanumeric() || c.is_ascii_punctuation());
+        plausible.then(|| icon.to_string())
+    }
+}
+
+/// `title` without characters that are unsafe in filenames.
+pub fn sanitize_filename(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| !['/', '\\', ':', '*', '?', '"', '<', '>', '|'].contains(c))
+        .collect::<String>()
+        .replace("  ", " ")
+        .trim()
+        .to_string()
 }
 
 /// Result returned by the agent after processing a transcript.
@@ -95,69 +249,1083 @@ pub struct AgentResult {
     pub saved_paths: Vec<PathBuf>,
     pub cleaned_transcript: String,
     pub raw_transcript: String,
+    /// Model that generated the notes.
+    pub model: String,
+    /// Model that failed before `ai_model.fallback` took over, if one did.
+    pub fallback_from: Option<String>,
+    /// Time spent in each stage, for the optional reply footer.
+    pub timings: StageTimings,
+    /// Set if the transcript had to be shortened to fit the model.
+    pub truncation: Option<Truncation>,
+    /// Tokens the models used for the notes, for the reply.
+    pub usage: TokenUsage,
+    /// Action items of the memo, with task extraction enabled; recorded in
+    /// the tasks file on save.
+    pub tasks: Vec<String>,
+}
+
+impl AgentResult {
+    /// The unsaved result of notes held during maintenance.
+    pub fn held(held: HeldNotes) -> Self {
+        Self {
+            notes: held.notes,
+            saved_paths: Vec::new(),
+            cleaned_transcript: String::new(),
+            raw_transcript: String::new(),
+            model: held.model,
+            fallback_from: None,
+            timings: StageTimings::default(),
+            truncation: None,
+            usage: TokenUsage::default(),
+            tasks: held.tasks,
+        }
+    }
+}
+
+/// How a transcript too long for the model was shortened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Truncation {
+    pub strategy: OverflowStrategy,
+    /// Length of the shortened text (sections) before and after, in characters.
+    pub original_chars: usize,
+    pub kept_chars: usize,
+}
+
+impl Truncation {
+    /// Warning for the reply, saying what the notes are missing.
+    pub fn notice(&self, language: Language) -> String {
+        let what = match self.strategy {
+            OverflowStrategy::TruncateHead => Msg::TranscriptTruncatedHead,
+            OverflowStrategy::TruncateTail => Msg::TranscriptTruncatedTail,
+            OverflowStrategy::Summarize => Msg::TranscriptSummarized,
+        }
+        .fill(
+            language,
+            &[
+                ("kept", &self.kept_chars),
+                ("original", &self.original_chars),
+            ],
+        );
+        Msg::TranscriptTooLong.fill(language, &[("what", &what)])
+    }
+
+    /// Keep the beginning (`TruncateTail`) or the end (`TruncateHead`) of
+    /// `text`, at most `max_chars` characters cut between words.
+    fn cut(text: &str, max_chars: usize, strategy: OverflowStrategy) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        if chars.len() <= max_chars {
+            return text.to_string();
+        }
+        if strategy == OverflowStrategy::TruncateHead {
+            let kept: String = chars[chars.len() - max_chars..].iter().collect();
+            let kept = match kept.find(char::is_whitespace) {
+                Some(space) => &kept[space..],
+                None => &kept[..],
+            };
+            return format!("…{}", kept.trim_start());
+        }
+        let kept: String = chars[..max_chars].iter().collect();
+        let kept = match kept.rfind(char::is_whitespace) {
+            Some(space) => &kept[..space],
+            None => &kept[..],
+        };
+        format!("{}…", kept.trim_end())
+    }
+}
+
+/// Label used for the i-th candidate in judge prompts and selection buttons.
+pub fn candidate_label(i: usize) -> char {
+    (b'A' + i as u8) as char
+}
+
+/// Highest temperature reached by generation retries.
+const MAX_RETRY_TEMPERATURE: f32 = 1.2;
+/// Added to `top_p` on each generation retry.
+const RETRY_TOP_P_STEP: f32 = 0.05;
+/// Highest temperature of the review pass, which should fix rather than
+/// rewrite.
+const REVIEW_TEMPERATURE: f32 = 0.3;
+
+/// Names of the languages memos are most often in, in Italian and English.
+const LANGUAGE_NAMES: &[(&str, &str, &str)] = &[
+    ("it", "italiano", "Italian"),
+    ("en", "inglese", "English"),
+    ("fr", "francese", "French"),
+    ("de", "tedesco", "German"),
+    ("es", "spagnolo", "Spanish"),
+    ("pt", "portoghese", "Portuguese"),
+    ("nl", "olandese", "Dutch"),
+    ("ro", "rumeno", "Romanian"),
+    ("pl", "polacco", "Polish"),
+    ("ru", "russo", "Russian"),
+    ("uk", "ucraino", "Ukrainian"),
+    ("ar", "arabo", "Arabic"),
+    ("zh", "cinese", "Chinese"),
+    ("ja", "giapponese", "Japanese"),
+];
+
+/// Instruction to write the notes in the memo's `language` (ISO 639-1).
+/// Memos in the language of the prompts need none.
+fn language_instruction(language: &str, prompt_language: Language) -> Option<String> {
+    if language == prompt_language.code() {
+        return None;
+    }
+    let name = LANGUAGE_NAMES
+        .iter()
+        .find(|(code, _, _)| *code == language)
+        .map_or(language, |(_, it, en)| match prompt_language {
+            Language::It => it,
+            Language::En => en,
+        });
+    Some(Msg::LanguageInstruction.fill(prompt_language, &[("name", &name), ("code", &language)]))
 }
 
 /// Agent that orchestrates tools to generate notes from voice transcripts.
 pub struct NoteGeneratorAgent {
     corrector: Corrector,
+    segmenter: Segmenter,
+    outliner: Outliner,
+    summarizer: Summarizer,
+    task_extractor: TaskExtractor,
+    classifier: Classifier,
     notes_reader: NotesReader,
     note_writer: NoteWriter,
-    ollama: OllamaClient,
+    ai: Arc<dyn AiProvider>,
+    /// Second model run next to `ai`, keeping the better result.
+    compare_ai: Option<Arc<dyn AiProvider>>,
+    compare_selection: CompareSelection,
     notes_dir: String,
+    filename_mode: FilenameMode,
+    note_date: NoteDateSource,
     correction_enabled: bool,
+    /// Generate notes with the model; otherwise the transcript becomes one
+    /// plain note.
+    llm_enabled: bool,
+    /// Constrain generated notes to the JSON schema of [`NotesResponse`].
+    structured_output: bool,
+    /// Record the corrector's changes in the correction dictionary.
+    learn_fixes: bool,
+    auto_tags_enabled: bool,
+    tags_from_vault: bool,
+    related_from_graph: bool,
+    /// Further generation attempts after an unusable answer.
+    generation_retries: u32,
+    /// Have the model review and fix its notes before they are saved.
+    review_pass: bool,
+    retry_temperature_step: f32,
     generation_temperature: f32,
     generation_top_p: f32,
+    task_extraction_enabled: bool,
+    tasks_dir: PathBuf,
+    tasks_file: Option<String>,
+    /// Note types from `templates_dir` that memos are classified into.
+    note_types: Vec<NoteType>,
+    /// Extra frontmatter properties of every note, `[frontmatter] properties`.
+    properties: BTreeMap<String, String>,
+    /// Subfolders the notes are saved in, for their attachment links.
+    routing: Routing,
+    mermaid_enabled: bool,
+    icons_enabled: bool,
+    references_enabled: bool,
+    /// Folder of per-media notes that references are recorded in.
+    library_dir: Option<PathBuf>,
+    /// File that expenses are appended to, with `[expenses]` configured.
+    expenses_file: Option<PathBuf>,
+    expense_tags: Vec<String>,
+    default_currency: String,
+    vault_dir: PathBuf,
+    /// Flush the task, library and expense files to disk (`output.fsync`).
+    fsync: bool,
+    date_resolver: DateResolver,
+    /// Transcripts above this length are split into topics first.
+    segment_above_chars: Option<usize>,
+    /// Outline the notes first, then write each with its own prompt.
+    two_phase: bool,
+    max_notes: Option<usize>,
+    /// Let the model see (and keep) the transcript's `[mm:ss]` markers.
+    keep_timestamps: bool,
+    max_transcript_chars: Option<usize>,
+    overflow: OverflowStrategy,
+    /// Token budget of the existing notes listed in the prompt; 0 lists all.
+    existing_notes_tokens: usize,
+    /// Narrows the listed notes to those most similar to the memo.
+    embeddings: Option<EmbeddingIndex>,
+    prompt_overrides: PromptsConfig,
+    /// Language of the built-in prompts.
+    prompt_language: Language,
+    /// System prompt template of note generation, see [`Msg::NotesSystemPrompt`].
+    notes_system_prompt: String,
+    /// Target folder relative to the vault, used to pick prompt overrides.
+    notes_folder: String,
+    /// When the memo was sent; relative dates in tasks resolve against it.
+    received_at: Option<DateTime<Utc>>,
+    /// `source` recorded in the notes' frontmatter.
+    source: &'static str,
+    /// Language detected in the memo; notes are written in it.
+    language: Option<String>,
+    /// Telegram message the memo was sent in.
+    source_message: Option<SourceMessage>,
+    telegram_link_enabled: bool,
+    /// Remember the source message of saved notes to report their deletion.
+    report_deleted_notes: bool,
+    /// Transcription provider, recorded in the capture history.
+    transcription_provider: String,
+    /// Receives the tokens of the notes as the model streams them.
+    token_updates: Option<TokenUpdates>,
+    data_dir: String,
 }
 
 impl NoteGeneratorAgent {
-    pub fn new(config: &Config) -> Self {
-        let corrector_ollama = OllamaClient::new(
-            config.ai_model.endpoint.clone(),
-            config.ai_model.model.clone(),
-        );
-        let agent_ollama = OllamaClient::new(
-            config.ai_model.endpoint.clone(),
-            config.ai_model.model.clone(),
-        );
+    /// An agent for `config`; fails if its AI backend can't be set up.
+    pub fn new(config: &Config) -> Result<Self, PipelineError> {
+        let ai = ai_provider::create_ai_provider(&config.ai_model).map_err(PipelineError::Llm)?;
+        let ai = response_cache::with_cache(ai, config);
+        let language = config.general.language;
+
+        let mut corrector = Corrector::new(
+            ai.clone(),
+            config.correction.temperature,
+            config.correction.top_p,
+            language,
+        )
+        .with_known_fixes(Self::known_fixes(config));
+        if let Some(prompt) = config.prompts.corrector_system() {
+            corrector = corrector.with_system_prompt(prompt);
+        }
 
-        Self {
-            corrector: Corrector::new(
-                corrector_ollama,
-                config.correction.temperature,
-                config.correction.top_p,
-            ),
-            notes_reader: NotesReader::new(),
-            note_writer: NoteWriter::new(),
-            ollama: agent_ollama,
+        let compare_ai = match &config.notes_generation.compare_model {
+            Some(model) => {
+                let compare = ai_provider::create_ai_provider(&AiModelConfig {
+                    model: model.clone(),
+                    ..config.ai_model.clone()
+                })
+                .map_err(PipelineError::Llm)?;
+                Some(response_cache::with_cache(compare, config))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            corrector,
+            segmenter: Segmenter::new(ai.clone(), config.notes_generation.top_p, language),
+            outliner: Outliner::new(ai.clone(), config.notes_generation.top_p, language),
+            summarizer: Summarizer::new(ai.clone(), config.notes_generation.top_p, language),
+            task_extractor: TaskExtractor::new(ai.clone(), config.notes_generation.top_p, language),
+            classifier: Classifier::new(ai.clone(), config.notes_generation.top_p, language),
+            notes_reader: NotesReader::new()
+                .with_property_names(config.frontmatter.rename.clone())
+                .with_ignore(config.output.ignore.clone())
+                .with_index(NotesIndex::from_config(config)),
+            note_writer: NoteWriter::new()
+                .with_template(config.note_template())
+                .with_property_names(config.frontmatter.rename.clone())
+                .with_routing(Routing::new(&config.output.routing))
+                .with_merge(config.output.merge_existing)
+                .with_backlinks(config.output.backlinks)
+                .with_ignore(config.output.ignore.clone())
+                .with_fsync(config.output.fsync)
+                .with_vault(config.vault_dir()),
+            ai,
+            compare_ai,
+            compare_selection: config.notes_generation.compare_selection,
             notes_dir: config.output.notes_dir.clone(),
+            filename_mode: config.output.filename_mode,
+            note_date: config.output.note_date,
             correction_enabled: config.correction.enabled,
+            llm_enabled: config.ai_model.enabled(),
+            structured_output: config.ai_model.structured_output,
+            learn_fixes: config.correction.learn_fixes,
+            auto_tags_enabled: config.features.enable_auto_tags,
+            tags_from_vault: config.features.tags_from_vault,
+            related_from_graph: config.features.related_from_graph,
+            generation_retries: config.notes_generation.retries,
+            review_pass: config.notes_generation.review_pass,
+            retry_temperature_step: config.notes_generation.retry_temperature_step,
             generation_temperature: config.notes_generation.temperature,
             generation_top_p: config.notes_generation.top_p,
+            task_extraction_enabled: config.features.enable_task_extraction,
+            tasks_dir: PathBuf::from(&config.output.tasks_dir),
+            tasks_file: config.output.tasks_file.clone(),
+            note_types: config
+                .output
+                .templates_dir
+                .as_deref()
+                .map(|dir| note_types::load(Path::new(dir)))
+                .unwrap_or_default(),
+            properties: config.frontmatter.properties.clone(),
+            routing: Routing::new(&config.output.routing),
+            mermaid_enabled: config.features.enable_mermaid,
+            icons_enabled: config.features.enable_icons,
+            references_enabled: config.features.extract_references,
+            library_dir: config.library_dir(),
+            expenses_file: config.expenses_file(),
+            expense_tags: config.expenses.tags.clone(),
+            default_currency: config.expenses.currency.clone(),
+            vault_dir: config.vault_dir(),
+            fsync: config.output.fsync,
+            date_resolver: DateResolver::new(config.locale.timezone),
+            segment_above_chars: config.notes_generation.segment_above_chars,
+            two_phase: config.notes_generation.two_phase,
+            max_notes: config.notes_generation.max_notes.filter(|max| *max > 0),
+            keep_timestamps: config.notes_generation.keep_timestamps,
+            max_transcript_chars: config.notes_generation.max_transcript_chars,
+            overflow: config.notes_generation.overflow,
+            existing_notes_tokens: config.notes_generation.existing_notes_tokens,
+            embeddings: EmbeddingIndex::from_config(config),
+            prompt_overrides: config.prompts.clone(),
+            prompt_language: language,
+            notes_system_prompt: config
+                .prompts
+                .notes_system()
+                .unwrap_or_else(|| Msg::NotesSystemPrompt.text(language).to_string()),
+            notes_folder: config.notes_folder(),
+            received_at: None,
+            source: "voice-memo",
+            language: None,
+            source_message: None,
+            token_updates: None,
+            telegram_link_enabled: config.features.telegram_link,
+            report_deleted_notes: config.features.report_deleted_notes,
+            transcription_provider: config.transcription.provider.clone(),
+            data_dir: config.output.data_dir.clone(),
+        })
+    }
+
+    /// Recurring fixes from the correction dictionary, if it is enabled.
+    fn known_fixes(config: &Config) -> Vec<KnownFix> {
+        if !config.correction.learn_fixes {
+            return Vec::new();
+        }
+        CorrectionDictionary::new(&config.output.data_dir)
+            .known_fixes(
+                config.correction.min_fix_count,
+                config.correction.max_known_fixes,
+            )
+            .unwrap_or_else(|e| {
+                log::warn!("Agent: failed to load the correction dictionary: {}", e);
+                Vec::new()
+            })
+    }
+
+    /// Resolve relative dates against the time the memo was sent rather than
+    /// the time it is processed.
+    pub fn with_received_at(mut self, received_at: DateTime<Utc>) -> Self {
+        self.received_at = Some(received_at);
+        self
+    }
+
+    /// Record the language the transcriber detected and write the notes in it.
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Link the notes to the Telegram message the memo was sent in.
+    pub fn with_source_message(mut self, source: SourceMessage) -> Self {
+        self.source_message = Some(source);
+        self
+    }
+
+    /// Stream the notes from the model, counting the tokens into `tokens`.
+    pub fn with_token_updates(mut self, tokens: TokenUpdates) -> Self {
+        self.token_updates = Some(tokens);
+        self
+    }
+
+    /// Save notes held during maintenance as the agent that generated them
+    /// would have: from the same message, sent at the same time.
+    pub fn for_held(mut self, held: &HeldNotes) -> Self {
+        self.received_at = held.received_at.or(Some(held.held_at));
+        self.source_message = held.source_message.clone();
+        if held.document {
+            self = self.for_document();
+        }
+        self
+    }
+
+    /// `result`, not yet saved, as notes to hold until maintenance ends;
+    /// saved later by an agent [`for_held`](Self::for_held) with `save_to`.
+    pub fn hold(&self, result: AgentResult, chat_id: i64, vault: Option<String>) -> HeldNotes {
+        HeldNotes {
+            chat_id,
+            notes_dir: self.notes_dir.clone(),
+            vault,
+            notes: result.notes,
+            held_at: Utc::now(),
+            source_message: self.source_message.clone(),
+            received_at: self.received_at,
+            tasks: result.tasks,
+            model: result.model,
+            document: self.source == "document",
+        }
+    }
+
+    /// Generate from a written document: there are no transcription errors
+    /// to correct, and notes record `source: document`.
+    pub fn for_document(mut self) -> Self {
+        self.correction_enabled = false;
+        self.source = "document";
+        self
+    }
+
+    /// Save the notes of a generated result to the vault.
+    pub async fn save(&self, result: AgentResult) -> Result<AgentResult, PipelineError> {
+        self.save_to(result, &self.notes_dir).await
+    }
+
+    /// Save the notes of a generated result to `dir` instead of the vault.
+    pub async fn save_to(
+        &self,
+        mut result: AgentResult,
+        dir: &str,
+    ) -> Result<AgentResult, PipelineError> {
+        // Step 4: Save notes
+        log::info!("Agent: Step 4 - Saving notes to {}", dir);
+        let save = self
+            .note_writer
+            .run((result.notes.clone(), dir.to_string()))
+            .instrument(tracing::info_span!("save", notes = result.notes.len()));
+        result.saved_paths = result
+            .timings
+            .time("save", save)
+            .await
+            .context("Agent: failed to save notes")
+            .map_err(PipelineError::Save)?;
+
+        // The notes are saved; a failed library, expenses or tasks update is only logged
+        if let Err(e) = self.record_extracted(&result).await {
+            log::warn!(
+                "Agent: failed to record references, expenses or tasks: {:#}",
+                e
+            );
+        }
+        if let Err(e) = self.record_sources(&result) {
+            log::warn!("Agent: failed to record source links: {:#}", e);
+        }
+        if let Err(e) = self.record_history(&result) {
+            log::warn!("Agent: failed to record the capture history: {:#}", e);
+        }
+
+        Ok(result)
+    }
+
+    /// Record the notes' references in the library, their expenses in the
+    /// expenses file and the memo's tasks in the tasks file, when configured.
+    async fn record_extracted(&self, result: &AgentResult) -> anyhow::Result<()> {
+        let notes = &result.notes;
+        let library = self
+            .library_dir
+            .as_ref()
+            .filter(|_| notes.iter().any(|note| !note.references.is_empty()));
+        let expenses = self
+            .expenses_file
+            .as_ref()
+            .filter(|_| notes.iter().any(|note| !note.expenses.is_empty()));
+        if library.is_none() && expenses.is_none() && result.tasks.is_empty() {
+            return Ok(());
+        }
+
+        let _lock = vault_lock::lock(&self.vault_dir).await?;
+        if let Some(library_dir) = library {
+            library::record(library_dir, notes, self.fsync)?;
+        }
+        if let Some(path) = expenses {
+            expenses::record(path, notes, &self.expense_tags, self.fsync)?;
+        }
+        if !result.tasks.is_empty() {
+            let at = self.received_at.unwrap_or_else(Utc::now);
+            let path = tasks::tasks_path(
+                &self.tasks_dir,
+                self.tasks_file.as_deref(),
+                &self.date_resolver,
+                at,
+            );
+            // Each task links back to the (first) note of its memo
+            let source = result
+                .saved_paths
+                .first()
+                .and_then(|path| path.file_stem())
+                .map(|stem| stem.to_string_lossy());
+            tasks::record(
+                &path,
+                &result.tasks,
+                source.as_deref(),
+                &self.date_resolver,
+                at,
+                self.fsync,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Remember which message the saved notes came from, so their deletion
+    /// can be reported in the chat.
+    fn record_sources(&self, result: &AgentResult) -> anyhow::Result<()> {
+        let Some(source) = self
+            .source_message
+            .as_ref()
+            .filter(|_| self.report_deleted_notes)
+        else {
+            return Ok(());
+        };
+        let sources: Vec<NoteSource> = result
+            .notes
+            .iter()
+            .zip(&result.saved_paths)
+            .map(|(note, path)| NoteSource {
+                path: path.clone(),
+                title: note.title.clone(),
+                chat_id: source.chat_id,
+                message_id: source.message_id,
+            })
+            .collect();
+        SourceLinkLog::new(&self.data_dir).append(&sources)
+    }
+
+    /// Add the saved notes to the source message's entry in `/history`.
+    fn record_history(&self, result: &AgentResult) -> anyhow::Result<()> {
+        let Some(source) = &self.source_message else {
+            return Ok(());
+        };
+        HistoryLog::new(&self.data_dir).append(&HistoryEntry {
+            timestamp: self.received_at.unwrap_or_else(Utc::now),
+            chat_id: source.chat_id,
+            message_id: source.message_id,
+            status: CaptureStatus::Saved,
+            provider: match self.source {
+                "document" => "document".to_string(),
+                _ => self.transcription_provider.clone(),
+            },
+            model: self.llm_enabled.then(|| result.model.clone()),
+            notes: result
+                .notes
+                .iter()
+                .zip(&result.saved_paths)
+                .map(|(note, path)| CapturedNote {
+                    title: note.title.clone(),
+                    path: path.clone(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Run correction and note generation without writing to the vault.
+    ///
+    /// The returned result has no `saved_paths`; call `save` to write it.
+    /// `attachments` are files already stored in the attachments folder
+    /// (e.g. the archived audio); each note embeds them with a relative link.
+    /// With a comparison model configured, the judge picks the better candidate.
+    pub async fn generate(
+        &self,
+        raw_transcript: String,
+        attachments: &[PathBuf],
+    ) -> Result<AgentResult, PipelineError> {
+        let mut candidates = self
+            .generate_candidates(raw_transcript, attachments)
+            .await?;
+        let winner = if candidates.len() > 1 {
+            let before = self.usage();
+            let winner = self
+                .judge(&candidates)
+                .instrument(tracing::info_span!("judge"))
+                .await;
+            let judged = self.record_usage(&before);
+            candidates[winner].usage.add(&judged);
+            winner
+        } else {
+            0
+        };
+        Ok(candidates.swap_remove(winner))
+    }
+
+    /// Tokens used by this agent's models so far.
+    pub fn usage(&self) -> TokenUsage {
+        let mut usage = self.ai.usage();
+        if let Some(compare) = &self.compare_ai {
+            usage.add(&compare.usage());
         }
+        usage
     }
 
-    /// Process a raw transcript through the full agent pipeline.
-    pub async fn process_transcript(&self, raw_transcript: String) -> Result<AgentResult> {
-        // Step 1: Correct transcription (if enabled)
+    /// Log the tokens used since `before` and add them to the totals of
+    /// `/status`. Returns them.
+    fn record_usage(&self, before: &TokenUsage) -> TokenUsage {
+        let spent = self.usage().since(before);
+        if spent.is_empty() {
+            return spent;
+        }
+        log::info!(
+            "Agent: {} model request(s), {} prompt + {} completion tokens",
+            spent.requests,
+            spent.prompt_tokens,
+            spent.completion_tokens
+        );
+        if let Err(e) = UsageStats::new(&self.data_dir).add(&spent) {
+            log::warn!("Agent: failed to record token usage: {}", e);
+        }
+        spent
+    }
+
+    /// Whether results from the comparison model should be offered to the
+    /// user instead of being judged automatically.
+    pub fn asks_user_to_choose(&self) -> bool {
+        self.compare_ai.is_some() && self.compare_selection == CompareSelection::Ask
+    }
+
+    /// Generate notes with the primary model and, if configured, the
+    /// comparison model concurrently.
+    ///
+    /// Returns one candidate per model that succeeded, primary first; fails
+    /// only if every model failed.
+    pub async fn generate_candidates(
+        &self,
+        raw_transcript: String,
+        attachments: &[PathBuf],
+    ) -> Result<Vec<AgentResult>, PipelineError> {
+        if !self.llm_enabled {
+            return Ok(vec![self.plain_result(raw_transcript, attachments).await]);
+        }
+        // With the model down, fail fast instead of retrying every step, so
+        // the caller saves the raw transcript as an unprocessed note
+        if !self.ai.is_available().await {
+            return Err(PipelineError::Llm(anyhow::anyhow!(
+                "{} is not reachable",
+                self.ai.describe()
+            )));
+        }
+        let mut timings = StageTimings::default();
+        let usage_before = self.usage();
+        let stripped = transcription::strip_timestamps(&raw_transcript);
+        let timestamped = stripped != raw_transcript;
+        let transcript = if self.keep_timestamps {
+            raw_transcript.clone()
+        } else {
+            stripped
+        };
+        let correction_started = Instant::now();
+        let cleaned_transcript = self
+            .correct(&transcript)
+            .instrument(tracing::info_span!("correction"))
+            .await;
+        if self.correction_enabled {
+            timings.record("correct", correction_started.elapsed());
+        }
+        let tasks = self
+            .extract_tasks(&cleaned_transcript)
+            .instrument(tracing::info_span!("tasks"))
+            .await;
+        let note_type = self
+            .classify(&cleaned_transcript)
+            .instrument(tracing::info_span!("classification"))
+            .await;
+        let notes_started = Instant::now();
+        let existing_notes = self
+            .read_existing_notes()
+            .instrument(tracing::info_span!("read_notes"))
+            .await;
+        let similar_notes = self
+            .similar_notes(&existing_notes, &cleaned_transcript)
+            .instrument(tracing::info_span!("embeddings"))
+            .await;
+        let listed_notes = prompt_notes::select(
+            similar_notes.as_deref().unwrap_or(&existing_notes),
+            &cleaned_transcript,
+            self.existing_notes_tokens,
+        );
+        let segments = self
+            .segment(&cleaned_transcript, &listed_notes)
+            .instrument(tracing::info_span!("segmentation"))
+            .await;
+        let (segments, truncation) = self.fit_to_context(segments).await;
+        let mut instructions = self
+            .prompt_overrides
+            .instructions_for(&self.notes_folder, &cleaned_transcript);
+        let language = self.prompt_language;
+        let max_notes_instruction = self
+            .max_notes
+            .map(|max| Msg::MaxNotesInstruction.fill(language, &[("max", &max)]));
+        if let Some(instruction) = &max_notes_instruction {
+            instructions.push(instruction);
+        }
+        let note_type_instruction = note_type
+            .filter(|note_type| !note_type.sections().is_empty())
+            .map(|note_type| {
+                Msg::NoteTypeInstruction.fill(
+                    language,
+                    &[
+                        ("name", &note_type.name),
+                        ("sections", &note_type.sections().join(", ")),
+                    ],
+                )
+            });
+        if let Some(instruction) = &note_type_instruction {
+            instructions.push(instruction);
+        }
+        if self.mermaid_enabled {
+            instructions.push(Msg::MermaidInstruction.text(language));
+        }
+        if self.icons_enabled {
+            instructions.push(Msg::IconInstruction.text(language));
+        }
+        if self.keep_timestamps && timestamped {
+            instructions.push(Msg::TimestampInstruction.text(language));
+        }
+        if self.references_enabled {
+            instructions.push(Msg::ReferencesInstruction.text(language));
+        }
+        let expenses_instruction = self.expenses_file.as_ref().map(|_| {
+            let tag = self.expense_tags.first().map_or("spese", String::as_str);
+            Msg::ExpensesInstruction.fill(language, &[("tag", &tag)])
+        });
+        if let Some(instruction) = &expenses_instruction {
+            instructions.push(instruction);
+        }
+        let language_instruction = self
+            .language
+            .as_deref()
+            .and_then(|memo_language| language_instruction(memo_language, language));
+        if let Some(instruction) = &language_instruction {
+            instructions.push(instruction);
+        }
+
+        // Step 3: Generate notes with LLM (context-aware). Notes planned by
+        // the outline only see the existing notes it related them to
+        let system_prompts: Vec<String> = if self.is_outlined(&segments) {
+            segments
+                .iter()
+                .map(|segment| {
+                    let related: Vec<NoteMeta> = listed_notes
+                        .iter()
+                        .filter(|note| {
+                            let stem = note.filename.strip_suffix(".md").unwrap_or(&note.filename);
+                            segment.related.iter().any(|r| r.eq_ignore_ascii_case(stem))
+                        })
+                        .cloned()
+                        .collect();
+                    Self::build_system_prompt(
+                        language,
+                        &self.notes_system_prompt,
+                        &related,
+                        &instructions,
+                        &segment.text,
+                    )
+                })
+                .collect()
+        } else {
+            let system_prompt = Self::build_system_prompt(
+                language,
+                &self.notes_system_prompt,
+                &listed_notes,
+                &instructions,
+                &cleaned_transcript,
+            );
+            vec![system_prompt; segments.len()]
+        };
+        let primary = self
+            .generate_notes(
+                self.ai.as_ref(),
+                &system_prompts,
+                &segments,
+                &existing_notes,
+                attachments,
+            )
+            .instrument(tracing::info_span!("generation", model = %self.ai.model()));
+        let outcomes = match &self.compare_ai {
+            Some(compare) => {
+                log::info!(
+                    "Agent: Step 3 - Generating with {} and {} concurrently",
+                    self.ai.model(),
+                    compare.model()
+                );
+                let secondary = self
+                    .generate_notes(
+                        compare.as_ref(),
+                        &system_prompts,
+                        &segments,
+                        &existing_notes,
+                        attachments,
+                    )
+                    .instrument(tracing::info_span!("generation", model = %compare.model()));
+                let (a, b) = tokio::join!(primary, secondary);
+                vec![(&self.ai, a), (compare, b)]
+            }
+            None => vec![(&self.ai, primary.await)],
+        };
+        timings.record("notes", notes_started.elapsed());
+        let usage = self.record_usage(&usage_before);
+
+        let mut candidates = Vec::new();
+        let mut first_error = None;
+        for (client, outcome) in outcomes {
+            match outcome {
+                Ok(mut notes) => {
+                    self.add_properties(&mut notes, &cleaned_transcript);
+                    if let Some(note_type) = note_type {
+                        for note in &mut notes {
+                            note_type.apply(note);
+                        }
+                    }
+                    candidates.push(AgentResult {
+                        notes,
+                        saved_paths: Vec::new(),
+                        cleaned_transcript: cleaned_transcript.clone(),
+                        raw_transcript: raw_transcript.clone(),
+                        model: client
+                            .fallback_model()
+                            .unwrap_or_else(|| client.model().to_string()),
+                        fallback_from: client.fallback_model().map(|_| client.model().to_string()),
+                        timings: timings.clone(),
+                        truncation,
+                        usage,
+                        tasks: tasks.clone(),
+                    });
+                }
+                Err(e) => {
+                    log::warn!("Agent: generation with {} failed: {}", client.model(), e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) if candidates.is_empty() => Err(e),
+            _ => Ok(candidates),
+        }
+    }
+
+    /// Step 1: Correct transcription (if enabled), falling back to the raw text.
+    async fn correct(&self, raw_transcript: &str) -> String {
         log::info!(
             "Agent: Step 1 - Correcting transcription (enabled={})",
             self.correction_enabled
         );
-        let cleaned_transcript = if self.correction_enabled {
-            match self.corrector.run(raw_transcript.clone()).await {
-                Ok(cleaned) => cleaned,
-                Err(e) => {
-                    log::warn!("Agent: correction failed, using raw transcript: {}", e);
-                    raw_transcript.clone()
+        if !self.correction_enabled {
+            return raw_transcript.to_string();
+        }
+        match self.corrector.run(raw_transcript.to_string()).await {
+            Ok(cleaned) => {
+                if self.learn_fixes {
+                    match CorrectionDictionary::new(&self.data_dir).learn(raw_transcript, &cleaned)
+                    {
+                        Ok(0) => {}
+                        Ok(n) => {
+                            log::info!("Agent: recorded {} correction(s) in the dictionary", n)
+                        }
+                        Err(e) => {
+                            log::warn!("Agent: failed to update the correction dictionary: {}", e)
+                        }
+                    }
+                }
+                cleaned
+            }
+            Err(e) => {
+                log::warn!(
+                    "Agent: {} failed, using raw transcript: {}",
+                    self.corrector.name(),
+                    e
+                );
+                raw_transcript.to_string()
+            }
+        }
+    }
+
+    /// Step 1a: Find the memo's action items, with task extraction enabled
+    /// (none on failure).
+    async fn extract_tasks(&self, cleaned_transcript: &str) -> Vec<String> {
+        if !self.task_extraction_enabled {
+            return Vec::new();
+        }
+        log::info!("Agent: Step 1a - Extracting tasks");
+        match self
+            .task_extractor
+            .run(cleaned_transcript.to_string())
+            .await
+        {
+            Ok(tasks) => tasks,
+            Err(e) => {
+                log::warn!("Agent: {} failed: {}", self.task_extractor.name(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Give `notes` the `[frontmatter] properties`, with their placeholders
+    /// filled for each note; properties left empty are skipped.
+    fn add_properties(&self, notes: &mut [Note], transcript: &str) {
+        if self.properties.is_empty() {
+            return;
+        }
+        let project = self
+            .prompt_overrides
+            .detected_project(transcript)
+            .unwrap_or_default();
+        for note in notes {
+            let date = note.date.format("%Y-%m-%d").to_string();
+            for (key, value) in &self.properties {
+                let value = template::render(
+                    value,
+                    &[
+                        ("title", &note.title),
+                        ("date", &date),
+                        ("source", &note.source),
+                        ("language", note.language.as_deref().unwrap_or_default()),
+                        ("detected_project", project),
+                    ],
+                );
+                if !value.trim().is_empty() {
+                    note.properties
+                        .insert(key.clone(), serde_yaml::Value::String(value));
                 }
             }
+        }
+    }
+
+    /// Step 1d: Pick the memo's note type among the templates of
+    /// `templates_dir` (none on failure).
+    async fn classify(&self, cleaned_transcript: &str) -> Option<&NoteType> {
+        if self.note_types.is_empty() {
+            return None;
+        }
+        log::info!("Agent: Step 1d - Classifying the memo");
+        let types = self
+            .note_types
+            .iter()
+            .map(|note_type| (note_type.name.clone(), note_type.description.clone()))
+            .collect();
+        match self
+            .classifier
+            .run((cleaned_transcript.to_string(), types))
+            .await
+        {
+            Ok(name) => {
+                let name = name?;
+                self.note_types
+                    .iter()
+                    .find(|note_type| note_type.name == name)
+            }
+            Err(e) => {
+                log::warn!("Agent: {} failed: {}", self.classifier.name(), e);
+                None
+            }
+        }
+    }
+
+    /// Step 1c: Shorten sections longer than `max_transcript_chars` as
+    /// configured, so the model sees them whole instead of silently losing
+    /// what overflows its context.
+    async fn fit_to_context(&self, segments: Vec<Segment>) -> (Vec<Segment>, Option<Truncation>) {
+        let Some(max_chars) = self.max_transcript_chars else {
+            return (segments, None);
+        };
+        let mut truncation: Option<Truncation> = None;
+        let mut fitted = Vec::with_capacity(segments.len());
+        for segment in segments {
+            let original_chars = segment.text.chars().count();
+            if original_chars <= max_chars {
+                fitted.push(segment);
+                continue;
+            }
+
+            let mut strategy = self.overflow;
+            let text = if strategy == OverflowStrategy::Summarize {
+                log::info!(
+                    "Agent: Step 1c - Summarizing a section of {} chars",
+                    original_chars
+                );
+                match self.summarizer.run((segment.text.clone(), max_chars)).await {
+                    Ok(summary) => {
+                        Truncation::cut(&summary, max_chars, OverflowStrategy::TruncateTail)
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "Agent: {} failed, truncating instead: {}",
+                            self.summarizer.name(),
+                            e
+                        );
+                        strategy = OverflowStrategy::TruncateTail;
+                        Truncation::cut(&segment.text, max_chars, strategy)
+                    }
+                }
+            } else {
+                log::info!(
+                    "Agent: Step 1c - Truncating a section of {} chars",
+                    original_chars
+                );
+                Truncation::cut(&segment.text, max_chars, strategy)
+            };
+
+            let kept_chars = text.chars().count();
+            let total = truncation.get_or_insert(Truncation {
+                strategy,
+                original_chars: 0,
+                kept_chars: 0,
+            });
+            total.strategy = strategy;
+            total.original_chars += original_chars;
+            total.kept_chars += kept_chars;
+            fitted.push(Segment { text, ..segment });
+        }
+        (fitted, truncation)
+    }
+
+    /// Step 1b: Split long transcripts into topics, so each gets a focused
+    /// prompt. Short transcripts and segmentation failures yield one segment.
+    ///
+    /// With `two_phase`, the outline plans one section per note instead,
+    /// relating each to the `listed_notes` it concerns.
+    async fn segment(&self, cleaned_transcript: &str, listed_notes: &[NoteMeta]) -> Vec<Segment> {
+        let whole = vec![Segment {
+            topic: String::new(),
+            text: cleaned_transcript.to_string(),
+            related: Vec::new(),
+        }];
+        match self.segment_above_chars {
+            Some(limit) if cleaned_transcript.chars().count() > limit => {}
+            None if self.two_phase => {}
+            _ => return whole,
+        }
+
+        let (name, outcome) = if self.two_phase {
+            log::info!("Agent: Step 1b - Outlining the notes of the transcript");
+            let stems = listed_notes
+                .iter()
+                .map(|note| {
+                    note.filename
+                        .strip_suffix(".md")
+                        .unwrap_or(&note.filename)
+                        .to_string()
+                })
+                .collect();
+            let outcome = self
+                .outliner
+                .run((cleaned_transcript.to_string(), stems))
+                .await;
+            (self.outliner.name(), outcome)
         } else {
-            raw_transcript.clone()
+            log::info!("Agent: Step 1b - Segmenting long transcript by topic");
+            let outcome = self.segmenter.run(cleaned_transcript.to_string()).await;
+            (self.segmenter.name(), outcome)
         };
+        match outcome {
+            Ok(segments) if !segments.is_empty() => segments,
+            Ok(_) => whole,
+            Err(e) => {
+                log::warn!("Agent: {} failed, using whole transcript: {}", name, e);
+                whole
+            }
+        }
+    }
+
+    /// Whether `segments` are the notes planned by a two-phase outline, each
+    /// generated as one note.
+    fn is_outlined(&self, segments: &[Segment]) -> bool {
+        self.two_phase && segments.len() > 1
+    }
 
-        // Step 2: Read existing notes index
+    /// Step 2: Read existing notes index (empty on failure).
+    async fn read_existing_notes(&self) -> Vec<NoteMeta> {
         log::info!("Agent: Step 2 - Reading existing notes index");
-        let existing_notes = match self.notes_reader.run(self.notes_dir.clone()).await {
+        match self.notes_reader.run(self.notes_dir.clone()).await {
             Ok(notes) => {
                 log::info!(
                     "Agent: Step 2 - Reading existing notes index ({} notes)",
@@ -169,138 +1337,639 @@ impl NoteGeneratorAgent {
                 log::warn!("Agent: failed to read existing notes: {}", e);
                 Vec::new()
             }
+        }
+    }
+
+    /// Step 2b: The existing notes most similar to the transcript, with an
+    /// embedding model configured; `None` lists them all.
+    async fn similar_notes(
+        &self,
+        existing_notes: &[NoteMeta],
+        transcript: &str,
+    ) -> Option<Vec<NoteMeta>> {
+        let index = self.embeddings.as_ref()?;
+        if existing_notes.is_empty() {
+            return None;
+        }
+        log::info!("Agent: Step 2b - Finding the existing notes most similar to the memo");
+        match index.most_similar(existing_notes, transcript).await {
+            Ok(similar) => Some(similar),
+            Err(e) => {
+                log::warn!(
+                    "Agent: embedding retrieval failed, listing all notes: {}",
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Step 3: Generate, post-process and link notes with one model.
+    ///
+    /// Each segment is a separate LLM call; links, ids and attachments are
+    /// then handled across the whole batch.
+    async fn generate_notes(
+        &self,
+        client: &dyn AiProvider,
+        system_prompts: &[String],
+        segments: &[Segment],
+        existing_notes: &[NoteMeta],
+        attachments: &[PathBuf],
+    ) -> Result<Vec<Note>, PipelineError> {
+        log::info!("Agent: Step 3 - Generating notes with {}", client.model());
+
+        let mut generated = Vec::new();
+        for (i, (segment, system_prompt)) in segments.iter().zip(system_prompts).enumerate() {
+            let user_prompt = if segments.len() == 1 {
+                self.build_user_prompt(&segment.text)
+            } else if self.is_outlined(segments) {
+                self.build_outlined_prompt(segment, i, segments.len())
+            } else {
+                self.build_segment_prompt(segment, i, segments.len())
+            };
+
+            let notes = self
+                .generate_segment(client, system_prompt, &user_prompt)
+                .await?;
+            generated.extend(notes);
+        }
+        if self.review_pass {
+            generated = self
+                .review(client, segments, existing_notes, generated)
+                .instrument(tracing::info_span!("review"))
+                .await;
+        }
+        if let Some(max) = self.max_notes {
+            generated = Self::cap_notes(generated, max);
+        }
+
+        let now = Utc::now();
+        let date = self.note_date(now);
+        let mut notes: Vec<Note> = generated
+            .into_iter()
+            .map(|nd| Note {
+                title: nd.title,
+                content: nd.content,
+                tags: if self.auto_tags_enabled {
+                    nd.tags.iter().map(|t| Note::sanitize_tag(t)).collect()
+                } else {
+                    Vec::new()
+                },
+                date,
+                source: self.source.to_string(),
+                language: self.language.clone(),
+                icon: nd
+                    .icon
+                    .as_deref()
+                    .filter(|_| self.icons_enabled)
+                    .and_then(Note::sanitize_icon),
+                references: if self.references_enabled {
+                    nd.references
+                        .into_iter()
+                        .filter_map(Reference::sanitize)
+                        .collect()
+                } else {
+                    Vec::new()
+                },
+                expenses: if self.expenses_file.is_some() {
+                    nd.expenses
+                        .into_iter()
+                        .filter_map(|e| e.sanitize(&self.default_currency))
+                        .collect()
+                } else {
+                    Vec::new()
+                },
+                telegram_link: self
+                    .source_message
+                    .as_ref()
+                    .filter(|_| self.telegram_link_enabled)
+                    .and_then(|source| source.link.clone()),
+                properties: BTreeMap::new(),
+                related_notes: nd.related_notes.unwrap_or_default(),
+                id: None,
+            })
+            .collect();
+
+        log::info!("Agent: Step 3 - Generated {} note(s)", notes.len());
+
+        // Converge on the vault's taxonomy instead of inventing new tags
+        if self.auto_tags_enabled && self.tags_from_vault {
+            let model = TagModel::from_notes(existing_notes);
+            for note in &mut notes {
+                note.tags = model.refine(&note.tags);
+            }
+        }
+
+        // Due dates for tasks with relative date phrases ("domani" → 📅 2024-05-17)
+        if self.task_extraction_enabled {
+            let received_at = self.received_at.unwrap_or(now);
+            for note in &mut notes {
+                note.content = self
+                    .date_resolver
+                    .annotate_tasks(&note.content, received_at);
+            }
+        }
+
+        // Diagrams must parse, otherwise Obsidian shows an error block
+        if self.mermaid_enabled {
+            for note in &mut notes {
+                note.content = mermaid::drop_invalid_blocks(&note.content);
+            }
+        }
+
+        if self.filename_mode == FilenameMode::Zettel {
+            Self::assign_zettel_ids(&mut notes, existing_notes, date);
+        }
+
+        // Step 3b: Post-process — inject [[links]] for existing note titles and cross-link batch notes
+        let mut notes = Self::post_process_links(notes, existing_notes);
+
+        // Relate notes to the clusters of the vault they link into
+        if self.related_from_graph {
+            Self::relate_from_graph(&mut notes, existing_notes);
+        }
+
+        // Step 3c: Embed attachments relative to each note's location
+        self.embed_attachments(&mut notes, attachments);
+
+        Ok(notes)
+    }
+
+    /// Notes for one generation prompt. While the model answers with no
+    /// notes or with JSON that doesn't fit, the prompt is retried up to
+    /// `generation_retries` times, each time warmer and with a stricter
+    /// instruction; attempts go to the [`GenerationLog`].
+    async fn generate_segment(
+        &self,
+        client: &dyn AiProvider,
+        system_prompt: &str,
+        user_prompt: &str,
+    ) -> Result<Vec<NoteData>, PipelineError> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let step = (attempt - 1) as f32;
+            let temperature = (self.generation_temperature + step * self.retry_temperature_step)
+                .min(MAX_RETRY_TEMPERATURE);
+            let top_p = (self.generation_top_p + step * RETRY_TOP_P_STEP).min(1.0);
+            let mut user_prompt = user_prompt.to_string();
+            if attempt > 1 {
+                user_prompt.push_str("\n\n");
+                user_prompt.push_str(Msg::RetryInstruction.text(self.prompt_language));
+            }
+
+            let request = ChatRequest {
+                system_prompt: system_prompt.to_string(),
+                user_prompt,
+                temperature,
+                top_p,
+                format: self.notes_format(),
+            };
+            let llm_response = match &self.token_updates {
+                Some(tokens) => client.chat_streaming(request, tokens.clone()).await,
+                None => client.chat(request).await,
+            }
+            .context("Agent: LLM note generation failed")
+            .map_err(PipelineError::Llm)?;
+
+            let outcome = self
+                .parse_notes(client, &llm_response)
+                .await
+                .and_then(|response| {
+                    anyhow::ensure!(!response.notes.is_empty(), "The answer has no notes");
+                    Ok(response.notes)
+                });
+            let error = outcome.as_ref().err().map(|e| format!("{:#}", e));
+            if error.is_some() || attempt > 1 {
+                self.record_attempt(client.model(), attempt, temperature, top_p, error);
+            }
+            match outcome {
+                Ok(notes) => return Ok(notes),
+                Err(e) if attempt > self.generation_retries => return Err(PipelineError::Llm(e)),
+                Err(e) => log::warn!(
+                    "Agent: generation attempt {} with {} failed, retrying: {:#}",
+                    attempt,
+                    client.model(),
+                    e
+                ),
+            }
+        }
+    }
+
+    /// Step 3a: Ask the model to check its notes against the transcript
+    /// (missing points, made-up facts, links to no note) and fix them. Any
+    /// failure keeps the notes as generated.
+    async fn review(
+        &self,
+        client: &dyn AiProvider,
+        segments: &[Segment],
+        existing_notes: &[NoteMeta],
+        notes: Vec<NoteData>,
+    ) -> Vec<NoteData> {
+        log::info!(
+            "Agent: Step 3a - Reviewing {} note(s) with {}",
+            notes.len(),
+            client.model()
+        );
+        let language = self.prompt_language;
+        let transcript: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
+        let response = NotesResponse { notes };
+        let json = match serde_json::to_string_pretty(&response) {
+            Ok(json) => json,
+            Err(e) => {
+                log::warn!("Agent: failed to serialize notes for review: {}", e);
+                return response.notes;
+            }
+        };
+        let mut user_prompt = Msg::ReviewUserPrompt.fill(
+            language,
+            &[("transcript", &transcript.join("\n\n")), ("notes", &json)],
+        );
+        let broken = Self::broken_links(&response.notes, existing_notes);
+        if !broken.is_empty() {
+            user_prompt
+                .push_str(&Msg::ReviewBrokenLinks.fill(language, &[("links", &broken.join(", "))]));
+        }
+
+        let request = ChatRequest {
+            system_prompt: Msg::ReviewSystemPrompt.text(language).to_string(),
+            user_prompt,
+            temperature: self.generation_temperature.min(REVIEW_TEMPERATURE),
+            top_p: self.generation_top_p,
+            format: self.notes_format(),
+        };
+        let reviewed = match client.chat(request).await {
+            Ok(answer) => self.parse_notes(client, &answer).await,
+            Err(e) => Err(e),
+        };
+        match reviewed {
+            Ok(reviewed) if !reviewed.notes.is_empty() => reviewed.notes,
+            Ok(_) => {
+                log::warn!("Agent: the review returned no notes, keeping the generated ones");
+                response.notes
+            }
+            Err(e) => {
+                log::warn!("Agent: review failed, keeping the generated notes: {:#}", e);
+                response.notes
+            }
+        }
+    }
+
+    /// Merge the notes beyond the first `max` into the longest of those, as
+    /// sections under their titles, so `max_notes` holds whatever the model
+    /// answered.
+    fn cap_notes(mut notes: Vec<NoteData>, max: usize) -> Vec<NoteData> {
+        if notes.len() <= max || max == 0 {
+            return notes;
+        }
+        log::info!(
+            "Agent: merging {} note(s) over max_notes = {}",
+            notes.len() - max,
+            max
+        );
+        let overflow = notes.split_off(max);
+        let target = notes
+            .iter_mut()
+            .max_by_key(|note| note.content.chars().count())
+            .expect("max is at least 1");
+        for note in overflow {
+            target.content = format!(
+                "{}\n\n## {}\n\n{}",
+                target.content.trim_end(),
+                note.title,
+                note.content.trim()
+            );
+            for tag in note.tags {
+                if !target.tags.contains(&tag) {
+                    target.tags.push(tag);
+                }
+            }
+            if let Some(related) = note.related_notes {
+                let target_related = target.related_notes.get_or_insert_with(Vec::new);
+                for link in related {
+                    if !target_related.contains(&link) {
+                        target_related.push(link);
+                    }
+                }
+            }
+            target.icon = target.icon.take().or(note.icon);
+            target.references.extend(note.references);
+            target.expenses.extend(note.expenses);
+        }
+        notes
+    }
+
+    /// `[[links]]` of `notes` that match neither an existing note (by
+    /// filename, title or alias) nor another note of the batch.
+    fn broken_links(notes: &[NoteData], existing_notes: &[NoteMeta]) -> Vec<String> {
+        let mut known: HashSet<String> = notes.iter().map(|n| n.title.to_lowercase()).collect();
+        for note in existing_notes {
+            let stem = note.filename.strip_suffix(".md").unwrap_or(&note.filename);
+            known.insert(stem.to_lowercase());
+            known.insert(note.title.to_lowercase());
+            known.extend(note.aliases.iter().map(|a| a.to_lowercase()));
+        }
+        let mut broken: Vec<String> = Vec::new();
+        for note in notes {
+            for link in link_graph::wiki_links(&note.content) {
+                let name = link.rsplit('/').next().unwrap_or(&link).to_lowercase();
+                if !known.contains(&name) && !broken.contains(&link) {
+                    broken.push(link);
+                }
+            }
+        }
+        broken
+    }
+
+    fn record_attempt(
+        &self,
+        model: &str,
+        attempt: u32,
+        temperature: f32,
+        top_p: f32,
+        error: Option<String>,
+    ) {
+        let record = GenerationAttempt {
+            timestamp: Utc::now(),
+            chat_id: self.source_message.as_ref().map(|source| source.chat_id),
+            message_id: self.source_message.as_ref().map(|source| source.message_id),
+            model: model.to_string(),
+            attempt,
+            temperature,
+            top_p,
+            error,
         };
+        if let Err(e) = GenerationLog::new(&self.data_dir).append(&record) {
+            log::warn!("Agent: failed to record generation attempt: {}", e);
+        }
+    }
+
+    /// Format asked of the model for the notes JSON.
+    fn notes_format(&self) -> ResponseFormat {
+        if self.structured_output {
+            ResponseFormat::Schema(ai_provider::schema_for::<NotesResponse>())
+        } else {
+            ResponseFormat::Json
+        }
+    }
 
-        // Step 3: Generate notes with LLM (context-aware)
-        log::info!("Agent: Step 3 - Generating notes with LLM");
-        let system_prompt = Self::build_system_prompt(&existing_notes);
-        let user_prompt = Self::build_user_prompt(&cleaned_transcript);
+    /// Parse the model's notes, repairing the usual JSON mistakes (fences,
+    /// commentary, trailing commas). If that's not enough the model is asked
+    /// once to fix its own answer before the memo fails.
+    async fn parse_notes(
+        &self,
+        client: &dyn AiProvider,
+        answer: &str,
+    ) -> anyhow::Result<NotesResponse> {
+        let parse = |answer: &str| {
+            json_repair::parse(answer).and_then(|value| {
+                serde_json::from_value::<NotesResponse>(value)
+                    .context("The JSON doesn't match the notes format")
+            })
+        };
+        let error = match parse(answer) {
+            Ok(notes) => return Ok(notes),
+            Err(e) => e,
+        };
 
-        let llm_response = self
-            .ollama
+        log::warn!(
+            "Agent: invalid notes JSON from {} ({:#}), asking for a fix",
+            client.model(),
+            error
+        );
+        let fixed = client
             .chat(ChatRequest {
-                system_prompt,
-                user_prompt,
-                temperature: self.generation_temperature,
+                system_prompt: Msg::JsonRepairPrompt.text(self.prompt_language).to_string(),
+                user_prompt: Msg::JsonRepairInput.fill(
+                    self.prompt_language,
+                    &[("error", &format!("{:#}", error)), ("json", &answer)],
+                ),
+                temperature: 0.0,
                 top_p: self.generation_top_p,
-                json_format: true,
+                format: self.notes_format(),
             })
             .await
-            .context("Agent: LLM note generation failed")?;
-
-        let notes_response: NotesResponse = serde_json::from_str(&llm_response)
-            .context("Agent: failed to parse notes JSON from LLM")?;
-
-        let now = Utc::now();
-        let notes: Vec<Note> = notes_response
-            .notes
-            .into_iter()
-            .map(|nd| Note {
-                title: nd.title,
-                content: nd.content,
-                tags: nd.tags.iter().map(|t| Note::sanitize_tag(t)).collect(),
-                date: now,
-                source: "voice-memo".to_string(),
-                related_notes: nd.related_notes.unwrap_or_default(),
-            })
-            .collect();
+            .context("Agent: JSON repair request failed")?;
+        parse(&fixed).context("Agent: failed to parse notes JSON from LLM, even after a repair")
+    }
 
-        log::info!("Agent: Step 3 - Generated {} note(s)", notes.len());
+    /// Date of the notes: when the memo was sent, unless configured to use
+    /// the processing time `now`.
+    fn note_date(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match (self.note_date, self.received_at) {
+            (NoteDateSource::Message, Some(received_at)) => received_at,
+            _ => now,
+        }
+    }
 
-        // Step 3b: Post-process — inject [[links]] for existing note titles and cross-link batch notes
-        let notes = Self::post_process_links(notes, &existing_notes);
+    fn embed_attachments(&self, notes: &mut [Note], attachments: &[PathBuf]) {
+        if attachments.is_empty() {
+            return;
+        }
+        for note in notes {
+            let note_path = self
+                .routing
+                .note_dir(Path::new(&self.notes_dir), note)
+                .join(note.generate_filename());
+            note.content.push_str("\n\n");
+            for attachment in attachments {
+                note.content
+                    .push_str(&attachments::embed_markdown(&note_path, attachment));
+                note.content.push('\n');
+            }
+        }
+    }
 
-        // Step 4: Save notes
-        log::info!("Agent: Step 4 - Saving notes");
-        let saved_paths = self
-            .note_writer
-            .run((notes.clone(), self.notes_dir.clone()))
-            .await
-            .context("Agent: failed to save notes")?;
+    /// Without a model: one note holding the transcript in paragraphs, titled
+    /// with its first sentence and without tags.
+    async fn plain_result(&self, raw_transcript: String, attachments: &[PathBuf]) -> AgentResult {
+        log::info!("Agent: no AI provider, writing the transcript as a plain note");
+        let transcript = if self.keep_timestamps {
+            raw_transcript.clone()
+        } else {
+            transcription::strip_timestamps(&raw_transcript)
+        };
+        let date = self.note_date(Utc::now());
+        let title = pending::first_sentence(&transcription::strip_timestamps(&transcript))
+            .unwrap_or_else(|| format!("Memo vocale {}", date.format("%Y-%m-%d %H.%M")));
+        let mut notes = vec![Note {
+            title,
+            content: plain_notes::paragraphs(&transcript),
+            tags: Vec::new(),
+            date,
+            source: self.source.to_string(),
+            language: self.language.clone(),
+            icon: None,
+            references: Vec::new(),
+            expenses: Vec::new(),
+            telegram_link: self
+                .source_message
+                .as_ref()
+                .filter(|_| self.telegram_link_enabled)
+                .and_then(|source| source.link.clone()),
+            properties: BTreeMap::new(),
+            related_notes: Vec::new(),
+            id: None,
+        }];
+        if self.filename_mode == FilenameMode::Zettel {
+            let existing_notes = self.read_existing_notes().await;
+            Self::assign_zettel_ids(&mut notes, &existing_notes, date);
+        }
+        self.add_properties(&mut notes, &transcript);
+        self.embed_attachments(&mut notes, attachments);
 
-        Ok(AgentResult {
+        AgentResult {
             notes,
-            saved_paths,
-            cleaned_transcript,
+            saved_paths: Vec::new(),
+            cleaned_transcript: transcript,
             raw_transcript,
-        })
+            model: "none".to_string(),
+            fallback_from: None,
+            timings: StageTimings::default(),
+            truncation: None,
+            usage: TokenUsage::default(),
+            tasks: Vec::new(),
+        }
     }
 
-    /// Build the system prompt, injecting existing notes context.
-    fn build_system_prompt(existing_notes: &[NoteMeta]) -> String {
-        let mut prompt = String::new();
-
-        // Existing notes context first — so the LLM sees them prominently
-        if !existing_notes.is_empty() {
-            prompt.push_str("## NOTE ESISTENTI NEL SISTEMA\n\n");
-            prompt.push_str("Queste sono le note già presenti nel vault. DEVI consultare questa lista per i link interni e i related_notes.\n\n");
+    /// Ask the primary model which candidate better captures the transcript.
+    ///
+    /// Returns the index of the winner; any judge failure keeps the primary.
+    async fn judge(&self, candidates: &[AgentResult]) -> usize {
+        log::info!("Agent: judging {} candidates", candidates.len());
+        let response = self
+            .ai
+            .chat(ChatRequest {
+                system_prompt: Msg::JudgeSystemPrompt
+                    .text(self.prompt_language)
+                    .to_string(),
+                user_prompt: self.build_judge_prompt(candidates),
+                temperature: 0.0,
+                top_p: self.generation_top_p,
+                format: ResponseFormat::Json,
+            })
+            .await;
 
-            for note in existing_notes {
-                let stem = note
-                    .filename
-                    .strip_suffix(".md")
-                    .unwrap_or(&note.filename);
-                prompt.push_str(&format!("- **{}** (file: `{}`)", note.title, stem));
-                if !note.date.is_empty() {
-                    prompt.push_str(&format!(" ({})", note.date));
-                }
-                if !note.tags.is_empty() {
-                    prompt.push_str(&format!(" [{}]", note.tags.join(", ")));
-                }
-                prompt.push('\n');
+        match response.map(|r| Self::parse_judge_verdict(&r, candidates.len())) {
+            Ok(Some(winner)) => {
+                log::info!("Agent: judge picked {}", candidates[winner].model);
+                winner
+            }
+            Ok(None) => {
+                log::warn!("Agent: judge verdict unreadable, keeping primary model");
+                0
+            }
+            Err(e) => {
+                log::warn!("Agent: judge failed, keeping primary model: {}", e);
+                0
             }
-
-            prompt.push('\n');
         }
+    }
 
-        prompt.push_str(r#"Sei un assistente esperto nella creazione di note strutturate per un sistema di gestione della conoscenza personale (second brain) in Obsidian.
-
-Il tuo compito è:
-1. Analizzare la trascrizione di un messaggio vocale
-2. Identificare i concetti chiave, idee e informazioni importanti
-3. Creare una o più note in formato Markdown ben strutturate
-
-Regole per la creazione delle note:
-- Se la trascrizione contiene più argomenti distinti, crea note separate per ciascuno
-- Ogni nota deve avere un titolo chiaro e descrittivo
-- Struttura il contenuto con headers (##), elenchi puntati e formattazione appropriata
-- Suggerisci 2-5 tag rilevanti per ogni nota. I tag NON devono contenere spazi (usa il trattino `-` al posto degli spazi, es: "machine-learning" invece di "machine learning")
-- Mantieni il tono e l'intento originale del messaggio
-- Se ci sono task o azioni da fare, evidenziali chiaramente
-
-## LINK INTERNI (OBBLIGATORIO)
-
-Questa è una funzionalità CRITICA. Devi creare collegamenti tra le note usando la sintassi Obsidian `[[Titolo Nota]]`.
+    /// Render the transcript and each candidate (labelled A, B, ...) for the judge.
+    fn build_judge_prompt(&self, candidates: &[AgentResult]) -> String {
+        let language = self.prompt_language;
+        let mut prompt = Msg::JudgeTranscript.fill(
+            language,
+            &[("transcript", &candidates[0].cleaned_transcript)],
+        );
+        for (i, candidate) in candidates.iter().enumerate() {
+            prompt.push_str(&Msg::JudgeCandidate.fill(language, &[("label", &candidate_label(i))]));
+            for note in &candidate.notes {
+                prompt.push_str(&format!(
+                    "### {}\nTags: {}\n\n{}\n\n",
+                    note.title,
+                    note.tags.join(", "),
+                    note.content
+                ));
+            }
+        }
+        prompt
+    }
 
-### Regole per i link inline nel contenuto:
-- Quando nel contenuto fai riferimento a un concetto o argomento che corrisponde a una nota esistente, DEVI racchiuderlo in `[[nome file]]` usando il NOME FILE (senza .md) dalla lista delle note esistenti, NON il titolo
-- Inserisci i link in modo naturale nel testo, non forzarli dove non hanno senso
-- Esempio: se esiste una nota con file `Architettura Microservizi`, scrivi "...come descritto in [[Architettura Microservizi]]..."
+    /// Parse `{"winner": "B"}` into a candidate index.
+    fn parse_judge_verdict(response: &str, candidates: usize) -> Option<usize> {
+        let verdict: serde_json::Value = serde_json::from_str(response).ok()?;
+        let label = verdict["winner"].as_str()?.trim().to_uppercase();
+        (0..candidates).find(|&i| candidate_label(i).to_string() == label)
+    }
 
-### Regole per related_notes:
-- DEVI popolare il campo "related_notes" con i NOMI FILE (senza .md) delle note esistenti che sono tematicamente correlate
-- Controlla i tag in comune e gli argomenti affini per identificare le correlazioni
-- Non lasciare "related_notes" vuoto se ci sono note esistenti pertinenti
+    /// Build the system prompt from `template` (see [`Msg::NotesSystemPrompt`]),
+    /// filling `{{existing_notes}}`, `{{instructions}}` and `{{transcript}}`
+    /// with headers in `language`.
+    ///
+    /// The existing notes go first and the instructions last when the
+    /// template has no placeholder for them, so links keep working with
+    /// any wording.
+    fn build_system_prompt(
+        language: Language,
+        template: &str,
+        existing_notes: &[NoteMeta],
+        instructions: &[&str],
+        transcript: &str,
+    ) -> String {
+        // Existing notes context first — so the LLM sees them prominently
+        let mut notes = String::new();
+        if !existing_notes.is_empty() {
+            notes.push_str(Msg::ExistingNotesHeader.text(language));
 
-### Regole per note multiple dalla stessa trascrizione:
-- Se crei più note dalla stessa trascrizione, DEVI farle riferimento tra loro con [[link]] nel contenuto
-- Ogni nota deve menzionare le altre note generate nello stesso batch dove pertinente"#);
+            for note in existing_notes {
+                notes.push_str(&prompt_notes::prompt_line(note));
+            }
 
-        prompt.push_str(r#"
+            notes.push('\n');
+        }
 
-Formato di output: JSON valido con array "notes" contenente oggetti con campi:
-- "title" (stringa)
-- "content" (markdown — DEVE contenere [[link]] a note esistenti e note sorelle dove pertinente)
-- "tags" (array di stringhe)
-- "related_notes" (array di stringhe — titoli ESATTI di note esistenti correlate, NON lasciare vuoto se ci sono correlazioni)
+        // Per-folder / per-project overrides from `[prompts.overrides]`
+        let mut extra = String::new();
+        if !instructions.is_empty() {
+            extra.push_str(Msg::InstructionsHeader.text(language));
+            for instruction in instructions {
+                extra.push_str(&format!("\n- {}", instruction.trim()));
+            }
+        }
 
-Rispondi SOLO con il JSON, senza testo aggiuntivo prima o dopo."#);
+        let mut template = template.trim_end().to_string();
+        if !template.contains("{{existing_notes}}") {
+            template.insert_str(0, "{{existing_notes}}");
+        }
+        if !template.contains("{{instructions}}") {
+            template.push_str("{{instructions}}");
+        }
+        template::render(
+            &template,
+            &[
+                ("existing_notes", &notes),
+                ("instructions", &extra),
+                ("transcript", transcript),
+            ],
+        )
+    }
 
-        prompt
+    /// Assign unique Zettelkasten IDs (`YYYYMMDDHHMM`) to a batch of notes.
+    ///
+    /// IDs start at `now` and are bumped one minute at a time when they collide
+    /// with an existing note or an earlier note of the same batch.
+    fn assign_zettel_ids(notes: &mut [Note], existing_notes: &[NoteMeta], now: DateTime<Utc>) {
+        let mut taken: std::collections::HashSet<String> =
+            existing_notes.iter().filter_map(|n| n.id.clone()).collect();
+        let mut candidate = now;
+
+        for note in notes.iter_mut() {
+            let mut id = candidate.format("%Y%m%d%H%M").to_string();
+            while taken.contains(&id) {
+                candidate += chrono::Duration::minutes(1);
+                id = candidate.format("%Y%m%d%H%M").to_string();
+            }
+            taken.insert(id.clone());
+            note.id = Some(id);
+        }
     }
 
     /// Post-process notes to ensure internal links are present.
     ///
+    /// 0. Resolves links and related_notes that reference an existing note by
+    ///    its Zettelkasten ID to that note's filename stem.
     /// 1. Scans each note's content for exact title matches of existing notes
     ///    and wraps unlinked mentions in `[[]]`.
     /// 2. Cross-links notes generated in the same batch: adds sibling titles
@@ -319,6 +1988,27 @@ Rispondi SOLO con il JSON, senza testo aggiuntivo prima o dopo."#);
             })
             .collect();
 
+        // Map: zettel id -> filename stem for existing notes that carry an ID
+        let existing_ids: Vec<(&str, String)> = existing_notes
+            .iter()
+            .filter_map(|n| {
+                let id = n.id.as_deref()?;
+                let stem = n.filename.strip_suffix(".md").unwrap_or(&n.filename);
+                Some((id, stem.to_string()))
+            })
+            .collect();
+
+        // Map: alias -> filename stem, from the notes' `aliases:` frontmatter
+        let existing_aliases: Vec<(&str, String)> = existing_notes
+            .iter()
+            .flat_map(|n| {
+                let stem = n.filename.strip_suffix(".md").unwrap_or(&n.filename);
+                n.aliases
+                    .iter()
+                    .map(move |a| (a.as_str(), stem.to_string()))
+            })
+            .collect();
+
         let batch_stems: Vec<String> = notes.iter().map(|n| n.filename_stem()).collect();
         let batch_titles: Vec<String> = notes.iter().map(|n| n.title.clone()).collect();
         let batch_tags: Vec<std::collections::HashSet<String>> = notes
@@ -327,6 +2017,14 @@ Rispondi SOLO con il JSON, senza testo aggiuntivo prima o dopo."#);
             .collect();
 
         for i in 0..notes.len() {
+            // --- Resolve ID-based links first: [[202405141230]] -> [[202405141230 Title]] ---
+            for (id, stem) in &existing_ids {
+                let id_link = format!("[[{}]]", id);
+                if notes[i].content.contains(&id_link) {
+                    notes[i].content = notes[i].content.replace(&id_link, &format!("[[{}]]", stem));
+                }
+            }
+
             // --- Inject [[links]] for existing note titles mentioned in content ---
             for (title, stem) in &existing_links {
                 let wiki_link = format!("[[{}]]", stem);
@@ -346,6 +2044,17 @@ Rispondi SOLO con il JSON, senza testo aggiuntivo prima o dopo."#);
                 }
             }
 
+            // --- Link alias mentions as [[filename|alias]], keeping the wording ---
+            for (alias, stem) in &existing_aliases {
+                let alias_link = format!("[[{}|{}]]", stem, alias);
+                notes[i].content = notes[i]
+                    .content
+                    .replace(&format!("[[{}]]", alias), &alias_link);
+                if !notes[i].content.contains(&format!("[[{}", stem)) {
+                    notes[i].content = Self::link_whole_word(&notes[i].content, alias, &alias_link);
+                }
+            }
+
             // --- Fix LLM-generated links for sibling notes: replace title-based with filename-based ---
             for j in 0..notes.len() {
                 if i == j {
@@ -376,8 +2085,22 @@ Rispondi SOLO con il JSON, senza testo aggiuntivo prima o dopo."#);
             // --- Convert any title-based related_notes to filename stems ---
             let mut fixed_related: Vec<String> = Vec::new();
             for rel in &notes[i].related_notes {
-                // Check if it matches an existing note title → use stem
-                if let Some((_, stem)) = existing_links.iter().find(|(t, _)| *t == rel.as_str()) {
+                // Check if it matches an existing note ID → use stem
+                if let Some((_, stem)) = existing_ids.iter().find(|(id, _)| *id == rel.as_str()) {
+                    if !fixed_related.contains(stem) {
+                        fixed_related.push(stem.clone());
+                    }
+                } else if let Some((_, stem)) =
+                    existing_links.iter().find(|(t, _)| *t == rel.as_str())
+                {
+                    // Matches an existing note title → use stem
+                    if !fixed_related.contains(stem) {
+                        fixed_related.push(stem.clone());
+                    }
+                } else if let Some((_, stem)) =
+                    existing_aliases.iter().find(|(a, _)| *a == rel.as_str())
+                {
+                    // Matches an alias of an existing note → use stem
                     if !fixed_related.contains(stem) {
                         fixed_related.push(stem.clone());
                     }
@@ -399,26 +2122,96 @@ Rispondi SOLO con il JSON, senza testo aggiuntivo prima o dopo."#);
         notes
     }
 
+    /// Add the existing notes closest to each note in the vault's link graph
+    /// (see [`LinkGraph::suggest`]) to its `related_notes`.
+    fn relate_from_graph(notes: &mut [Note], existing_notes: &[NoteMeta]) {
+        let graph = LinkGraph::from_notes(existing_notes);
+        for note in notes {
+            let mut linked = link_graph::wiki_links(&note.content);
+            linked.extend(note.related_notes.iter().cloned());
+            let suggested = graph.suggest(&note.tags, &linked, existing_notes);
+            note.related_notes.extend(suggested);
+        }
+    }
+
+    /// Replace whole-word occurrences of `word` outside existing links with `link`.
+    ///
+    /// Aliases are often short ("K8s", "AI"), so unlike titles they must not
+    /// match inside longer words.
+    fn link_whole_word(content: &str, word: &str, link: &str) -> String {
+        let is_word_char = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric());
+        let mut result = String::with_capacity(content.len());
+        let mut copied = 0;
+
+        for (pos, _) in content.match_indices(word) {
+            let before = &content[..pos];
+            let after = &content[pos + word.len()..];
+            let inside_link = before
+                .rfind("[[")
+                .is_some_and(|open| !before[open..].contains("]]"));
+            let standalone =
+                !is_word_char(before.chars().next_back()) && !is_word_char(after.chars().next());
+
+            if standalone && !inside_link {
+                result.push_str(&content[copied..pos]);
+                result.push_str(link);
+                copied = pos + word.len();
+            }
+        }
+        result.push_str(&content[copied..]);
+        result
+    }
+
     /// Build the user prompt from the transcript.
-    fn build_user_prompt(transcript: &str) -> String {
-        format!(
-            "Trascrizione del messaggio vocale:\n\n---\n{}\n---\n\nCrea note strutturate da questa trascrizione.",
-            transcript
+    fn build_user_prompt(&self, transcript: &str) -> String {
+        Msg::UserPrompt.fill(self.prompt_language, &[("transcript", &transcript)])
+    }
+
+    /// Build the user prompt for one topic of a segmented transcript.
+    fn build_segment_prompt(&self, segment: &Segment, index: usize, total: usize) -> String {
+        Msg::SegmentPrompt.fill(
+            self.prompt_language,
+            &[
+                ("index", &(index + 1)),
+                ("total", &total),
+                ("topic", &segment.topic),
+                ("text", &segment.text),
+            ],
+        )
+    }
+
+    /// Build the user prompt for one note planned by a two-phase outline.
+    fn build_outlined_prompt(&self, segment: &Segment, index: usize, total: usize) -> String {
+        Msg::OutlinedNotePrompt.fill(
+            self.prompt_language,
+            &[
+                ("index", &(index + 1)),
+                ("total", &total),
+                ("title", &segment.topic),
+                ("text", &segment.text),
+            ],
         )
     }
 }
 
-#[derive(Debug, Deserialize)]
+/// The notes JSON asked of the model; its schema constrains the answer.
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
 struct NotesResponse {
     notes: Vec<NoteData>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
 struct NoteData {
     title: String,
     content: String,
     tags: Vec<String>,
     related_notes: Option<Vec<String>>,
+    #[serde(default)]
+    icon: Option<String>,
+    #[serde(default)]
+    references: Vec<Reference>,
+    #[serde(default)]
+    expenses: Vec<Expense>,
 }
 
 #[cfg(test)]
@@ -435,7 +2228,14 @@ mod tests {
                 .unwrap()
                 .with_timezone(&Utc),
             source: "voice-memo".to_string(),
+            language: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+            telegram_link: None,
+            properties: BTreeMap::new(),
             related_notes: vec![],
+            id: None,
         };
         let filename = note.generate_filename();
         // Filename is title with unsafe chars removed, preserving spaces
@@ -451,7 +2251,14 @@ mod tests {
             tags: vec![],
             date: Utc::now(),
             source: "voice-memo".to_string(),
+            language: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+            telegram_link: None,
+            properties: BTreeMap::new(),
             related_notes: vec![],
+            id: None,
         };
         assert_eq!(note.filename_stem(), "My Great Note");
     }
@@ -472,19 +2279,99 @@ mod tests {
             tags: vec!["rust".to_string()],
             date: Utc::now(),
             source: "voice-memo".to_string(),
+            language: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+            telegram_link: None,
+            properties: BTreeMap::new(),
             related_notes: vec!["Other Note".to_string(), "Another".to_string()],
+            id: None,
         };
-        let md = note.to_markdown();
-        assert!(md.contains("[[Other Note]]"), "should have wiki-link for related note");
-        assert!(md.contains("[[Another]]"), "should have wiki-link for related note");
+        let md = note.to_markdown(None, &BTreeMap::new());
+        assert!(
+            md.contains("[[Other Note]]"),
+            "should have wiki-link for related note"
+        );
+        assert!(
+            md.contains("[[Another]]"),
+            "should have wiki-link for related note"
+        );
         assert!(md.contains("related:"));
     }
 
+    #[test]
+    fn test_icon_frontmatter_and_sanitizing() {
+        assert_eq!(Note::sanitize_icon(" 🛒 spesa").as_deref(), Some("🛒"));
+        assert_eq!(Note::sanitize_icon("👩‍💻").as_deref(), Some("👩‍💻"));
+        assert_eq!(Note::sanitize_icon("idea"), None);
+        assert_eq!(Note::sanitize_icon("\"💡\""), None);
+        assert_eq!(Note::sanitize_icon(""), None);
+
+        let note = Note {
+            title: "Spesa".to_string(),
+            content: "Latte".to_string(),
+            tags: Vec::new(),
+            date: Utc::now(),
+            source: "voice-memo".to_string(),
+            language: None,
+            icon: Some("🛒".to_string()),
+            references: vec![],
+            expenses: vec![],
+            telegram_link: None,
+            properties: BTreeMap::new(),
+            related_notes: Vec::new(),
+            id: None,
+        };
+        assert!(note
+            .to_markdown(None, &BTreeMap::new())
+            .contains("icon: 🛒\n"));
+    }
+
     #[test]
     fn test_build_system_prompt_without_existing() {
-        let prompt = NoteGeneratorAgent::build_system_prompt(&[]);
+        let italian = Msg::NotesSystemPrompt.text(Language::It);
+        let prompt = NoteGeneratorAgent::build_system_prompt(Language::It, italian, &[], &[], "");
         assert!(!prompt.contains("NOTE ESISTENTI"));
         assert!(prompt.contains("related_notes"));
+        assert!(!prompt.contains("ISTRUZIONI AGGIUNTIVE"));
+
+        let prompt = NoteGeneratorAgent::build_system_prompt(
+            Language::It,
+            italian,
+            &[],
+            &["Aggiungi una sezione 'Stakeholder'."],
+            "",
+        );
+        assert!(
+            prompt.contains("## ISTRUZIONI AGGIUNTIVE\n\n- Aggiungi una sezione 'Stakeholder'.")
+        );
+        assert!(prompt.ends_with("senza testo aggiuntivo prima o dopo."));
+
+        // Custom templates get the transcript, and the notes and instructions
+        // even without a placeholder for them
+        let existing = vec![NoteMeta {
+            title: "Rust Tips".to_string(),
+            date: String::new(),
+            tags: vec![],
+            filename: "Rust Tips.md".to_string(),
+            source: String::new(),
+            id: None,
+            aliases: vec![],
+            links: vec![],
+            folder: String::new(),
+        }];
+        let prompt = NoteGeneratorAgent::build_system_prompt(
+            Language::It,
+            "Riassumi in una nota: {{transcript}}\n",
+            &existing,
+            &["Sii breve."],
+            "comprare il latte",
+        );
+        assert!(prompt.starts_with("## NOTE ESISTENTI NEL SISTEMA"));
+        assert!(prompt.contains(
+            "Riassumi in una nota: comprare il latte\n\n## ISTRUZIONI AGGIUNTIVE\n\n- Sii breve."
+        ));
     }
 
     #[test]
@@ -495,8 +2382,18 @@ mod tests {
             tags: vec!["rust".to_string(), "programming".to_string()],
             filename: "20240115_rust-tips.md".to_string(),
             source: "voice-memo".to_string(),
+            id: None,
+            aliases: vec![],
+            links: vec![],
+            folder: String::new(),
         }];
-        let prompt = NoteGeneratorAgent::build_system_prompt(&existing);
+        let prompt = NoteGeneratorAgent::build_system_prompt(
+            Language::It,
+            Msg::NotesSystemPrompt.text(Language::It),
+            &existing,
+            &[],
+            "",
+        );
         assert!(prompt.contains("NOTE ESISTENTI NEL SISTEMA"));
         assert!(prompt.contains("Rust Tips"));
         assert!(prompt.contains("rust, programming"));
@@ -506,7 +2403,49 @@ mod tests {
         // Existing notes should appear before the main instructions
         let notes_pos = prompt.find("NOTE ESISTENTI").unwrap();
         let rules_pos = prompt.find("Regole per la creazione").unwrap();
-        assert!(notes_pos < rules_pos, "Existing notes should appear before rules");
+        assert!(
+            notes_pos < rules_pos,
+            "Existing notes should appear before rules"
+        );
+    }
+
+    #[test]
+    fn test_english_prompts() {
+        let existing = vec![NoteMeta {
+            title: "Rust Tips".to_string(),
+            date: String::new(),
+            tags: vec![],
+            filename: "Rust Tips.md".to_string(),
+            source: String::new(),
+            id: None,
+            aliases: vec![],
+            links: vec![],
+            folder: String::new(),
+        }];
+        let prompt = NoteGeneratorAgent::build_system_prompt(
+            Language::En,
+            Msg::NotesSystemPrompt.text(Language::En),
+            &existing,
+            &["Be brief."],
+            "",
+        );
+        assert!(prompt.starts_with("## EXISTING NOTES IN THE SYSTEM"));
+        assert!(prompt.contains("INTERNAL LINKS (REQUIRED)"));
+        assert!(prompt.contains("## ADDITIONAL INSTRUCTIONS\n\n- Be brief."));
+
+        // Memos in the language of the prompts need no instruction
+        assert_eq!(language_instruction("en", Language::En), None);
+        assert_eq!(
+            language_instruction("it", Language::En).unwrap(),
+            "The transcript is in Italian (it): write the title, content and tags of the notes in this language, not in English"
+        );
+        assert!(language_instruction("it", Language::It).is_none());
+        assert!(language_instruction("en", Language::It)
+            .unwrap()
+            .starts_with("La trascrizione è in inglese (en)"));
+        assert!(language_instruction("sw", Language::It)
+            .unwrap()
+            .contains("in sw (sw)"));
     }
 
     #[test]
@@ -517,6 +2456,10 @@ mod tests {
             tags: vec!["architettura".to_string()],
             filename: "Architettura Microservizi.md".to_string(),
             source: "voice-memo".to_string(),
+            id: None,
+            aliases: vec![],
+            links: vec![],
+            folder: String::new(),
         }];
         let notes = vec![Note {
             title: "API Gateway".to_string(),
@@ -524,7 +2467,14 @@ mod tests {
             tags: vec!["api".to_string()],
             date: Utc::now(),
             source: "voice-memo".to_string(),
+            language: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+            telegram_link: None,
+            properties: BTreeMap::new(),
             related_notes: vec![],
+            id: None,
         }];
 
         let result = NoteGeneratorAgent::post_process_links(notes, &existing);
@@ -533,6 +2483,68 @@ mod tests {
         assert!(!result[0].content.contains("[[[["));
     }
 
+    #[test]
+    fn test_broken_links_for_review() {
+        let existing = vec![NoteMeta {
+            title: "Rust Tips".to_string(),
+            date: "2024-01-10".to_string(),
+            tags: vec![],
+            filename: "20240110_rust-tips.md".to_string(),
+            source: "voice-memo".to_string(),
+            id: None,
+            aliases: vec!["Consigli Rust".to_string()],
+            links: vec![],
+            folder: String::new(),
+        }];
+        let note = |title: &str, content: &str| NoteData {
+            title: title.to_string(),
+            content: content.to_string(),
+            tags: vec![],
+            related_notes: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+        };
+        let notes = vec![
+            note(
+                "Ownership",
+                "Vedi [[Rust Tips]], [[consigli rust]], [[progetti/20240110_rust-tips]] e [[Borrowing]].",
+            ),
+            note("Borrowing", "Collegata a [[Lifetimes|durata]] e [[Ownership]]."),
+        ];
+        assert_eq!(
+            NoteGeneratorAgent::broken_links(&notes, &existing),
+            ["Lifetimes"]
+        );
+    }
+
+    #[test]
+    fn test_cap_notes_merges_overflow_into_longest() {
+        let note = |title: &str, content: &str, tags: &[&str]| NoteData {
+            title: title.to_string(),
+            content: content.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            related_notes: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+        };
+        let notes = vec![
+            note("Breve", "Poco.", &["idea"]),
+            note("Lunga", "Un contenuto decisamente più lungo.", &["lavoro"]),
+            note("Extra", "Dettaglio in più.", &["lavoro", "dettagli"]),
+        ];
+        let capped = NoteGeneratorAgent::cap_notes(notes, 2);
+        assert_eq!(capped.len(), 2);
+        assert_eq!(capped[0].content, "Poco.");
+        assert_eq!(
+            capped[1].content,
+            "Un contenuto decisamente più lungo.\n\n## Extra\n\nDettaglio in più."
+        );
+        assert_eq!(capped[1].tags, ["lavoro", "dettagli"]);
+        assert_eq!(NoteGeneratorAgent::cap_notes(capped, 5).len(), 2);
+    }
+
     #[test]
     fn test_post_process_links_uses_filename_not_title() {
         // Existing note with old-style filename (different from title)
@@ -542,6 +2554,10 @@ mod tests {
             tags: vec!["rust".to_string()],
             filename: "20240110_rust-tips.md".to_string(),
             source: "voice-memo".to_string(),
+            id: None,
+            aliases: vec![],
+            links: vec![],
+            folder: String::new(),
         }];
         let notes = vec![Note {
             title: "Appunti".to_string(),
@@ -549,7 +2565,14 @@ mod tests {
             tags: vec!["rust".to_string()],
             date: Utc::now(),
             source: "voice-memo".to_string(),
+            language: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+            telegram_link: None,
+            properties: BTreeMap::new(),
             related_notes: vec![],
+            id: None,
         }];
 
         let result = NoteGeneratorAgent::post_process_links(notes, &existing);
@@ -566,6 +2589,10 @@ mod tests {
             tags: vec!["rust".to_string()],
             filename: "20240110_rust-tips.md".to_string(),
             source: "voice-memo".to_string(),
+            id: None,
+            aliases: vec![],
+            links: vec![],
+            folder: String::new(),
         }];
         let notes = vec![Note {
             title: "Appunti".to_string(),
@@ -574,7 +2601,14 @@ mod tests {
             tags: vec!["rust".to_string()],
             date: Utc::now(),
             source: "voice-memo".to_string(),
+            language: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+            telegram_link: None,
+            properties: BTreeMap::new(),
             related_notes: vec![],
+            id: None,
         }];
 
         let result = NoteGeneratorAgent::post_process_links(notes, &existing);
@@ -592,7 +2626,14 @@ mod tests {
                 tags: vec!["rust".to_string(), "coding".to_string()],
                 date: Utc::now(),
                 source: "voice-memo".to_string(),
+                language: None,
+                icon: None,
+                references: vec![],
+                expenses: vec![],
+                telegram_link: None,
+                properties: BTreeMap::new(),
                 related_notes: vec![],
+                id: None,
             },
             Note {
                 title: "Nota B".to_string(),
@@ -600,7 +2641,14 @@ mod tests {
                 tags: vec!["rust".to_string()],
                 date: Utc::now(),
                 source: "voice-memo".to_string(),
+                language: None,
+                icon: None,
+                references: vec![],
+                expenses: vec![],
+                telegram_link: None,
+                properties: BTreeMap::new(),
                 related_notes: vec![],
+                id: None,
             },
             Note {
                 title: "Nota C".to_string(),
@@ -608,7 +2656,14 @@ mod tests {
                 tags: vec!["unrelated".to_string()],
                 date: Utc::now(),
                 source: "voice-memo".to_string(),
+                language: None,
+                icon: None,
+                references: vec![],
+                expenses: vec![],
+                telegram_link: None,
+                properties: BTreeMap::new(),
                 related_notes: vec![],
+                id: None,
             },
         ];
 
@@ -620,4 +2675,188 @@ mod tests {
         assert!(!result[0].related_notes.contains(&"Nota C".to_string()));
         assert!(!result[2].related_notes.contains(&"Nota A".to_string()));
     }
+    #[test]
+    fn test_parse_judge_verdict() {
+        assert_eq!(
+            NoteGeneratorAgent::parse_judge_verdict(r#"{"winner": "B", "reason": "x"}"#, 2),
+            Some(1)
+        );
+        assert_eq!(
+            NoteGeneratorAgent::parse_judge_verdict(r#"{"winner": "a"}"#, 2),
+            Some(0)
+        );
+        assert_eq!(
+            NoteGeneratorAgent::parse_judge_verdict(r#"{"winner": "C"}"#, 2),
+            None
+        );
+        assert_eq!(NoteGeneratorAgent::parse_judge_verdict("boh", 2), None);
+    }
+
+    #[test]
+    fn test_generate_filename_with_zettel_id() {
+        let note = Note {
+            title: "Idea".to_string(),
+            content: "content".to_string(),
+            tags: vec![],
+            date: Utc::now(),
+            source: "voice-memo".to_string(),
+            language: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+            telegram_link: None,
+            properties: BTreeMap::new(),
+            related_notes: vec![],
+            id: Some("202405141230".to_string()),
+        };
+        assert_eq!(note.generate_filename(), "202405141230 Idea.md");
+        // Quoted, so YAML keeps the ID a string
+        assert!(note
+            .to_markdown(None, &BTreeMap::new())
+            .contains("id: '202405141230'\n"));
+    }
+
+    #[test]
+    fn test_assign_zettel_ids_are_unique() {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-05-14T12:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let existing = vec![NoteMeta {
+            title: "Old".to_string(),
+            date: "2024-05-14".to_string(),
+            tags: vec![],
+            filename: "202405141230 Old.md".to_string(),
+            source: "voice-memo".to_string(),
+            id: Some("202405141230".to_string()),
+            aliases: vec![],
+            links: vec![],
+            folder: String::new(),
+        }];
+        let mut notes: Vec<Note> = ["A", "B"]
+            .iter()
+            .map(|t| Note {
+                title: t.to_string(),
+                content: String::new(),
+                tags: vec![],
+                date: now,
+                source: "voice-memo".to_string(),
+                language: None,
+                icon: None,
+                references: vec![],
+                expenses: vec![],
+                telegram_link: None,
+                properties: BTreeMap::new(),
+                related_notes: vec![],
+                id: None,
+            })
+            .collect();
+
+        NoteGeneratorAgent::assign_zettel_ids(&mut notes, &existing, now);
+        assert_eq!(notes[0].id.as_deref(), Some("202405141231"));
+        assert_eq!(notes[1].id.as_deref(), Some("202405141232"));
+    }
+
+    #[test]
+    fn test_post_process_resolves_zettel_id_links() {
+        let existing = vec![NoteMeta {
+            title: "Kubernetes".to_string(),
+            date: "2024-05-01".to_string(),
+            tags: vec![],
+            filename: "202405010900 Kubernetes.md".to_string(),
+            source: "voice-memo".to_string(),
+            id: Some("202405010900".to_string()),
+            aliases: vec![],
+            links: vec![],
+            folder: String::new(),
+        }];
+        let notes = vec![Note {
+            title: "Deploy".to_string(),
+            content: "Vedi [[202405010900]].".to_string(),
+            tags: vec![],
+            date: Utc::now(),
+            source: "voice-memo".to_string(),
+            language: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+            telegram_link: None,
+            properties: BTreeMap::new(),
+            related_notes: vec!["202405010900".to_string()],
+            id: None,
+        }];
+
+        let result = NoteGeneratorAgent::post_process_links(notes, &existing);
+        assert!(result[0].content.contains("[[202405010900 Kubernetes]]"));
+        assert_eq!(result[0].related_notes, vec!["202405010900 Kubernetes"]);
+    }
+
+    #[test]
+    fn test_post_process_links_alias_mentions() {
+        let existing = vec![NoteMeta {
+            title: "Kubernetes".to_string(),
+            date: "2024-05-01".to_string(),
+            tags: vec![],
+            filename: "Kubernetes.md".to_string(),
+            source: "voice-memo".to_string(),
+            id: None,
+            aliases: vec!["K8s".to_string()],
+            links: vec![],
+            folder: String::new(),
+        }];
+        let notes = vec![Note {
+            title: "Deploy".to_string(),
+            content: "Migrare su K8s, non su K8sLite.".to_string(),
+            tags: vec![],
+            date: Utc::now(),
+            source: "voice-memo".to_string(),
+            language: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+            telegram_link: None,
+            properties: BTreeMap::new(),
+            related_notes: vec!["K8s".to_string()],
+            id: None,
+        }];
+
+        let result = NoteGeneratorAgent::post_process_links(notes, &existing);
+        assert_eq!(
+            result[0].content,
+            "Migrare su [[Kubernetes|K8s]], non su K8sLite."
+        );
+        assert_eq!(result[0].related_notes, vec!["Kubernetes"]);
+    }
+
+    #[test]
+    fn test_notes_schema_is_inlined() {
+        let schema = ai_provider::schema_for::<NotesResponse>();
+        let note = &schema["properties"]["notes"]["items"];
+        assert_eq!(note["type"], "object");
+        assert_eq!(
+            note["required"],
+            serde_json::json!(["content", "tags", "title"])
+        );
+        assert_eq!(
+            note["properties"]["expenses"]["items"]["properties"]["amount"]["type"],
+            "number"
+        );
+        assert!(!schema.to_string().contains("$ref"));
+    }
+
+    #[test]
+    fn test_truncation_cuts_between_words() {
+        let text = "prima parte del memo e poi la fine";
+        assert_eq!(
+            Truncation::cut(text, 17, OverflowStrategy::TruncateTail),
+            "prima parte del…"
+        );
+        assert_eq!(
+            Truncation::cut(text, 12, OverflowStrategy::TruncateHead),
+            "…poi la fine"
+        );
+        assert_eq!(
+            Truncation::cut(text, 100, OverflowStrategy::TruncateHead),
+            text
+        );
+    }
 }
@@ -0,0 +1,219 @@
+//! Note types (meeting, idea, journal…) from the templates in
+//! `output.templates_dir`: a memo classified as one of them gets notes laid
+//! out in its `##` sections, with its frontmatter fields.
+//!
+//! A template is a markdown file named after its type (`meeting.md`):
+//!
+//! ```markdown
+//! ---
+//! description: Riunione o chiamata con altre persone
+//! type: meeting
+//! ---
+//! {{content}}
+//!
+//! ## Partecipanti
+//!
+//! ## Decisioni
+//! ```
+//!
+//! `description` tells the classifier when the type applies; the other
+//! fields are added to the frontmatter of the notes. `{{content}}` marks
+//! where text outside the sections goes (before the first one by default).
+
+use anyhow::{Context, Result};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::note_generator::Note;
+use crate::template;
+
+/// A note type and its template.
+#[derive(Debug, Clone)]
+pub struct NoteType {
+    /// The template's file stem, e.g. `meeting`.
+    pub name: String,
+    pub description: Option<String>,
+    /// Frontmatter fields of the notes, e.g. `type: meeting`.
+    pub properties: BTreeMap<String, Value>,
+    body: String,
+}
+
+impl NoteType {
+    /// Parse the template `content` of the type `name`.
+    pub fn parse(name: &str, content: &str) -> Result<Self> {
+        let (mut fields, body) = match content
+            .strip_prefix("---\n")
+            .and_then(|rest| rest.split_once("\n---"))
+        {
+            Some((yaml, body)) => {
+                let fields: Mapping = if yaml.trim().is_empty() {
+                    Mapping::new()
+                } else {
+                    serde_yaml::from_str(yaml).context("Invalid template frontmatter")?
+                };
+                (
+                    fields,
+                    body.trim_start_matches('-').trim_start_matches('\n'),
+                )
+            }
+            None => (Mapping::new(), content),
+        };
+        let description = fields
+            .remove("description")
+            .and_then(|value| value.as_str().map(str::to_string));
+        let properties = fields
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.as_str()?.to_string(), value)))
+            .collect();
+        Ok(Self {
+            name: name.to_string(),
+            description,
+            properties,
+            body: body.trim_end().to_string(),
+        })
+    }
+
+    /// Titles of the template's `##` sections, in order.
+    pub fn sections(&self) -> Vec<&str> {
+        self.body.lines().filter_map(heading).collect()
+    }
+
+    /// Lay out `content` in the template: each `##` section of the content
+    /// goes under the template's heading of the same name (empty headings
+    /// are kept), sections the template lacks are appended at the end.
+    pub fn render(&self, content: &str) -> String {
+        let (intro, mut sections) = split_sections(content);
+        let mut body = String::new();
+        for line in self.body.lines() {
+            body.push_str(line);
+            body.push('\n');
+            let Some(title) = heading(line) else { continue };
+            if let Some(i) = sections
+                .iter()
+                .position(|(section, _)| section.eq_ignore_ascii_case(title))
+            {
+                let (_, text) = sections.remove(i);
+                if !text.is_empty() {
+                    body.push_str(&format!("\n{}\n", text));
+                }
+            }
+        }
+        for (title, text) in sections {
+            body.push_str(&format!("\n## {}\n\n{}\n", title, text));
+        }
+
+        let rendered = if body.contains("{{content}}") {
+            template::render(&body, &[("content", &intro)])
+        } else if intro.is_empty() {
+            body
+        } else {
+            format!("{}\n\n{}", intro, body)
+        };
+        rendered.trim().to_string()
+    }
+
+    /// Give `note` the type's layout and frontmatter fields.
+    pub fn apply(&self, note: &mut Note) {
+        note.content = self.render(&note.content);
+        for (key, value) in &self.properties {
+            note.properties.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+/// The note types of the templates in `dir`; unreadable or invalid ones are
+/// skipped with a warning.
+pub fn load(dir: &Path) -> Vec<NoteType> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!(
+                "Failed to read templates directory {}: {}",
+                dir.display(),
+                e
+            );
+            return Vec::new();
+        }
+    };
+    let mut types: Vec<NoteType> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            let parsed = std::fs::read_to_string(&path)
+                .context("Failed to read template")
+                .and_then(|content| NoteType::parse(&name, &content));
+            match parsed {
+                Ok(note_type) => Some(note_type),
+                Err(e) => {
+                    log::warn!("Skipping note template {}: {:#}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect();
+    types.sort_by(|a, b| a.name.cmp(&b.name));
+    types
+}
+
+/// The title of a `## ` heading line.
+fn heading(line: &str) -> Option<&str> {
+    line.strip_prefix("## ").map(str::trim)
+}
+
+/// The text before the first `##` heading of `content`, and each section's
+/// title and text.
+fn split_sections(content: &str) -> (String, Vec<(String, String)>) {
+    let mut intro = String::new();
+    let mut sections: Vec<(String, String)> = Vec::new();
+    for line in content.lines() {
+        if let Some(title) = heading(line) {
+            sections.push((title.to_string(), String::new()));
+            continue;
+        }
+        let text = match sections.last_mut() {
+            Some((_, text)) => text,
+            None => &mut intro,
+        };
+        text.push_str(line);
+        text.push('\n');
+    }
+    let trim = |text: String| text.trim().to_string();
+    (
+        trim(intro),
+        sections
+            .into_iter()
+            .map(|(title, text)| (title, trim(text)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_fills_the_template_sections() {
+        let meeting = NoteType::parse(
+            "meeting",
+            "---\ndescription: Riunione\ntype: meeting\n---\n\
+            {{content}}\n\n## Partecipanti\n\n## Decisioni\n\n## Azioni\n",
+        )
+        .unwrap();
+        assert_eq!(meeting.description.as_deref(), Some("Riunione"));
+        assert_eq!(
+            meeting.properties.get("type"),
+            Some(&Value::String("meeting".to_string()))
+        );
+        assert_eq!(meeting.sections(), ["Partecipanti", "Decisioni", "Azioni"]);
+
+        let content = "Riunione sul budget.\n\n## Azioni\n\n- [ ] Inviare il verbale\n\n\
+            ## partecipanti\n\nMarco, Anna\n\n## Note\n\nSala prenotata fino alle 12";
+        assert_eq!(
+            meeting.render(content),
+            "Riunione sul budget.\n\n## Partecipanti\n\nMarco, Anna\n\n## Decisioni\n\n\
+            ## Azioni\n\n- [ ] Inviare il verbale\n\n## Note\n\nSala prenotata fino alle 12"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! Persistent index of the vault's notes (`output.notes_index`), so a memo
+//! doesn't read and parse every note of a large vault.
+//!
+//! The metadata of each note is kept in `<data_dir>/notes-index.json` with
+//! the file's modification time and size: only new and changed files are
+//! read again. With `output.watch_notes` the notes folder is also watched,
+//! and while nothing changes in it the last list is reused without even
+//! listing the folder.
+
+use anyhow::{Context, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::config::Config;
+use crate::tools::{NoteMeta, NotesReader};
+
+/// A note file and what was read from it (`None`: not a valid note).
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    modified: SystemTime,
+    size: u64,
+    meta: Option<NoteMeta>,
+}
+
+/// The files of one notes folder, by path relative to it.
+#[derive(Default, Serialize, Deserialize)]
+struct Folder {
+    /// [`NotesReader::settings`] the entries were read with.
+    settings: String,
+    files: BTreeMap<String, Entry>,
+}
+
+/// `<data_dir>/notes-index.json`: one entry per notes folder (vault).
+#[derive(Default, Serialize, Deserialize)]
+struct Store {
+    folders: HashMap<String, Folder>,
+}
+
+/// A watched notes folder and its notes as of the last scan.
+struct Watched {
+    _watcher: RecommendedWatcher,
+    /// Set by the watcher on any change below the folder.
+    changed: Arc<AtomicBool>,
+    notes: Option<(String, Vec<NoteMeta>)>,
+}
+
+/// Notes folders watched by this process, by path.
+static WATCHED: Mutex<BTreeMap<String, Watched>> = Mutex::new(BTreeMap::new());
+
+/// Index of the notes of the vaults.
+#[derive(Debug, Clone)]
+pub struct NotesIndex {
+    path: PathBuf,
+    watch: bool,
+}
+
+impl NotesIndex {
+    /// The index of `config`, if `output.notes_index` is enabled.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        config.output.notes_index.then(|| Self {
+            path: Path::new(&config.output.data_dir).join("notes-index.json"),
+            watch: config.output.watch_notes,
+        })
+    }
+
+    /// The notes of `notes_dir` as `reader` reads them, reading again only
+    /// the files changed since the last call.
+    pub fn notes(&self, notes_dir: &str, reader: &NotesReader) -> Result<Vec<NoteMeta>> {
+        let settings = reader.settings();
+        if self.watch {
+            if let Some(notes) = unchanged(notes_dir, &settings) {
+                log::info!("NotesIndex: {} unchanged, {} notes", notes_dir, notes.len());
+                return Ok(notes);
+            }
+            // Before the scan, so changes made during it are seen next time
+            watch(notes_dir);
+        }
+
+        let (notes, read) = self.scan(notes_dir, reader, &settings)?;
+        log::info!(
+            "NotesIndex: {} notes in {}, {} read again",
+            notes.len(),
+            notes_dir,
+            read
+        );
+        if self.watch {
+            if let Some(watched) = WATCHED.lock().unwrap().get_mut(notes_dir) {
+                watched.notes = Some((settings, notes.clone()));
+            }
+        }
+        Ok(notes)
+    }
+
+    /// The notes of `notes_dir`, from the stored index where the files are
+    /// unchanged, and how many files were read again.
+    fn scan(
+        &self,
+        notes_dir: &str,
+        reader: &NotesReader,
+        settings: &str,
+    ) -> Result<(Vec<NoteMeta>, usize)> {
+        let dir = Path::new(notes_dir);
+        let mut store = self.load();
+        let previous = store
+            .folders
+            .remove(notes_dir)
+            .filter(|folder| folder.settings == settings);
+        let mut dirty = previous.is_none();
+        let mut known = previous.unwrap_or_default().files;
+
+        let mut files = BTreeMap::new();
+        let mut read = 0;
+        for path in reader.scan(dir)? {
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                continue;
+            };
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            let size = metadata.len();
+            let key = path
+                .strip_prefix(dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let entry = match known.remove(&key) {
+                Some(entry) if entry.modified == modified && entry.size == size => entry,
+                _ => {
+                    read += 1;
+                    Entry {
+                        modified,
+                        size,
+                        meta: reader.read_note(dir, &path),
+                    }
+                }
+            };
+            files.insert(key, entry);
+        }
+        // Files left in `known` were deleted
+        dirty |= read > 0 || !known.is_empty();
+
+        let notes = files
+            .values()
+            .filter_map(|entry| entry.meta.clone())
+            .collect();
+        if dirty {
+            store.folders.insert(
+                notes_dir.to_string(),
+                Folder {
+                    settings: settings.to_string(),
+                    files,
+                },
+            );
+            if let Err(e) = self.save(&store) {
+                log::warn!("NotesIndex: failed to store the notes index: {}", e);
+            }
+        }
+        Ok((notes, read))
+    }
+
+    /// The stored index; an unreadable one is rebuilt.
+    fn load(&self) -> Store {
+        let Ok(content) = std::fs::read_to_string(&self.path) else {
+            return Store::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!(
+                "NotesIndex: invalid {}, rebuilding it: {}",
+                self.path.display(),
+                e
+            );
+            Store::default()
+        })
+    }
+
+    fn save(&self, store: &Store) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create data directory")?;
+        }
+        let content = serde_json::to_string(store).context("Failed to serialize notes index")?;
+        std::fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// The notes of the last scan of `notes_dir`, if it is watched and nothing
+/// changed in it since.
+fn unchanged(notes_dir: &str, settings: &str) -> Option<Vec<NoteMeta>> {
+    let watched = WATCHED.lock().unwrap();
+    let watched = watched.get(notes_dir)?;
+    if watched.changed.load(Ordering::SeqCst) {
+        return None;
+    }
+    let (scanned_with, notes) = watched.notes.as_ref()?;
+    (scanned_with == settings).then(|| notes.clone())
+}
+
+/// Start watching `notes_dir` if it isn't yet, and clear its changed flag.
+/// Without a watcher every call scans the folder.
+fn watch(notes_dir: &str) {
+    let mut watched = WATCHED.lock().unwrap();
+    if let Some(watched) = watched.get(notes_dir) {
+        watched.changed.store(false, Ordering::SeqCst);
+        return;
+    }
+
+    let changed = Arc::new(AtomicBool::new(false));
+    let flag = changed.clone();
+    let watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+            Ok(_) => flag.store(true, Ordering::SeqCst),
+            Err(e) => {
+                log::warn!("NotesIndex: watch error: {}", e);
+                flag.store(true, Ordering::SeqCst);
+            }
+        })
+        .and_then(|mut watcher| {
+            watcher.watch(Path::new(notes_dir), RecursiveMode::Recursive)?;
+            Ok(watcher)
+        });
+    match watcher {
+        Ok(watcher) => {
+            log::info!("NotesIndex: watching {}", notes_dir);
+            watched.insert(
+                notes_dir.to_string(),
+                Watched {
+                    _watcher: watcher,
+                    changed,
+                    notes: None,
+                },
+            );
+        }
+        Err(e) => log::warn!("NotesIndex: failed to watch {}: {}", notes_dir, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_reads_only_changed_files() {
+        let root = std::env::temp_dir().join(format!("dot-index-{}", uuid::Uuid::new_v4()));
+        let notes_dir = root.join("notes");
+        std::fs::create_dir_all(notes_dir.join("Cucina")).unwrap();
+        let write = |name: &str, title: &str| {
+            std::fs::write(
+                notes_dir.join(name),
+                format!("---\ntitle: {}\n---\n", title),
+            )
+            .unwrap()
+        };
+        write("Orto.md", "Orto");
+        write("Cucina/Pane.md", "Pane");
+
+        let index = NotesIndex {
+            path: root.join("data/notes-index.json"),
+            watch: false,
+        };
+        let reader = NotesReader::new();
+        let dir = notes_dir.to_string_lossy().to_string();
+        let settings = reader.settings();
+        let titles = |notes: Vec<NoteMeta>| -> Vec<String> {
+            notes.into_iter().map(|note| note.title).collect()
+        };
+
+        let (notes, read) = index.scan(&dir, &reader, &settings).unwrap();
+        assert_eq!(
+            (titles(notes), read),
+            (vec!["Pane".into(), "Orto".into()], 2)
+        );
+        assert!(index.path.exists());
+
+        let (_, read) = index.scan(&dir, &reader, &settings).unwrap();
+        assert_eq!(read, 0);
+
+        write("Orto.md", "Orto e frutteto");
+        std::fs::remove_file(notes_dir.join("Cucina/Pane.md")).unwrap();
+        let (notes, read) = index.scan(&dir, &reader, &settings).unwrap();
+        assert_eq!(
+            (titles(notes), read),
+            (vec!["Orto e frutteto".to_string()], 1)
+        );
+
+        // Other reader settings read everything again
+        let (_, read) = index.scan(&dir, &reader, "other").unwrap();
+        assert_eq!(read, 1);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
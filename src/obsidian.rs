@@ -0,0 +1,55 @@
+use std::path::Path;
+
+/// `obsidian://open` URI opening `note_path` in the vault named `vault`.
+///
+/// `vault_dir` is the vault root on disk; the `file` parameter is the note's
+/// path relative to it, without the `.md` extension. Returns `None` when the
+/// note lies outside the vault.
+pub fn open_uri(vault: &str, vault_dir: &Path, note_path: &Path) -> Option<String> {
+    let relative = note_path.strip_prefix(vault_dir).ok()?;
+    let file = relative
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    Some(format!(
+        "obsidian://open?vault={}&file={}",
+        percent_encode(vault),
+        percent_encode(&file)
+    ))
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters.
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_uri_encodes_relative_path() {
+        let uri = open_uri(
+            "Second Brain",
+            Path::new("./vault"),
+            Path::new("./vault/Work/Caffè & idee.md"),
+        );
+        assert_eq!(
+            uri.as_deref(),
+            Some("obsidian://open?vault=Second%20Brain&file=Work%2FCaff%C3%A8%20%26%20idee")
+        );
+        assert!(open_uri("V", Path::new("./vault"), Path::new("./other/a.md")).is_none());
+    }
+}
@@ -1,64 +1,298 @@
 use anyhow::{Context, Result};
 
-/// Shared HTTP client for Ollama API calls.
-pub struct OllamaClient {
-    pub endpoint: String,
-    pub model: String,
-    client: reqwest::Client,
-}
+use crate::ai_provider::{AiProvider, ChatRequest, ResponseFormat, TokenUpdates};
+use crate::config::{AiModelConfig, RetryConfig};
+use crate::retry;
+use crate::usage::{TokenUsage, UsageMeter};
 
-/// Parameters for a chat request to Ollama.
-pub struct ChatRequest {
-    pub system_prompt: String,
-    pub user_prompt: String,
-    pub temperature: f32,
-    pub top_p: f32,
-    pub json_format: bool,
+/// Language model served by Ollama (`ollama_local` and `ollama_remote`).
+pub struct OllamaProvider {
+    endpoint: String,
+    model: String,
+    client: reqwest::Client,
+    retry: RetryConfig,
+    /// Model options sent with every request, see [`OllamaProvider::with_options`].
+    options: serde_json::Map<String, serde_json::Value>,
+    keep_alive: Option<serde_json::Value>,
+    usage: UsageMeter,
 }
 
-impl OllamaClient {
+impl OllamaProvider {
     pub fn new(endpoint: String, model: String) -> Self {
         Self {
             endpoint,
             model,
             client: reqwest::Client::new(),
+            retry: RetryConfig::default(),
+            options: serde_json::Map::new(),
+            keep_alive: None,
+            usage: UsageMeter::default(),
         }
     }
 
-    /// Send a chat request to the Ollama API and return the response content.
-    pub async fn chat(&self, request: ChatRequest) -> Result<String> {
+    /// Retry failed chat requests as configured in `retry`.
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Send `options` (`num_ctx`, `seed`...) with every request, under the
+    /// request's own temperature and top_p, and `keep_alive` if set.
+    pub fn with_options(
+        mut self,
+        options: serde_json::Map<String, serde_json::Value>,
+        keep_alive: Option<serde_json::Value>,
+    ) -> Self {
+        self.options = options;
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Request body for `/api/chat`.
+    fn body(&self, request: ChatRequest, stream: bool) -> serde_json::Value {
+        let mut options = self.options.clone();
+        options.insert("temperature".to_string(), request.temperature.into());
+        options.insert("top_p".to_string(), request.top_p.into());
         let mut body = serde_json::json!({
             "model": self.model,
             "messages": [
                 { "role": "system", "content": request.system_prompt },
                 { "role": "user", "content": request.user_prompt }
             ],
-            "stream": false,
-            "options": {
-                "temperature": request.temperature,
-                "top_p": request.top_p
-            }
+            "stream": stream,
+            "options": options
         });
+        if let Some(keep_alive) = &self.keep_alive {
+            body["keep_alive"] = keep_alive.clone();
+        }
+
+        match request.format {
+            ResponseFormat::Text => {}
+            ResponseFormat::Json => body["format"] = serde_json::json!("json"),
+            ResponseFormat::Schema(schema) => body["format"] = schema,
+        }
+        body
+    }
+
+    async fn post(&self, body: &serde_json::Value) -> Result<reqwest::Response> {
+        let url = format!("{}/api/chat", self.endpoint);
+        let response =
+            retry::send_with_backoff(&self.retry, "Ollama", || self.client.post(&url).json(body))
+                .await
+                .context("Failed to send request to Ollama")?;
 
-        if request.json_format {
-            body["format"] = serde_json::json!("json");
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Ollama API error ({}): {}", status, error_text);
         }
+        Ok(response)
+    }
+
+    /// Whether the server has the model, as listed by `/api/tags`.
+    pub async fn has_model(&self) -> Result<bool> {
+        let response = self
+            .client
+            .get(format!("{}/api/tags", self.endpoint))
+            .timeout(std::time::Duration::from_secs(5))
+            .send()
+            .await
+            .context("Failed to list Ollama models")?
+            .error_for_status()
+            .context("Failed to list Ollama models")?;
+        let tags: serde_json::Value = response
+            .json()
+            .await
+            .context("Failed to parse Ollama model list")?;
+        Ok(lists_model(&tags, &self.model))
+    }
 
-        let response = self.client
-            .post(format!("{}/api/chat", self.endpoint))
-            .json(&body)
+    /// Download the model with `/api/pull`, waiting until it is complete.
+    pub async fn pull_model(&self) -> Result<()> {
+        let response = self
+            .client
+            .post(format!("{}/api/pull", self.endpoint))
+            .json(&serde_json::json!({ "model": self.model, "stream": false }))
             .send()
             .await
-            .context("Failed to send request to Ollama")?;
+            .context("Failed to send pull request to Ollama")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Ollama API error ({}): {}", status, error_text);
+        }
+        Ok(())
+    }
 
+    /// Embedding of `text` from `/api/embeddings`, for an embedding model
+    /// such as `nomic-embed-text`.
+    pub async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let url = format!("{}/api/embeddings", self.endpoint);
+        let body = serde_json::json!({ "model": self.model, "prompt": text });
+        let response =
+            retry::send_with_backoff(&self.retry, "Ollama", || self.client.post(&url).json(&body))
+                .await
+                .context("Failed to send embedding request to Ollama")?;
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
             anyhow::bail!("Ollama API error ({}): {}", status, error_text);
         }
 
-        let response_json: serde_json::Value = response.json().await
+        let response_json: serde_json::Value = response
+            .json()
+            .await
+            .context("Failed to parse Ollama embedding")?;
+        let embedding: Vec<f32> = serde_json::from_value(response_json["embedding"].clone())
+            .context("No embedding in Ollama response")?;
+        anyhow::ensure!(!embedding.is_empty(), "Empty embedding from {}", self.model);
+        Ok(embedding)
+    }
+
+    /// Record the token counts Ollama reports with a complete answer.
+    fn record_usage(&self, response: &serde_json::Value) {
+        self.usage
+            .record_fields(response, "prompt_eval_count", "eval_count");
+    }
+
+    /// Append the content of one line of a streamed answer to `content`; the
+    /// last line carries the token counts. Returns whether the line carried a
+    /// token.
+    fn read_stream_line(&self, line: &[u8], content: &mut String) -> Result<bool> {
+        let line = String::from_utf8_lossy(line);
+        if line.trim().is_empty() {
+            return Ok(false);
+        }
+        let chunk: serde_json::Value =
+            serde_json::from_str(line.trim()).context("Failed to parse Ollama stream")?;
+        if let Some(error) = chunk["error"].as_str() {
+            anyhow::bail!("Ollama stream error: {}", error);
+        }
+        if chunk["done"] == true {
+            self.record_usage(&chunk);
+        }
+        let token = chunk["message"]["content"].as_str().unwrap_or_default();
+        content.push_str(token);
+        Ok(!token.is_empty())
+    }
+}
+
+/// `model` with the `:latest` tag Ollama assumes when none is given.
+fn with_tag(model: &str) -> String {
+    if model.contains(':') {
+        model.to_string()
+    } else {
+        format!("{}:latest", model)
+    }
+}
+
+/// Whether `model` is among the models of an `/api/tags` answer.
+fn lists_model(tags: &serde_json::Value, model: &str) -> bool {
+    let model = with_tag(model);
+    tags["models"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry["name"].as_str())
+        .any(|name| with_tag(name) == model)
+}
+
+/// Whether an Ollama server has its configured model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelStatus {
+    Ready,
+    Missing,
+    Unreachable,
+}
+
+/// The Ollama provider of `config`, if it uses one.
+fn provider_for(config: &AiModelConfig) -> Option<OllamaProvider> {
+    matches!(config.provider.as_str(), "ollama_local" | "ollama_remote")
+        .then(|| OllamaProvider::new(config.endpoint.clone(), config.model.clone()))
+}
+
+/// Whether the Ollama server of `config` has its model; `None` for other
+/// providers.
+pub async fn model_status(config: &AiModelConfig) -> Option<ModelStatus> {
+    let ollama = provider_for(config)?;
+    Some(match ollama.has_model().await {
+        Ok(true) => ModelStatus::Ready,
+        Ok(false) => ModelStatus::Missing,
+        Err(_) => ModelStatus::Unreachable,
+    })
+}
+
+/// Make sure the Ollama servers of `config` and its fallbacks have their
+/// models, pulling missing ones with `pull_model` and failing otherwise, so
+/// a typo in the model name stops the bot at startup instead of failing
+/// every memo. Unreachable servers are left to the memos, which degrade to
+/// unprocessed notes.
+pub async fn ensure_models(config: &AiModelConfig) -> Result<()> {
+    let mut next = Some(config);
+    while let Some(config) = next {
+        next = config.fallback.as_deref();
+        let Some(ollama) = provider_for(config) else {
+            continue;
+        };
+        match ollama.has_model().await {
+            Ok(true) => {}
+            Ok(false) if config.pull_model => {
+                log::info!(
+                    "Pulling {} on {}, this may take a while",
+                    config.model,
+                    ollama.describe()
+                );
+                ollama
+                    .pull_model()
+                    .await
+                    .with_context(|| format!("Failed to pull {}", config.model))?;
+                log::info!("Pulled {}", config.model);
+            }
+            Ok(false) => anyhow::bail!(
+                "Model '{}' is not installed on {}: run `ollama pull {}` or set ai_model.pull_model = true",
+                config.model,
+                ollama.describe(),
+                config.model
+            ),
+            Err(e) => log::warn!("Could not check the models of {}: {:#}", ollama.describe(), e),
+        }
+    }
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl AiProvider for OllamaProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn describe(&self) -> String {
+        format!("Ollama at {}", self.endpoint)
+    }
+
+    /// Whether the Ollama server answers, checked with a quick model listing.
+    async fn is_available(&self) -> bool {
+        self.client
+            .get(format!("{}/api/tags", self.endpoint))
+            .timeout(std::time::Duration::from_secs(5))
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success())
+    }
+
+    fn usage(&self) -> TokenUsage {
+        self.usage.total()
+    }
+
+    /// Send a chat request to the Ollama API and return the response content.
+    async fn chat(&self, request: ChatRequest) -> Result<String> {
+        let response = self.post(&self.body(request, false)).await?;
+
+        let response_json: serde_json::Value = response
+            .json()
+            .await
             .context("Failed to parse Ollama response")?;
+        self.record_usage(&response_json);
 
         let content = response_json["message"]["content"]
             .as_str()
@@ -67,4 +301,103 @@ impl OllamaClient {
 
         Ok(content)
     }
+
+    /// Like `chat`, with `"stream": true`: Ollama sends one JSON line per
+    /// token, counted into `tokens`.
+    async fn chat_streaming(&self, request: ChatRequest, tokens: TokenUpdates) -> Result<String> {
+        let mut response = self.post(&self.body(request, true)).await?;
+
+        let mut content = String::new();
+        let mut count = 0;
+        let mut buffer: Vec<u8> = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .context("Failed to read Ollama stream")?
+        {
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                if self.read_stream_line(&line, &mut content)? {
+                    count += 1;
+                    let _ = tokens.send(count);
+                }
+            }
+        }
+        self.read_stream_line(&buffer, &mut content)?;
+
+        anyhow::ensure!(!content.is_empty(), "No content in Ollama response");
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_passes_options_through() {
+        let options = serde_json::json!({ "num_ctx": 8192, "seed": 42, "temperature": 2.0 });
+        let provider = OllamaProvider::new(String::new(), "llama3.2:3b".to_string())
+            .with_options(options.as_object().unwrap().clone(), Some("30m".into()));
+        let body = provider.body(
+            ChatRequest {
+                system_prompt: "Sistema".to_string(),
+                user_prompt: "Utente".to_string(),
+                temperature: 0.5,
+                top_p: 0.9,
+                format: ResponseFormat::Json,
+            },
+            false,
+        );
+        assert_eq!(body["options"]["num_ctx"], 8192);
+        assert_eq!(body["options"]["seed"], 42);
+        // The request's own sampling wins over the configured options
+        assert_eq!(body["options"]["temperature"], 0.5);
+        assert_eq!(body["keep_alive"], "30m");
+        assert_eq!(body["format"], "json");
+    }
+
+    #[test]
+    fn test_lists_model_with_default_tag() {
+        let tags = serde_json::json!({
+            "models": [
+                { "name": "llama3.2:3b", "model": "llama3.2:3b" },
+                { "name": "mistral:latest", "model": "mistral:latest" }
+            ]
+        });
+        assert!(lists_model(&tags, "llama3.2:3b"));
+        assert!(lists_model(&tags, "mistral"));
+        assert!(!lists_model(&tags, "llama3.2"));
+        assert!(!lists_model(&tags, "qwen2.5:7b"));
+        assert!(!lists_model(&serde_json::json!({}), "mistral"));
+    }
+
+    #[test]
+    fn test_read_stream_lines() {
+        let provider = OllamaProvider::new(String::new(), "llama3.2:3b".to_string());
+        let mut content = String::new();
+        let stream = [
+            r#"{"message":{"role":"assistant","content":"{\"notes\""},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":": []}"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":""},"done":true,"prompt_eval_count":40,"eval_count":2}"#,
+            "",
+        ];
+        let tokens = stream
+            .iter()
+            .map(|line| {
+                provider
+                    .read_stream_line(line.as_bytes(), &mut content)
+                    .unwrap()
+            })
+            .filter(|&token| token)
+            .count();
+        assert_eq!(tokens, 2);
+        assert_eq!(content, r#"{"notes": []}"#);
+        assert_eq!(provider.usage().prompt_tokens, 40);
+        assert_eq!(provider.usage().completion_tokens, 2);
+
+        let error = br#"{"error":"model 'llama3' not found"}"#;
+        assert!(provider.read_stream_line(error, &mut content).is_err());
+    }
 }
@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+
+use crate::ai_provider::{AiProvider, ChatRequest, ResponseFormat};
+use crate::config::RetryConfig;
+use crate::retry;
+use crate::usage::{TokenUsage, UsageMeter};
+
+/// Language model behind an OpenAI-style `/chat/completions` API
+/// (`openai_compatible`): OpenAI, Groq, Together, LM Studio, vLLM...
+pub struct OpenAiCompatibleProvider {
+    /// Base URL up to the API version, e.g. `https://api.openai.com/v1`.
+    base_url: String,
+    model: String,
+    /// Bearer token; local servers usually need none.
+    api_key: Option<String>,
+    client: reqwest::Client,
+    retry: RetryConfig,
+    usage: UsageMeter,
+}
+
+impl OpenAiCompatibleProvider {
+    pub fn new(base_url: &str, model: String, api_key: Option<String>) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+            api_key,
+            client: reqwest::Client::new(),
+            retry: RetryConfig::default(),
+            usage: UsageMeter::default(),
+        }
+    }
+
+    /// Retry failed chat requests as configured in `retry`.
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    /// Request body for `request`, in the chat completions format.
+    fn body(&self, request: ChatRequest) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "model": self.model,
+            "messages": [
+                { "role": "system", "content": request.system_prompt },
+                { "role": "user", "content": request.user_prompt }
+            ],
+            "temperature": request.temperature,
+            "top_p": request.top_p
+        });
+
+        match request.format {
+            ResponseFormat::Text => {}
+            ResponseFormat::Json => {
+                body["response_format"] = serde_json::json!({ "type": "json_object" })
+            }
+            ResponseFormat::Schema(schema) => {
+                body["response_format"] = serde_json::json!({
+                    "type": "json_schema",
+                    "json_schema": { "name": "response", "schema": schema }
+                })
+            }
+        }
+        body
+    }
+}
+
+#[async_trait::async_trait]
+impl AiProvider for OpenAiCompatibleProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn describe(&self) -> String {
+        format!("the chat API at {}", self.base_url)
+    }
+
+    /// Whether the API answers, checked with its model listing.
+    async fn is_available(&self) -> bool {
+        self.authorized(self.client.get(format!("{}/models", self.base_url)))
+            .timeout(std::time::Duration::from_secs(5))
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success())
+    }
+
+    fn usage(&self) -> TokenUsage {
+        self.usage.total()
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<String> {
+        let body = self.body(request);
+        let url = format!("{}/chat/completions", self.base_url);
+        let response = retry::send_with_backoff(&self.retry, "Chat API", || {
+            self.authorized(self.client.post(&url)).json(&body)
+        })
+        .await
+        .context("Failed to send request to the chat API")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Chat API error ({}): {}", status, error_text);
+        }
+
+        let response_json: serde_json::Value = response
+            .json()
+            .await
+            .context("Failed to parse chat API response")?;
+        self.usage.record_fields(
+            &response_json["usage"],
+            "prompt_tokens",
+            "completion_tokens",
+        );
+
+        let content = response_json["choices"][0]["message"]["content"]
+            .as_str()
+            .context("No content in chat API response")?
+            .to_string();
+
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_asks_for_the_response_format() {
+        let provider =
+            OpenAiCompatibleProvider::new("http://localhost:1234/v1/", "qwen2.5".to_string(), None);
+        assert_eq!(provider.base_url, "http://localhost:1234/v1");
+
+        let request = |format| ChatRequest {
+            system_prompt: "Sistema".to_string(),
+            user_prompt: "Utente".to_string(),
+            temperature: 0.5,
+            top_p: 0.9,
+            format,
+        };
+        let body = provider.body(request(ResponseFormat::Text));
+        assert_eq!(body["model"], "qwen2.5");
+        assert_eq!(body["messages"][1]["content"], "Utente");
+        assert!(body.get("response_format").is_none());
+
+        let body = provider.body(request(ResponseFormat::Json));
+        assert_eq!(body["response_format"]["type"], "json_object");
+
+        let schema = serde_json::json!({ "type": "object" });
+        let body = provider.body(request(ResponseFormat::Schema(schema.clone())));
+        assert_eq!(body["response_format"]["json_schema"]["schema"], schema);
+    }
+}
@@ -0,0 +1,126 @@
+//! Native Opus decoding through libopus (the `audiopus` bindings), so Telegram
+//! voice notes can be transcribed on hosts without ffmpeg (`--features opus`).
+
+use anyhow::{Context, Result};
+use audiopus::coder::Decoder as OpusDecoder;
+use audiopus::packet::Packet;
+use audiopus::{Channels, MutSignals, SampleRate};
+use symphonia::core::formats::FormatReader;
+
+/// Opus decodes natively at 16 kHz, the rate Whisper expects.
+pub const SAMPLE_RATE: u32 = 16000;
+/// Longest Opus frame (120 ms) at 16 kHz, per channel.
+const MAX_FRAME: usize = 1920;
+/// OGG pre-skip is always expressed at 48 kHz.
+const PRE_SKIP_DIVISOR: u32 = 48000 / SAMPLE_RATE;
+
+/// A libopus decoder for one mono or stereo stream.
+pub struct Decoder {
+    inner: OpusDecoder,
+    channels: usize,
+}
+
+impl Decoder {
+    pub fn new(channels: usize) -> Result<Self> {
+        let layout = match channels {
+            1 => Channels::Mono,
+            2 => Channels::Stereo,
+            n => anyhow::bail!("Unsupported Opus channel count: {}", n),
+        };
+        let inner = OpusDecoder::new(SampleRate::Hz16000, layout)
+            .context("libopus failed to create decoder")?;
+        Ok(Self { inner, channels })
+    }
+
+    /// Decode one packet, appending interleaved samples to `out`.
+    pub fn decode(&mut self, packet: &[u8], out: &mut Vec<f32>) -> Result<()> {
+        let start = out.len();
+        out.resize(start + MAX_FRAME * self.channels, 0.0);
+        let decoded = Packet::try_from(packet)
+            .and_then(|packet| {
+                let signals = MutSignals::try_from(&mut out[start..])?;
+                self.inner.decode_float(Some(packet), signals, false)
+            })
+            .context("libopus failed to decode packet");
+        match decoded {
+            Ok(decoded) => {
+                out.truncate(start + decoded * self.channels);
+                Ok(())
+            }
+            Err(e) => {
+                out.truncate(start);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Decode an Opus track demuxed by Symphonia into one 16 kHz signal per channel.
+pub fn decode_track(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    channels: usize,
+    pre_skip: u32,
+) -> Result<Vec<Vec<f32>>> {
+    let mut decoder = Decoder::new(channels)?;
+    let mut samples = Vec::new();
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(symphonia::core::errors::Error::IoError(e))
+                if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                break
+            }
+            Err(e) => return Err(e).context("Failed to read packet"),
+        };
+        if packet.track_id() == track_id {
+            decoder.decode(packet.buf(), &mut samples)?;
+        }
+    }
+
+    // Drop the encoder priming samples
+    let skip = (pre_skip / PRE_SKIP_DIVISOR) as usize * channels;
+    samples.drain(..skip.min(samples.len()));
+
+    Ok((0..channels)
+        .map(|c| samples.iter().skip(c).step_by(channels).copied().collect())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audiopus::coder::Encoder;
+    use audiopus::Application;
+
+    #[test]
+    fn test_decode_round_trip() {
+        // 20 ms frames of a 440 Hz tone
+        let frame = 320;
+        let tone: Vec<f32> = (0..frame * 10)
+            .map(|i| (i as f32 * 440.0 * std::f32::consts::TAU / SAMPLE_RATE as f32).sin() * 0.5)
+            .collect();
+
+        let encoder = Encoder::new(SampleRate::Hz16000, Channels::Mono, Application::Voip).unwrap();
+        let packets: Vec<Vec<u8>> = tone
+            .chunks(frame)
+            .map(|chunk| {
+                let mut packet = vec![0u8; 4000];
+                let len = encoder.encode_float(chunk, &mut packet).unwrap();
+                packet.truncate(len);
+                packet
+            })
+            .collect();
+
+        let mut decoder = Decoder::new(1).unwrap();
+        let mut samples = Vec::new();
+        for packet in &packets {
+            decoder.decode(packet, &mut samples).unwrap();
+        }
+        assert_eq!(samples.len(), tone.len());
+        assert!(samples.iter().any(|s| s.abs() > 0.1));
+        assert!(Decoder::new(3).is_err());
+    }
+}
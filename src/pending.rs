@@ -0,0 +1,274 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::atomic_write::write_atomic;
+use crate::errors::PipelineError;
+use crate::note_generator::Note;
+
+/// Longest fallback note title, in characters.
+const MAX_TITLE_CHARS: usize = 60;
+
+/// Automatic retries of a memo before it is left to `/retry`.
+pub const MAX_AUTO_RETRIES: u32 = 5;
+
+/// Pipeline stage a memo failed at. It is retried automatically once the
+/// dependency of that stage is healthy again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FailedStage {
+    /// Telegram unreachable while fetching the audio.
+    Download,
+    /// Conversion or transcription of the audio.
+    Transcription,
+    /// The LLM (Ollama) failed to generate the notes.
+    #[default]
+    Generation,
+    /// The vault could not be written.
+    Save,
+}
+
+impl FailedStage {
+    pub fn of(err: &PipelineError) -> Self {
+        match err {
+            PipelineError::Download(_) => FailedStage::Download,
+            PipelineError::Convert(_) | PipelineError::Transcribe { .. } => {
+                FailedStage::Transcription
+            }
+            PipelineError::Llm(_) => FailedStage::Generation,
+            PipelineError::Save(_) => FailedStage::Save,
+        }
+    }
+}
+
+/// A memo that failed at some stage of the pipeline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingMemo {
+    pub chat_id: i64,
+    pub message_id: i32,
+    /// When the memo was sent, used for dates in the regenerated notes.
+    pub received_at: DateTime<Utc>,
+    #[serde(default)]
+    pub stage: FailedStage,
+    /// Telegram file of the audio, to download it again.
+    #[serde(default)]
+    pub file_id: Option<String>,
+    /// Set once transcription succeeded.
+    #[serde(default)]
+    pub raw_transcript: Option<String>,
+    /// Language detected in the transcript, with `language = "auto"`.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Folder the notes belong in (the chat's profile or group folder).
+    pub notes_dir: String,
+    /// Vault of `notes_dir` (`None` for the default vault).
+    #[serde(default)]
+    pub vault: Option<String>,
+    /// Link to the memo's message, for the notes' `telegram_link`.
+    #[serde(default)]
+    pub telegram_link: Option<String>,
+    /// Translate the audio to English (`task = "translate"` or `#en` caption).
+    #[serde(default)]
+    pub translate: bool,
+    /// Transcript-only note saved in place of the real ones.
+    #[serde(default)]
+    pub fallback_note: Option<PathBuf>,
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+/// Minimal note keeping a transcript whose notes could not be generated, so
+/// the capture is never lost: the first sentence as title, the transcript as
+/// body and `tag` (`features.fallback_tag`) as the only tag.
+pub fn fallback_note(transcript: &str, date: DateTime<Utc>, tag: &str) -> Note {
+    Note {
+        title: fallback_title(transcript, date),
+        content: transcript.trim().to_string(),
+        tags: vec![tag.to_string()],
+        date,
+        source: "voice-memo".to_string(),
+        language: None,
+        icon: None,
+        references: vec![],
+        expenses: vec![],
+        telegram_link: None,
+        properties: BTreeMap::new(),
+        related_notes: Vec::new(),
+        id: None,
+    }
+}
+
+/// First sentence of the transcript, or the date of a memo without text.
+fn fallback_title(transcript: &str, date: DateTime<Utc>) -> String {
+    first_sentence(transcript)
+        .unwrap_or_else(|| format!("Memo vocale {}", date.format("%Y-%m-%d %H.%M")))
+}
+
+/// First sentence of the transcript, cut at a word boundary if long.
+pub fn first_sentence(transcript: &str) -> Option<String> {
+    let sentence = transcript
+        .split(['.', '!', '?', '\n'])
+        .map(str::trim)
+        .find(|s| !s.is_empty())?;
+    if sentence.chars().count() <= MAX_TITLE_CHARS {
+        return Some(sentence.to_string());
+    }
+
+    let mut title = String::new();
+    for word in sentence.split_whitespace() {
+        if title.chars().count() + word.chars().count() + 1 > MAX_TITLE_CHARS {
+            break;
+        }
+        if !title.is_empty() {
+            title.push(' ');
+        }
+        title.push_str(word);
+    }
+    if title.is_empty() {
+        title = sentence.chars().take(MAX_TITLE_CHARS).collect();
+    }
+    Some(format!("{}…", title))
+}
+
+/// Memos waiting to be retried, persisted to `<data_dir>/pending.json`.
+pub struct PendingStore {
+    path: PathBuf,
+    memos: Mutex<Vec<PendingMemo>>,
+}
+
+impl PendingStore {
+    /// Load the pending memos, starting empty if the file does not exist yet.
+    pub fn load<P: AsRef<Path>>(data_dir: P) -> Result<Self> {
+        let path = data_dir.as_ref().join("pending.json");
+        let memos = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse pending memos: {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read pending memos: {}", path.display()))
+            }
+        };
+
+        Ok(Self {
+            path,
+            memos: Mutex::new(memos),
+        })
+    }
+
+    /// Add a memo, or update it if it is already pending.
+    pub fn add(&self, memo: PendingMemo) -> Result<()> {
+        let mut memos = self.memos.lock().unwrap();
+        match memos
+            .iter_mut()
+            .find(|m| m.chat_id == memo.chat_id && m.message_id == memo.message_id)
+        {
+            Some(existing) => *existing = memo,
+            None => memos.push(memo),
+        }
+        self.persist(&memos)
+    }
+
+    pub fn all(&self) -> Vec<PendingMemo> {
+        self.memos.lock().unwrap().clone()
+    }
+
+    /// Most recent pending memo of a chat.
+    pub fn latest(&self, chat_id: i64) -> Option<PendingMemo> {
+        self.memos
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|m| m.chat_id == chat_id)
+            .cloned()
+    }
+
+    /// Forget a memo once its notes have been generated.
+    pub fn remove(&self, chat_id: i64, message_id: i32) -> Result<()> {
+        let mut memos = self.memos.lock().unwrap();
+        memos.retain(|m| !(m.chat_id == chat_id && m.message_id == message_id));
+        self.persist(&memos)
+    }
+
+    fn persist(&self, memos: &[PendingMemo]) -> Result<()> {
+        let json =
+            serde_json::to_string_pretty(memos).context("Failed to serialize pending memos")?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create data directory")?;
+        }
+        // Write to a temp file and rename, so a crash never loses the list
+        write_atomic(&self.path, &json, false).context("Failed to write pending memos")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_fallback_note_and_pending_store() {
+        let date = Utc.with_ymd_and_hms(2024, 5, 14, 9, 30, 0).unwrap();
+        let note = fallback_note(
+            "Domani chiamare Luca per il preventivo. Poi sentire la banca.",
+            date,
+            "needs-processing",
+        );
+        assert_eq!(note.title, "Domani chiamare Luca per il preventivo");
+        assert_eq!(note.tags, vec!["needs-processing"]);
+        assert!(note.content.ends_with("sentire la banca."));
+
+        let long = fallback_note(&"parola ".repeat(30), date, "needs-processing");
+        assert!(long.title.ends_with('…'));
+        assert!(long.title.chars().count() <= MAX_TITLE_CHARS + 1);
+        assert!(fallback_note("  ", date, "needs-processing")
+            .title
+            .starts_with("Memo vocale "));
+
+        let dir = std::env::temp_dir().join(format!("dot-pending-{}", uuid::Uuid::new_v4()));
+        let store = PendingStore::load(&dir).unwrap();
+        let memo = |message_id| PendingMemo {
+            chat_id: 7,
+            message_id,
+            received_at: date,
+            stage: FailedStage::Download,
+            file_id: Some("file".to_string()),
+            raw_transcript: None,
+            language: None,
+            notes_dir: "./notes".to_string(),
+            vault: None,
+            telegram_link: None,
+            translate: false,
+            fallback_note: None,
+            attempts: 0,
+        };
+        store.add(memo(1)).unwrap();
+        store.add(memo(2)).unwrap();
+        // Re-adding a memo updates it in place
+        let mut retried = memo(1);
+        retried.stage = FailedStage::Generation;
+        retried.raw_transcript = Some("testo".to_string());
+        store.add(retried).unwrap();
+
+        let reloaded = PendingStore::load(&dir).unwrap();
+        assert_eq!(reloaded.all().len(), 2);
+        assert_eq!(reloaded.all()[0].stage, FailedStage::Generation);
+        assert_eq!(reloaded.latest(7).unwrap().message_id, 2);
+        assert!(reloaded.latest(8).is_none());
+        reloaded.remove(7, 2).unwrap();
+        assert_eq!(
+            PendingStore::load(&dir)
+                .unwrap()
+                .latest(7)
+                .unwrap()
+                .message_id,
+            1
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+//! Notes made without a language model (`ai_model.provider = "none"`): the
+//! transcript itself, laid out in paragraphs, for transcription-only capture.
+
+/// Paragraphs grow to about this many characters before a break.
+const PARAGRAPH_CHARS: usize = 600;
+
+/// Lay out a transcript in paragraphs. Breaks fall between the timestamped
+/// segments of a transcript with `[mm:ss]` lines (the pauses Whisper found),
+/// otherwise between sentences.
+pub fn paragraphs(transcript: &str) -> String {
+    let lines: Vec<&str> = transcript
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let units: Vec<&str> = if lines.len() > 1 {
+        lines
+    } else {
+        lines.iter().flat_map(|line| sentences(line)).collect()
+    };
+
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for unit in units {
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(unit);
+        if current.chars().count() >= PARAGRAPH_CHARS {
+            paragraphs.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(current);
+    }
+    paragraphs.join("\n\n")
+}
+
+/// Sentences of `text`, each with its closing punctuation.
+fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let ends_sentence = matches!(c, '.' | '!' | '?')
+            && chars.peek().is_some_and(|(_, next)| next.is_whitespace());
+        if ends_sentence {
+            sentences.push(text[start..=i].trim());
+            start = i + 1;
+        }
+    }
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paragraphs_break_between_sentences_and_segments() {
+        let sentence = "Questa è una frase di prova abbastanza lunga da riempire un paragrafo. ";
+        let text = sentence.repeat(12);
+        let laid_out = paragraphs(&text);
+        let parts: Vec<&str> = laid_out.split("\n\n").collect();
+        assert_eq!(parts.len(), 2);
+        assert!(parts.iter().all(|p| p.ends_with("paragrafo.")));
+
+        assert_eq!(paragraphs("Breve. Memo."), "Breve. Memo.");
+        assert_eq!(
+            paragraphs("[00:00] Primo punto.\n[00:12] Secondo punto."),
+            "[00:00] Primo punto. [00:12] Secondo punto."
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Choice of the existing notes listed in the generation prompt: a large
+//! vault's full list would overflow the model's context, so the notes most
+//! likely to be linked get the token budget.
+
+use std::collections::HashSet;
+
+use crate::tools::NoteMeta;
+
+/// Rough characters per token, as for `max_transcript_chars`.
+const CHARS_PER_TOKEN: usize = 4;
+/// Weight of each tag of a note that the transcript mentions.
+const TAG_WEIGHT: usize = 2;
+/// Weight of each word of a note's title that the transcript mentions.
+const TITLE_WEIGHT: usize = 1;
+/// Shorter words ("come", "per") say nothing about the topic.
+const MIN_KEYWORD_CHARS: usize = 4;
+
+/// The line of `note` in the prompt's list of existing notes.
+pub fn prompt_line(note: &NoteMeta) -> String {
+    let stem = note.filename.strip_suffix(".md").unwrap_or(&note.filename);
+    let mut line = format!("- **{}** (file: `{}`)", note.title, stem);
+    if let Some(id) = &note.id {
+        line.push_str(&format!(" (id: {})", id));
+    }
+    if !note.aliases.is_empty() {
+        line.push_str(&format!(" (alias: {})", note.aliases.join(", ")));
+    }
+    if !note.date.is_empty() {
+        line.push_str(&format!(" ({})", note.date));
+    }
+    if !note.tags.is_empty() {
+        line.push_str(&format!(" [{}]", note.tags.join(", ")));
+    }
+    line.push('\n');
+    line
+}
+
+/// Lowercase words of `text` long enough to name a topic.
+fn keywords(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_KEYWORD_CHARS)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// How strongly `note` relates to a transcript with `keywords`: its tags
+/// (and their `nested/parts`) and title words the transcript mentions.
+fn relevance(note: &NoteMeta, keywords: &HashSet<String>) -> usize {
+    let tags = note
+        .tags
+        .iter()
+        .filter(|tag| {
+            tag.trim_start_matches('#')
+                .split(['/', '-', '_'])
+                .any(|part| keywords.contains(&part.to_lowercase()))
+        })
+        .count();
+    let title = self::keywords(&note.title).intersection(keywords).count();
+    tags * TAG_WEIGHT + title * TITLE_WEIGHT
+}
+
+/// The notes of `notes` to list in the prompt for `transcript`, within
+/// about `budget_tokens` (0 lists them all): notes the transcript relates
+/// to first, then the most recent ones.
+pub fn select(notes: &[NoteMeta], transcript: &str, budget_tokens: usize) -> Vec<NoteMeta> {
+    if budget_tokens == 0 {
+        return notes.to_vec();
+    }
+    let keywords = keywords(transcript);
+    let mut ranked: Vec<(usize, &NoteMeta)> = notes
+        .iter()
+        .map(|note| (relevance(note, &keywords), note))
+        .collect();
+    // ISO dates sort chronologically as strings
+    ranked.sort_by(|(a, a_note), (b, b_note)| b.cmp(a).then(b_note.date.cmp(&a_note.date)));
+
+    let mut budget = budget_tokens * CHARS_PER_TOKEN;
+    let mut selected = Vec::new();
+    for (_, note) in ranked {
+        let chars = prompt_line(note).chars().count();
+        if chars > budget {
+            break;
+        }
+        budget -= chars;
+        selected.push(note.clone());
+    }
+    if selected.len() < notes.len() {
+        log::info!(
+            "Agent: listing {} of {} existing notes in the prompt (existing_notes_tokens)",
+            selected.len(),
+            notes.len()
+        );
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str, date: &str, tags: &[&str]) -> NoteMeta {
+        NoteMeta {
+            title: title.to_string(),
+            date: date.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            filename: format!("{}.md", title),
+            source: String::new(),
+            id: None,
+            aliases: Vec::new(),
+            links: Vec::new(),
+            folder: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_select_prefers_related_then_recent_notes() {
+        let notes = vec![
+            note("Lista spesa", "2024-01-10", &["casa"]),
+            note("Riunione budget", "2024-05-02", &["lavoro"]),
+            note("Ricetta carbonara", "2023-11-20", &["cucina/ricette"]),
+            note("Idee vacanze", "2024-06-01", &["viaggi"]),
+            note("Progetto Kubernetes", "2022-03-15", &["devops"]),
+        ];
+        let transcript = "Stasera provo una nuova ricetta, poi sistemo il cluster Kubernetes.";
+
+        let all = select(&notes, transcript, 0);
+        assert_eq!(all.len(), notes.len());
+
+        // Room for the two related notes and the most recent other one
+        let chars: usize = [2, 4, 3]
+            .iter()
+            .map(|&i| prompt_line(&notes[i]).chars().count())
+            .sum();
+        let budget = chars.div_ceil(CHARS_PER_TOKEN);
+        let titles: Vec<String> = select(&notes, transcript, budget)
+            .into_iter()
+            .map(|n| n.title)
+            .collect();
+        assert_eq!(
+            titles,
+            ["Ricetta carbonara", "Progetto Kubernetes", "Idee vacanze"]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Folders already reported as close to their cap, so the admin is warned
+/// once per crossing rather than on every memo.
+static WARNED: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Size cap of a folder, with least recently used files evicted beyond it.
+pub struct Quota {
+    dir: PathBuf,
+    max_bytes: u64,
+    warn_percent: u8,
+}
+
+/// Outcome of [`Quota::enforce`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct QuotaReport {
+    /// Size of the folder after eviction.
+    pub used_bytes: u64,
+    pub max_bytes: u64,
+    pub evicted_files: usize,
+    pub freed_bytes: u64,
+    /// The folder just reached the warning level (reported once until it
+    /// drops below it again).
+    pub approaching: bool,
+}
+
+struct StoredFile {
+    path: PathBuf,
+    size: u64,
+    last_used: SystemTime,
+}
+
+impl Quota {
+    pub fn new<P: AsRef<Path>>(dir: P, max_mb: u64, warn_percent: u8) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            max_bytes: max_mb * 1024 * 1024,
+            warn_percent: warn_percent.min(100),
+        }
+    }
+
+    fn warn_bytes(&self) -> u64 {
+        self.max_bytes / 100 * u64::from(self.warn_percent)
+    }
+
+    /// Bring the folder under its cap: once it is exceeded, the least
+    /// recently used files (except `keep`) are deleted until the folder is
+    /// back at the warning level.
+    pub fn enforce(&self, keep: &[PathBuf]) -> Result<QuotaReport> {
+        let mut files = Vec::new();
+        collect_files(&self.dir, &mut files)?;
+        let mut used: u64 = files.iter().map(|f| f.size).sum();
+
+        let mut report = QuotaReport {
+            max_bytes: self.max_bytes,
+            ..QuotaReport::default()
+        };
+        if used > self.max_bytes {
+            files.sort_by_key(|f| f.last_used);
+            for file in files.iter().filter(|f| !keep.contains(&f.path)) {
+                if used <= self.warn_bytes() {
+                    break;
+                }
+                match std::fs::remove_file(&file.path) {
+                    Ok(()) => {
+                        log::info!("Quota: evicted {}", file.path.display());
+                        used -= file.size;
+                        report.evicted_files += 1;
+                        report.freed_bytes += file.size;
+                    }
+                    Err(e) => log::warn!("Quota: failed to evict {}: {}", file.path.display(), e),
+                }
+            }
+        }
+        report.used_bytes = used;
+
+        let mut warned = WARNED.lock().unwrap();
+        if used >= self.warn_bytes() && report.evicted_files == 0 {
+            report.approaching = warned.insert(self.dir.clone());
+        } else {
+            warned.remove(&self.dir);
+        }
+        Ok(report)
+    }
+}
+
+/// Files below `dir`, recursively; a missing folder has none.
+fn collect_files(dir: &Path, files: &mut Vec<StoredFile>) -> Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else if metadata.is_file() {
+            // Access times are often not updated (noatime); fall back to mtime
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            let last_used = metadata.accessed().map_or(modified, |at| at.max(modified));
+            files.push(StoredFile {
+                path: entry.path(),
+                size: metadata.len(),
+                last_used,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used_down_to_warning_level() {
+        let dir = std::env::temp_dir().join(format!("dot-quota-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("audio")).unwrap();
+        let mb = vec![0u8; 1024 * 1024];
+        let now = SystemTime::now();
+        let paths: Vec<PathBuf> = (0..4)
+            .map(|i| {
+                let path = dir.join("audio").join(format!("memo-{}.ogg", i));
+                std::fs::write(&path, &mb).unwrap();
+                let at = now - std::time::Duration::from_secs(3600 * (4 - i));
+                let file = std::fs::File::options().write(true).open(&path).unwrap();
+                file.set_times(std::fs::FileTimes::new().set_accessed(at).set_modified(at))
+                    .unwrap();
+                path
+            })
+            .collect();
+
+        // 4 MB under a 5 MB cap: past the 60% warning level, reported once
+        let quota = Quota::new(&dir, 5, 60);
+        let report = quota.enforce(&[]).unwrap();
+        assert!(report.approaching);
+        assert_eq!(report.evicted_files, 0);
+        assert!(!quota.enforce(&[]).unwrap().approaching);
+
+        // Over a 3 MB cap: the oldest files go, except the kept one
+        let quota = Quota::new(&dir, 3, 67);
+        let report = quota.enforce(&[paths[0].clone()]).unwrap();
+        assert_eq!(report.evicted_files, 2);
+        assert_eq!(report.used_bytes, 2 * 1024 * 1024);
+        assert!(paths[0].exists() && !paths[1].exists() && !paths[2].exists());
+        assert!(paths[3].exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
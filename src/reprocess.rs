@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, Utc};
+use std::path::PathBuf;
+
+use crate::audit::TranscriptLog;
+use crate::config::Config;
+use crate::note_generator::NoteGeneratorAgent;
+
+/// Options for the `reprocess` subcommand.
+#[derive(Debug, Default)]
+pub struct ReprocessOptions {
+    /// Transcript log to replay (defaults to `<data_dir>/transcripts.jsonl`).
+    pub transcripts_path: Option<PathBuf>,
+    /// Folder for the regenerated notes (defaults to `<data_dir>/reprocess/<timestamp>`).
+    pub output_dir: Option<PathBuf>,
+    /// Only memos sent on or after this day.
+    pub since: Option<NaiveDate>,
+    /// Stop after this many memos.
+    pub limit: Option<usize>,
+}
+
+impl ReprocessOptions {
+    /// Parse `reprocess [--out DIR] [--since YYYY-MM-DD] [--limit N] [TRANSCRIPTS_FILE]`.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut options = ReprocessOptions::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .ok_or_else(|| anyhow::anyhow!("{} requires a value", arg))
+            };
+            match arg.as_str() {
+                "--out" => options.output_dir = Some(PathBuf::from(value()?)),
+                "--since" => {
+                    options.since = Some(
+                        NaiveDate::parse_from_str(value()?, "%Y-%m-%d")
+                            .context("--since expects a date like 2024-05-17")?,
+                    )
+                }
+                "--limit" => options.limit = Some(value()?.parse()?),
+                other if other.starts_with("--") => {
+                    anyhow::bail!("Unknown reprocess option: {}", other)
+                }
+                path => options.transcripts_path = Some(PathBuf::from(path)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Regenerate notes from stored transcripts with the current prompts and
+/// models, writing them to a separate folder for side-by-side comparison.
+/// The vault is read for link context but never written to.
+pub async fn run(config: &Config, options: ReprocessOptions) -> Result<()> {
+    let path = options.transcripts_path.clone().unwrap_or_else(|| {
+        TranscriptLog::new(&config.output.data_dir)
+            .path()
+            .to_path_buf()
+    });
+    let output_dir = options.output_dir.clone().unwrap_or_else(|| {
+        PathBuf::from(&config.output.data_dir)
+            .join("reprocess")
+            .join(Utc::now().format("%Y%m%d-%H%M%S").to_string())
+    });
+    std::fs::create_dir_all(&output_dir).context("Failed to create reprocess output folder")?;
+    let output_dir_str = output_dir.to_string_lossy().to_string();
+
+    let mut records = TranscriptLog::load_records(&path)?;
+    if let Some(since) = options.since {
+        records.retain(|r| r.timestamp.date_naive() >= since);
+    }
+    if let Some(limit) = options.limit {
+        records.truncate(limit);
+    }
+
+    println!(
+        "🔁 Reprocessing {} memo(s) from {} into {}\n",
+        records.len(),
+        path.display(),
+        output_dir.display()
+    );
+
+    let (mut notes_written, mut failed) = (0, 0);
+    for (i, record) in records.iter().enumerate() {
+        println!(
+            "#{} {} chat {} msg {}",
+            i + 1,
+            record.timestamp.format("%Y-%m-%d %H:%M"),
+            record.chat_id,
+            record.message_id
+        );
+
+        let agent = NoteGeneratorAgent::new(config)?.with_received_at(record.timestamp);
+        let generated = match agent.generate(record.raw_transcript.clone(), &[]).await {
+            Ok(r) => r,
+            Err(e) => {
+                println!("   ❌ [{}] {}\n", e.code(), e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        match agent.save_to(generated, &output_dir_str).await {
+            Ok(result) => {
+                for saved in &result.saved_paths {
+                    println!("   + {}", saved.display());
+                }
+                notes_written += result.saved_paths.len();
+                println!();
+            }
+            Err(e) => {
+                println!("   ❌ [{}] {}\n", e.code(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "Summary: {} memo(s), {} note(s) written, {} failed",
+        records.len(),
+        notes_written,
+        failed
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reprocess_options_parse() {
+        let args: Vec<String> = [
+            "--out",
+            "/tmp/cmp",
+            "--since",
+            "2024-05-01",
+            "--limit",
+            "3",
+            "t.jsonl",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let options = ReprocessOptions::parse(&args).unwrap();
+        assert_eq!(options.output_dir, Some(PathBuf::from("/tmp/cmp")));
+        assert_eq!(options.since, NaiveDate::from_ymd_opt(2024, 5, 1));
+        assert_eq!(options.limit, Some(3));
+        assert_eq!(options.transcripts_path, Some(PathBuf::from("t.jsonl")));
+
+        assert!(ReprocessOptions::parse(&["--since".to_string(), "ieri".to_string()]).is_err());
+        assert!(ReprocessOptions::parse(&["--out".to_string()]).is_err());
+    }
+}
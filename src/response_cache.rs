@@ -0,0 +1,187 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::ai_provider::{AiProvider, ChatRequest, ResponseFormat, TokenUpdates};
+use crate::config::Config;
+use crate::usage::TokenUsage;
+
+/// A cached answer and when the model gave it.
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    created_at: DateTime<Utc>,
+    response: String,
+}
+
+/// Model answers kept in `<data_dir>/llm_cache`, one file per request, so
+/// `/retry`, comparison runs and `eval` replays of an unchanged prompt don't
+/// wait for (or pay) the model again.
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    pub fn new<P: AsRef<Path>>(data_dir: P, ttl: Duration) -> Self {
+        Self {
+            dir: data_dir.as_ref().join("llm_cache"),
+            ttl,
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// The answer stored for `key`, unless it is older than the TTL (it is
+    /// then removed).
+    fn get(&self, key: &str) -> Option<String> {
+        let path = self.path(key);
+        let content = std::fs::read_to_string(&path).ok()?;
+        let cached: CachedResponse = serde_json::from_str(&content).ok()?;
+        let age = (Utc::now() - cached.created_at)
+            .to_std()
+            .unwrap_or_default();
+        if age > self.ttl {
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        Some(cached.response)
+    }
+
+    fn put(&self, key: &str, response: &str) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).context("Failed to create LLM cache directory")?;
+        let cached = CachedResponse {
+            created_at: Utc::now(),
+            response: response.to_string(),
+        };
+        let content = serde_json::to_string(&cached).context("Failed to serialize response")?;
+        std::fs::write(self.path(key), content).context("Failed to write LLM cache entry")
+    }
+}
+
+/// Cache key of `request` sent to `model`: every field that changes the answer.
+fn request_key(model: &str, request: &ChatRequest) -> String {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    model.hash(&mut hasher);
+    request.system_prompt.hash(&mut hasher);
+    request.user_prompt.hash(&mut hasher);
+    request.temperature.to_bits().hash(&mut hasher);
+    request.top_p.to_bits().hash(&mut hasher);
+    match &request.format {
+        ResponseFormat::Text => "text".hash(&mut hasher),
+        ResponseFormat::Json => "json".hash(&mut hasher),
+        ResponseFormat::Schema(schema) => schema.to_string().hash(&mut hasher),
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Provider answering repeated requests from a [`ResponseCache`].
+struct CachedProvider {
+    inner: Arc<dyn AiProvider>,
+    cache: ResponseCache,
+}
+
+impl CachedProvider {
+    /// The cached answer to `request`, or else the inner provider's
+    /// (streamed to `tokens`, if given), then cached.
+    async fn answer(&self, request: ChatRequest, tokens: Option<TokenUpdates>) -> Result<String> {
+        let key = request_key(self.inner.model(), &request);
+        if let Some(response) = self.cache.get(&key) {
+            log::info!("LLM cache hit for {} ({})", self.inner.model(), key);
+            return Ok(response);
+        }
+        let response = match tokens {
+            Some(tokens) => self.inner.chat_streaming(request, tokens).await?,
+            None => self.inner.chat(request).await?,
+        };
+        if let Err(e) = self.cache.put(&key, &response) {
+            log::warn!("Failed to cache LLM response: {}", e);
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait::async_trait]
+impl AiProvider for CachedProvider {
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn describe(&self) -> String {
+        self.inner.describe()
+    }
+
+    async fn is_available(&self) -> bool {
+        self.inner.is_available().await
+    }
+
+    /// Cache hits cost nothing, so only the inner provider's requests count.
+    fn usage(&self) -> TokenUsage {
+        self.inner.usage()
+    }
+
+    fn fallback_model(&self) -> Option<String> {
+        self.inner.fallback_model()
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<String> {
+        self.answer(request, None).await
+    }
+
+    async fn chat_streaming(&self, request: ChatRequest, tokens: TokenUpdates) -> Result<String> {
+        self.answer(request, Some(tokens)).await
+    }
+}
+
+/// `ai` with the response cache of `ai_model.cache_ttl_secs`, if enabled.
+pub fn with_cache(ai: Arc<dyn AiProvider>, config: &Config) -> Arc<dyn AiProvider> {
+    match config.ai_model.cache_ttl_secs {
+        Some(ttl) if ttl > 0 => Arc::new(CachedProvider {
+            inner: ai,
+            cache: ResponseCache::new(&config.output.data_dir, Duration::from_secs(ttl)),
+        }),
+        _ => ai,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(user_prompt: &str) -> ChatRequest {
+        ChatRequest {
+            system_prompt: "Sistema".to_string(),
+            user_prompt: user_prompt.to_string(),
+            temperature: 0.7,
+            top_p: 0.9,
+            format: ResponseFormat::Json,
+        }
+    }
+
+    #[test]
+    fn test_cache_keys_and_expiry() {
+        let key = request_key("llama3.2:3b", &request("Ciao"));
+        assert_eq!(key, request_key("llama3.2:3b", &request("Ciao")));
+        assert_ne!(key, request_key("mistral", &request("Ciao")));
+        assert_ne!(key, request_key("llama3.2:3b", &request("Ciao!")));
+
+        let dir = std::env::temp_dir().join(format!("dot-llm-cache-{}", uuid::Uuid::new_v4()));
+        let cache = ResponseCache::new(&dir, Duration::from_secs(60));
+        assert_eq!(cache.get(&key), None);
+        cache.put(&key, "{\"notes\": []}").unwrap();
+        assert_eq!(cache.get(&key).as_deref(), Some("{\"notes\": []}"));
+
+        let expired = ResponseCache::new(&dir, Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(expired.get(&key), None);
+        assert_eq!(cache.get(&key), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
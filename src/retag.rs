@@ -0,0 +1,258 @@
+//! `/retag`: rename a tag across the vault, in the frontmatter `tags:` and in
+//! inline `#tags`, the way Obsidian's tag rename does (nested tags follow:
+//! `#rust/async` becomes `#programming/async` when renaming `rust`).
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use crate::atomic_write::write_atomic;
+use crate::config::Config;
+use crate::tools::notes_reader::collect_notes;
+use crate::vault_lock;
+
+/// A tag as typed by the user, without `#`; `None` if it isn't a valid tag.
+pub fn parse_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#');
+    let valid = !tag.is_empty()
+        && !tag.starts_with('/')
+        && !tag.ends_with('/')
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '/')
+        && !tag.chars().all(|c| c.is_ascii_digit());
+    valid.then(|| tag.to_string())
+}
+
+/// Rename `old` to `new` in every note of the vault of `config`, skipping
+/// hidden folders and `output.ignore`. Returns the notes changed.
+pub async fn retag_vault(config: &Config, old: &str, new: &str) -> Result<Vec<PathBuf>> {
+    let dir = config.vault_dir();
+    let _lock = vault_lock::lock(&dir).await?;
+    let ignore = config.output.ignore.clone();
+    let fsync = config.output.fsync;
+    let (old, new) = (old.to_string(), new.to_string());
+    tokio::task::spawn_blocking(move || {
+        let notes = collect_notes(&dir, &ignore)
+            .with_context(|| format!("Failed to read {}", dir.display()))?;
+        let mut changed = Vec::new();
+        for path in notes {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            if let Some(content) = retag(&content, &old, &new) {
+                write_atomic(&path, &content, fsync)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                changed.push(path);
+            }
+        }
+        Ok(changed)
+    })
+    .await?
+}
+
+/// `content` with the tag renamed, or `None` if the note doesn't carry it.
+pub fn retag(content: &str, old: &str, new: &str) -> Option<String> {
+    let (frontmatter, body) = split_frontmatter(content);
+    let mut result = String::with_capacity(content.len());
+    if let Some(frontmatter) = frontmatter {
+        result.push_str(&retag_frontmatter(frontmatter, old, new));
+    }
+    result.push_str(&retag_body(body, old, new));
+    (result != content).then_some(result)
+}
+
+/// The frontmatter block (with its `---` lines) and the rest of the note.
+fn split_frontmatter(content: &str) -> (Option<&str>, &str) {
+    if !content.starts_with("---\n") {
+        return (None, content);
+    }
+    let mut offset = 4;
+    for line in content[4..].split_inclusive('\n') {
+        offset += line.len();
+        if line.trim_end() == "---" {
+            return (Some(&content[..offset]), &content[offset..]);
+        }
+    }
+    (None, content)
+}
+
+/// `tag` renamed if it is `old` or nested below it (case-insensitively, as
+/// Obsidian compares tags).
+fn rename(tag: &str, old: &str, new: &str) -> Option<String> {
+    let lower = tag.to_lowercase();
+    let old = old.to_lowercase();
+    if lower == old {
+        Some(new.to_string())
+    } else if lower.starts_with(&format!("{}/", old)) {
+        let nested: String = tag.chars().skip(old.chars().count()).collect();
+        Some(format!("{}{}", new, nested))
+    } else {
+        None
+    }
+}
+
+/// Rename the tag in the `tags:` list, as a block list or a flow list, and
+/// drop entries the rename made duplicate.
+fn retag_frontmatter(frontmatter: &str, old: &str, new: &str) -> String {
+    let mut result = String::with_capacity(frontmatter.len());
+    let mut in_tags = false;
+    let mut seen: Vec<String> = Vec::new();
+    for line in frontmatter.split_inclusive('\n') {
+        let text = line.trim_end_matches('\n');
+        if let Some(value) = text.strip_prefix("tags:") {
+            in_tags = value.trim().is_empty();
+            if !in_tags {
+                result.push_str(&format!("tags: {}\n", retag_flow_list(value, old, new)));
+                continue;
+            }
+        } else if in_tags && text.trim_start().starts_with('-') {
+            let item = text.trim_start()[1..].trim();
+            let (quote, tag) = unquote(item);
+            let tag = tag.trim_start_matches('#');
+            let renamed = rename(tag, old, new);
+            let tag = renamed.as_deref().unwrap_or(tag);
+            if seen.contains(&tag.to_lowercase()) {
+                continue;
+            }
+            seen.push(tag.to_lowercase());
+            if renamed.is_some() {
+                let indent = &text[..text.len() - text.trim_start().len()];
+                result.push_str(&format!("{}- {}{}{}\n", indent, quote, tag, quote));
+                continue;
+            }
+        } else if !text.starts_with(' ') {
+            in_tags = false;
+        }
+        result.push_str(line);
+    }
+    result
+}
+
+/// `tags: [a, b]` or `tags: a, b` with the tag renamed; unchanged if it
+/// isn't there.
+fn retag_flow_list(value: &str, old: &str, new: &str) -> String {
+    let value = value.trim();
+    let bracketed = value.starts_with('[') && value.ends_with(']');
+    let inner = if bracketed {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    };
+
+    let mut changed = false;
+    let mut seen: Vec<String> = Vec::new();
+    let mut items: Vec<String> = Vec::new();
+    for item in inner.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+        let (quote, tag) = unquote(item);
+        let tag = tag.trim_start_matches('#');
+        let renamed = rename(tag, old, new);
+        changed |= renamed.is_some();
+        let tag = renamed.as_deref().unwrap_or(tag);
+        if seen.contains(&tag.to_lowercase()) {
+            continue;
+        }
+        seen.push(tag.to_lowercase());
+        items.push(match renamed {
+            Some(_) => format!("{}{}{}", quote, tag, quote),
+            None => item.to_string(),
+        });
+    }
+    if !changed {
+        return value.to_string();
+    }
+    if bracketed {
+        format!("[{}]", items.join(", "))
+    } else {
+        items.join(", ")
+    }
+}
+
+/// The quote around a YAML scalar (empty if plain) and its content.
+fn unquote(item: &str) -> (&str, &str) {
+    for quote in ["\"", "'"] {
+        if item.len() >= 2 && item.starts_with(quote) && item.ends_with(quote) {
+            return (quote, &item[1..item.len() - 1]);
+        }
+    }
+    ("", item)
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || c == '/'
+}
+
+/// Rename inline `#tags` in the body, leaving code blocks and headings alone.
+fn retag_body(body: &str, old: &str, new: &str) -> String {
+    let mut result = String::with_capacity(body.len());
+    let mut in_code = false;
+    for line in body.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if in_code || !line.contains('#') {
+            result.push_str(line);
+            continue;
+        }
+
+        let mut prev: Option<char> = None;
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            let at_tag = c == '#' && prev.is_none_or(|p| p.is_whitespace() || p == '(');
+            if at_tag {
+                let tag_len = rest[1..]
+                    .find(|c: char| !is_tag_char(c))
+                    .unwrap_or(rest.len() - 1);
+                let tag = &rest[1..1 + tag_len];
+                if let Some(renamed) = rename(tag, old, new) {
+                    result.push('#');
+                    result.push_str(&renamed);
+                    rest = &rest[1 + tag_len..];
+                    prev = tag.chars().last();
+                    continue;
+                }
+            }
+            result.push(c);
+            rest = &rest[c.len_utf8()..];
+            prev = Some(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retag_frontmatter_and_inline_tags() {
+        let note = "---\n\
+            title: \"Lezione\"\n\
+            tags:\n  - Rust\n  - rust/async\n  - programming\n\
+            ---\n\n\
+            # Lezione #rust\n\n\
+            Appunti su #rust e #rustacean (vedi #Rust/async).\n\n\
+            ```\nlet tag = \"#rust\";\n```\n";
+
+        let retagged = retag(note, "rust", "programming").unwrap();
+        assert_eq!(
+            retagged,
+            "---\n\
+            title: \"Lezione\"\n\
+            tags:\n  - programming\n  - programming/async\n\
+            ---\n\n\
+            # Lezione #programming\n\n\
+            Appunti su #programming e #rustacean (vedi #programming/async).\n\n\
+            ```\nlet tag = \"#rust\";\n```\n"
+        );
+        assert!(retag(&retagged, "rust", "programming").is_none());
+
+        let flow = "---\ntags: [idea, \"#todo\"]\n---\nTesto\n";
+        assert_eq!(
+            retag(flow, "todo", "da-fare").unwrap(),
+            "---\ntags: [idea, \"da-fare\"]\n---\nTesto\n"
+        );
+
+        assert_eq!(parse_tag("#progetti/dot").as_deref(), Some("progetti/dot"));
+        assert!(parse_tag("2024").is_none());
+        assert!(parse_tag("due parole").is_none());
+    }
+}
@@ -0,0 +1,105 @@
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::time::Duration;
+
+use crate::config::RetryConfig;
+
+/// Send the request made by `build`, retrying connection failures, `429 Too
+/// Many Requests` and 5xx answers with exponential backoff as configured in
+/// `policy`. Any other answer, and the last failure, is returned as is.
+///
+/// `build` is called once per try, since multipart bodies can't be reused.
+pub async fn send_with_backoff(
+    policy: &RetryConfig,
+    service: &str,
+    build: impl Fn() -> RequestBuilder,
+) -> reqwest::Result<Response> {
+    let attempts = policy.attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        let result = build().send().await;
+        let retry_after = match &result {
+            Ok(response) if is_transient(response.status()) => retry_after(response),
+            Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => None,
+            _ => return result,
+        };
+        if attempt >= attempts {
+            return result;
+        }
+
+        let delay = backoff_delay(policy, attempt, retry_after);
+        match &result {
+            Ok(response) => log::warn!(
+                "{} answered {}, retrying in {:.1}s ({}/{})",
+                service,
+                response.status(),
+                delay.as_secs_f32(),
+                attempt,
+                attempts - 1
+            ),
+            Err(e) => log::warn!(
+                "{} request failed ({}), retrying in {:.1}s ({}/{})",
+                service,
+                e,
+                delay.as_secs_f32(),
+                attempt,
+                attempts - 1
+            ),
+        }
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Answers worth another try: rate limits and server-side failures.
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The wait a `Retry-After` header asks for, when given in seconds.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?;
+    let secs: f64 = value.to_str().ok()?.trim().parse().ok()?;
+    (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+}
+
+/// Wait before try `attempt + 1`: the server's `Retry-After` if it sent one,
+/// otherwise `backoff_ms` doubled for each earlier retry, capped at
+/// `max_backoff_secs`.
+fn backoff_delay(policy: &RetryConfig, attempt: u32, retry_after: Option<Duration>) -> Duration {
+    let max = Duration::from_secs(policy.max_backoff_secs);
+    let delay = retry_after.unwrap_or_else(|| {
+        let factor = 1u64 << (attempt - 1).min(16);
+        Duration::from_millis(policy.backoff_ms.saturating_mul(factor))
+    });
+    delay.min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let policy = RetryConfig {
+            attempts: 5,
+            backoff_ms: 500,
+            max_backoff_secs: 3,
+        };
+        let delays: Vec<_> = (1..=4)
+            .map(|attempt| backoff_delay(&policy, attempt, None))
+            .collect();
+        assert_eq!(delays, [500, 1000, 2000, 3000].map(Duration::from_millis));
+        assert_eq!(
+            backoff_delay(&policy, 1, Some(Duration::from_secs(2))),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            backoff_delay(&policy, 1, Some(Duration::from_secs(60))),
+            Duration::from_secs(3)
+        );
+
+        assert!(is_transient(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_transient(StatusCode::BAD_GATEWAY));
+        assert!(!is_transient(StatusCode::UNAUTHORIZED));
+    }
+}
@@ -0,0 +1,145 @@
+//! Subfolders of the notes directory picked by `[output.routing]` rules, so
+//! a large vault isn't one flat folder.
+//!
+//! A rule maps a tag pattern or a keyword to a folder: `"work/*" = "Work"`
+//! routes the notes tagged `work/…`, `ricetta = "Cucina"` those tagged
+//! `ricetta` or with the word in their title. When several rules match, the
+//! longest pattern wins.
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::note_generator::Note;
+
+/// The routing rules of a vault.
+#[derive(Debug, Clone, Default)]
+pub struct Routing {
+    /// Lowercase patterns and their folders, most specific first.
+    rules: Vec<(String, String)>,
+}
+
+impl Routing {
+    /// Routing by `rules`; rules whose folder leaves the notes directory are
+    /// ignored.
+    pub fn new(rules: &BTreeMap<String, String>) -> Self {
+        let mut rules: Vec<(String, String)> = rules
+            .iter()
+            .filter_map(|(pattern, folder)| {
+                let folder = folder.trim_matches('/');
+                let inside = Path::new(folder)
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)));
+                if folder.is_empty() || !inside {
+                    log::warn!("Ignoring routing rule {} → {}", pattern, folder);
+                    return None;
+                }
+                let pattern = pattern.trim().trim_start_matches('#').to_lowercase();
+                Some((pattern, folder.to_string()))
+            })
+            .collect();
+        rules.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+        Self { rules }
+    }
+
+    /// The folder `note` goes to, relative to the notes directory.
+    pub fn folder_for(&self, note: &Note) -> Option<&str> {
+        let tags: Vec<String> = note
+            .tags
+            .iter()
+            .map(|tag| tag.trim_start_matches('#').to_lowercase())
+            .collect();
+        let title = note.title.to_lowercase();
+        let words: Vec<&str> = title.split(|c: char| !c.is_alphanumeric()).collect();
+        self.rules
+            .iter()
+            .find(|(pattern, _)| {
+                tags.iter().any(|tag| glob_match(pattern, tag))
+                    || (!pattern.contains('*') && words.contains(&pattern.as_str()))
+            })
+            .map(|(_, folder)| folder.as_str())
+    }
+
+    /// The directory `note` is saved in.
+    pub fn note_dir(&self, notes_dir: &Path, note: &Note) -> PathBuf {
+        match self.folder_for(note) {
+            Some(folder) => notes_dir.join(folder),
+            None => notes_dir.to_path_buf(),
+        }
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` stands for any characters.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*`: the whole text must match
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str, tags: &[&str]) -> Note {
+        Note {
+            title: title.to_string(),
+            content: String::new(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            date: chrono::Utc::now(),
+            source: "voice-memo".to_string(),
+            related_notes: vec![],
+            id: None,
+            language: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+            telegram_link: None,
+            properties: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_routes_by_tag_pattern_and_keyword() {
+        let routing = Routing::new(&BTreeMap::from([
+            ("work/*".to_string(), "Work/".to_string()),
+            ("work/acme/*".to_string(), "Work/Acme".to_string()),
+            ("ricetta".to_string(), "Cucina".to_string()),
+            ("spesa".to_string(), "../fuori".to_string()),
+        ]));
+
+        assert_eq!(
+            routing.folder_for(&note("Budget", &["work/finance"])),
+            Some("Work")
+        );
+        assert_eq!(
+            routing.folder_for(&note("Kickoff", &["#Work/Acme/kickoff"])),
+            Some("Work/Acme")
+        );
+        assert_eq!(
+            routing.folder_for(&note("Ricetta della carbonara", &[])),
+            Some("Cucina")
+        );
+        assert_eq!(routing.folder_for(&note("Ricettario", &["work"])), None);
+        assert_eq!(routing.folder_for(&note("Lista spesa", &["spesa"])), None);
+        assert_eq!(
+            routing.note_dir(Path::new("notes"), &note("Pasta", &["ricetta"])),
+            Path::new("notes/Cucina")
+        );
+
+        assert!(glob_match("a*c*e", "abcde"));
+        assert!(!glob_match("a*a", "a"));
+    }
+}
@@ -0,0 +1,251 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::tools::{NotesReader, Tool};
+
+/// How long a scanned index is reused before the notes folder is read again.
+const INDEX_TTL: Duration = Duration::from_secs(60);
+/// Queries whose results are kept per index; inline queries repeat a lot
+/// while the user types.
+const MAX_CACHED_QUERIES: usize = 256;
+const MAX_RESULTS: usize = 20;
+const EXCERPT_CHARS: usize = 200;
+
+/// A note matching a search.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub title: String,
+    pub excerpt: String,
+    pub path: PathBuf,
+}
+
+struct IndexedNote {
+    title: String,
+    date: String,
+    tags: Vec<String>,
+    path: PathBuf,
+    body: String,
+}
+
+struct Index {
+    built: Instant,
+    notes: Vec<IndexedNote>,
+    results: HashMap<String, Vec<SearchHit>>,
+}
+
+/// Full-text search over the notes index, with the index and recent results
+/// cached between queries. Each notes folder (one per vault) has its own index.
+pub struct NoteSearch {
+    indexes: Mutex<HashMap<String, Index>>,
+}
+
+impl NoteSearch {
+    pub fn new() -> Self {
+        Self {
+            indexes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Notes in `notes_dir` matching every word of `query`, best first. An
+    /// empty query lists the most recent notes.
+    pub async fn search(&self, notes_dir: &str, query: &str) -> Result<Vec<SearchHit>> {
+        let query = query
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+
+        let mut indexes = self.indexes.lock().await;
+        let stale = indexes
+            .get(notes_dir)
+            .is_none_or(|i| i.built.elapsed() > INDEX_TTL);
+        if stale {
+            let index = Index {
+                built: Instant::now(),
+                notes: load_notes(notes_dir).await?,
+                results: HashMap::new(),
+            };
+            indexes.insert(notes_dir.to_string(), index);
+        }
+        let index = indexes.get_mut(notes_dir).expect("index was just built");
+
+        if let Some(hits) = index.results.get(&query) {
+            return Ok(hits.clone());
+        }
+        let hits = rank(&index.notes, &query);
+        if index.results.len() >= MAX_CACHED_QUERIES {
+            index.results.clear();
+        }
+        index.results.insert(query, hits.clone());
+        Ok(hits)
+    }
+
+    /// Drop the cached indexes, after notes were rewritten in place.
+    pub async fn invalidate(&self) {
+        self.indexes.lock().await.clear();
+    }
+}
+
+async fn load_notes(notes_dir: &str) -> Result<Vec<IndexedNote>> {
+    let metas = NotesReader::new().run(notes_dir.to_string()).await?;
+    let dir = PathBuf::from(notes_dir);
+    let notes = tokio::task::spawn_blocking(move || {
+        metas
+            .into_iter()
+            .map(|meta| {
+                let path = dir.join(&meta.folder).join(&meta.filename);
+                let body = std::fs::read_to_string(&path)
+                    .map(|content| strip_frontmatter(&content).to_string())
+                    .unwrap_or_default();
+                IndexedNote {
+                    title: meta.title,
+                    date: meta.date,
+                    tags: meta.tags,
+                    path,
+                    body,
+                }
+            })
+            .collect()
+    })
+    .await?;
+    Ok(notes)
+}
+
+fn rank(notes: &[IndexedNote], query: &str) -> Vec<SearchHit> {
+    let terms: Vec<&str> = query.split(' ').filter(|t| !t.is_empty()).collect();
+    let mut scored: Vec<(usize, &IndexedNote)> = notes
+        .iter()
+        .filter_map(|note| score(note, &terms).map(|s| (s, note)))
+        .collect();
+    // Best score first, newer notes breaking ties (and ordering empty queries)
+    scored.sort_by(|(sa, a), (sb, b)| sb.cmp(sa).then_with(|| b.date.cmp(&a.date)));
+
+    scored
+        .into_iter()
+        .take(MAX_RESULTS)
+        .map(|(_, note)| SearchHit {
+            title: note.title.clone(),
+            excerpt: excerpt(&note.body, &terms),
+            path: note.path.clone(),
+        })
+        .collect()
+}
+
+/// Title matches weigh most, then tags, then the body. `None` unless every
+/// term matches somewhere.
+fn score(note: &IndexedNote, terms: &[&str]) -> Option<usize> {
+    let title = note.title.to_lowercase();
+    let body = note.body.to_lowercase();
+    terms.iter().try_fold(0, |total, term| {
+        let in_tags = note.tags.iter().any(|t| t.to_lowercase().contains(term));
+        let points = 3 * usize::from(title.contains(term))
+            + 2 * usize::from(in_tags)
+            + usize::from(body.contains(term));
+        (points > 0).then_some(total + points)
+    })
+}
+
+/// The first body line mentioning a term (or the first line), without
+/// Markdown markers and cut to `EXCERPT_CHARS`.
+fn excerpt(body: &str, terms: &[&str]) -> String {
+    let lines: Vec<&str> = body
+        .lines()
+        .map(|l| l.trim_start_matches(['#', '-', '*', '>', ' ']).trim())
+        .filter(|l| !l.is_empty())
+        .collect();
+    let line = lines
+        .iter()
+        .find(|l| {
+            let lower = l.to_lowercase();
+            terms.iter().any(|t| lower.contains(t))
+        })
+        .or(lines.first())
+        .copied()
+        .unwrap_or_default();
+
+    if line.chars().count() > EXCERPT_CHARS {
+        let cut: String = line.chars().take(EXCERPT_CHARS).collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        line.to_string()
+    }
+}
+
+/// Note body after the YAML frontmatter, if any.
+pub fn strip_frontmatter(content: &str) -> &str {
+    let trimmed = content.trim_start();
+    let Some(rest) = trimmed.strip_prefix("---") else {
+        return content;
+    };
+    match rest.find("\n---") {
+        Some(end) => rest[end + 4..]
+            .trim_start_matches(|c| c != '\n')
+            .trim_start(),
+        None => content,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_search_ranks_title_over_body_and_caches() {
+        let dir = std::env::temp_dir().join(format!("dot-search-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("Ownership.md"),
+            "---\ntitle: \"Ownership in Rust\"\ndate: 2024-05-01\ntags:\n  - rust\n---\n\n\
+            # Ownership in Rust\n\nOgni valore ha un solo proprietario.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("Borrow.md"),
+            "---\ntitle: \"Borrow checker\"\ndate: 2024-05-02\n---\n\n\
+            Il borrow checker applica le regole di ownership.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("Spesa.md"),
+            "---\ntitle: \"Spesa\"\ndate: 2024-05-03\n---\n\nLatte e pane\n",
+        )
+        .unwrap();
+
+        let search = NoteSearch::new();
+        let notes_dir = dir.to_string_lossy().to_string();
+        let hits = search
+            .search(&notes_dir, "  Rust   ownership ")
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].title, "Ownership in Rust");
+        assert_eq!(hits[0].excerpt, "Ownership in Rust");
+
+        let hits = search.search(&notes_dir, "ownership").await.unwrap();
+        let titles: Vec<&str> = hits.iter().map(|h| h.title.as_str()).collect();
+        assert_eq!(titles, vec!["Ownership in Rust", "Borrow checker"]);
+        assert_eq!(
+            hits[1].excerpt,
+            "Il borrow checker applica le regole di ownership."
+        );
+
+        // Empty queries list the newest notes first
+        let hits = search.search(&notes_dir, "").await.unwrap();
+        assert_eq!(hits[0].title, "Spesa");
+
+        // Other vaults get their own index
+        let other = dir.join("Lavoro");
+        std::fs::create_dir_all(&other).unwrap();
+        let other_dir = other.to_string_lossy().to_string();
+        assert!(search.search(&other_dir, "latte").await.unwrap().is_empty());
+
+        // Results come from the cached index until it expires
+        std::fs::remove_file(dir.join("Spesa.md")).unwrap();
+        assert_eq!(search.search(&notes_dir, "latte").await.unwrap().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use teloxide::types::Message;
+
+use crate::note_generator::AgentResult;
+
+/// Prefix of the inline-button callback data used to pick a candidate.
+const CALLBACK_PREFIX: &str = "pick";
+/// How long the buttons stay usable: past it, the candidates are dropped.
+const SELECTION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Selections kept at most; the oldest are dropped first.
+const MAX_PENDING: usize = 100;
+
+struct Pending {
+    /// The memo the candidates were generated from.
+    memo: Message,
+    candidates: Vec<AgentResult>,
+    created: Instant,
+}
+
+/// Candidates from the comparison models waiting for the user to pick one.
+///
+/// Nothing is saved to the vault until a choice arrives; pending entries live
+/// in memory only, are lost on restart and expire after [`SELECTION_TTL`].
+#[derive(Default)]
+pub struct PendingSelections {
+    next_key: AtomicU64,
+    pending: Mutex<HashMap<u64, Pending>>,
+}
+
+impl PendingSelections {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store candidates and return the key to embed in the buttons.
+    ///
+    /// Expired selections are dropped, and the oldest ones past
+    /// [`MAX_PENDING`].
+    pub fn insert(&self, memo: Message, candidates: Vec<AgentResult>) -> u64 {
+        let key = self.next_key.fetch_add(1, Ordering::Relaxed);
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, p| p.created.elapsed() <= SELECTION_TTL);
+        if pending.len() >= MAX_PENDING {
+            // Keys grow with time, so the smallest are the oldest
+            let mut keys: Vec<u64> = pending.keys().copied().collect();
+            keys.sort_unstable();
+            for old in &keys[..=keys.len() - MAX_PENDING] {
+                pending.remove(old);
+            }
+        }
+        pending.insert(
+            key,
+            Pending {
+                memo,
+                candidates,
+                created: Instant::now(),
+            },
+        );
+        key
+    }
+
+    /// Take the chosen candidate and its memo, dropping the other candidates.
+    ///
+    /// Returns `None` if the selection was already made, expired or is unknown.
+    pub fn take(&self, key: u64, index: usize) -> Option<(Message, AgentResult)> {
+        let Pending {
+            memo,
+            mut candidates,
+            created,
+        } = self.pending.lock().unwrap().remove(&key)?;
+        (created.elapsed() <= SELECTION_TTL && index < candidates.len())
+            .then(|| (memo, candidates.swap_remove(index)))
+    }
+
+    /// Callback data for the button choosing `index` of selection `key`.
+    pub fn callback_data(key: u64, index: usize) -> String {
+        format!("{}:{}:{}", CALLBACK_PREFIX, key, index)
+    }
+
+    /// Parse callback data produced by [`PendingSelections::callback_data`].
+    pub fn parse_callback_data(data: &str) -> Option<(u64, usize)> {
+        let mut parts = data.split(':');
+        if parts.next()? != CALLBACK_PREFIX {
+            return None;
+        }
+        let key = parts.next()?.parse().ok()?;
+        let index = parts.next()?.parse().ok()?;
+        parts.next().is_none().then_some((key, index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memo() -> Message {
+        serde_json::from_value(serde_json::json!({
+            "message_id": 7,
+            "date": 1715689800,
+            "chat": { "id": 42, "type": "private", "first_name": "Ada" },
+            "text": "memo",
+        }))
+        .unwrap()
+    }
+
+    fn result(model: &str) -> AgentResult {
+        AgentResult {
+            notes: vec![],
+            saved_paths: vec![],
+            cleaned_transcript: String::new(),
+            raw_transcript: String::new(),
+            model: model.to_string(),
+            fallback_from: None,
+            timings: Default::default(),
+            truncation: None,
+            usage: Default::default(),
+            tasks: vec![],
+        }
+    }
+
+    #[test]
+    fn test_take_selected_candidate_once() {
+        let pending = PendingSelections::new();
+        let key = pending.insert(memo(), vec![result("llama3"), result("qwen2.5")]);
+
+        let data = PendingSelections::callback_data(key, 1);
+        assert_eq!(
+            PendingSelections::parse_callback_data(&data),
+            Some((key, 1))
+        );
+        assert_eq!(PendingSelections::parse_callback_data("other:1:0"), None);
+
+        let (memo, chosen) = pending.take(key, 1).unwrap();
+        assert_eq!((memo.id.0, chosen.model.as_str()), (7, "qwen2.5"));
+        assert!(pending.take(key, 0).is_none());
+    }
+
+    #[test]
+    fn test_old_selections_are_dropped() {
+        let pending = PendingSelections::new();
+        let expired = pending.insert(memo(), vec![result("llama3")]);
+        let Some(past) = Instant::now().checked_sub(SELECTION_TTL * 2) else {
+            return;
+        };
+        pending
+            .pending
+            .lock()
+            .unwrap()
+            .get_mut(&expired)
+            .unwrap()
+            .created = past;
+        assert!(pending.take(expired, 0).is_none());
+
+        let keys: Vec<u64> = (0..=MAX_PENDING)
+            .map(|_| pending.insert(memo(), vec![result("llama3")]))
+            .collect();
+        assert_eq!(pending.pending.lock().unwrap().len(), MAX_PENDING);
+        assert!(pending.take(keys[0], 0).is_none());
+        assert!(pending.take(keys[MAX_PENDING], 0).is_some());
+    }
+}
@@ -0,0 +1,129 @@
+//! Running as a systemd service (`Type=notify`): readiness once the startup
+//! self-check passed, watchdog pings, and a graceful stop on SIGTERM. Outside
+//! systemd (no `NOTIFY_SOCKET`) the notifications are no-ops.
+
+use anyhow::{Context, Result};
+use std::time::Duration;
+
+use crate::ai_provider;
+use crate::config::Config;
+use crate::ollama;
+use crate::tools::NoteWriter;
+use crate::transcription;
+
+/// Check that memos can be processed before telling systemd the bot is up:
+/// providers are configured, Ollama has the model, and the vault is writable.
+pub async fn self_check(config: &Config) -> Result<()> {
+    transcription::create_transcription_provider(&config.transcription)
+        .context("Self-check: transcription provider")?;
+    // The provider already fell back to a hosted one; say so once
+    if config.transcription.provider == "whisper_local" && transcription::local_backend().is_none()
+    {
+        log::warn!("Self-check: built without local Whisper (--features metal, cuda or cpu), using a hosted provider");
+    }
+    let ai =
+        ai_provider::create_ai_provider(&config.ai_model).context("Self-check: AI provider")?;
+    NoteWriter::preflight(&config.output.notes_dir, config.output.min_free_space_mb)
+        .context("Self-check: notes directory")?;
+
+    // Memos degrade to unprocessed notes without the model, so this only warns
+    if config.ai_model.enabled() && !ai.is_available().await {
+        log::warn!("Self-check: {} is not reachable", ai.describe());
+    }
+    ollama::ensure_models(&config.ai_model)
+        .await
+        .context("Self-check: AI model")?;
+    Ok(())
+}
+
+/// Tell systemd the bot is up and serving.
+pub fn ready(status: &str) {
+    #[cfg(unix)]
+    notify(&[
+        sd_notify::NotifyState::Ready,
+        sd_notify::NotifyState::Status(status),
+    ]);
+    #[cfg(not(unix))]
+    let _ = status;
+}
+
+/// Tell systemd the bot is shutting down.
+pub fn stopping() {
+    #[cfg(unix)]
+    notify(&[
+        sd_notify::NotifyState::Stopping,
+        sd_notify::NotifyState::Status("Finishing the memos in progress"),
+    ]);
+}
+
+#[cfg(unix)]
+fn notify(states: &[sd_notify::NotifyState]) {
+    if let Err(e) = sd_notify::notify(false, states) {
+        log::warn!("Failed to notify systemd: {}", e);
+    }
+}
+
+/// Ping the systemd watchdog (with `WatchdogSec=` in the unit) from the
+/// runtime the dispatcher runs on, so a stalled runtime gets the bot
+/// restarted. No-op without a watchdog.
+pub fn spawn_watchdog() {
+    #[cfg(unix)]
+    {
+        let mut usec = 0;
+        if !sd_notify::watchdog_enabled(false, &mut usec) {
+            return;
+        }
+        let interval = ping_interval(Duration::from_micros(usec));
+        log::info!("systemd watchdog: pinging every {:?}", interval);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                notify(&[sd_notify::NotifyState::Watchdog]);
+            }
+        });
+    }
+}
+
+/// Pings twice per watchdog timeout, as systemd recommends.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn ping_interval(timeout: Duration) -> Duration {
+    (timeout / 2).max(Duration::from_millis(100))
+}
+
+/// Wait for SIGTERM (systemd stopping the unit) or Ctrl+C.
+pub async fn terminated() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = sigterm.recv() => log::info!("SIGTERM received"),
+                    _ = tokio::signal::ctrl_c() => log::info!("Ctrl+C received"),
+                }
+                return;
+            }
+            Err(e) => log::warn!("Failed to listen for SIGTERM: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+    log::info!("Ctrl+C received");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_is_pinged_twice_per_timeout() {
+        assert_eq!(
+            ping_interval(Duration::from_secs(30)),
+            Duration::from_secs(15)
+        );
+        assert_eq!(
+            ping_interval(Duration::from_millis(50)),
+            Duration::from_millis(100)
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use teloxide::prelude::*;
+
+use crate::telegram::Bot;
+
+/// Serializes appends and rewrites of the link log within the process.
+static LOG_LOCK: Mutex<()> = Mutex::new(());
+
+/// The Telegram message a memo was sent in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMessage {
+    pub chat_id: i64,
+    pub message_id: i32,
+    /// Link opening the message in Telegram, see [`message_link`].
+    pub link: Option<String>,
+}
+
+impl SourceMessage {
+    pub fn of(bot: &Bot, msg: &Message) -> Self {
+        Self {
+            chat_id: msg.chat.id.0,
+            message_id: msg.id.0,
+            link: message_link(msg, bot_id(bot)),
+        }
+    }
+}
+
+/// Link opening `msg` in Telegram: the t.me link of messages in groups and
+/// channels, or for private chats a `tg://openmessage` link into the chat
+/// with the bot (opened by Telegram Desktop and Android). Messages in basic
+/// groups have no link.
+pub fn message_link(msg: &Message, bot_id: Option<u64>) -> Option<String> {
+    if let Some(url) = msg.url() {
+        return Some(url.to_string());
+    }
+    let bot_id = bot_id.filter(|_| msg.chat.is_private())?;
+    Some(format!(
+        "tg://openmessage?user_id={}&message_id={}",
+        bot_id, msg.id.0
+    ))
+}
+
+/// The bot's user ID, which is the part of its token before the colon.
+fn bot_id(bot: &Bot) -> Option<u64> {
+    bot.inner().token().split(':').next()?.parse().ok()
+}
+
+/// A saved note and the message it came from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteSource {
+    pub path: PathBuf,
+    pub title: String,
+    pub chat_id: i64,
+    pub message_id: i32,
+}
+
+/// Reverse map from saved notes to their Telegram messages, kept in
+/// `<data_dir>/source_links.jsonl`, so a note deleted from the vault can be
+/// reported in the chat it came from.
+pub struct SourceLinkLog {
+    path: PathBuf,
+}
+
+impl SourceLinkLog {
+    pub fn new<P: AsRef<Path>>(data_dir: P) -> Self {
+        Self {
+            path: data_dir.as_ref().join("source_links.jsonl"),
+        }
+    }
+
+    pub fn append(&self, sources: &[NoteSource]) -> Result<()> {
+        let _guard = LOG_LOCK.lock().unwrap();
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create data directory")?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open source links: {}", self.path.display()))?;
+        for source in sources {
+            let line = serde_json::to_string(source).context("Failed to serialize source link")?;
+            writeln!(file, "{}", line).context("Failed to write source link")?;
+        }
+        Ok(())
+    }
+
+    /// Remove and return the entries whose note no longer exists.
+    pub fn take_missing(&self) -> Result<Vec<NoteSource>> {
+        let _guard = LOG_LOCK.lock().unwrap();
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", self.path.display()));
+            }
+        };
+
+        let (kept, missing): (Vec<NoteSource>, Vec<NoteSource>) = content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .partition(|source: &NoteSource| source.path.exists());
+        if missing.is_empty() {
+            return Ok(missing);
+        }
+
+        let mut rewritten = String::new();
+        for source in &kept {
+            rewritten.push_str(&serde_json::to_string(source)?);
+            rewritten.push('\n');
+        }
+        std::fs::write(&self.path, rewritten)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        Ok(missing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_missing_returns_deleted_notes_once() {
+        let dir = std::env::temp_dir().join(format!("dot-links-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = |name: &str, message_id| NoteSource {
+            path: dir.join(format!("{}.md", name)),
+            title: name.to_string(),
+            chat_id: 42,
+            message_id,
+        };
+        std::fs::write(dir.join("Tenuta.md"), "").unwrap();
+        std::fs::write(dir.join("Eliminata.md"), "").unwrap();
+
+        let log = SourceLinkLog::new(&dir);
+        log.append(&[source("Tenuta", 1), source("Eliminata", 2)])
+            .unwrap();
+        assert!(log.take_missing().unwrap().is_empty());
+
+        std::fs::remove_file(dir.join("Eliminata.md")).unwrap();
+        assert_eq!(log.take_missing().unwrap(), vec![source("Eliminata", 2)]);
+        assert!(log.take_missing().unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::tools::NoteMeta;
+
+/// Notes a pair of tags must share before one suggests the other.
+const MIN_CO_OCCURRENCES: usize = 3;
+/// Share of a tag's notes that must also carry the suggested tag.
+const MIN_CONFIDENCE: f32 = 0.5;
+/// Most tags added to a note by suggestion.
+const MAX_SUGGESTED: usize = 2;
+
+/// Tag usage and co-occurrence in the existing vault, used to steer the tags
+/// proposed by the LLM towards the user's own taxonomy.
+#[derive(Debug, Default)]
+pub struct TagModel {
+    /// Vault spelling of each tag, keyed by its lowercase form.
+    spelling: HashMap<String, String>,
+    /// Notes carrying each tag.
+    counts: HashMap<String, usize>,
+    /// Notes carrying both tags, for each ordered pair.
+    pairs: HashMap<(String, String), usize>,
+}
+
+impl TagModel {
+    pub fn from_notes(notes: &[NoteMeta]) -> Self {
+        let mut model = Self::default();
+        for note in notes {
+            let mut tags: Vec<String> = Vec::new();
+            for tag in &note.tags {
+                let tag = tag.trim().trim_start_matches('#');
+                let key = tag.to_lowercase();
+                if key.is_empty() || tags.contains(&key) {
+                    continue;
+                }
+                model
+                    .spelling
+                    .entry(key.clone())
+                    .or_insert_with(|| tag.to_string());
+                tags.push(key);
+            }
+            for a in &tags {
+                *model.counts.entry(a.clone()).or_default() += 1;
+                for b in &tags {
+                    if a != b {
+                        *model.pairs.entry((a.clone(), b.clone())).or_default() += 1;
+                    }
+                }
+            }
+        }
+        model
+    }
+
+    /// Keep the proposed tags the vault already uses (in its spelling) and
+    /// add tags that usually go with them.
+    ///
+    /// When none of the proposed tags is known the note is about a new topic,
+    /// so its tags are kept as they are. An empty vault changes nothing.
+    pub fn refine(&self, proposed: &[String]) -> Vec<String> {
+        if self.counts.is_empty() {
+            return proposed.to_vec();
+        }
+
+        let mut known: Vec<String> = Vec::new();
+        for tag in proposed {
+            let key = tag.to_lowercase();
+            if self.counts.contains_key(&key) && !known.contains(&key) {
+                known.push(key);
+            }
+        }
+        if known.is_empty() {
+            return proposed.to_vec();
+        }
+
+        let mut suggested: Vec<(&String, f32)> = Vec::new();
+        for ((a, b), &together) in &self.pairs {
+            if !known.contains(a) || known.contains(b) || together < MIN_CO_OCCURRENCES {
+                continue;
+            }
+            let confidence = together as f32 / self.counts[a] as f32;
+            if confidence < MIN_CONFIDENCE {
+                continue;
+            }
+            match suggested.iter_mut().find(|(tag, _)| *tag == b) {
+                Some((_, best)) => *best = best.max(confidence),
+                None => suggested.push((b, confidence)),
+            }
+        }
+        suggested.sort_by(|x, y| y.1.total_cmp(&x.1).then_with(|| x.0.cmp(y.0)));
+
+        known
+            .iter()
+            .chain(
+                suggested
+                    .into_iter()
+                    .take(MAX_SUGGESTED)
+                    .map(|(tag, _)| tag),
+            )
+            .map(|key| self.spelling[key].clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(tags: &[&str]) -> NoteMeta {
+        NoteMeta {
+            title: String::new(),
+            date: String::new(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            filename: String::new(),
+            source: String::new(),
+            id: None,
+            aliases: Vec::new(),
+            links: Vec::new(),
+            folder: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_refine_converges_on_vault_taxonomy() {
+        let vault = vec![
+            note(&["Rust", "programming"]),
+            note(&["rust", "programming"]),
+            note(&["rust", "programming", "cli"]),
+            note(&["rust"]),
+            note(&["cucina"]),
+        ];
+        let model = TagModel::from_notes(&vault);
+
+        let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        // Unknown tags are dropped, known ones take the vault spelling and
+        // frequent companions are added
+        assert_eq!(
+            model.refine(&tags(&["RUST", "borrow-checker"])),
+            vec!["Rust", "programming"]
+        );
+        // A note on a new topic keeps its own tags
+        assert_eq!(model.refine(&tags(&["viaggi"])), vec!["viaggi"]);
+        assert_eq!(TagModel::default().refine(&tags(&["rust"])), vec!["rust"]);
+    }
+}
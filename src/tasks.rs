@@ -0,0 +1,110 @@
+//! Tasks found by `enable_task_extraction`, appended as Obsidian Tasks
+//! checkboxes to a file per day in `output.tasks_dir` (or to one
+//! `output.tasks_file`), each linking back to the note it came from.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+use crate::atomic_write::write_atomic;
+use crate::dates::DateResolver;
+
+/// Where the tasks of a memo sent `at` go: `<tasks_dir>/<tasks_file>`, or
+/// `<tasks_dir>/YYYY-MM-DD.md` for the memo's day.
+pub fn tasks_path(
+    tasks_dir: &Path,
+    tasks_file: Option<&str>,
+    resolver: &DateResolver,
+    at: DateTime<Utc>,
+) -> PathBuf {
+    match tasks_file {
+        Some(file) => tasks_dir.join(file),
+        None => tasks_dir.join(format!("{}.md", resolver.local_date(at).format("%Y-%m-%d"))),
+    }
+}
+
+/// Append `tasks` to `path` as open checkboxes linking to the note `source`
+/// (a filename stem), with due dates for their date phrases.
+///
+/// The caller must hold the vault lock.
+pub fn record(
+    path: &Path,
+    tasks: &[String],
+    source: Option<&str>,
+    resolver: &DateResolver,
+    at: DateTime<Utc>,
+    fsync: bool,
+) -> Result<()> {
+    if tasks.is_empty() {
+        return Ok(());
+    }
+    let mut content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).context("Failed to create tasks folder")?;
+            }
+            let title = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy())
+                .unwrap_or_default();
+            format!("# {}\n\n", title)
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    for task in tasks {
+        // Due dates go last, where the Tasks plugin looks for them
+        let line = match source {
+            Some(source) => format!("- [ ] {} ([[{}]])", task, source),
+            None => format!("- [ ] {}", task),
+        };
+        content.push_str(&resolver.annotate_tasks(&line, at));
+        content.push('\n');
+    }
+    write_atomic(path, &content, fsync)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    log::info!(
+        "Tasks: recorded {} task(s) in {}",
+        tasks.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_appends_checkboxes_to_the_day_file() {
+        let dir = std::env::temp_dir().join(format!("dot-tasks-{}", uuid::Uuid::new_v4()));
+        let resolver = DateResolver::new(chrono_tz::Europe::Rome);
+        // Late evening in Rome is already the next day in the file name
+        let at = DateTime::parse_from_rfc3339("2024-05-15T22:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let path = tasks_path(&dir, None, &resolver, at);
+        assert_eq!(path, dir.join("2024-05-16.md"));
+        assert_eq!(
+            tasks_path(&dir, Some("Tasks.md"), &resolver, at),
+            dir.join("Tasks.md")
+        );
+
+        let tasks = vec!["Chiamare Marco domani".to_string()];
+        record(&path, &tasks, Some("Preventivo"), &resolver, at, false).unwrap();
+        let milk = ["Comprare il latte".to_string()];
+        record(&path, &milk, None, &resolver, at, false).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# 2024-05-16\n\n\
+            - [ ] Chiamare Marco domani ([[Preventivo]]) 📅 2024-05-17\n\
+            - [ ] Comprare il latte\n"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,58 @@
+use std::future::Future;
+use std::time::Duration;
+use teloxide::adaptors::throttle::{Settings, Throttle};
+use teloxide::requests::{Output, Request};
+use teloxide::RequestError;
+
+/// Minimum spacing of status-message edits in one chat (about one message
+/// per second per chat is allowed); intermediate updates are coalesced.
+pub const EDIT_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest text Telegram accepts in one message.
+pub const MAX_MESSAGE_CHARS: usize = 4096;
+/// Retries after a 429 before the error is returned.
+const MAX_RETRIES: u32 = 3;
+
+/// The bot handlers talk to: teloxide's [`Throttle`] adaptor queues requests
+/// within Telegram's limits, globally (30/s), per chat (1/s) and per group
+/// (20/min), and holds a chat's queue back after a `429`.
+pub type Bot = Throttle<teloxide::Bot>;
+
+/// Create the throttled bot. Its queue worker runs on the tokio runtime.
+pub fn bot(token: &str) -> Bot {
+    // Retrying is left to `send_retrying`, which bounds it
+    Throttle::spawn_with_settings(teloxide::Bot::new(token), Settings::default().no_retry())
+}
+
+/// Sending for Bot API requests that survives flood limits.
+///
+/// A `429 Too Many Requests` answer is retried after the `retry_after`
+/// Telegram asks for, so bursts from several users slow replies down instead
+/// of dropping them.
+pub trait SendRetrying: Request<Err = RequestError> {
+    fn send_retrying(self) -> impl Future<Output = Result<Output<Self>, RequestError>> + Send;
+}
+
+impl<R> SendRetrying for R
+where
+    R: Request<Err = RequestError> + Send + Sync,
+    Output<R>: Send,
+{
+    async fn send_retrying(self) -> Result<Output<Self>, RequestError> {
+        let mut retries = 0;
+        loop {
+            match self.send_ref().await {
+                Err(RequestError::RetryAfter(after)) if retries < MAX_RETRIES => {
+                    retries += 1;
+                    log::warn!(
+                        "Telegram flood limit hit, retrying in {}s ({}/{})",
+                        after.seconds(),
+                        retries,
+                        MAX_RETRIES
+                    );
+                    tokio::time::sleep(after.duration()).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
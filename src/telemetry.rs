@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::{BatchConfigBuilder, BatchSpanProcessor, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::Subscriber;
+use tracing_subscriber::layer::SubscriberExt;
+
+use crate::config::TelemetryConfig;
+
+/// Finished spans waiting for export at most: past it, new spans are dropped
+/// rather than piling up while the collector is unreachable.
+const MAX_QUEUE: usize = 2048;
+/// Spans sent in one OTLP request at most.
+const MAX_BATCH: usize = 256;
+/// How long finished spans are collected before a batch is sent.
+const BATCH_DELAY: Duration = Duration::from_secs(2);
+
+/// The installed provider, flushed by [`shutdown`].
+static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// Records spans (events are left to the `log` backend) into `provider`.
+fn subscriber(provider: &SdkTracerProvider) -> impl Subscriber + Send + Sync {
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// Install the span collector and start the OTLP/HTTP exporter when an
+/// endpoint is configured. Without one, spans are no-ops.
+pub fn init(config: &TelemetryConfig) -> Result<()> {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(());
+    };
+
+    // Failed exports are retried by the exporter, with exponential backoff
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()
+        .context("Failed to create OTLP exporter")?;
+    let batch = BatchConfigBuilder::default()
+        .with_max_queue_size(MAX_QUEUE)
+        .with_max_export_batch_size(MAX_BATCH)
+        .with_scheduled_delay(BATCH_DELAY)
+        .build();
+    let provider = SdkTracerProvider::builder()
+        .with_span_processor(
+            BatchSpanProcessor::builder(exporter)
+                .with_batch_config(batch)
+                .build(),
+        )
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build();
+
+    tracing::subscriber::set_global_default(subscriber(&provider))
+        .context("Failed to install tracing subscriber")?;
+    let _ = PROVIDER.set(provider);
+    log::info!("Telemetry: exporting spans to {}", endpoint);
+    Ok(())
+}
+
+/// Export the spans still queued, before the process exits.
+pub fn shutdown() {
+    if let Some(provider) = PROVIDER.get() {
+        if let Err(e) = provider.shutdown() {
+            log::warn!("Telemetry: failed to export the last spans: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::SpanId;
+    use opentelemetry::Value;
+    use opentelemetry_sdk::error::OTelSdkResult;
+    use opentelemetry_sdk::trace::SpanData;
+    use std::sync::{Arc, Mutex};
+    use tracing::Instrument;
+
+    /// Keeps exported spans in memory.
+    #[derive(Debug, Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<SpanData>>>);
+
+    impl opentelemetry_sdk::trace::SpanExporter for Captured {
+        async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+            self.0.lock().unwrap().extend(batch);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spans_share_trace_and_link_parents() {
+        let captured = Captured::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(captured.clone())
+            .build();
+
+        let _guard = tracing::subscriber::set_default(subscriber(&provider));
+        async {
+            async {}
+                .instrument(tracing::info_span!("transcription", provider = "groq"))
+                .await;
+        }
+        .instrument(tracing::info_span!("memo", chat_id = 42))
+        .await;
+
+        let spans = captured.0.lock().unwrap();
+        let [child, root] = &spans[..] else {
+            panic!("expected two spans, got {}", spans.len());
+        };
+        assert_eq!((&*child.name, &*root.name), ("transcription", "memo"));
+        assert_eq!(child.span_context.trace_id(), root.span_context.trace_id());
+        assert_eq!(child.parent_span_id, root.span_context.span_id());
+        assert_eq!(root.parent_span_id, SpanId::INVALID);
+        let attribute = |span: &SpanData, key: &str| {
+            span.attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(attribute(root, "chat_id"), Some(Value::I64(42)));
+        assert_eq!(attribute(child, "provider"), Some(Value::from("groq")));
+    }
+}
@@ -0,0 +1,64 @@
+/// Fill the `{{name}}` placeholders of a note template with `values`.
+///
+/// Everything else passes through untouched, so Obsidian and Templater
+/// syntax (`{{date:YYYY-MM-DD}}`, `<% tp.file.title %>`) is left for their own
+/// post-processing. A placeholder the bot knows can be kept literal by
+/// escaping it as `\{{title}}`.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        // `\{{` is an escaped placeholder: drop the backslash, keep the braces
+        if rest[..start].ends_with('\\') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let value = after.find("}}").and_then(|end| {
+            let name = after[..end].trim();
+            values
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| (*value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                out.push_str(value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_passes_foreign_placeholders_through() {
+        let template = "# {{title}}\n\
+            Creata: {{date:YYYY-MM-DD}} <% tp.file.creation_date() %>\n\
+            Titolo Obsidian: \\{{title}}\n\n{{ content }}\n{{unclosed";
+        let rendered = render(
+            template,
+            &[("title", "Spesa"), ("content", "Comprare il latte")],
+        );
+        assert_eq!(
+            rendered,
+            "# Spesa\n\
+            Creata: {{date:YYYY-MM-DD}} <% tp.file.creation_date() %>\n\
+            Titolo Obsidian: {{title}}\n\nComprare il latte\n{{unclosed"
+        );
+    }
+}
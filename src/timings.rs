@@ -0,0 +1,71 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Wall-clock time of each pipeline stage for one memo, in order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StageTimings(Vec<(&'static str, Duration)>);
+
+impl StageTimings {
+    pub fn record(&mut self, stage: &'static str, duration: Duration) {
+        self.0.push((stage, duration));
+    }
+
+    /// Record a stage that ran before the ones already recorded.
+    pub fn prepend(&mut self, stage: &'static str, duration: Duration) {
+        self.0.insert(0, (stage, duration));
+    }
+
+    /// Run `future`, recording how long it took under `stage`.
+    pub async fn time<F: Future>(&mut self, stage: &'static str, future: F) -> F::Output {
+        let started = Instant::now();
+        let output = future.await;
+        self.record(stage, started.elapsed());
+        output
+    }
+
+    /// Reply footer, e.g. `⏱ transcribe 14s · correct 6s · notes 9s`.
+    pub fn footer(&self) -> Option<String> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let stages: Vec<String> = self
+            .0
+            .iter()
+            .map(|(stage, duration)| format!("{} {}", stage, format_secs(*duration)))
+            .collect();
+        Some(format!("⏱ {}", stages.join(" · ")))
+    }
+}
+
+/// Whole seconds, with one decimal below ten seconds.
+fn format_secs(duration: Duration) -> String {
+    let secs = duration.as_secs_f64();
+    if secs < 10.0 {
+        format!("{:.1}s", secs)
+    } else {
+        format!("{:.0}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_footer_lists_stages_in_order() {
+        let mut timings = StageTimings::default();
+        assert_eq!(timings.footer(), None);
+
+        timings.record("correct", Duration::from_millis(6200));
+        let answer = timings.time("notes", async { 42 }).await;
+        assert_eq!(answer, 42);
+        timings.prepend("transcribe", Duration::from_secs(14));
+
+        let footer = timings.footer().unwrap();
+        assert!(
+            footer.starts_with("⏱ transcribe 14s · correct 6.2s · notes 0.0s"),
+            "{}",
+            footer
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use super::Tool;
+use crate::ai_provider::{AiProvider, ChatRequest, ResponseFormat};
+use crate::i18n::{Language, Msg};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Picks the note type of a transcript (meeting, idea, journal…) among the
+/// templates of `output.templates_dir`, using an LLM.
+pub struct Classifier {
+    ai: Arc<dyn AiProvider>,
+    top_p: f32,
+    language: Language,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClassificationResponse {
+    #[serde(default, rename = "type")]
+    note_type: Option<String>,
+}
+
+impl Classifier {
+    /// A classifier prompting in `language`.
+    pub fn new(ai: Arc<dyn AiProvider>, top_p: f32, language: Language) -> Self {
+        Self {
+            ai,
+            top_p,
+            language,
+        }
+    }
+
+    fn user_prompt(
+        language: Language,
+        transcript: &str,
+        types: &[(String, Option<String>)],
+    ) -> String {
+        let types: Vec<String> = types
+            .iter()
+            .map(|(name, description)| match description {
+                Some(description) => format!("- {}: {}", name, description),
+                None => format!("- {}", name),
+            })
+            .collect();
+        Msg::ClassifierUserPrompt.fill(
+            language,
+            &[("types", &types.join("\n")), ("transcript", &transcript)],
+        )
+    }
+
+    /// The type named in the model's JSON response, if it is one of `types`.
+    fn parse(
+        response: serde_json::Value,
+        types: &[(String, Option<String>)],
+    ) -> Result<Option<String>> {
+        let parsed: ClassificationResponse = serde_json::from_value(response)
+            .context("Classifier: failed to parse classification JSON from LLM")?;
+        Ok(parsed.note_type.and_then(|chosen| {
+            types
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(chosen.trim()))
+                .map(|(name, _)| name.clone())
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for Classifier {
+    /// The transcript and the name and description of each note type.
+    type Input = (String, Vec<(String, Option<String>)>);
+    type Output = Option<String>;
+
+    fn name(&self) -> &str {
+        "classifier"
+    }
+
+    async fn run(
+        &self,
+        (transcript, types): (String, Vec<(String, Option<String>)>),
+    ) -> Result<Option<String>> {
+        let response = self
+            .ai
+            .chat_json(ChatRequest {
+                system_prompt: Msg::ClassifierSystemPrompt.text(self.language).to_string(),
+                user_prompt: Self::user_prompt(self.language, &transcript, &types),
+                temperature: 0.0,
+                top_p: self.top_p,
+                format: ResponseFormat::Json,
+            })
+            .await?;
+
+        let note_type = Self::parse(response, &types)?;
+        log::info!(
+            "Classifier: note type {}",
+            note_type.as_deref().unwrap_or("none")
+        );
+        Ok(note_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_accepts_only_known_types() {
+        let types = vec![
+            ("meeting".to_string(), Some("Riunione".to_string())),
+            ("idea".to_string(), None),
+        ];
+        let parse = |response| Classifier::parse(response, &types).unwrap();
+        assert_eq!(
+            parse(serde_json::json!({"type": "Meeting"})).as_deref(),
+            Some("meeting")
+        );
+        assert_eq!(parse(serde_json::json!({"type": "journal"})), None);
+        assert_eq!(parse(serde_json::json!({"type": null})), None);
+
+        let prompt = Classifier::user_prompt(Language::It, "Ciao", &types);
+        assert!(prompt.starts_with("Tipi di nota:\n- meeting: Riunione\n- idea\n"));
+        let prompt = Classifier::user_prompt(Language::En, "Hi", &types);
+        assert!(prompt.starts_with("Note types:\n- meeting: Riunione\n- idea\n"));
+    }
+}
@@ -1,46 +1,60 @@
-use anyhow::Result;
-use crate::ollama::{OllamaClient, ChatRequest};
 use super::Tool;
+use crate::ai_provider::{AiProvider, ChatRequest, ResponseFormat};
+use crate::corrections::KnownFix;
+use crate::i18n::{Language, Msg};
+use crate::template;
+use anyhow::Result;
+use std::sync::Arc;
 
 /// Corrects transcription errors using an LLM.
 pub struct Corrector {
-    ollama: OllamaClient,
+    ai: Arc<dyn AiProvider>,
     temperature: f32,
     top_p: f32,
+    /// Recurring mis-transcriptions learned from earlier memos.
+    known_fixes: Vec<KnownFix>,
+    language: Language,
+    /// System prompt template, with a `{{transcript}}` placeholder.
+    prompt: String,
 }
 
 impl Corrector {
-    pub fn new(ollama: OllamaClient, temperature: f32, top_p: f32) -> Self {
-        Self { ollama, temperature, top_p }
+    /// A corrector prompting in `language`, with its built-in system prompt.
+    pub fn new(ai: Arc<dyn AiProvider>, temperature: f32, top_p: f32, language: Language) -> Self {
+        Self {
+            ai,
+            temperature,
+            top_p,
+            known_fixes: Vec::new(),
+            language,
+            prompt: Msg::CorrectorSystemPrompt.text(language).to_string(),
+        }
     }
 
-    fn system_prompt() -> &'static str {
-        r#"Sei un esperto correttore di trascrizioni vocali italiane.
-
-Il tuo compito è correggere errori di trascrizione automatica mantenendo il significato originale.
-
-Correzioni da fare:
-- Parole mal riconosciute dal sistema di trascrizione
-- Errori grammaticali dovuti alla trascrizione automatica
-- Punteggiatura mancante o errata
-- Maiuscole appropriate (nomi propri, inizio frasi)
-- Parole incomplete o frammentate
+    /// Use `template` as the system prompt instead of the built-in one.
+    pub fn with_system_prompt(mut self, template: String) -> Self {
+        self.prompt = template;
+        self
+    }
 
-IMPORTANTE:
-- NON aggiungere informazioni che non ci sono
-- NON cambiare il significato originale
-- NON rimuovere dettagli importanti
-- Mantieni lo stile colloquiale se presente
-- Se una parola sembra tecnica o è un nome proprio, mantienila anche se sembra strana
+    pub fn with_known_fixes(mut self, known_fixes: Vec<KnownFix>) -> Self {
+        self.known_fixes = known_fixes;
+        self
+    }
 
-Rispondi SOLO con il testo corretto, senza commenti o spiegazioni."#
+    fn system_prompt(&self, transcript: &str) -> String {
+        let mut prompt = template::render(self.prompt.trim_end(), &[("transcript", transcript)]);
+        if !self.known_fixes.is_empty() {
+            prompt.push_str(Msg::CorrectorKnownFixes.text(self.language));
+            for fix in &self.known_fixes {
+                prompt.push_str(&format!("\n- \"{}\" → \"{}\"", fix.raw, fix.corrected));
+            }
+        }
+        prompt
     }
 
-    fn user_prompt(transcript: &str) -> String {
-        format!(
-            "Trascrizione automatica da correggere:\n\n---\n{}\n---\n\nCorreggi eventuali errori mantenendo il significato originale.",
-            transcript
-        )
+    fn user_prompt(&self, transcript: &str) -> String {
+        Msg::CorrectorUserPrompt.fill(self.language, &[("transcript", &transcript)])
     }
 }
 
@@ -56,16 +70,22 @@ impl Tool for Corrector {
     async fn run(&self, raw_transcript: String) -> Result<String> {
         log::info!("Corrector: cleaning transcription with LLM...");
 
-        let result = self.ollama.chat(ChatRequest {
-            system_prompt: Self::system_prompt().to_string(),
-            user_prompt: Self::user_prompt(&raw_transcript),
-            temperature: self.temperature,
-            top_p: self.top_p,
-            json_format: false,
-        }).await?;
+        let result = self
+            .ai
+            .chat(ChatRequest {
+                system_prompt: self.system_prompt(&raw_transcript),
+                user_prompt: self.user_prompt(&raw_transcript),
+                temperature: self.temperature,
+                top_p: self.top_p,
+                format: ResponseFormat::Text,
+            })
+            .await?;
 
-        log::info!("Corrector: transcription cleaned ({} → {} chars)",
-                   raw_transcript.len(), result.len());
+        log::info!(
+            "Corrector: transcription cleaned ({} → {} chars)",
+            raw_transcript.len(),
+            result.len()
+        );
 
         Ok(result)
     }
@@ -0,0 +1,389 @@
+use super::Tool;
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Document formats accepted as text notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Text,
+    Markdown,
+    Html,
+    Rst,
+    /// Word documents, converted by `pandoc`.
+    Docx,
+    /// PDFs, converted by `pdftotext` (poppler).
+    Pdf,
+}
+
+impl DocumentFormat {
+    /// Detect the format from the file extension, then the MIME type, then
+    /// the first bytes of the content.
+    pub fn detect(file_name: &str, mime: Option<&str>, bytes: &[u8]) -> Option<Self> {
+        let extension = file_name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_lowercase());
+        let by_extension = match extension.as_deref() {
+            Some("txt") => Some(Self::Text),
+            Some("md" | "markdown") => Some(Self::Markdown),
+            Some("html" | "htm" | "xhtml") => Some(Self::Html),
+            Some("rst") => Some(Self::Rst),
+            Some("docx") => Some(Self::Docx),
+            Some("pdf") => Some(Self::Pdf),
+            _ => None,
+        };
+        let by_mime = || match mime? {
+            "text/plain" => Some(Self::Text),
+            "text/markdown" => Some(Self::Markdown),
+            "text/html" | "application/xhtml+xml" => Some(Self::Html),
+            "text/x-rst" => Some(Self::Rst),
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+                Some(Self::Docx)
+            }
+            "application/pdf" => Some(Self::Pdf),
+            _ => None,
+        };
+        let by_content = || {
+            if bytes.starts_with(b"%PDF") {
+                return Some(Self::Pdf);
+            }
+            let head = String::from_utf8_lossy(&bytes[..bytes.len().min(512)]).to_lowercase();
+            let head = head.trim_start();
+            if head.starts_with("<!doctype html") || head.starts_with("<html") {
+                Some(Self::Html)
+            } else {
+                None
+            }
+        };
+        by_extension.or_else(by_mime).or_else(by_content)
+    }
+}
+
+/// A document received in chat.
+pub struct Document {
+    pub file_name: String,
+    pub mime: Option<String>,
+    pub bytes: Vec<u8>,
+}
+
+/// Converts incoming documents (HTML, reStructuredText, docx, PDF, plain
+/// text) into clean Markdown for the note pipeline.
+pub struct DocumentConverter;
+
+impl DocumentConverter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Run an external converter, feeding `bytes` on stdin and returning stdout.
+pub(crate) fn run_external(program: &str, args: &[&str], bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {} (is it installed?)", program))?;
+
+    // Write on another thread so a full stdout pipe cannot deadlock us
+    let mut stdin = child.stdin.take().context("No stdin for converter")?;
+    let input = bytes.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("{} failed", program))?;
+    let _ = writer.join();
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+#[async_trait::async_trait]
+impl Tool for DocumentConverter {
+    type Input = Document;
+    type Output = String;
+
+    fn name(&self) -> &str {
+        "document_converter"
+    }
+
+    async fn run(&self, document: Document) -> Result<String> {
+        let format = DocumentFormat::detect(
+            &document.file_name,
+            document.mime.as_deref(),
+            &document.bytes,
+        )
+        .with_context(|| format!("Unsupported document format: {}", document.file_name))?;
+        log::info!(
+            "DocumentConverter: converting {} as {:?}",
+            document.file_name,
+            format
+        );
+
+        let bytes = document.bytes;
+        let markdown = tokio::task::spawn_blocking(move || -> Result<String> {
+            let text = || String::from_utf8_lossy(&bytes).into_owned();
+            Ok(match format {
+                DocumentFormat::Text | DocumentFormat::Markdown => text(),
+                DocumentFormat::Html => html2md::parse_html(&text()),
+                DocumentFormat::Rst => rst_to_markdown(&text()),
+                DocumentFormat::Docx => String::from_utf8_lossy(&run_external(
+                    "pandoc",
+                    &["-f", "docx", "-t", "gfm", "--wrap=none"],
+                    &bytes,
+                )?)
+                .into_owned(),
+                DocumentFormat::Pdf => String::from_utf8_lossy(&run_external(
+                    "pdftotext",
+                    &["-layout", "-", "-"],
+                    &bytes,
+                )?)
+                .into_owned(),
+            })
+        })
+        .await
+        .context("Document conversion task failed")??;
+
+        let markdown = clean_markdown(&markdown);
+        if markdown.is_empty() {
+            anyhow::bail!("No text found in {}", document.file_name);
+        }
+        Ok(markdown)
+    }
+}
+
+/// Normalize line endings, strip trailing spaces and collapse runs of blank
+/// lines.
+fn clean_markdown(markdown: &str) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut blank_run = 0;
+    for line in markdown
+        .replace("\r\n", "\n")
+        .replace('\u{c}', "\n")
+        .lines()
+    {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_run += 1;
+            if blank_run > 1 {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.trim().to_string()
+}
+
+/// Convert the common subset of reStructuredText to Markdown: section titles,
+/// inline literals, hyperlinks and code blocks. Other directives are dropped.
+fn rst_to_markdown(rst: &str) -> String {
+    const ADORNMENTS: &str = "=-~^\"'`#*+:._";
+    let is_underline = |line: &str| {
+        let line = line.trim_end();
+        line.len() >= 3
+            && line
+                .chars()
+                .next()
+                .is_some_and(|c| ADORNMENTS.contains(c) && line.chars().all(|d| d == c))
+    };
+
+    let lines: Vec<&str> = rst.lines().collect();
+    // Heading levels follow the order in which adornment styles first appear
+    let mut styles: Vec<char> = Vec::new();
+    let mut out: Vec<String> = Vec::new();
+    let mut in_code = false;
+    let mut skip_directive = false;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let indented = line.starts_with(' ') || line.starts_with('\t');
+
+        if in_code || skip_directive {
+            if line.trim().is_empty() || indented {
+                let opening_blank =
+                    line.trim().is_empty() && out.last().is_some_and(|l| l.starts_with("```"));
+                if in_code && !opening_blank {
+                    out.push(line.trim_start_matches("   ").to_string());
+                }
+                i += 1;
+                continue;
+            }
+            if in_code {
+                close_code_block(&mut out);
+            }
+            in_code = false;
+            skip_directive = false;
+        }
+
+        let trimmed = line.trim();
+        if let Some(directive) = trimmed.strip_prefix(".. ") {
+            if let Some(language) = directive
+                .strip_prefix("code-block::")
+                .or_else(|| directive.strip_prefix("code::"))
+            {
+                out.push(format!("```{}", language.trim()));
+                in_code = true;
+            } else {
+                skip_directive = true;
+            }
+            i += 1;
+            continue;
+        }
+        if let Some(before) = trimmed.strip_suffix("::").filter(|_| !indented) {
+            // Paragraph ending in `::` introduces a literal block
+            if !before.trim().is_empty() {
+                out.push(format!("{}:", before));
+            }
+            out.push("```".to_string());
+            in_code = true;
+            i += 1;
+            continue;
+        }
+
+        // Overlined titles: `===` / Title / `===`
+        let (title, adornment, consumed) = if is_underline(line)
+            && i + 2 < lines.len()
+            && !lines[i + 1].trim().is_empty()
+            && is_underline(lines[i + 2])
+        {
+            (Some(lines[i + 1].trim()), line.chars().next(), 3)
+        } else if !trimmed.is_empty()
+            && !indented
+            && i + 1 < lines.len()
+            && is_underline(lines[i + 1])
+            && lines[i + 1].trim_end().len() >= trimmed.len()
+        {
+            (Some(trimmed), lines[i + 1].chars().next(), 2)
+        } else {
+            (None, None, 1)
+        };
+        if let (Some(title), Some(adornment)) = (title, adornment) {
+            let level = match styles.iter().position(|&c| c == adornment) {
+                Some(level) => level + 1,
+                None => {
+                    styles.push(adornment);
+                    styles.len()
+                }
+            };
+            out.push(format!(
+                "{} {}",
+                "#".repeat(level.min(6)),
+                rst_inline(title)
+            ));
+            i += consumed;
+            continue;
+        }
+
+        out.push(rst_inline(line));
+        i += 1;
+    }
+    if in_code {
+        close_code_block(&mut out);
+    }
+    out.join("\n")
+}
+
+/// Close a fenced code block, keeping the blank line that followed it outside.
+fn close_code_block(out: &mut Vec<String>) {
+    let trailing_blank = out.last().is_some_and(|l| l.trim().is_empty());
+    if trailing_blank {
+        out.pop();
+    }
+    out.push("```".to_string());
+    if trailing_blank {
+        out.push(String::new());
+    }
+}
+
+/// Inline reStructuredText markup: `text <url>`_ links and ``literal``.
+fn rst_inline(line: &str) -> String {
+    let mut out = line.to_string();
+    while let Some(close) = out.find(">`_") {
+        let Some(start) = out[..close].rfind('`') else {
+            break;
+        };
+        let Some((text, url)) = out[start + 1..close].rsplit_once(" <") else {
+            break;
+        };
+        let link = format!("[{}]({})", text.trim(), url);
+        // Anonymous links end with a double underscore
+        let end = close + 3 + usize::from(out[close + 3..].starts_with('_'));
+        out.replace_range(start..end, &link);
+    }
+    out.replace("``", "`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_convert_html_and_rst_to_markdown() {
+        assert_eq!(
+            DocumentFormat::detect("note", Some("application/pdf"), b""),
+            Some(DocumentFormat::Pdf)
+        );
+        assert_eq!(
+            DocumentFormat::detect("export", None, b"<!DOCTYPE html><p>x</p>"),
+            Some(DocumentFormat::Html)
+        );
+        assert_eq!(
+            DocumentFormat::detect("foto.jpg", Some("image/jpeg"), b""),
+            None
+        );
+
+        let converter = DocumentConverter::new();
+        let html = Document {
+            file_name: "pagina.html".to_string(),
+            mime: None,
+            bytes: b"<h1>Riunione</h1><p>Decisioni <b>prese</b></p><ul><li>uno</li></ul>".to_vec(),
+        };
+        let markdown = converter.run(html).await.unwrap();
+        assert!(markdown.contains("Riunione\n==="), "{}", markdown);
+        assert!(markdown.contains("**prese**"), "{}", markdown);
+
+        let rst = "\
+=======
+Diario
+=======
+
+Sezione
+-------
+
+Usa ``cargo test`` e vedi `la guida <https://example.com>`_.
+
+.. note::
+
+   Nota ignorata.
+
+Esempio::
+
+   fn main() {}
+
+Fine.
+";
+        let doc = Document {
+            file_name: "diario.rst".to_string(),
+            mime: None,
+            bytes: rst.as_bytes().to_vec(),
+        };
+        assert_eq!(
+            converter.run(doc).await.unwrap(),
+            "# Diario\n\n## Sezione\n\n\
+            Usa `cargo test` e vedi [la guida](https://example.com).\n\n\
+            Esempio:\n```\nfn main() {}\n```\n\nFine."
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use super::Tool;
+use crate::ai_provider::{AiProvider, ChatRequest, ResponseFormat};
+use crate::i18n::{Language, Msg};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Parses short log memos ("corso 5 km, dormito 7 ore") into values for the
+/// configured metrics using an LLM.
+pub struct MetricExtractor {
+    ai: Arc<dyn AiProvider>,
+    /// Metric name → description shown to the model.
+    names: BTreeMap<String, String>,
+    top_p: f32,
+    language: Language,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetricsResponse {
+    #[serde(default)]
+    metrics: BTreeMap<String, serde_json::Value>,
+}
+
+impl MetricExtractor {
+    /// An extractor of the `names` metrics, prompting in `language`.
+    pub fn new(
+        ai: Arc<dyn AiProvider>,
+        names: BTreeMap<String, String>,
+        top_p: f32,
+        language: Language,
+    ) -> Self {
+        Self {
+            ai,
+            names,
+            top_p,
+            language,
+        }
+    }
+
+    fn system_prompt(&self) -> String {
+        let metrics: String = self
+            .names
+            .iter()
+            .map(|(name, description)| format!("- {}: {}\n", name, description))
+            .collect();
+        Msg::MetricsSystemPrompt.fill(self.language, &[("metrics", &metrics)])
+    }
+
+    /// Keep numeric values of known metrics from the model's JSON response.
+    fn parse(&self, response: &str) -> Result<BTreeMap<String, f64>> {
+        let parsed: MetricsResponse = serde_json::from_str(response)
+            .context("MetricExtractor: failed to parse metrics JSON from LLM")?;
+        Ok(parsed
+            .metrics
+            .into_iter()
+            .filter(|(name, _)| self.names.contains_key(name))
+            .filter_map(|(name, value)| {
+                // Small models sometimes quote numbers or use a decimal comma
+                let number = match value {
+                    serde_json::Value::Number(n) => n.as_f64(),
+                    serde_json::Value::String(s) => s.trim().replace(',', ".").parse().ok(),
+                    _ => None,
+                }?;
+                number.is_finite().then_some((name, number))
+            })
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for MetricExtractor {
+    type Input = String;
+    type Output = BTreeMap<String, f64>;
+
+    fn name(&self) -> &str {
+        "metric_extractor"
+    }
+
+    async fn run(&self, text: String) -> Result<BTreeMap<String, f64>> {
+        let response = self
+            .ai
+            .chat(ChatRequest {
+                system_prompt: self.system_prompt(),
+                user_prompt: text,
+                temperature: 0.0,
+                top_p: self.top_p,
+                format: ResponseFormat::Json,
+            })
+            .await?;
+
+        let metrics = self.parse(&response)?;
+        log::info!("MetricExtractor: {} metric(s) found", metrics.len());
+        Ok(metrics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ollama::OllamaProvider;
+
+    #[test]
+    fn test_parse_keeps_known_numeric_metrics() {
+        let names = BTreeMap::from([
+            ("corsa_km".to_string(), "chilometri corsi".to_string()),
+            ("sonno_ore".to_string(), "ore dormite".to_string()),
+            ("umore".to_string(), "umore da 1 a 5".to_string()),
+        ]);
+        let extractor = MetricExtractor::new(
+            Arc::new(OllamaProvider::new(String::new(), String::new())),
+            names,
+            0.9,
+            Language::It,
+        );
+        let metrics = extractor
+            .parse(r#"{"metrics": {"corsa_km": 5, "sonno_ore": "7,5", "umore": "buono", "passi": 9000}}"#)
+            .unwrap();
+        assert_eq!(
+            metrics,
+            BTreeMap::from([
+                ("corsa_km".to_string(), 5.0),
+                ("sonno_ore".to_string(), 7.5)
+            ])
+        );
+        assert!(extractor
+            .system_prompt()
+            .contains("- sonno_ore: ore dormite\n"));
+    }
+}
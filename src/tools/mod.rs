@@ -1,10 +1,24 @@
+pub mod classifier;
 pub mod corrector;
-pub mod notes_reader;
+pub mod document_converter;
+pub mod metric_extractor;
 pub mod note_writer;
+pub mod notes_reader;
+pub mod outliner;
+pub mod segmenter;
+pub mod summarizer;
+pub mod task_extractor;
 
+pub use classifier::Classifier;
 pub use corrector::Corrector;
-pub use notes_reader::{NotesReader, NoteMeta};
-pub use note_writer::NoteWriter;
+pub use document_converter::{Document, DocumentConverter};
+pub use metric_extractor::MetricExtractor;
+pub use note_writer::{NoteWriter, PreflightError};
+pub use notes_reader::{NoteMeta, NotesReader};
+pub use outliner::Outliner;
+pub use segmenter::{Segment, Segmenter};
+pub use summarizer::Summarizer;
+pub use task_extractor::TaskExtractor;
 
 use anyhow::Result;
 
@@ -1,17 +1,289 @@
-use anyhow::{Context, Result};
-use std::path::PathBuf;
+use super::notes_reader::{collect_notes, DEFAULT_IGNORE};
+use super::{NotesReader, Tool};
+use crate::atomic_write::write_atomic;
 use crate::note_generator::Note;
-use super::Tool;
+use crate::routing::Routing;
+use crate::vault_lock;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Section of a note listing its related notes, where backlinks go.
+const RELATED_HEADING: &str = "## Note correlate";
+
+/// Below this much free space a note cannot be written reliably.
+const MIN_WRITABLE_BYTES: u64 = 1024 * 1024;
+
+/// Why the notes directory is not usable for saving.
+#[derive(Debug, thiserror::Error)]
+pub enum PreflightError {
+    #[error("notes directory '{dir}' is not writable: {source}")]
+    NotWritable {
+        dir: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("notes directory '{dir}' is out of disk space ({available_mb} MB free)")]
+    InsufficientSpace { dir: String, available_mb: u64 },
+}
+
+/// Outcome of a successful pre-flight check.
+#[derive(Debug)]
+pub struct PreflightReport {
+    pub available_mb: u64,
+    /// Set when free space is below the configured warning threshold.
+    pub low_space: bool,
+}
 
 /// Saves notes to the filesystem as Markdown files.
-pub struct NoteWriter;
+pub struct NoteWriter {
+    /// Body template (`output.note_template`); `None` uses the default layout.
+    template: Option<String>,
+    /// Vault locked while writing; defaults to the notes directory itself.
+    vault_dir: Option<PathBuf>,
+    /// Append notes to an existing note of the same title instead of
+    /// overwriting it (`output.merge_existing`).
+    merge: bool,
+    /// Flush each note to disk before it replaces the old file (`output.fsync`).
+    fsync: bool,
+    /// Names the built-in frontmatter keys are written under.
+    property_names: BTreeMap<String, String>,
+    /// Subfolders of the notes directory by tag or keyword.
+    routing: Routing,
+    /// Link each new note from the existing notes in its `related_notes`
+    /// (`output.backlinks`).
+    backlinks: bool,
+    /// Globs of vault paths that never get backlinks (`output.ignore`).
+    ignore: Vec<String>,
+}
 
 impl NoteWriter {
     pub fn new() -> Self {
-        Self
+        Self {
+            template: None,
+            vault_dir: None,
+            merge: false,
+            fsync: false,
+            property_names: BTreeMap::new(),
+            routing: Routing::default(),
+            backlinks: false,
+            ignore: DEFAULT_IGNORE.iter().map(|glob| glob.to_string()).collect(),
+        }
+    }
+
+    pub fn with_ignore(mut self, ignore: Vec<String>) -> Self {
+        self.ignore = ignore;
+        self
+    }
+
+    pub fn with_backlinks(mut self, backlinks: bool) -> Self {
+        self.backlinks = backlinks;
+        self
+    }
+
+    pub fn with_routing(mut self, routing: Routing) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    pub fn with_property_names(mut self, property_names: BTreeMap<String, String>) -> Self {
+        self.property_names = property_names;
+        self
+    }
+
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    pub fn with_merge(mut self, merge: bool) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    pub fn with_vault(mut self, vault_dir: PathBuf) -> Self {
+        self.vault_dir = Some(vault_dir);
+        self
+    }
+
+    pub fn with_template(mut self, template: Option<String>) -> Self {
+        self.template = template;
+        self
+    }
+
+    /// Check that `notes_dir` exists (creating it if needed), is writable and
+    /// has free space, before any expensive work is done on a memo.
+    pub fn preflight(
+        notes_dir: &str,
+        warn_below_mb: u64,
+    ) -> Result<PreflightReport, PreflightError> {
+        let not_writable = |source| PreflightError::NotWritable {
+            dir: notes_dir.to_string(),
+            source,
+        };
+
+        std::fs::create_dir_all(notes_dir).map_err(not_writable)?;
+
+        // Probe write permission with a throwaway file
+        let probe = Path::new(notes_dir).join(format!(".dot-write-probe-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&probe, b"").map_err(not_writable)?;
+        if let Err(e) = std::fs::remove_file(&probe) {
+            log::warn!("NoteWriter: failed to remove write probe: {}", e);
+        }
+
+        let available = fs2::available_space(notes_dir).map_err(not_writable)?;
+        let available_mb = available / (1024 * 1024);
+        if available < MIN_WRITABLE_BYTES {
+            return Err(PreflightError::InsufficientSpace {
+                dir: notes_dir.to_string(),
+                available_mb,
+            });
+        }
+
+        let low_space = available_mb < warn_below_mb;
+        if low_space {
+            log::warn!("NoteWriter: only {} MB free in {}", available_mb, notes_dir);
+        }
+
+        Ok(PreflightReport {
+            available_mb,
+            low_space,
+        })
+    }
+
+    /// The note in `notes_dir` that `note` updates: the file it would be
+    /// saved as, or else a note with the same title.
+    fn existing_note(&self, note: &Note, notes_dir: &Path) -> Option<PathBuf> {
+        let path = notes_dir.join(note.generate_filename());
+        if path.exists() {
+            return Some(path);
+        }
+        std::fs::read_dir(notes_dir)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+            .find(|path| {
+                std::fs::read_to_string(path)
+                    .ok()
+                    .and_then(|content| NotesReader::title_of(&content, &self.property_names))
+                    .is_some_and(|title| title.trim().eq_ignore_ascii_case(note.title.trim()))
+            })
+    }
+
+    /// `existing` with the content of `note` appended as a section dated
+    /// with the note, and that date as `modified:` (or its configured name)
+    /// in its frontmatter.
+    fn merge(&self, existing: &str, note: &Note) -> String {
+        let date = note.date.format("%Y-%m-%d").to_string();
+        let key = self
+            .property_names
+            .get("modified")
+            .map_or("modified", String::as_str);
+        let modified = format!("{}: {}", key, date);
+        let mut merged = match existing
+            .strip_prefix("---\n")
+            .and_then(|rest| rest.split_once("\n---"))
+        {
+            Some((yaml, body)) => {
+                let mut fields: Vec<&str> = yaml
+                    .lines()
+                    .filter(|line| {
+                        !line
+                            .strip_prefix(key)
+                            .is_some_and(|rest| rest.starts_with(':'))
+                    })
+                    .collect();
+                fields.push(&modified);
+                format!("---\n{}\n---{}", fields.join("\n"), body)
+            }
+            None => format!("---\n{}\n---\n\n{}", modified, existing),
+        };
+        merged.truncate(merged.trim_end().len());
+        merged.push_str(&format!("\n\n## {}\n\n{}\n", date, note.content.trim()));
+        merged
+    }
+
+    /// Link each saved note from the existing notes of `notes_dir` it lists
+    /// in `related_notes`. Failures are logged: the notes are already saved.
+    fn add_backlinks(&self, notes: &[Note], saved_paths: &[PathBuf], notes_dir: &Path) {
+        let vault = match collect_notes(notes_dir, &self.ignore) {
+            Ok(vault) => vault,
+            Err(e) => {
+                log::warn!("NoteWriter: failed to list notes for backlinks: {}", e);
+                return;
+            }
+        };
+        let by_stem: BTreeMap<String, &PathBuf> = vault
+            .iter()
+            .filter(|path| !saved_paths.contains(path))
+            .filter_map(|path| Some((path.file_stem()?.to_string_lossy().to_string(), path)))
+            .collect();
+
+        for (note, path) in notes.iter().zip(saved_paths) {
+            let Some(stem) = path.file_stem().map(|stem| stem.to_string_lossy()) else {
+                continue;
+            };
+            for related in &note.related_notes {
+                let Some(target) = by_stem.get(related) else {
+                    continue;
+                };
+                let updated = std::fs::read_to_string(target)
+                    .ok()
+                    .and_then(|content| with_backlink(&content, &stem));
+                let Some(updated) = updated else { continue };
+                match write_atomic(target, &updated, self.fsync) {
+                    Ok(()) => log::info!("NoteWriter: linked {} from {}", stem, target.display()),
+                    Err(e) => log::warn!(
+                        "NoteWriter: failed to add backlink to {}: {}",
+                        target.display(),
+                        e
+                    ),
+                }
+            }
+        }
     }
 }
 
+/// `content` with a link to the note `stem` in its "Note correlate" section
+/// (added at the end if missing), or `None` if the section already links it.
+fn with_backlink(content: &str, stem: &str) -> Option<String> {
+    let link = format!("[[{}]]", stem);
+    let aliased = format!("[[{}|", stem);
+    let entry = format!("- {}", link);
+    let mut lines: Vec<&str> = content.trim_end().lines().collect();
+    let Some(start) = lines.iter().position(|line| line.trim() == RELATED_HEADING) else {
+        return Some(format!(
+            "{}\n\n---\n\n{}\n\n- {}\n",
+            content.trim_end(),
+            RELATED_HEADING,
+            link
+        ));
+    };
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| line.starts_with("# ") || line.starts_with("## "))
+        .map_or(lines.len(), |i| start + 1 + i);
+    let section = &lines[start + 1..end];
+    if section
+        .iter()
+        .any(|line| line.contains(&link) || line.contains(&aliased))
+    {
+        return None;
+    }
+
+    // After the section's last line, or below the heading if it is empty
+    match section.iter().rposition(|line| !line.trim().is_empty()) {
+        Some(last) => lines.insert(start + 2 + last, &entry),
+        None => {
+            lines.insert(start + 1, &entry);
+            lines.insert(start + 1, "");
+        }
+    }
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+    Some(updated)
+}
+
 #[async_trait::async_trait]
 impl Tool for NoteWriter {
     type Input = (Vec<Note>, String);
@@ -24,23 +296,123 @@ impl Tool for NoteWriter {
     async fn run(&self, input: (Vec<Note>, String)) -> Result<Vec<PathBuf>> {
         let (notes, notes_dir) = input;
 
-        std::fs::create_dir_all(&notes_dir)
-            .context("Failed to create notes directory")?;
+        std::fs::create_dir_all(&notes_dir).context("Failed to create notes directory")?;
+        let vault_dir = self
+            .vault_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(&notes_dir));
+        let _lock = vault_lock::lock(&vault_dir).await?;
 
         let mut saved_paths = Vec::new();
 
         for note in &notes {
             let filename = note.generate_filename();
-            let filepath = PathBuf::from(&notes_dir).join(&filename);
+            let dir = self.routing.note_dir(Path::new(&notes_dir), note);
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create folder: {}", dir.display()))?;
+            let filepath = dir.join(&filename);
+
+            if let Some(existing) = self.merge.then(|| self.existing_note(note, &dir)).flatten() {
+                let content = std::fs::read_to_string(&existing)
+                    .with_context(|| format!("Failed to read note: {}", existing.display()))?;
+                write_atomic(&existing, &self.merge(&content, note), self.fsync)
+                    .with_context(|| format!("Failed to update note: {}", existing.display()))?;
+                log::info!("NoteWriter: merged into {}", existing.display());
+                saved_paths.push(existing);
+                continue;
+            }
 
-            std::fs::write(&filepath, note.to_markdown())
+            let markdown = note.to_markdown(self.template.as_deref(), &self.property_names);
+            write_atomic(&filepath, &markdown, self.fsync)
                 .with_context(|| format!("Failed to write note: {}", filename))?;
 
             log::info!("NoteWriter: saved {}", filepath.display());
             saved_paths.push(filepath);
         }
 
+        if self.backlinks {
+            self.add_backlinks(&notes, &saved_paths, Path::new(&notes_dir));
+        }
+
         log::info!("NoteWriter: saved {} note(s)", saved_paths.len());
         Ok(saved_paths)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preflight_writable_dir() {
+        let dir = std::env::temp_dir().join(format!("dot-preflight-{}", uuid::Uuid::new_v4()));
+        let dir_str = dir.to_string_lossy().to_string();
+
+        let report = NoteWriter::preflight(&dir_str, 0).unwrap();
+        assert!(!report.low_space);
+        // The probe file must not be left behind
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        let report = NoteWriter::preflight(&dir_str, u64::MAX).unwrap();
+        assert!(report.low_space);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_with_backlink_is_idempotent() {
+        let plain = "---\ntitle: Orto\n---\n\nPomodori.\n";
+        let linked = with_backlink(plain, "Semina").unwrap();
+        assert_eq!(
+            linked,
+            "---\ntitle: Orto\n---\n\nPomodori.\n\n---\n\n## Note correlate\n\n- [[Semina]]\n"
+        );
+        assert_eq!(with_backlink(&linked, "Semina"), None);
+
+        let related = "Pomodori.\n\n## Note correlate\n\n- [[Raccolto|raccolto]]\n\n\
+            ## Fonti\n\n- libro\n";
+        assert_eq!(
+            with_backlink(related, "Semina").unwrap(),
+            "Pomodori.\n\n## Note correlate\n\n- [[Raccolto|raccolto]]\n- [[Semina]]\n\n\
+            ## Fonti\n\n- libro\n"
+        );
+        assert_eq!(with_backlink(related, "Raccolto"), None);
+        assert_eq!(
+            with_backlink("## Note correlate\n", "Semina").unwrap(),
+            "## Note correlate\n\n- [[Semina]]\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_appends_a_dated_section() {
+        let note = Note {
+            title: "Progetto Orto".to_string(),
+            content: "Piantati i pomodori.".to_string(),
+            tags: vec![],
+            date: chrono::DateTime::parse_from_rfc3339("2024-05-16T10:00:00Z")
+                .unwrap()
+                .with_timezone(&chrono::Utc),
+            source: "voice-memo".to_string(),
+            related_notes: vec![],
+            id: None,
+            language: None,
+            icon: None,
+            references: vec![],
+            expenses: vec![],
+            telegram_link: None,
+            properties: Default::default(),
+        };
+        let existing = "---\ntitle: \"Progetto Orto\"\nmodified: 2024-05-01\n---\n\n\
+            Prima semina.\n";
+        assert_eq!(
+            NoteWriter::new().merge(existing, &note),
+            "---\ntitle: \"Progetto Orto\"\nmodified: 2024-05-16\n---\n\n\
+            Prima semina.\n\n## 2024-05-16\n\nPiantati i pomodori.\n"
+        );
+        assert_eq!(
+            NoteWriter::new().merge("Appunti sparsi", &note),
+            "---\nmodified: 2024-05-16\n---\n\nAppunti sparsi\n\n## 2024-05-16\n\n\
+            Piantati i pomodori.\n"
+        );
+    }
+}
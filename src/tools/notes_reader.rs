@@ -1,16 +1,33 @@
-use anyhow::Result;
-use serde::Deserialize;
-use std::path::Path;
 use super::Tool;
+use crate::link_graph;
+use crate::notes_index::NotesIndex;
+use crate::routing::glob_match;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Paths below the notes directory that are not notes: Obsidian's settings,
+/// note templates and deleted notes.
+pub const DEFAULT_IGNORE: &[&str] = &[".obsidian/**", "templates/**", ".trash/**"];
 
 /// Metadata extracted from a note's YAML frontmatter.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NoteMeta {
     pub title: String,
     pub date: String,
     pub tags: Vec<String>,
     pub filename: String,
+    #[allow(dead_code)] // kept for callers that want to filter by origin
     pub source: String,
+    /// Zettelkasten ID from the `id:` frontmatter field, if any.
+    pub id: Option<String>,
+    /// Alternative names from the `aliases:` frontmatter field.
+    pub aliases: Vec<String>,
+    /// Targets of the note's `[[wiki-links]]`.
+    pub links: Vec<String>,
+    /// Subfolder of the notes directory the note is in (`""` at the top).
+    pub folder: String,
 }
 
 /// Raw YAML frontmatter structure for deserialization.
@@ -21,28 +38,170 @@ struct Frontmatter {
     #[serde(default)]
     tags: Vec<String>,
     source: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_id")]
+    id: Option<String>,
+    #[serde(default, alias = "alias", deserialize_with = "deserialize_aliases")]
+    aliases: Vec<String>,
+}
+
+/// Accept both `id: "202405141230"` and the unquoted `id: 202405141230`,
+/// which YAML parses as a number.
+fn deserialize_id<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_yaml::Value>::deserialize(deserializer)?;
+    Ok(match value {
+        Some(serde_yaml::Value::String(s)) => Some(s),
+        Some(serde_yaml::Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
+/// Obsidian accepts `aliases` as a list or as a single (comma-separated) string.
+fn deserialize_aliases<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_yaml::Value>::deserialize(deserializer)?;
+    let scalar = |v: &serde_yaml::Value| match v {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+    let aliases = match value {
+        Some(serde_yaml::Value::Sequence(items)) => items.iter().filter_map(scalar).collect(),
+        Some(serde_yaml::Value::String(s)) => s.split(',').map(str::to_string).collect(),
+        Some(other) => scalar(&other).into_iter().collect(),
+        None => Vec::new(),
+    };
+    Ok(aliases
+        .into_iter()
+        .map(|a: String| a.trim().to_string())
+        .filter(|a| !a.is_empty())
+        .collect())
 }
 
 /// Scans a notes directory and reads YAML frontmatter from .md files.
-pub struct NotesReader;
+pub struct NotesReader {
+    /// Names the built-in keys are written under (`[frontmatter] rename`).
+    property_names: BTreeMap<String, String>,
+    /// Globs of paths left out of the index (`output.ignore`).
+    ignore: Vec<String>,
+    /// Cache of the notes read so far (`output.notes_index`).
+    index: Option<NotesIndex>,
+}
 
 impl NotesReader {
     pub fn new() -> Self {
-        Self
+        Self {
+            property_names: BTreeMap::new(),
+            ignore: DEFAULT_IGNORE.iter().map(|glob| glob.to_string()).collect(),
+            index: None,
+        }
     }
 
-    /// Parse YAML frontmatter from markdown content between `---` markers.
-    fn parse_frontmatter(content: &str) -> Option<Frontmatter> {
+    pub fn with_index(mut self, index: Option<NotesIndex>) -> Self {
+        self.index = index;
+        self
+    }
+
+    pub fn with_ignore(mut self, ignore: Vec<String>) -> Self {
+        self.ignore = ignore;
+        self
+    }
+
+    pub fn with_property_names(mut self, property_names: BTreeMap<String, String>) -> Self {
+        self.property_names = property_names;
+        self
+    }
+
+    /// Parse YAML frontmatter from markdown content between `---` markers,
+    /// reading built-in keys under their names in `property_names`.
+    fn parse_frontmatter(
+        content: &str,
+        property_names: &BTreeMap<String, String>,
+    ) -> Option<Frontmatter> {
         let content = content.trim_start();
         if !content.starts_with("---") {
             return None;
         }
 
+        // The closing marker starts a line, so values may contain `---`
         let after_first = &content[3..];
-        let end = after_first.find("---")?;
+        let end = after_first.find("\n---")?;
         let yaml_str = &after_first[..end];
 
-        serde_yaml::from_str(yaml_str).ok()
+        if property_names.is_empty() {
+            return serde_yaml::from_str(yaml_str).ok();
+        }
+        let mut fields: serde_yaml::Mapping = serde_yaml::from_str(yaml_str).ok()?;
+        for (key, name) in property_names {
+            if let Some(value) = fields.remove(name.as_str()) {
+                fields.insert(key.as_str().into(), value);
+            }
+        }
+        // Back through text, which reads plain scalars (`title: 2024`) as strings
+        serde_yaml::from_str(&serde_yaml::to_string(&fields).ok()?).ok()
+    }
+
+    /// The `title:` of a note's frontmatter, if any.
+    pub(super) fn title_of(
+        content: &str,
+        property_names: &BTreeMap<String, String>,
+    ) -> Option<String> {
+        Self::parse_frontmatter(content, property_names)?.title
+    }
+
+    /// The markdown files below `dir` that are notes.
+    pub(crate) fn scan(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        collect_notes(dir, &self.ignore)
+    }
+
+    /// What would make a note read differently, so that an index built
+    /// with other settings is not reused.
+    pub(crate) fn settings(&self) -> String {
+        format!("{:?} {:?}", self.ignore, self.property_names)
+    }
+
+    /// The metadata of the note at `path` below `dir`; `None` if it can't be
+    /// read or has no frontmatter.
+    pub(crate) fn read_note(&self, dir: &Path, path: &Path) -> Option<NoteMeta> {
+        // Notes routed into subfolders are still linked by file name
+        let folder = path
+            .parent()
+            .and_then(|parent| parent.strip_prefix(dir).ok())
+            .map(|folder| folder.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        let filename = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        let content = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                log::warn!("NotesReader: failed to read {}: {}", filename, e);
+                return None;
+            }
+        };
+
+        let Some(fm) = Self::parse_frontmatter(&content, &self.property_names) else {
+            log::warn!("NotesReader: no valid frontmatter in {}", filename);
+            return None;
+        };
+        Some(NoteMeta {
+            title: fm.title.unwrap_or_else(|| filename.clone()),
+            date: fm.date.unwrap_or_default(),
+            tags: fm.tags,
+            filename,
+            source: fm.source.unwrap_or_default(),
+            id: fm.id,
+            aliases: fm.aliases,
+            links: link_graph::wiki_links(&content),
+            folder,
+        })
     }
 }
 
@@ -62,55 +221,63 @@ impl Tool for NotesReader {
             return Ok(Vec::new());
         }
 
-        let mut notes = Vec::new();
-
-        let entries = std::fs::read_dir(dir)?;
-        for entry in entries {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(e) => {
-                    log::warn!("NotesReader: failed to read dir entry: {}", e);
-                    continue;
-                }
-            };
+        let notes = match &self.index {
+            Some(index) => index.notes(&notes_dir, self)?,
+            None => self
+                .scan(dir)?
+                .iter()
+                .filter_map(|path| self.read_note(dir, path))
+                .collect(),
+        };
 
-            let path = entry.path();
-            if path.extension().and_then(|e| e.to_str()) != Some("md") {
-                continue;
-            }
+        log::info!("NotesReader: found {} existing notes", notes.len());
+        Ok(notes)
+    }
+}
 
-            let filename = path.file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
-
-            let content = match std::fs::read_to_string(&path) {
-                Ok(c) => c,
-                Err(e) => {
-                    log::warn!("NotesReader: failed to read {}: {}", filename, e);
-                    continue;
-                }
-            };
+/// Markdown files below `root`, except hidden files and folders (`.git`,
+/// `.trash`, temporary writes) and the paths matching an `ignore` glob such
+/// as `templates/**`.
+pub(crate) fn collect_notes(root: &Path, ignore: &[String]) -> Result<Vec<PathBuf>> {
+    let mut notes = Vec::new();
+    walk(root, root, ignore, &mut notes)?;
+    Ok(notes)
+}
 
-            match Self::parse_frontmatter(&content) {
-                Some(fm) => {
-                    notes.push(NoteMeta {
-                        title: fm.title.unwrap_or_else(|| filename.clone()),
-                        date: fm.date.unwrap_or_default(),
-                        tags: fm.tags,
-                        filename: filename.clone(),
-                        source: fm.source.unwrap_or_default(),
-                    });
-                }
-                None => {
-                    log::warn!("NotesReader: no valid frontmatter in {}", filename);
-                }
+fn walk(root: &Path, dir: &Path, ignore: &[String], notes: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                log::warn!("NotesReader: failed to read dir entry: {}", e);
+                continue;
             }
+        };
+        // Hidden entries: temporary writes, `.git`, sync clients' folders
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let mut relative = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let is_dir = path.is_dir();
+        if is_dir {
+            // So that `templates/**` also matches the folder itself
+            relative.push('/');
+        }
+        if ignore.iter().any(|glob| glob_match(glob, &relative)) {
+            continue;
+        }
+        if is_dir {
+            walk(root, &path, ignore, notes)?;
+        } else if path.extension().and_then(|e| e.to_str()) == Some("md") {
+            notes.push(path);
         }
-
-        log::info!("NotesReader: found {} existing notes", notes.len());
-        Ok(notes)
     }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -130,7 +297,7 @@ tags:
 
 # Some content here
 "#;
-        let fm = NotesReader::parse_frontmatter(content).unwrap();
+        let fm = NotesReader::parse_frontmatter(content, &BTreeMap::new()).unwrap();
         assert_eq!(fm.title.unwrap(), "Test Note");
         assert_eq!(fm.date.unwrap(), "2024-01-15");
         assert_eq!(fm.tags, vec!["rust", "coding"]);
@@ -140,15 +307,145 @@ tags:
     #[test]
     fn test_parse_frontmatter_missing_fields() {
         let content = "---\ntitle: \"Minimal\"\n---\n\nContent";
-        let fm = NotesReader::parse_frontmatter(content).unwrap();
+        let fm = NotesReader::parse_frontmatter(content, &BTreeMap::new()).unwrap();
         assert_eq!(fm.title.unwrap(), "Minimal");
         assert!(fm.tags.is_empty());
         assert!(fm.date.is_none());
     }
 
+    #[test]
+    fn test_parse_frontmatter_zettel_id() {
+        let quoted = "---\ntitle: \"Zettel\"\nid: \"202405141230\"\n---\n";
+        let fm = NotesReader::parse_frontmatter(quoted, &BTreeMap::new()).unwrap();
+        assert_eq!(fm.id.as_deref(), Some("202405141230"));
+
+        let unquoted = "---\ntitle: \"Zettel\"\nid: 202405141230\n---\n";
+        let fm = NotesReader::parse_frontmatter(unquoted, &BTreeMap::new()).unwrap();
+        assert_eq!(fm.id.as_deref(), Some("202405141230"));
+    }
+
+    #[test]
+    fn test_parse_frontmatter_aliases() {
+        let list = "---\ntitle: \"Kubernetes\"\naliases:\n  - K8s\n  - Kube\n---\n";
+        let fm = NotesReader::parse_frontmatter(list, &BTreeMap::new()).unwrap();
+        assert_eq!(fm.aliases, vec!["K8s", "Kube"]);
+
+        let single = "---\ntitle: \"Kubernetes\"\nalias: K8s, Kube\n---\n";
+        let fm = NotesReader::parse_frontmatter(single, &BTreeMap::new()).unwrap();
+        assert_eq!(fm.aliases, vec!["K8s", "Kube"]);
+    }
+
+    #[test]
+    fn test_generated_frontmatter_round_trips() {
+        use crate::note_generator::Note;
+
+        let titles = [
+            "Riunione: \"budget\" 2024",
+            "Due righe\ndi titolo",
+            "Prima --- dopo",
+            "#idea: sì, no",
+            "yes",
+        ];
+        for title in titles {
+            let note = Note {
+                title: title.to_string(),
+                content: "Contenuto".to_string(),
+                tags: vec!["lavoro/riunioni".to_string(), "2024".to_string()],
+                date: chrono::DateTime::parse_from_rfc3339("2024-05-16T10:00:00Z")
+                    .unwrap()
+                    .with_timezone(&chrono::Utc),
+                source: "voice-memo".to_string(),
+                related_notes: vec!["Budget: Q2".to_string()],
+                id: Some("202405161000".to_string()),
+                language: None,
+                icon: None,
+                references: vec![],
+                expenses: vec![],
+                telegram_link: None,
+                properties: BTreeMap::from([("type".to_string(), "voice".into())]),
+            };
+            let markdown = note.to_markdown(None, &BTreeMap::new());
+            assert!(markdown.contains("\ntype: voice\n"));
+            let fm = NotesReader::parse_frontmatter(&markdown, &BTreeMap::new()).unwrap();
+            assert_eq!(fm.title.as_deref(), Some(title));
+            assert_eq!(fm.id.as_deref(), Some("202405161000"));
+            assert_eq!(fm.date.as_deref(), Some("2024-05-16"));
+            assert_eq!(fm.tags, ["lavoro/riunioni", "2024"]);
+            assert_eq!(fm.source.as_deref(), Some("voice-memo"));
+
+            // Renamed keys read back under their built-in names
+            let names = BTreeMap::from([("date".to_string(), "created".to_string())]);
+            let markdown = note.to_markdown(None, &names);
+            assert!(markdown.contains("\ncreated: 2024-05-16\n"));
+            assert!(!markdown.contains("\ndate:"));
+            let fm = NotesReader::parse_frontmatter(&markdown, &names).unwrap();
+            assert_eq!(fm.date.as_deref(), Some("2024-05-16"));
+        }
+    }
+
+    #[test]
+    fn test_parse_frontmatter_renamed_keys() {
+        let content = "---\nname: \"Riunione\"\ncreated: 2024-05-16\ndate: 2020-01-01\n\
+            tags:\n  - 2024\n---\n";
+        let names = BTreeMap::from([
+            ("title".to_string(), "name".to_string()),
+            ("date".to_string(), "created".to_string()),
+        ]);
+        let fm = NotesReader::parse_frontmatter(content, &names).unwrap();
+        assert_eq!(fm.title.as_deref(), Some("Riunione"));
+        assert_eq!(fm.date.as_deref(), Some("2024-05-16"));
+        assert_eq!(fm.tags, ["2024"]);
+    }
+
+    #[tokio::test]
+    async fn test_run_reads_subfolders_but_not_hidden_ones() {
+        let dir = std::env::temp_dir().join(format!("dot-reader-{}", uuid::Uuid::new_v4()));
+        for (path, title) in [
+            ("Inbox.md", "Inbox"),
+            ("Cucina/Dolci/Tiramisù.md", "Tiramisù"),
+            (".trash/Vecchia.md", "Vecchia"),
+            ("templates/meeting.md", "Riunione"),
+            ("Cucina/Bozze/Pane.md", "Pane"),
+        ] {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, format!("---\ntitle: {}\n---\n", title)).unwrap();
+        }
+
+        let mut notes = NotesReader::new()
+            .run(dir.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        notes.sort_by(|a, b| a.title.cmp(&b.title));
+        let found: Vec<(&str, &str)> = notes
+            .iter()
+            .map(|note| (note.folder.as_str(), note.filename.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("", "Inbox.md"),
+                ("Cucina/Bozze", "Pane.md"),
+                ("Cucina/Dolci", "Tiramisù.md")
+            ]
+        );
+
+        // Configured globs replace the defaults; hidden folders stay skipped
+        let mut notes = NotesReader::new()
+            .with_ignore(vec!["*/Bozze/**".to_string()])
+            .run(dir.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        notes.sort_by(|a, b| a.title.cmp(&b.title));
+        let titles: Vec<&str> = notes.iter().map(|note| note.title.as_str()).collect();
+        assert_eq!(titles, ["Inbox", "Riunione", "Tiramisù"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_parse_frontmatter_no_markers() {
         let content = "# Just a heading\nNo frontmatter here.";
-        assert!(NotesReader::parse_frontmatter(content).is_none());
+        assert!(NotesReader::parse_frontmatter(content, &BTreeMap::new()).is_none());
     }
 }
@@ -0,0 +1,113 @@
+use super::segmenter::SegmentStart;
+use super::{Segment, Segmenter, Tool};
+use crate::ai_provider::{AiProvider, ChatRequest, ResponseFormat};
+use crate::i18n::{Language, Msg};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Plans the notes of a transcript before they are written, for two-phase
+/// generation: one section per note, with its title and the existing notes
+/// it relates to.
+///
+/// Like the [`Segmenter`], the LLM answers with sentence numbers, so each
+/// note's section keeps the transcript's wording.
+pub struct Outliner {
+    ai: Arc<dyn AiProvider>,
+    top_p: f32,
+    language: Language,
+}
+
+#[derive(Debug, Deserialize)]
+struct OutlineResponse {
+    notes: Vec<SegmentStart>,
+}
+
+impl Outliner {
+    /// An outliner prompting in `language`.
+    pub fn new(ai: Arc<dyn AiProvider>, top_p: f32, language: Language) -> Self {
+        Self {
+            ai,
+            top_p,
+            language,
+        }
+    }
+
+    fn user_prompt(language: Language, sentences: &[String], existing: &[String]) -> String {
+        let numbered: Vec<String> = sentences
+            .iter()
+            .enumerate()
+            .map(|(i, s)| format!("[{}] {}", i + 1, s))
+            .collect();
+        let existing = if existing.is_empty() {
+            Msg::OutlinerNoExistingNotes.text(language).to_string()
+        } else {
+            existing
+                .iter()
+                .map(|stem| format!("- {}", stem))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        Msg::OutlinerUserPrompt.fill(
+            language,
+            &[("existing", &existing), ("sentences", &numbered.join("\n"))],
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for Outliner {
+    /// The transcript and the filename stems of the existing notes.
+    type Input = (String, Vec<String>);
+    type Output = Vec<Segment>;
+
+    fn name(&self) -> &str {
+        "outliner"
+    }
+
+    async fn run(&self, (transcript, existing): (String, Vec<String>)) -> Result<Vec<Segment>> {
+        let sentences = Segmenter::split_sentences(&transcript);
+        log::info!(
+            "Outliner: planning notes for {} sentences...",
+            sentences.len()
+        );
+
+        let response = self
+            .ai
+            .chat_json(ChatRequest {
+                system_prompt: Msg::OutlinerSystemPrompt.text(self.language).to_string(),
+                user_prompt: Self::user_prompt(self.language, &sentences, &existing),
+                temperature: 0.0,
+                top_p: self.top_p,
+                format: ResponseFormat::Json,
+            })
+            .await?;
+
+        let parsed: OutlineResponse = serde_json::from_value(response)
+            .context("Outliner: failed to parse outline JSON from LLM")?;
+        let mut notes = Segmenter::build_segments(&sentences, parsed.notes);
+        // Only notes that exist can be linked
+        for note in &mut notes {
+            note.related
+                .retain(|stem| existing.iter().any(|e| e.eq_ignore_ascii_case(stem)));
+        }
+
+        log::info!("Outliner: {} note(s) planned", notes.len());
+        Ok(notes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_prompt_lists_existing_notes() {
+        let sentences = vec!["Primo punto.".to_string(), "Secondo punto.".to_string()];
+        let prompt = Outliner::user_prompt(Language::It, &sentences, &["Rust Tips".to_string()]);
+        assert!(prompt.contains("- Rust Tips\n"));
+        assert!(prompt.contains("[1] Primo punto.\n[2] Secondo punto."));
+        assert!(Outliner::user_prompt(Language::It, &sentences, &[]).contains("(nessuna)"));
+        assert!(Outliner::user_prompt(Language::En, &sentences, &[]).contains("(none)"));
+    }
+}
@@ -0,0 +1,227 @@
+use super::Tool;
+use crate::ai_provider::{AiProvider, ChatRequest, ResponseFormat};
+use crate::i18n::{Language, Msg};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Sentences longer than this many words are split further, so transcripts
+/// without punctuation still get usable boundaries.
+const MAX_SENTENCE_WORDS: usize = 40;
+
+/// One topical section of a transcript.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub topic: String,
+    pub text: String,
+    /// Existing notes (filename stems) the outline relates to the section.
+    pub related: Vec<String>,
+}
+
+/// Splits long transcripts into topical sections using an LLM.
+///
+/// The transcript is numbered sentence by sentence and the LLM only answers
+/// with the sentence where each topic starts, so the original wording is kept
+/// verbatim and the response stays small even for very long memos.
+pub struct Segmenter {
+    ai: Arc<dyn AiProvider>,
+    top_p: f32,
+    language: Language,
+}
+
+#[derive(Debug, Deserialize)]
+struct SegmentsResponse {
+    segments: Vec<SegmentStart>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct SegmentStart {
+    pub(super) topic: String,
+    pub(super) start: usize,
+    #[serde(default)]
+    pub(super) related: Vec<String>,
+}
+
+impl Segmenter {
+    /// A segmenter prompting in `language`.
+    pub fn new(ai: Arc<dyn AiProvider>, top_p: f32, language: Language) -> Self {
+        Self {
+            ai,
+            top_p,
+            language,
+        }
+    }
+
+    fn user_prompt(&self, sentences: &[String]) -> String {
+        let numbered: Vec<String> = sentences
+            .iter()
+            .enumerate()
+            .map(|(i, s)| format!("[{}] {}", i + 1, s))
+            .collect();
+        Msg::SegmenterUserPrompt.fill(self.language, &[("sentences", &numbered.join("\n"))])
+    }
+
+    /// Split text into sentences at `.`, `!` and `?`, breaking overlong ones.
+    pub fn split_sentences(text: &str) -> Vec<String> {
+        let mut sentences = Vec::new();
+        let mut current = String::new();
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            current.push(c);
+            let at_boundary = matches!(c, '.' | '!' | '?')
+                && chars.peek().is_none_or(|next| next.is_whitespace());
+            if at_boundary {
+                sentences.push(current.trim().to_string());
+                current.clear();
+            }
+        }
+        if !current.trim().is_empty() {
+            sentences.push(current.trim().to_string());
+        }
+
+        sentences
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .flat_map(|s| {
+                let words: Vec<&str> = s.split_whitespace().collect();
+                words
+                    .chunks(MAX_SENTENCE_WORDS)
+                    .map(|chunk| chunk.join(" "))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Build segments from the LLM's start positions (1-based sentence numbers).
+    ///
+    /// Out-of-range and duplicate starts are ignored; the first segment always
+    /// begins at the first sentence.
+    pub(super) fn build_segments(sentences: &[String], starts: Vec<SegmentStart>) -> Vec<Segment> {
+        let mut starts: Vec<(usize, String, Vec<String>)> = starts
+            .into_iter()
+            .filter(|s| s.start >= 1 && s.start <= sentences.len())
+            .map(|s| (s.start - 1, s.topic, s.related))
+            .collect();
+        starts.sort_by_key(|(start, _, _)| *start);
+        starts.dedup_by_key(|(start, _, _)| *start);
+
+        match starts.first_mut() {
+            Some(first) => first.0 = 0,
+            None => starts.push((0, String::new(), Vec::new())),
+        }
+
+        starts
+            .iter()
+            .enumerate()
+            .map(|(i, (start, topic, related))| {
+                let end = starts
+                    .get(i + 1)
+                    .map_or(sentences.len(), |(next, _, _)| *next);
+                Segment {
+                    topic: topic.clone(),
+                    text: sentences[*start..end].join(" "),
+                    related: related.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for Segmenter {
+    type Input = String;
+    type Output = Vec<Segment>;
+
+    fn name(&self) -> &str {
+        "segmenter"
+    }
+
+    async fn run(&self, transcript: String) -> Result<Vec<Segment>> {
+        let sentences = Self::split_sentences(&transcript);
+        log::info!(
+            "Segmenter: splitting {} sentences into topics...",
+            sentences.len()
+        );
+
+        let response = self
+            .ai
+            .chat_json(ChatRequest {
+                system_prompt: Msg::SegmenterSystemPrompt.text(self.language).to_string(),
+                user_prompt: self.user_prompt(&sentences),
+                temperature: 0.0,
+                top_p: self.top_p,
+                format: ResponseFormat::Json,
+            })
+            .await?;
+
+        let parsed: SegmentsResponse = serde_json::from_value(response)
+            .context("Segmenter: failed to parse segments JSON from LLM")?;
+        let segments = Self::build_segments(&sentences, parsed.segments);
+
+        log::info!("Segmenter: {} topic(s) found", segments.len());
+        Ok(segments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(topic: &str, start: usize) -> SegmentStart {
+        SegmentStart {
+            topic: topic.to_string(),
+            start,
+            related: vec![],
+        }
+    }
+
+    #[test]
+    fn test_split_sentences() {
+        let sentences = Segmenter::split_sentences("Primo punto. Versione 1.2 pronta! E poi? Fine");
+        assert_eq!(
+            sentences,
+            vec!["Primo punto.", "Versione 1.2 pronta!", "E poi?", "Fine"]
+        );
+
+        let long = vec!["parola"; MAX_SENTENCE_WORDS + 5].join(" ");
+        assert_eq!(Segmenter::split_sentences(&long).len(), 2);
+    }
+
+    #[test]
+    fn test_build_segments_from_starts() {
+        let sentences: Vec<String> = ["a.", "b.", "c.", "d."]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let segments = Segmenter::build_segments(
+            &sentences,
+            vec![
+                start("Due", 3),
+                start("Uno", 2),
+                start("Fuori", 9),
+                start("Doppio", 3),
+            ],
+        );
+        assert_eq!(
+            segments,
+            vec![
+                Segment {
+                    topic: "Uno".to_string(),
+                    text: "a. b.".to_string(),
+                    related: vec![]
+                },
+                Segment {
+                    topic: "Due".to_string(),
+                    text: "c. d.".to_string(),
+                    related: vec![]
+                },
+            ]
+        );
+
+        let single = Segmenter::build_segments(&sentences, vec![]);
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].text, "a. b. c. d.");
+    }
+}
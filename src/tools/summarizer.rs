@@ -0,0 +1,105 @@
+use super::Tool;
+use crate::ai_provider::{AiProvider, ChatRequest, ResponseFormat};
+use crate::i18n::{Language, Msg};
+use anyhow::Result;
+use std::sync::Arc;
+
+/// Shortens transcripts that don't fit the model's context using an LLM.
+///
+/// The transcript is summarized in pieces of at most the target length, each
+/// to its share of the target, so the summarizer's own prompt fits too.
+pub struct Summarizer {
+    ai: Arc<dyn AiProvider>,
+    top_p: f32,
+    language: Language,
+}
+
+impl Summarizer {
+    /// A summarizer prompting in `language`.
+    pub fn new(ai: Arc<dyn AiProvider>, top_p: f32, language: Language) -> Self {
+        Self {
+            ai,
+            top_p,
+            language,
+        }
+    }
+
+    fn user_prompt(&self, piece: &str, target_chars: usize) -> String {
+        Msg::SummarizerUserPrompt.fill(
+            self.language,
+            &[("chars", &target_chars), ("piece", &piece)],
+        )
+    }
+}
+
+/// Pieces of `text` of at most `max_chars` characters, split between words.
+fn pieces(text: &str, max_chars: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + word.chars().count() + 1 > max_chars {
+            pieces.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+#[async_trait::async_trait]
+impl Tool for Summarizer {
+    /// The transcript and the length in characters to bring it under.
+    type Input = (String, usize);
+    type Output = String;
+
+    fn name(&self) -> &str {
+        "summarizer"
+    }
+
+    async fn run(&self, (transcript, max_chars): (String, usize)) -> Result<String> {
+        let pieces = pieces(&transcript, max_chars.max(1));
+        let target = max_chars / pieces.len();
+        log::info!(
+            "Summarizer: shortening {} chars in {} piece(s) to ~{} chars",
+            transcript.chars().count(),
+            pieces.len(),
+            max_chars
+        );
+
+        let mut summaries = Vec::new();
+        for piece in &pieces {
+            let summary = self
+                .ai
+                .chat(ChatRequest {
+                    system_prompt: Msg::SummarizerSystemPrompt.text(self.language).to_string(),
+                    user_prompt: self.user_prompt(piece, target),
+                    temperature: 0.2,
+                    top_p: self.top_p,
+                    format: ResponseFormat::Text,
+                })
+                .await?;
+            summaries.push(summary.trim().to_string());
+        }
+        Ok(summaries.join("\n\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pieces_split_between_words() {
+        let text = "uno due tre quattro cinque sei";
+        assert_eq!(
+            pieces(text, 14),
+            vec!["uno due tre", "quattro cinque", "sei"]
+        );
+        assert_eq!(pieces(text, 100), vec![text]);
+    }
+}
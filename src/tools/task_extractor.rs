@@ -0,0 +1,91 @@
+use super::Tool;
+use crate::ai_provider::{AiProvider, ChatRequest, ResponseFormat};
+use crate::i18n::{Language, Msg};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Finds the action items of a transcript ("chiamare Marco domani") using an
+/// LLM, for the tasks files of `enable_task_extraction`.
+pub struct TaskExtractor {
+    ai: Arc<dyn AiProvider>,
+    top_p: f32,
+    language: Language,
+}
+
+#[derive(Debug, Deserialize)]
+struct TasksResponse {
+    #[serde(default)]
+    tasks: Vec<String>,
+}
+
+impl TaskExtractor {
+    /// A task extractor prompting in `language`.
+    pub fn new(ai: Arc<dyn AiProvider>, top_p: f32, language: Language) -> Self {
+        Self {
+            ai,
+            top_p,
+            language,
+        }
+    }
+
+    /// Non-empty, single-line tasks from the model's JSON response.
+    fn parse(response: serde_json::Value) -> Result<Vec<String>> {
+        let parsed: TasksResponse = serde_json::from_value(response)
+            .context("TaskExtractor: failed to parse tasks JSON from LLM")?;
+        Ok(parsed
+            .tasks
+            .into_iter()
+            .map(|task| task.split_whitespace().collect::<Vec<_>>().join(" "))
+            .map(|task| task.trim_start_matches(['-', '*', '•', ' ']).to_string())
+            .filter(|task| !task.is_empty())
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for TaskExtractor {
+    type Input = String;
+    type Output = Vec<String>;
+
+    fn name(&self) -> &str {
+        "task_extractor"
+    }
+
+    async fn run(&self, transcript: String) -> Result<Vec<String>> {
+        let response = self
+            .ai
+            .chat_json(ChatRequest {
+                system_prompt: Msg::TaskExtractorSystemPrompt
+                    .text(self.language)
+                    .to_string(),
+                user_prompt: Msg::TaskExtractorUserPrompt
+                    .fill(self.language, &[("transcript", &transcript)]),
+                temperature: 0.0,
+                top_p: self.top_p,
+                format: ResponseFormat::Json,
+            })
+            .await?;
+
+        let tasks = Self::parse(response)?;
+        log::info!("TaskExtractor: {} task(s) found", tasks.len());
+        Ok(tasks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cleans_up_tasks() {
+        let tasks = TaskExtractor::parse(serde_json::json!({
+            "tasks": ["- Chiamare Marco\n domani", "  ", "Comprare il latte"]
+        }))
+        .unwrap();
+        assert_eq!(tasks, ["Chiamare Marco domani", "Comprare il latte"]);
+        assert!(TaskExtractor::parse(serde_json::json!({}))
+            .unwrap()
+            .is_empty());
+    }
+}
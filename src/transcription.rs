@@ -1,46 +1,337 @@
 use anyhow::{Context, Result};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use teloxide::types::File as TelegramFile;
 use teloxide::net::Download;
-use teloxide::Bot;
 use std::fs::File;
 use std::io::Write;
 use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
 
-use crate::config::TranscriptionConfig;
+use crate::audio::{AudioConverter, NativeConverter};
+use crate::config::{ChannelMode, RetryConfig, TimestampMode, TranscriptionConfig, WhisperTask};
+use crate::errors::PipelineError;
+use crate::i18n::{Language, Msg};
+use crate::retry;
+use crate::telegram::Bot;
 
 #[cfg(feature = "whisper-rs")]
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use crate::audio::WHISPER_SAMPLE_RATE;
+#[cfg(feature = "whisper-rs")]
+use whisper_rs::{
+    FullParams, SamplingStrategy, SegmentCallbackData, WhisperContext, WhisperContextParameters,
+};
+
+/// Trait for transcription providers.
+///
+/// Providers receive audio already downloaded from Telegram, either in memory
+/// or on local disk; the caller owns any temporary file and removes it.
+/// `transcription.language` value asking the provider to detect the language.
+pub const AUTO_LANGUAGE: &str = "auto";
+
+/// Text of a recording and, with `language = "auto"`, the language the
+/// provider detected (ISO 639-1).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcript {
+    pub text: String,
+    pub language: Option<String>,
+}
 
-/// Trait for transcription providers
 #[async_trait::async_trait]
 pub trait TranscriptionProvider: Send + Sync {
-    async fn transcribe(&self, bot: &Bot, file: &TelegramFile, temp_dir: &str) -> Result<String>;
+    async fn transcribe(&self, audio: &AudioInput) -> Result<Transcript>;
+
+    /// Like `transcribe`, additionally sending [`Progress`] to `progress`
+    /// as the recording is transcribed.
+    ///
+    /// Providers that transcribe in one request just ignore `progress`.
+    async fn transcribe_streaming(
+        &self,
+        audio: &AudioInput,
+        progress: ProgressUpdates,
+    ) -> Result<Transcript> {
+        drop(progress);
+        self.transcribe(audio).await
+    }
+}
+
+/// How far a running transcription has got.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// Share of the audio transcribed, 0-100.
+    pub percent: u8,
+    /// Transcript so far.
+    pub text: String,
+}
+
+/// Receives [`Progress`] while a transcription runs.
+pub type ProgressUpdates = tokio::sync::mpsc::UnboundedSender<Progress>;
+
+/// Share of a recording of `total_secs` transcribed once `done_secs` are.
+#[cfg_attr(not(feature = "whisper-rs"), allow(dead_code))]
+fn percent_done(done_secs: f64, total_secs: f64) -> u8 {
+    if total_secs <= 0.0 {
+        return 0;
+    }
+    (done_secs / total_secs * 100.0).clamp(0.0, 100.0) as u8
+}
+
+/// Downloaded audio, as handed to a transcription provider.
+///
+/// Files under `transcription.in_memory_max_mb` never touch disk, so the bot
+/// also runs on read-only filesystems; larger ones go to `output.temp_dir`.
+#[derive(Debug, Clone)]
+pub enum AudioInput {
+    File(PathBuf),
+    Memory { bytes: Arc<[u8]>, file_name: String },
+}
+
+impl AudioInput {
+    /// Name the audio is uploaded and archived under.
+    pub fn file_name(&self) -> String {
+        match self {
+            AudioInput::File(path) => path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            AudioInput::Memory { file_name, .. } => file_name.clone(),
+        }
+    }
+
+    /// File extension, used as the format hint when decoding.
+    pub fn extension(&self) -> Option<String> {
+        Path::new(&self.file_name())
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+    }
+
+    /// MIME type of the audio, from its extension, for uploads.
+    pub fn mime_type(&self) -> &'static str {
+        match self.extension().as_deref() {
+            Some("mp3") => "audio/mpeg",
+            Some("m4a" | "mp4" | "aac") => "audio/mp4",
+            Some("flac") => "audio/flac",
+            Some("wav") => "audio/wav",
+            Some("webm") => "audio/webm",
+            _ => "audio/ogg",
+        }
+    }
+
+    /// The raw audio bytes.
+    pub fn bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            AudioInput::File(path) => {
+                std::fs::read(path).context("Failed to read downloaded audio file")
+            }
+            AudioInput::Memory { bytes, .. } => Ok(bytes.to_vec()),
+        }
+    }
+
+    /// Remove the temporary file, if the audio was downloaded to disk.
+    pub fn cleanup(&self) {
+        if let AudioInput::File(path) = self {
+            if let Err(e) = std::fs::remove_file(path) {
+                log::warn!("Failed to remove temporary audio file: {}", e);
+            }
+        }
+    }
+}
+
+/// Prompt for Whisper: the configured initial prompt followed by the
+/// glossary terms, so it leans towards their spelling. `None` without either.
+fn whisper_prompt(config: &TranscriptionConfig) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(prompt) = config
+        .initial_prompt
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        parts.push(prompt.to_string());
+    }
+    let terms: Vec<&str> = config
+        .glossary
+        .iter()
+        .map(|term| term.trim())
+        .filter(|term| !term.is_empty())
+        .collect();
+    if !terms.is_empty() {
+        parts.push(format!("Glossario: {}.", terms.join(", ")));
+    }
+    // whisper.cpp takes the prompt as a C string
+    let prompt = parts.join(" ").replace('\0', "");
+    (!prompt.is_empty()).then_some(prompt)
+}
+
+/// The configured language, or `None` to let the provider detect it.
+fn fixed_language(config: &TranscriptionConfig) -> Option<String> {
+    let language = config.language.trim();
+    (!language.eq_ignore_ascii_case(AUTO_LANGUAGE)).then(|| language.to_string())
+}
+
+/// ISO 639-1 code for a language name as reported by hosted Whisper
+/// ("italian"); codes and unknown names are returned lowercased.
+fn language_code(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    let code = match name.as_str() {
+        "italian" => "it",
+        "english" => "en",
+        "french" => "fr",
+        "german" => "de",
+        "spanish" => "es",
+        "portuguese" => "pt",
+        "dutch" => "nl",
+        "romanian" => "ro",
+        "polish" => "pl",
+        "russian" => "ru",
+        "ukrainian" => "uk",
+        "arabic" => "ar",
+        "chinese" => "zh",
+        "japanese" => "ja",
+        _ => return name,
+    };
+    code.to_string()
+}
+
+/// Local Whisper support built into this binary: `None` without the
+/// `whisper-rs` feature, otherwise the acceleration it was built with.
+pub fn local_backend() -> Option<&'static str> {
+    if !cfg!(feature = "whisper-rs") {
+        None
+    } else if cfg!(feature = "metal") {
+        Some("Metal")
+    } else if cfg!(feature = "cuda") {
+        Some("CUDA")
+    } else {
+        Some("CPU")
+    }
+}
+
+/// Hosted providers `whisper_local` switches to, in order, when the binary
+/// has no local Whisper: the first whose API key is set.
+const LOCAL_FALLBACKS: [(&str, &str); 2] = [("groq", "GROQ_API_KEY"), ("openai", "OPENAI_API_KEY")];
+
+/// `config` switched to a hosted provider whose key `key_set` reports as set,
+/// if it asks for `whisper_local` and the binary has no local Whisper.
+fn local_fallback(
+    config: &TranscriptionConfig,
+    key_set: impl Fn(&str) -> bool,
+) -> Option<TranscriptionConfig> {
+    if config.provider != "whisper_local" || local_backend().is_some() {
+        return None;
+    }
+    let (provider, _) = LOCAL_FALLBACKS.iter().find(|(_, key)| key_set(key))?;
+    Some(TranscriptionConfig {
+        provider: provider.to_string(),
+        // The model and key of the local setup don't apply to the hosted one
+        api_key_env: None,
+        model: None,
+        ..config.clone()
+    })
+}
+
+fn api_key_set(key: &str) -> bool {
+    std::env::var(key).is_ok_and(|value| !value.trim().is_empty())
+}
+
+/// The provider memos go through, with the local backend, for `/status` and
+/// the startup log: "whisper_local (Metal)", "groq", or the hosted provider
+/// standing in for a local Whisper this build doesn't have.
+pub fn describe(config: &TranscriptionConfig, language: Language) -> String {
+    if config.provider != "whisper_local" {
+        return config.provider.clone();
+    }
+    match (local_backend(), local_fallback(config, api_key_set)) {
+        (Some(backend), _) => format!("whisper_local ({})", backend),
+        (None, Some(fallback)) => {
+            Msg::LocalWhisperFallback.fill(language, &[("provider", &fallback.provider)])
+        }
+        (None, None) => Msg::LocalWhisperMissing.text(language).to_string(),
+    }
 }
 
 /// Factory function to create the appropriate transcription provider
 pub fn create_transcription_provider(config: &TranscriptionConfig) -> Result<Box<dyn TranscriptionProvider>> {
+    if config.provider == "whisper_local" && local_backend().is_none() {
+        let Some(fallback) = local_fallback(config, api_key_set) else {
+            anyhow::bail!(
+                "This build has no local Whisper. Rebuild with --features metal (Mac), cuda (NVIDIA) or cpu, \
+                or set provider = \"groq\" or \"openai\" with GROQ_API_KEY or OPENAI_API_KEY."
+            );
+        };
+        log::debug!(
+            "No local Whisper in this build; transcribing with {}",
+            fallback.provider
+        );
+        return create_transcription_provider(&fallback);
+    }
     match config.provider.as_str() {
         "whisper_local" => {
             let model_path = config.model_path.as_deref()
                 .context("model_path is required for whisper_local provider")?;
             Ok(Box::new(WhisperLocalProvider {
                 model_path: model_path.to_string(),
-                language: config.language.clone(),
+                language: fixed_language(config),
+                converter: Arc::new(NativeConverter {
+                    denoise: config.denoise,
+                    normalize: config.normalize_gain,
+                    resample_quality: config.resample_quality,
+                }),
+                channels: config.channels,
+                channel_labels: config.channel_labels.clone(),
+                timestamps: config.timestamps,
+                options: WhisperOptions {
+                    threads: config.threads,
+                    use_gpu: config.use_gpu,
+                    gpu_device: config.gpu_device,
+                    translate: config.task == WhisperTask::Translate,
+                    chunk_secs: config.chunk_secs,
+                    checkpoint_dir: config.checkpoint_dir.as_ref().map(PathBuf::from),
+                    initial_prompt: whisper_prompt(config),
+                    unload_after_idle: config.unload_after_idle_secs.map(Duration::from_secs),
+                },
             }))
         }
-        "groq" => {
-            let api_key_env = config.api_key_env.as_deref()
-                .unwrap_or("GROQ_API_KEY");
-            let api_key = std::env::var(api_key_env)
-                .with_context(|| format!("Environment variable '{}' not set. Required for Groq provider.", api_key_env))?;
-            let model = config.model.as_deref()
-                .unwrap_or("whisper-large-v3-turbo")
-                .to_string();
-            Ok(Box::new(GroqProvider {
+        "groq" => whisper_api_provider(
+            config,
+            "Groq",
+            "https://api.groq.com/openai/v1/audio/transcriptions",
+            "GROQ_API_KEY",
+            "whisper-large-v3-turbo",
+        ),
+        "openai" => whisper_api_provider(
+            config,
+            "OpenAI",
+            "https://api.openai.com/v1/audio/transcriptions",
+            "OPENAI_API_KEY",
+            "whisper-1",
+        ),
+        "whisper_server" => {
+            let endpoint = config.endpoint.as_deref()
+                .context("endpoint is required for whisper_server provider")?;
+            // Self-hosted servers usually run without authentication
+            let api_key = match config.api_key_env.as_deref() {
+                Some(api_key_env) => Some(std::env::var(api_key_env)
+                    .with_context(|| format!("Environment variable '{}' not set. Required for Whisper server provider.", api_key_env))?),
+                None => None,
+            };
+            if config.channels == ChannelMode::Separate {
+                log::warn!("Whisper server can't transcribe channels separately; channels will be mixed");
+            }
+            let translate = config.task == WhisperTask::Translate;
+            Ok(Box::new(WhisperApiProvider {
+                service: "Whisper server",
+                url: if translate { translations_url(endpoint) } else { endpoint.to_string() },
+                translate,
                 api_key,
-                model,
-                language: config.language.clone(),
+                model: config.model.as_deref().unwrap_or("whisper-1").to_string(),
+                language: fixed_language(config),
+                prompt: whisper_prompt(config),
+                timestamps: config.timestamps,
+                retry: config.retry,
             }))
         }
         "deepgram" => {
@@ -51,117 +342,337 @@ pub fn create_transcription_provider(config: &TranscriptionConfig) -> Result<Box
             let model = config.model.as_deref()
                 .unwrap_or("nova-2")
                 .to_string();
+            if config.task == WhisperTask::Translate {
+                log::warn!("Deepgram can't translate; memos will be transcribed in the spoken language");
+            }
             Ok(Box::new(DeepgramProvider {
                 api_key,
                 model,
-                language: config.language.clone(),
+                language: fixed_language(config),
+                channels: config.channels,
+                channel_labels: config.channel_labels.clone(),
+                glossary: config.glossary.clone(),
+                timestamps: config.timestamps,
+                retry: config.retry,
             }))
         }
-        other => anyhow::bail!("Unknown transcription provider: '{}'. Use 'whisper_local', 'groq', or 'deepgram'.", other),
+        other => anyhow::bail!("Unknown transcription provider: '{}'. Use 'whisper_local', 'whisper_server', 'groq', 'openai', or 'deepgram'.", other),
     }
 }
 
+/// A translated transcript is in English, whatever was spoken.
+fn translated(transcript: Transcript, translate: bool) -> Transcript {
+    if translate {
+        Transcript {
+            language: Some("en".to_string()),
+            ..transcript
+        }
+    } else {
+        transcript
+    }
+}
+
+/// The OpenAI-style endpoint translating to English that sits next to the
+/// transcription endpoint `url`.
+fn translations_url(url: &str) -> String {
+    url.replace("/audio/transcriptions", "/audio/translations")
+}
+
+/// Hosted Whisper provider, with the API key from `api_key_env` (or
+/// `default_key_env`) and `model` (or `default_model`).
+fn whisper_api_provider(
+    config: &TranscriptionConfig,
+    service: &'static str,
+    url: &'static str,
+    default_key_env: &str,
+    default_model: &str,
+) -> Result<Box<dyn TranscriptionProvider>> {
+    let api_key_env = config.api_key_env.as_deref().unwrap_or(default_key_env);
+    let api_key = std::env::var(api_key_env).with_context(|| {
+        format!(
+            "Environment variable '{}' not set. Required for {} provider.",
+            api_key_env, service
+        )
+    })?;
+    let model = config.model.as_deref().unwrap_or(default_model).to_string();
+    if config.channels == ChannelMode::Separate {
+        log::warn!(
+            "{} can't transcribe channels separately; channels will be mixed",
+            service
+        );
+    }
+    let translate = config.task == WhisperTask::Translate;
+    Ok(Box::new(WhisperApiProvider {
+        service,
+        url: if translate {
+            translations_url(url)
+        } else {
+            url.to_string()
+        },
+        translate,
+        api_key: Some(api_key),
+        model,
+        language: fixed_language(config),
+        prompt: whisper_prompt(config),
+        timestamps: config.timestamps,
+        retry: config.retry,
+    }))
+}
+
 // ---------------------------------------------------------------------------
 // WhisperLocalProvider
 // ---------------------------------------------------------------------------
 
 pub struct WhisperLocalProvider {
     model_path: String,
-    language: String,
+    /// `None` detects the language.
+    language: Option<String>,
+    converter: Arc<dyn AudioConverter>,
+    channels: ChannelMode,
+    channel_labels: Vec<String>,
+    timestamps: TimestampMode,
+    options: WhisperOptions,
 }
 
-#[async_trait::async_trait]
-impl TranscriptionProvider for WhisperLocalProvider {
-    async fn transcribe(&self, bot: &Bot, file: &TelegramFile, temp_dir: &str) -> Result<String> {
-        // Download audio from Telegram
-        let audio_path = download_audio_file(bot, file, temp_dir).await?;
-
-        // Convert to WAV format
-        let wav_path = convert_audio_to_wav(&audio_path)
-            .context("Failed to convert audio to WAV")?;
+/// Runtime knobs for local Whisper.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "whisper-rs"), allow(dead_code))]
+struct WhisperOptions {
+    /// CPU threads; `None` lets whisper.cpp pick its default.
+    threads: Option<i32>,
+    /// Offload to GPU when built with the `metal` or `cuda` feature.
+    use_gpu: bool,
+    gpu_device: i32,
+    /// Translate to English instead of transcribing in the source language.
+    translate: bool,
+    unload_after_idle: Option<Duration>,
+    /// Length of the chunks long recordings are transcribed in.
+    chunk_secs: u32,
+    /// Where finished chunks are saved to resume an interrupted transcription.
+    checkpoint_dir: Option<PathBuf>,
+    /// Initial prompt with the glossary, see [`whisper_prompt`].
+    initial_prompt: Option<String>,
+}
 
-        // Transcribe
-        let transcript = transcribe_with_whisper(&wav_path, &self.model_path, &self.language)?;
+impl WhisperLocalProvider {
+    /// Decode and transcribe on a blocking thread, so the runtime stays free
+    /// to deliver progress updates while Whisper runs.
+    async fn run(
+        &self,
+        audio: &AudioInput,
+        progress: Option<ProgressUpdates>,
+    ) -> Result<Transcript> {
+        let audio = audio.clone();
+        let converter = self.converter.clone();
+        let model_path = self.model_path.clone();
+        let language = self.language.clone();
+        let options = self.options.clone();
+        let separate = self.channels == ChannelMode::Separate;
+        let channel_labels = self.channel_labels.clone();
+        let timestamps = self.timestamps;
+
+        // Blocking threads have no current span; parent the stages explicitly
+        let parent = tracing::Span::current();
+        tokio::task::spawn_blocking(move || {
+            // Decode to 16 kHz samples, one signal per channel if they are kept apart
+            let channels = tracing::info_span!(parent: &parent, "convert")
+                .in_scope(|| {
+                    if separate {
+                        converter.convert_channels(&audio)
+                    } else {
+                        converter.convert(&audio).map(|samples| vec![samples])
+                    }
+                })
+                .context("Failed to decode audio")
+                .map_err(PipelineError::Convert)?;
+
+            // Transcribe
+            tracing::info_span!(parent: &parent, "whisper").in_scope(|| {
+                let language = language.as_deref();
+                if channels.len() == 1 {
+                    let (segments, detected) = transcribe_with_whisper(
+                        &channels[0],
+                        &model_path,
+                        language,
+                        &options,
+                        progress.as_ref(),
+                    )?;
+                    let text = render_segments(&segments, timestamps);
+                    return Ok(Transcript {
+                        text,
+                        language: detected,
+                    });
+                }
+
+                log::info!("Transcribing {} channels separately", channels.len());
+                let (per_channel, detected): (Vec<_>, Vec<_>) = channels
+                    .iter()
+                    .map(|samples| {
+                        transcribe_with_whisper(samples, &model_path, language, &options, None)
+                    })
+                    .collect::<Result<Vec<_>>>()?
+                    .into_iter()
+                    .unzip();
+                Ok(Transcript {
+                    text: label_channels(&per_channel, &channel_labels, timestamps),
+                    language: detected.into_iter().flatten().next(),
+                })
+            })
+        })
+        .await
+        .context("Whisper transcription task failed")?
+        .map(|transcript| translated(transcript, self.options.translate))
+    }
+}
 
-        // Clean up temporary files
-        if let Err(e) = std::fs::remove_file(&audio_path) {
-            log::warn!("Failed to remove temporary audio file: {}", e);
-        }
-        if let Err(e) = std::fs::remove_file(&wav_path) {
-            log::warn!("Failed to remove temporary WAV file: {}", e);
-        }
+#[async_trait::async_trait]
+impl TranscriptionProvider for WhisperLocalProvider {
+    async fn transcribe(&self, audio: &AudioInput) -> Result<Transcript> {
+        self.run(audio, None).await
+    }
 
-        Ok(transcript)
+    async fn transcribe_streaming(
+        &self,
+        audio: &AudioInput,
+        progress: ProgressUpdates,
+    ) -> Result<Transcript> {
+        self.run(audio, Some(progress)).await
     }
 }
 
 // ---------------------------------------------------------------------------
-// GroqProvider
+// WhisperApiProvider (Groq, OpenAI)
 // ---------------------------------------------------------------------------
 
-pub struct GroqProvider {
-    api_key: String,
+/// Hosted Whisper behind the OpenAI `audio/transcriptions` API, which Groq
+/// and self-hosted servers (faster-whisper, whisper.cpp) implement as well.
+pub struct WhisperApiProvider {
+    /// Service name for logs and errors.
+    service: &'static str,
+    url: String,
+    /// `url` is the translations endpoint: English text from any language.
+    translate: bool,
+    /// Bearer token; `None` for servers without authentication.
+    api_key: Option<String>,
     model: String,
-    language: String,
+    language: Option<String>,
+    /// Initial prompt with the glossary, see [`whisper_prompt`].
+    prompt: Option<String>,
+    timestamps: TimestampMode,
+    retry: RetryConfig,
 }
 
 #[async_trait::async_trait]
-impl TranscriptionProvider for GroqProvider {
-    async fn transcribe(&self, bot: &Bot, file: &TelegramFile, temp_dir: &str) -> Result<String> {
-        // Download audio from Telegram (keep as OGG — Groq accepts it)
-        let audio_path = download_audio_file(bot, file, temp_dir).await?;
-
-        let file_bytes = std::fs::read(&audio_path)
-            .context("Failed to read downloaded audio file")?;
-
-        let file_name = audio_path.file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-
-        let file_part = reqwest::multipart::Part::bytes(file_bytes)
-            .file_name(file_name)
-            .mime_str("audio/ogg")?;
+impl TranscriptionProvider for WhisperApiProvider {
+    async fn transcribe(&self, audio: &AudioInput) -> Result<Transcript> {
+        // Upload the original file — both services accept OGG, but OpenAI
+        // picks the format from the extension and rejects Telegram's `.oga`
+        let file_bytes = audio.bytes()?;
+        let mime_type = audio.mime_type();
+        let file_name = match audio.file_name().strip_suffix(".oga") {
+            Some(stem) => format!("{}.ogg", stem),
+            None => audio.file_name(),
+        };
 
-        let form = reqwest::multipart::Form::new()
-            .part("file", file_part)
-            .text("model", self.model.clone())
-            .text("language", self.language.clone())
-            .text("response_format", "json");
+        // Forms are consumed by sending, so each try builds its own
+        let form = || {
+            let file_part = reqwest::multipart::Part::bytes(file_bytes.clone())
+                .file_name(file_name.clone())
+                .mime_str(mime_type)
+                .expect("audio MIME types are valid");
+            let mut form = reqwest::multipart::Form::new()
+                .part("file", file_part)
+                .text("model", self.model.clone());
+            // Translations take no source language
+            match &self.language {
+                Some(language) if !self.translate => form = form.text("language", language.clone()),
+                _ => {}
+            }
+            if let Some(prompt) = &self.prompt {
+                form = form.text("prompt", prompt.clone());
+            }
+            // Without a language the service detects it; only the verbose
+            // response reports which one, and the segment or word times
+            match (self.timestamps, &self.language) {
+                (TimestampMode::Off, Some(_)) => form.text("response_format", "json"),
+                (TimestampMode::Off, None) => form.text("response_format", "verbose_json"),
+                (TimestampMode::Segments, _) => form
+                    .text("response_format", "verbose_json")
+                    .text("timestamp_granularities[]", "segment"),
+                (TimestampMode::Words, _) => form
+                    .text("response_format", "verbose_json")
+                    .text("timestamp_granularities[]", "word"),
+            }
+        };
 
         let client = reqwest::Client::new();
-        let response = client
-            .post("https://api.groq.com/openai/v1/audio/transcriptions")
-            .bearer_auth(&self.api_key)
-            .multipart(form)
-            .send()
-            .await
-            .context("Failed to send request to Groq API")?;
-
-        // Clean up temp file
-        if let Err(e) = std::fs::remove_file(&audio_path) {
-            log::warn!("Failed to remove temporary audio file: {}", e);
-        }
+        let response = retry::send_with_backoff(&self.retry, self.service, || {
+            let request = client.post(&self.url).multipart(form());
+            match &self.api_key {
+                Some(api_key) => request.bearer_auth(api_key),
+                None => request,
+            }
+        })
+        .await
+        .with_context(|| format!("Failed to send request to {} API", self.service))?;
 
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Groq API error ({}): {}", status, error_text);
+            anyhow::bail!("{} API error ({}): {}", self.service, status, error_text);
         }
 
         let response_json: serde_json::Value = response.json().await
-            .context("Failed to parse Groq response")?;
+            .with_context(|| format!("Failed to parse {} response", self.service))?;
+
+        let text = match api_segments(&response_json, self.timestamps) {
+            Some(segments) => render_segments(&segments, self.timestamps),
+            None => response_json["text"]
+                .as_str()
+                .with_context(|| format!("No 'text' field in {} response", self.service))?
+                .to_string(),
+        };
 
-        let text = response_json["text"]
-            .as_str()
-            .context("No 'text' field in Groq response")?
-            .to_string();
+        let language = match &self.language {
+            Some(_) => None,
+            None => response_json["language"].as_str().map(language_code),
+        };
 
-        log::info!("Groq transcription complete: {} characters", text.len());
-        Ok(text)
+        log::info!(
+            "{} transcription complete: {} characters",
+            self.service,
+            text.len()
+        );
+        Ok(translated(Transcript { text, language }, self.translate))
     }
 }
 
+/// Timed words or segments of a `verbose_json` transcription response, as
+/// asked for by `timestamps`. `None` without timestamps or if the response
+/// has none.
+fn api_segments(
+    response: &serde_json::Value,
+    timestamps: TimestampMode,
+) -> Option<Vec<TimedSegment>> {
+    let (field, text_field) = match timestamps {
+        TimestampMode::Off => return None,
+        TimestampMode::Segments => ("segments", "text"),
+        TimestampMode::Words => ("words", "word"),
+    };
+    let items = response[field]
+        .as_array()
+        .filter(|items| !items.is_empty())?;
+    Some(
+        items
+            .iter()
+            .map(|item| TimedSegment {
+                start: item["start"].as_f64().unwrap_or(0.0),
+                text: item[text_field].as_str().unwrap_or_default().to_string(),
+            })
+            .collect(),
+    )
+}
+
 // ---------------------------------------------------------------------------
 // DeepgramProvider
 // ---------------------------------------------------------------------------
@@ -169,36 +680,59 @@ impl TranscriptionProvider for GroqProvider {
 pub struct DeepgramProvider {
     api_key: String,
     model: String,
-    language: String,
+    language: Option<String>,
+    channels: ChannelMode,
+    channel_labels: Vec<String>,
+    /// Terms boosted as keywords (key terms on Nova-3).
+    glossary: Vec<String>,
+    timestamps: TimestampMode,
+    retry: RetryConfig,
 }
 
 #[async_trait::async_trait]
 impl TranscriptionProvider for DeepgramProvider {
-    async fn transcribe(&self, bot: &Bot, file: &TelegramFile, temp_dir: &str) -> Result<String> {
-        let audio_path = download_audio_file(bot, file, temp_dir).await?;
+    async fn transcribe(&self, audio: &AudioInput) -> Result<Transcript> {
+        let file_bytes = audio.bytes()?;
 
-        let file_bytes = std::fs::read(&audio_path)
-            .context("Failed to read downloaded audio file")?;
-
-        // Clean up temp file early
-        if let Err(e) = std::fs::remove_file(&audio_path) {
-            log::warn!("Failed to remove temporary audio file: {}", e);
-        }
-
-        let url = format!(
-            "https://api.deepgram.com/v1/listen?model={}&language={}&smart_format=true",
-            self.model, self.language
+        let mut url = format!(
+            "https://api.deepgram.com/v1/listen?model={}&smart_format=true",
+            self.model
         );
+        match &self.language {
+            Some(language) => url.push_str(&format!("&language={}", language)),
+            None => url.push_str("&detect_language=true"),
+        }
+        let separate = self.channels == ChannelMode::Separate;
+        if separate {
+            url.push_str("&multichannel=true");
+        }
+        if separate || self.timestamps == TimestampMode::Segments {
+            url.push_str("&utterances=true");
+        }
+        let keyword_param = if self.model.starts_with("nova-3") {
+            "keyterm"
+        } else {
+            "keywords"
+        };
+        let keywords: Vec<(&str, &str)> = self
+            .glossary
+            .iter()
+            .map(|term| term.trim())
+            .filter(|term| !term.is_empty())
+            .map(|term| (keyword_param, term))
+            .collect();
 
         let client = reqwest::Client::new();
-        let response = client
-            .post(&url)
-            .header("Authorization", format!("Token {}", self.api_key))
-            .header("Content-Type", "audio/ogg")
-            .body(file_bytes)
-            .send()
-            .await
-            .context("Failed to send request to Deepgram API")?;
+        let response = retry::send_with_backoff(&self.retry, "Deepgram", || {
+            client
+                .post(&url)
+                .query(&keywords)
+                .header("Authorization", format!("Token {}", self.api_key))
+                .header("Content-Type", audio.mime_type())
+                .body(file_bytes.clone())
+        })
+        .await
+        .context("Failed to send request to Deepgram API")?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -208,6 +742,29 @@ impl TranscriptionProvider for DeepgramProvider {
 
         let response_json: serde_json::Value = response.json().await
             .context("Failed to parse Deepgram response")?;
+        let language = match &self.language {
+            Some(_) => None,
+            None => response_json["results"]["channels"][0]["detected_language"]
+                .as_str()
+                .map(language_code),
+        };
+
+        if separate {
+            let utterances = deepgram_utterances(&response_json)?;
+            let text = label_channels(&utterances, &self.channel_labels, self.timestamps);
+            log::info!("Deepgram transcription complete: {} characters", text.len());
+            return Ok(Transcript { text, language });
+        }
+        let timed = match self.timestamps {
+            TimestampMode::Off => None,
+            TimestampMode::Segments => deepgram_utterances(&response_json)?.into_iter().next(),
+            TimestampMode::Words => deepgram_words(&response_json),
+        };
+        if let Some(segments) = timed {
+            let text = render_segments(&segments, self.timestamps);
+            log::info!("Deepgram transcription complete: {} characters", text.len());
+            return Ok(Transcript { text, language });
+        }
 
         // Deepgram returns transcript in results.channels[0].alternatives[0].transcript
         let text = response_json["results"]["channels"][0]["alternatives"][0]["transcript"]
@@ -216,314 +773,909 @@ impl TranscriptionProvider for DeepgramProvider {
             .to_string();
 
         log::info!("Deepgram transcription complete: {} characters", text.len());
-        Ok(text)
+        Ok(Transcript { text, language })
     }
 }
 
-// ---------------------------------------------------------------------------
-// Shared helpers (download, convert, whisper)
-// ---------------------------------------------------------------------------
+/// Utterances of a `multichannel` Deepgram response, grouped by channel.
+fn deepgram_utterances(response: &serde_json::Value) -> Result<Vec<Vec<TimedSegment>>> {
+    let utterances = response["results"]["utterances"]
+        .as_array()
+        .context("No utterances in Deepgram response")?;
+    let channel_count = response["results"]["channels"]
+        .as_array()
+        .map_or(1, |channels| channels.len().max(1));
+
+    let mut channels: Vec<Vec<TimedSegment>> = vec![Vec::new(); channel_count];
+    for utterance in utterances {
+        let channel = utterance["channel"].as_u64().unwrap_or(0) as usize;
+        if channel >= channels.len() {
+            channels.resize(channel + 1, Vec::new());
+        }
+        channels[channel].push(TimedSegment {
+            start: utterance["start"].as_f64().unwrap_or(0.0),
+            text: utterance["transcript"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+    Ok(channels)
+}
 
-/// Download audio file from Telegram
-async fn download_audio_file(
-    bot: &Bot,
-    file: &TelegramFile,
-    temp_dir: &str,
-) -> Result<PathBuf> {
-    log::info!("Downloading audio file: {}", file.path);
+/// Words of a mono Deepgram response with their start times.
+fn deepgram_words(response: &serde_json::Value) -> Option<Vec<TimedSegment>> {
+    let words = response["results"]["channels"][0]["alternatives"][0]["words"].as_array()?;
+    Some(
+        words
+            .iter()
+            .map(|word| TimedSegment {
+                start: word["start"].as_f64().unwrap_or(0.0),
+                text: word["punctuated_word"]
+                    .as_str()
+                    .or_else(|| word["word"].as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+            .collect(),
+    )
+}
 
-    // Create temp directory if it doesn't exist
-    std::fs::create_dir_all(temp_dir)?;
+// ---------------------------------------------------------------------------
+// Timed segments
+// ---------------------------------------------------------------------------
 
-    // Generate unique filename
-    let file_name = format!("audio_{}.ogg", uuid::Uuid::new_v4());
-    let file_path = Path::new(temp_dir).join(&file_name);
+/// A piece of transcript and when it starts in the recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimedSegment {
+    /// Seconds from the start of the recording.
+    pub start: f64,
+    pub text: String,
+}
 
-    // Download file from Telegram
-    let mut stream = bot.download_file_stream(&file.path);
-    let mut dest_file = File::create(&file_path)
-        .context("Failed to create temporary audio file")?;
+/// Plain transcript from consecutive segments.
+fn join_segments(segments: &[TimedSegment]) -> String {
+    segments
+        .iter()
+        .map(|segment| segment.text.trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-    // Write chunks to file
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.context("Failed to download audio chunk")?;
-        dest_file.write_all(&chunk)
-            .context("Failed to write audio chunk to file")?;
+/// `[mm:ss]` (or `[h:mm:ss]`) marker for a time in the recording.
+pub fn format_timestamp(secs: f64) -> String {
+    let secs = secs.max(0.0) as u64;
+    match secs / 3600 {
+        0 => format!("[{:02}:{:02}]", secs / 60, secs % 60),
+        hours => format!("[{}:{:02}:{:02}]", hours, secs / 60 % 60, secs % 60),
     }
+}
 
-    log::info!("Audio file downloaded to: {}", file_path.display());
-    Ok(file_path)
+/// Transcript from consecutive segments, with markers as `timestamps` asks:
+/// one line per segment, or a marker inline whenever the time changes.
+fn render_segments(segments: &[TimedSegment], timestamps: TimestampMode) -> String {
+    let texts = segments
+        .iter()
+        .map(|segment| (format_timestamp(segment.start), segment.text.trim()))
+        .filter(|(_, text)| !text.is_empty());
+    match timestamps {
+        TimestampMode::Off => join_segments(segments),
+        TimestampMode::Segments => texts
+            .map(|(stamp, text)| format!("{} {}", stamp, text))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        TimestampMode::Words => {
+            let mut last_stamp = String::new();
+            let mut words = Vec::new();
+            for (stamp, text) in texts {
+                if stamp != last_stamp {
+                    words.push(format!("{} {}", stamp, text));
+                    last_stamp = stamp;
+                } else {
+                    words.push(text.to_string());
+                }
+            }
+            words.join(" ")
+        }
+    }
 }
 
-/// Convert audio using ffmpeg (fallback for unsupported formats like Opus)
-fn convert_with_ffmpeg(input_path: &Path, output_path: &Path) -> Result<()> {
-    use std::process::Command;
+/// Remove the `[mm:ss]` markers added by [`format_timestamp`].
+pub fn strip_timestamps(text: &str) -> String {
+    let is_timestamp = |inner: &str| {
+        let parts: Vec<&str> = inner.split(':').collect();
+        (2..=3).contains(&parts.len())
+            && parts
+                .iter()
+                .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+    };
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('[') {
+        stripped.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        match after.find(']') {
+            Some(close) if is_timestamp(&after[..close]) => {
+                let tail = &after[close + 1..];
+                rest = tail.strip_prefix(' ').unwrap_or(tail);
+            }
+            _ => {
+                stripped.push('[');
+                rest = after;
+            }
+        }
+    }
+    stripped.push_str(rest);
+    stripped
+}
 
-    log::info!("Converting audio with ffmpeg...");
+/// Interleave per-channel segments by start time into a transcript with one
+/// line per speaker turn, e.g. `Io: ...` / `Cliente: ...`, each starting with
+/// its time unless `timestamps` is off.
+///
+/// Channels without a name in `labels` are called "Canale N"; a recording
+/// with a single channel gives a plain transcript.
+pub fn label_channels(
+    channels: &[Vec<TimedSegment>],
+    labels: &[String],
+    timestamps: TimestampMode,
+) -> String {
+    if channels.len() <= 1 {
+        return channels
+            .first()
+            .map(|segments| render_segments(segments, timestamps))
+            .unwrap_or_default();
+    }
 
-    let output = Command::new("ffmpeg")
-        .arg("-i")
-        .arg(input_path)
-        .arg("-ar")
-        .arg("16000") // 16kHz sample rate
-        .arg("-ac")
-        .arg("1") // mono
-        .arg("-c:a")
-        .arg("pcm_s16le") // 16-bit PCM
-        .arg("-y") // overwrite output
-        .arg(output_path)
-        .output()
-        .context("Failed to run ffmpeg. Is ffmpeg installed?")?;
+    let mut timeline: Vec<(usize, &TimedSegment)> = channels
+        .iter()
+        .enumerate()
+        .flat_map(|(channel, segments)| segments.iter().map(move |segment| (channel, segment)))
+        .filter(|(_, segment)| !segment.text.trim().is_empty())
+        .collect();
+    timeline.sort_by(|a, b| a.1.start.total_cmp(&b.1.start).then(a.0.cmp(&b.0)));
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("ffmpeg conversion failed: {}", stderr);
+    let mut turns: Vec<(usize, f64, Vec<&str>)> = Vec::new();
+    for (channel, segment) in timeline {
+        match turns.last_mut() {
+            Some((speaker, _, texts)) if *speaker == channel => texts.push(segment.text.trim()),
+            _ => turns.push((channel, segment.start, vec![segment.text.trim()])),
+        }
     }
 
-    log::info!("Audio converted successfully with ffmpeg");
-    Ok(())
+    turns
+        .into_iter()
+        .map(|(channel, start, texts)| {
+            let label = labels
+                .get(channel)
+                .cloned()
+                .unwrap_or_else(|| format!("Canale {}", channel + 1));
+            match timestamps {
+                TimestampMode::Off => format!("{}: {}", label, texts.join(" ")),
+                _ => format!("{} {}: {}", format_timestamp(start), label, texts.join(" ")),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-/// Convert audio file to WAV format (16kHz, mono) for Whisper
-fn convert_audio_to_wav(input_path: &Path) -> Result<PathBuf> {
-    use symphonia::core::audio::SampleBuffer;
-    use symphonia::core::codecs::DecoderOptions;
-    use symphonia::core::formats::FormatOptions;
-    use symphonia::core::io::MediaSourceStream;
-    use symphonia::core::meta::MetadataOptions;
-    use symphonia::core::probe::Hint;
+// ---------------------------------------------------------------------------
+// Model cache
+// ---------------------------------------------------------------------------
 
-    log::info!("Converting audio to WAV format: {}", input_path.display());
+/// Keeps one loaded model in memory across requests.
+///
+/// Loading a Whisper model takes several seconds and gigabytes of RAM, so the
+/// context is loaded once and shared; each request creates its own state from
+/// it. Callers hold an `Arc`, so unloading never pulls a model out from under
+/// a running transcription — it is freed once the last user finishes.
+#[cfg_attr(not(feature = "whisper-rs"), allow(dead_code))]
+pub struct ModelCache<T> {
+    slot: Mutex<Option<CachedModel<T>>>,
+    reaper_started: AtomicBool,
+}
 
-    // Check if file exists and has content
-    let metadata = std::fs::metadata(input_path)
-        .context("Failed to read audio file metadata")?;
-    log::info!("Audio file size: {} bytes", metadata.len());
+struct CachedModel<T> {
+    key: String,
+    model: Arc<T>,
+    last_used: Instant,
+}
 
-    if metadata.len() == 0 {
-        anyhow::bail!("Audio file is empty");
+#[cfg_attr(not(feature = "whisper-rs"), allow(dead_code))]
+impl<T: Send + Sync + 'static> ModelCache<T> {
+    pub const fn new() -> Self {
+        Self {
+            slot: Mutex::new(None),
+            reaper_started: AtomicBool::new(false),
+        }
     }
 
-    // Open the media source
-    let src = std::fs::File::open(input_path)
-        .context("Failed to open audio file")?;
-    let mss = MediaSourceStream::new(Box::new(src), Default::default());
+    /// Return the cached model for `key`, loading it (and replacing any other
+    /// cached model) on a miss.
+    pub fn get_or_load(&self, key: &str, load: impl FnOnce() -> Result<T>) -> Result<Arc<T>> {
+        let mut slot = self.slot.lock().unwrap();
+        if let Some(cached) = slot.as_mut().filter(|c| c.key == key) {
+            cached.last_used = Instant::now();
+            return Ok(cached.model.clone());
+        }
 
-    // Create a probe hint
-    let mut hint = Hint::new();
-    if let Some(ext) = input_path.extension() {
-        let extension = ext.to_string_lossy();
-        log::info!("Audio file extension: {}", extension);
-        hint.with_extension(&extension);
-    } else {
-        log::warn!("Audio file has no extension, probing without hint");
+        let model = Arc::new(load()?);
+        *slot = Some(CachedModel {
+            key: key.to_string(),
+            model: model.clone(),
+            last_used: Instant::now(),
+        });
+        Ok(model)
+    }
+
+    /// Mark the cached model as just used, restarting its idle timer.
+    pub fn touch(&self) {
+        if let Some(cached) = self.slot.lock().unwrap().as_mut() {
+            cached.last_used = Instant::now();
+        }
     }
 
-    // Probe the media source
-    log::info!("Probing audio format...");
-    let probed = symphonia::default::get_probe()
-        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
-        .context("Failed to probe audio file. The audio format may not be supported.")?;
+    /// Drop the cached model if it has not been used for `idle`.
+    ///
+    /// Returns `true` if a model was unloaded.
+    pub fn unload_if_idle(&self, idle: Duration) -> bool {
+        let mut slot = self.slot.lock().unwrap();
+        match slot.as_ref() {
+            Some(cached) if cached.last_used.elapsed() >= idle => {
+                log::info!("Unloading model {} after {:?} idle", cached.key, idle);
+                *slot = None;
+                true
+            }
+            _ => false,
+        }
+    }
 
-    log::info!("Audio format detected successfully");
+    /// Start (once) a background thread unloading the model after `idle`.
+    pub fn start_idle_reaper(&'static self, idle: Duration) {
+        if self.reaper_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let interval = (idle / 4).clamp(Duration::from_secs(1), Duration::from_secs(60));
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            self.unload_if_idle(idle);
+        });
+    }
+}
 
-    let mut format = probed.format;
+/// Split `total` samples into chunks of `chunk_len`, folding a short tail
+/// (under a quarter chunk) into the previous chunk so Whisper never gets a
+/// fragment too short to transcribe reliably.
+#[cfg_attr(not(feature = "whisper-rs"), allow(dead_code))]
+fn chunk_bounds(total: usize, chunk_len: usize) -> Vec<std::ops::Range<usize>> {
+    let chunk_len = chunk_len.max(1);
+    let mut bounds: Vec<std::ops::Range<usize>> = (0..total)
+        .step_by(chunk_len)
+        .map(|start| start..(start + chunk_len).min(total))
+        .collect();
 
-    // Find the first audio track and extract codec params
-    log::info!("Finding audio track...");
-    let track = format.tracks()
-        .iter()
-        .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
-        .context("No audio track found in file")?;
-
-    let track_id = track.id;
-    let codec_params = track.codec_params.clone();
-    log::info!("Audio track found with codec type: {:?}", codec_params.codec);
-
-    // Get sample rate and channels before consuming format
-    let sample_rate = codec_params.sample_rate
-        .context("Sample rate not found")?;
-    let channels = codec_params.channels
-        .context("Channel info not found")?
-        .count();
-
-    // Create a decoder for the track
-    log::info!("Creating decoder for codec...");
-    let decoder_result = symphonia::default::get_codecs()
-        .make(&codec_params, &DecoderOptions::default());
-
-    // If Symphonia can't decode (e.g., Opus codec), fall back to ffmpeg
-    if decoder_result.is_err() {
-        log::warn!("Symphonia can't decode this format. Falling back to ffmpeg...");
-        let output_path = input_path.with_extension("wav");
-        convert_with_ffmpeg(input_path, &output_path)?;
-        return Ok(output_path);
-    }
-
-    let mut decoder = decoder_result.context("Failed to create decoder")?;
-    log::info!("Decoder created successfully");
-
-    // Decode and collect samples
-    let mut samples = Vec::new();
-    let mut sample_buf = None;
-
-    loop {
-        let packet = match format.next_packet() {
-            Ok(packet) => packet,
-            Err(symphonia::core::errors::Error::IoError(e))
-                if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
-            Err(e) => return Err(e).context("Failed to read packet")?,
-        };
+    if bounds.len() > 1 && bounds[bounds.len() - 1].len() < chunk_len / 4 {
+        let tail = bounds.pop().unwrap();
+        bounds.last_mut().unwrap().end = tail.end;
+    }
+    bounds
+}
 
-        if packet.track_id() != track_id {
-            continue;
-        }
+#[cfg(feature = "whisper-rs")]
+static WHISPER_MODEL: ModelCache<WhisperContext> = ModelCache::new();
+
+/// Chunks of a long recording transcribed so far, saved to
+/// `<checkpoint_dir>/<key>.json` after each chunk so a crash or a failure at
+/// chunk 17/40 resumes there instead of starting over.
+#[cfg_attr(not(feature = "whisper-rs"), allow(dead_code))]
+struct ChunkCheckpoint {
+    /// `None` when checkpoints are off.
+    path: Option<PathBuf>,
+    saved: SavedChunks,
+}
 
-        let decoded = decoder.decode(&packet)
-            .context("Failed to decode packet")?;
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedChunks {
+    /// Language detected on the first chunk.
+    language: Option<String>,
+    chunks: Vec<Vec<TimedSegment>>,
+}
 
-        if sample_buf.is_none() {
-            let spec = *decoded.spec();
-            let duration = decoded.capacity() as u64;
-            sample_buf = Some(SampleBuffer::<f32>::new(duration, spec));
+#[cfg_attr(not(feature = "whisper-rs"), allow(dead_code))]
+impl ChunkCheckpoint {
+    /// Resume the checkpoint `key` in `dir`, or start an empty one.
+    fn open(dir: Option<&Path>, key: &str) -> Self {
+        let path = dir.map(|dir| dir.join(format!("{}.json", key)));
+        let saved = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str::<SavedChunks>(&json).ok())
+            .unwrap_or_default();
+        if !saved.chunks.is_empty() {
+            log::info!(
+                "Resuming transcription after {} checkpointed chunk(s)",
+                saved.chunks.len()
+            );
         }
+        Self { path, saved }
+    }
+
+    /// Number of chunks already transcribed.
+    fn done(&self) -> usize {
+        self.saved.chunks.len()
+    }
 
-        if let Some(ref mut buf) = sample_buf {
-            buf.copy_interleaved_ref(decoded);
-            samples.extend_from_slice(buf.samples());
+    fn language(&self) -> Option<&str> {
+        self.saved.language.as_deref()
+    }
+
+    /// Segments of the transcribed chunks, in order.
+    fn segments(&self) -> Vec<TimedSegment> {
+        self.saved.chunks.concat()
+    }
+
+    /// Save one more finished chunk. A failed write only costs the resume.
+    fn record(&mut self, segments: Vec<TimedSegment>, language: Option<&str>) {
+        self.saved.chunks.push(segments);
+        self.saved.language = language.map(str::to_string);
+        let Some(path) = &self.path else {
+            return;
+        };
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(path, serde_json::to_vec(&self.saved)?));
+        if let Err(e) = written {
+            log::warn!(
+                "Failed to save transcription checkpoint {}: {}",
+                path.display(),
+                e
+            );
         }
     }
 
-    if samples.is_empty() {
-        anyhow::bail!("No audio samples decoded");
+    /// Remove the checkpoint once the whole recording is transcribed.
+    fn finish(self) {
+        if let Some(path) = self.path {
+            let _ = std::fs::remove_file(path);
+        }
     }
+}
 
-    log::info!("Original audio: {} Hz, {} channels, {} samples",
-               sample_rate, channels, samples.len());
+/// Checkpoint name for `samples` transcribed with the settings in
+/// `fingerprint`, so a resume never mixes chunks of different runs.
+#[cfg_attr(not(feature = "whisper-rs"), allow(dead_code))]
+fn checkpoint_key(samples: &[f32], fingerprint: &str) -> String {
+    use std::hash::{Hash, Hasher};
 
-    // Convert to mono if stereo
-    let mono_samples = if channels > 1 {
-        samples.chunks(channels)
-            .map(|chunk| chunk.iter().sum::<f32>() / channels as f32)
-            .collect::<Vec<f32>>()
-    } else {
-        samples
-    };
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    samples.len().hash(&mut hasher);
+    for sample in samples {
+        sample.to_bits().hash(&mut hasher);
+    }
+    fingerprint.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
 
-    // Resample to 16kHz if needed
-    let target_sample_rate = 16000;
-    let resampled = if sample_rate != target_sample_rate {
-        resample_audio(&mono_samples, sample_rate, target_sample_rate)
-    } else {
-        mono_samples
-    };
+// ---------------------------------------------------------------------------
+// Shared helpers (download, convert, whisper)
+// ---------------------------------------------------------------------------
 
-    // Convert f32 samples to i16 for WAV
-    let i16_samples: Vec<i16> = resampled.iter()
-        .map(|&s| (s.clamp(-1.0, 1.0) * 32767.0) as i16)
-        .collect();
+/// Download audio from Telegram, into memory when it is at most
+/// `in_memory_max_bytes` long and to a file in `temp_dir` otherwise.
+pub async fn download_audio(
+    bot: &Bot,
+    file: &TelegramFile,
+    temp_dir: &str,
+    in_memory_max_bytes: Option<u64>,
+) -> Result<AudioInput> {
+    log::info!("Downloading audio file: {}", file.path);
+    let file_name = format!(
+        "audio_{}.{}",
+        uuid::Uuid::new_v4(),
+        audio_extension(&file.path)
+    );
+
+    if in_memory_max_bytes.is_some_and(|max| u64::from(file.size) <= max) {
+        let mut bytes = Vec::with_capacity(file.size as usize);
+        let mut stream = bot.inner().download_file_stream(&file.path);
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.context("Failed to download audio chunk")?;
+            bytes.extend_from_slice(&chunk);
+        }
+        log::info!("Audio file downloaded in memory: {} bytes", bytes.len());
+        return Ok(AudioInput::Memory {
+            bytes: bytes.into(),
+            file_name,
+        });
+    }
 
-    // Write WAV file
-    let output_path = input_path.with_extension("wav");
-    let spec = hound::WavSpec {
-        channels: 1,
-        sample_rate: target_sample_rate,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
-    };
+    // Create temp directory if it doesn't exist
+    std::fs::create_dir_all(temp_dir)?;
 
-    let mut writer = hound::WavWriter::create(&output_path, spec)
-        .context("Failed to create WAV writer")?;
+    let file_path = Path::new(temp_dir).join(&file_name);
 
-    for sample in i16_samples {
-        writer.write_sample(sample)
-            .context("Failed to write WAV sample")?;
+    // Download file from Telegram
+    let mut stream = bot.inner().download_file_stream(&file.path);
+    let mut dest_file = File::create(&file_path)
+        .context("Failed to create temporary audio file")?;
+
+    // Write chunks to file
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.context("Failed to download audio chunk")?;
+        dest_file.write_all(&chunk)
+            .context("Failed to write audio chunk to file")?;
     }
 
-    writer.finalize()
-        .context("Failed to finalize WAV file")?;
+    log::info!("Audio file downloaded to: {}", file_path.display());
+    Ok(AudioInput::File(file_path))
+}
 
-    log::info!("Audio converted to: {}", output_path.display());
-    Ok(output_path)
+/// Extension of a Telegram file path ("voice/file_3.oga", "documents/
+/// file_5.m4a"), kept so the decoder gets the right format hint. Voice notes
+/// without one are OGG.
+fn audio_extension(telegram_path: &str) -> String {
+    Path::new(telegram_path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .filter(|ext| ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or_else(|| "ogg".to_string())
 }
 
-/// Simple linear resampling (for better quality, consider using a proper resampling library)
-fn resample_audio(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
-    if from_rate == to_rate {
-        return samples.to_vec();
+/// Transcript-so-far of a local Whisper run, fed by the segment callback.
+#[cfg(feature = "whisper-rs")]
+struct LiveProgress {
+    updates: Option<ProgressUpdates>,
+    total_secs: f64,
+    /// Start of the chunk being transcribed; segment times are relative to it.
+    offset_secs: f64,
+    text: String,
+}
+
+#[cfg(feature = "whisper-rs")]
+impl LiveProgress {
+    /// Add a decoded segment ending `end_cs` centiseconds into the chunk.
+    fn push(&mut self, text: &str, end_cs: i64) {
+        let Some(updates) = &self.updates else {
+            return;
+        };
+        let text = text.trim();
+        if !text.is_empty() {
+            if !self.text.is_empty() {
+                self.text.push(' ');
+            }
+            self.text.push_str(text);
+        }
+        let _ = updates.send(Progress {
+            percent: percent_done(self.offset_secs + end_cs as f64 / 100.0, self.total_secs),
+            text: self.text.clone(),
+        });
     }
+}
 
-    let ratio = from_rate as f64 / to_rate as f64;
-    let output_len = (samples.len() as f64 / ratio) as usize;
-    let mut output = Vec::with_capacity(output_len);
+/// whisper-rs never frees segment callbacks; closing the channel when the run
+/// ends (even on error) lets the receiver finish and frees the text.
+#[cfg(feature = "whisper-rs")]
+struct LiveProgressGuard(Arc<Mutex<LiveProgress>>);
 
-    for i in 0..output_len {
-        let src_idx = (i as f64 * ratio) as usize;
-        if src_idx < samples.len() {
-            output.push(samples[src_idx]);
+#[cfg(feature = "whisper-rs")]
+impl Drop for LiveProgressGuard {
+    fn drop(&mut self) {
+        if let Ok(mut live) = self.0.lock() {
+            live.updates = None;
+            live.text = String::new();
         }
     }
-
-    log::info!("Resampled from {} Hz to {} Hz", from_rate, to_rate);
-    output
 }
 
-/// Transcribe audio file using Whisper
+/// Transcribe 16 kHz mono samples using Whisper, returning timed segments
+/// and, when `language` is `None`, the detected language
 #[cfg(feature = "whisper-rs")]
-fn transcribe_with_whisper(wav_path: &Path, model_path: &str, language: &str) -> Result<String> {
+fn transcribe_with_whisper(
+    audio_data: &[f32],
+    model_path: &str,
+    language: Option<&str>,
+    options: &WhisperOptions,
+    progress: Option<&ProgressUpdates>,
+) -> Result<(Vec<TimedSegment>, Option<String>)> {
     log::info!("Transcribing audio with Whisper model: {}", model_path);
 
-    // Load Whisper model
-    let ctx = WhisperContext::new_with_params(
-        model_path,
-        WhisperContextParameters::default(),
-    ).context("Failed to load Whisper model")?;
-
-    // Load audio data
-    let mut reader = hound::WavReader::open(wav_path)
-        .context("Failed to open WAV file")?;
-
-    let audio_data: Vec<f32> = reader.samples::<i16>()
-        .map(|s| s.unwrap() as f32 / 32768.0)
-        .collect();
+    // Reuse the loaded model across requests; GPU settings are fixed at load time
+    let cache_key = format!(
+        "{} (gpu={}, device={})",
+        model_path, options.use_gpu, options.gpu_device
+    );
+    let ctx = WHISPER_MODEL.get_or_load(&cache_key, || {
+        log::info!("Loading Whisper model: {}", cache_key);
+        let mut ctx_params = WhisperContextParameters::default();
+        ctx_params
+            .use_gpu(options.use_gpu)
+            .gpu_device(options.gpu_device);
+        WhisperContext::new_with_params(model_path, ctx_params)
+            .context("Failed to load Whisper model")
+    })?;
+    if let Some(idle) = options.unload_after_idle {
+        WHISPER_MODEL.start_idle_reaper(idle);
+    }
 
     log::info!("Audio loaded: {} samples", audio_data.len());
 
+    // Resume a long recording from its last checkpointed chunk
+    let chunk_len = options.chunk_secs as usize * WHISPER_SAMPLE_RATE;
+    let chunks = chunk_bounds(audio_data.len(), chunk_len);
+    let fingerprint = format!(
+        "{} {:?} {} {}",
+        model_path, language, options.translate, options.chunk_secs
+    );
+    let mut checkpoint = ChunkCheckpoint::open(
+        options
+            .checkpoint_dir
+            .as_deref()
+            .filter(|_| chunks.len() > 1),
+        &checkpoint_key(audio_data, &fingerprint),
+    );
+    let resumed_language = checkpoint.language().map(str::to_string);
+
     // Create transcription state
     let mut state = ctx.create_state()
         .context("Failed to create Whisper state")?;
 
     // Configure transcription parameters
     let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-    params.set_language(Some(language));
+    // `None` makes whisper.cpp detect the language
+    params.set_language(language.or(resumed_language.as_deref()));
+    params.set_translate(options.translate);
+    if let Some(threads) = options.threads {
+        params.set_n_threads(threads);
+    }
+    if let Some(prompt) = &options.initial_prompt {
+        params.set_initial_prompt(prompt);
+    }
     params.set_print_progress(false);
     params.set_print_special(false);
     params.set_print_realtime(false);
     params.set_print_timestamps(false);
 
-    // Run transcription
-    state.full(params, &audio_data)
-        .context("Failed to run Whisper transcription")?;
+    // Report progress as whisper.cpp decodes each segment
+    let live = Arc::new(Mutex::new(LiveProgress {
+        updates: progress.cloned(),
+        total_secs: audio_data.len() as f64 / WHISPER_SAMPLE_RATE as f64,
+        offset_secs: 0.0,
+        text: String::new(),
+    }));
+    let _live_guard = LiveProgressGuard(Arc::clone(&live));
+    if progress.is_some() {
+        let live = Arc::clone(&live);
+        params.set_segment_callback_safe(move |segment: SegmentCallbackData| {
+            live.lock()
+                .unwrap()
+                .push(&segment.text, segment.end_timestamp);
+        });
+    }
 
-    // Extract transcribed text
-    let num_segments = state.full_n_segments()
-        .context("Failed to get number of segments")?;
+    // Run transcription chunk by chunk
+    let mut segments = checkpoint.segments();
+    let mut detected = resumed_language.clone();
+
+    for (n, range) in chunks.iter().enumerate().skip(checkpoint.done()) {
+        live.lock().unwrap().offset_secs = range.start as f64 / WHISPER_SAMPLE_RATE as f64;
+        state
+            .full(params.clone(), &audio_data[range.clone()])
+            .context("Failed to run Whisper transcription")?;
+
+        // Detect on the first chunk only, then keep the language for the rest
+        if language.is_none() && detected.is_none() {
+            let id = state
+                .full_lang_id_from_state()
+                .context("Failed to get detected language")?;
+            if let Some(code) = whisper_rs::get_lang_str(id) {
+                log::info!("Whisper detected language: {}", code);
+                params.set_language(Some(code));
+                detected = Some(code.to_string());
+            }
+        }
 
-    let mut transcript = String::new();
-    for i in 0..num_segments {
-        let segment = state.full_get_segment_text(i)
-            .context("Failed to get segment text")?;
-        transcript.push_str(&segment);
-        transcript.push(' ');
+        // Extract transcribed text
+        let num_segments = state
+            .full_n_segments()
+            .context("Failed to get number of segments")?;
+
+        let chunk_start = range.start as f64 / WHISPER_SAMPLE_RATE as f64;
+        let mut chunk_segments = Vec::new();
+        for i in 0..num_segments {
+            let text = state
+                .full_get_segment_text(i)
+                .context("Failed to get segment text")?;
+            // Segment times are in centiseconds from the chunk start
+            let t0 = state
+                .full_get_segment_t0(i)
+                .context("Failed to get segment start")?;
+            chunk_segments.push(TimedSegment {
+                start: chunk_start + t0 as f64 / 100.0,
+                text,
+            });
+        }
+        segments.extend(chunk_segments.iter().cloned());
+        checkpoint.record(chunk_segments, detected.as_deref());
+
+        log::info!("Whisper: {}/{} chunks transcribed", n + 1, chunks.len());
     }
 
-    let transcript = transcript.trim().to_string();
-    log::info!("Transcription complete: {} characters", transcript.len());
+    log::info!("Transcription complete: {} segments", segments.len());
+    checkpoint.finish();
+    WHISPER_MODEL.touch();
 
-    Ok(transcript)
+    Ok((segments, detected))
 }
 
 #[cfg(not(feature = "whisper-rs"))]
-fn transcribe_with_whisper(_wav_path: &Path, _model_path: &str, _language: &str) -> Result<String> {
-    anyhow::bail!("Whisper feature not enabled. Build with --features metal (Mac) or --features cuda (Windows)")
+fn transcribe_with_whisper(
+    _audio_data: &[f32],
+    _model_path: &str,
+    _language: Option<&str>,
+    _options: &WhisperOptions,
+    _progress: Option<&ProgressUpdates>,
+) -> Result<(Vec<TimedSegment>, Option<String>)> {
+    anyhow::bail!(
+        "Whisper feature not enabled. Build with --features metal (Mac), cuda (NVIDIA) or cpu"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_done_is_clamped() {
+        assert_eq!(percent_done(30.0, 75.0), 40);
+        assert_eq!(percent_done(80.0, 75.0), 100);
+        assert_eq!(percent_done(1.0, 0.0), 0);
+    }
+
+    #[test]
+    fn test_chunk_bounds_folds_short_tail() {
+        assert_eq!(chunk_bounds(250, 100), vec![0..100, 100..200, 200..250]);
+        assert_eq!(chunk_bounds(210, 100), vec![0..100, 100..210]);
+        assert_eq!(chunk_bounds(10, 100), vec![0..10]);
+        assert!(chunk_bounds(0, 100).is_empty());
+    }
+
+    #[test]
+    fn test_chunk_checkpoint_resumes_and_finishes() {
+        let dir = std::env::temp_dir().join(format!("dot-checkpoint-{}", uuid::Uuid::new_v4()));
+        let key = checkpoint_key(&[0.1, 0.2], "base it");
+        assert_ne!(key, checkpoint_key(&[0.1, 0.2], "base en"));
+
+        let segment = |start: f64, text: &str| TimedSegment {
+            start,
+            text: text.to_string(),
+        };
+        let mut checkpoint = ChunkCheckpoint::open(Some(&dir), &key);
+        assert_eq!(checkpoint.done(), 0);
+        checkpoint.record(
+            vec![segment(0.0, "Primo"), segment(30.0, "pezzo")],
+            Some("it"),
+        );
+        checkpoint.record(vec![segment(60.0, "Secondo")], Some("it"));
+
+        // A new run after a crash picks up both chunks
+        let resumed = ChunkCheckpoint::open(Some(&dir), &key);
+        assert_eq!(resumed.done(), 2);
+        assert_eq!(resumed.language(), Some("it"));
+        assert_eq!(resumed.segments().len(), 3);
+
+        resumed.finish();
+        assert_eq!(ChunkCheckpoint::open(Some(&dir), &key).done(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_label_channels_interleaves_by_time() {
+        let segment = |start: f64, text: &str| TimedSegment {
+            start,
+            text: text.to_string(),
+        };
+        let channels = vec![
+            vec![
+                segment(0.0, " Pronto?"),
+                segment(4.5, " Perfetto,"),
+                segment(6.0, " a domani."),
+            ],
+            vec![
+                segment(1.2, " Ciao, sono Marco."),
+                segment(2.8, " Ti richiamo per il preventivo."),
+            ],
+        ];
+
+        assert_eq!(
+            label_channels(&channels, &["Io".to_string()], TimestampMode::Off),
+            "Io: Pronto?\nCanale 2: Ciao, sono Marco. Ti richiamo per il preventivo.\nIo: Perfetto, a domani."
+        );
+        assert_eq!(
+            label_channels(&channels[..1], &[], TimestampMode::Off),
+            "Pronto? Perfetto, a domani."
+        );
+        assert_eq!(
+            label_channels(&channels, &[], TimestampMode::Segments)
+                .lines()
+                .nth(1),
+            Some("[00:01] Canale 2: Ciao, sono Marco. Ti richiamo per il preventivo.")
+        );
+
+        let response = serde_json::json!({
+            "results": {
+                "channels": [{}, {}],
+                "utterances": [
+                    {"channel": 1, "start": 1.2, "transcript": "Ciao"},
+                    {"channel": 0, "start": 0.1, "transcript": "Pronto?"}
+                ]
+            }
+        });
+        let utterances = deepgram_utterances(&response).unwrap();
+        assert_eq!(utterances[0], vec![segment(0.1, "Pronto?")]);
+        assert_eq!(
+            label_channels(&utterances, &[], TimestampMode::Off),
+            "Canale 1: Pronto?\nCanale 2: Ciao"
+        );
+    }
+
+    #[test]
+    fn test_timestamps_render_and_strip() {
+        let response = serde_json::json!({
+            "text": "Ciao a tutti. Oggi parliamo di Rust.",
+            "segments": [
+                {"start": 0.0, "text": " Ciao a tutti."},
+                {"start": 65.4, "text": " Oggi parliamo di Rust."}
+            ],
+            "words": [
+                {"start": 0.0, "word": "Ciao"},
+                {"start": 0.4, "word": "a"},
+                {"start": 1.2, "word": "tutti."}
+            ]
+        });
+        assert_eq!(api_segments(&response, TimestampMode::Off), None);
+
+        let segments = api_segments(&response, TimestampMode::Segments).unwrap();
+        let text = render_segments(&segments, TimestampMode::Segments);
+        assert_eq!(
+            text,
+            "[00:00] Ciao a tutti.\n[01:05] Oggi parliamo di Rust."
+        );
+        assert_eq!(
+            strip_timestamps(&text),
+            "Ciao a tutti.\nOggi parliamo di Rust."
+        );
+
+        let words = api_segments(&response, TimestampMode::Words).unwrap();
+        assert_eq!(
+            render_segments(&words, TimestampMode::Words),
+            "[00:00] Ciao a [00:01] tutti."
+        );
+
+        assert_eq!(format_timestamp(3725.0), "[1:02:05]");
+        assert_eq!(
+            strip_timestamps("[nota] a [1:02:05] b [12]"),
+            "[nota] a b [12]"
+        );
+    }
+
+    #[test]
+    fn test_whisper_server_needs_only_an_endpoint() {
+        let config: TranscriptionConfig =
+            toml::from_str("provider = \"whisper_server\"\nlanguage = \"it\"").unwrap();
+        assert!(create_transcription_provider(&config).is_err());
+
+        let config: TranscriptionConfig = toml::from_str(
+            "provider = \"whisper_server\"\nlanguage = \"it\"\n\
+            endpoint = \"http://gpu-box:8000/v1/audio/transcriptions\"",
+        )
+        .unwrap();
+        assert!(create_transcription_provider(&config).is_ok());
+        assert_eq!(
+            translations_url("http://gpu-box:8000/v1/audio/transcriptions"),
+            "http://gpu-box:8000/v1/audio/translations"
+        );
+    }
+
+    #[test]
+    fn test_whisper_local_falls_back_without_the_feature() {
+        let config: TranscriptionConfig = toml::from_str(
+            "provider = \"whisper_local\"\nlanguage = \"it\"\n\
+            model_path = \"models/ggml-base.bin\"\nmodel = \"base\"",
+        )
+        .unwrap();
+        let fallback = local_fallback(&config, |key| key == "OPENAI_API_KEY");
+        if local_backend().is_some() {
+            assert!(fallback.is_none());
+            return;
+        }
+        let fallback = fallback.unwrap();
+        assert_eq!(fallback.provider, "openai");
+        assert_eq!(fallback.model, None);
+        assert_eq!(local_fallback(&config, |_| true).unwrap().provider, "groq");
+        assert!(local_fallback(&config, |_| false).is_none());
+    }
+
+    #[test]
+    fn test_auto_language_and_detected_codes() {
+        let config: TranscriptionConfig =
+            toml::from_str("provider = \"groq\"\nlanguage = \"Auto\"").unwrap();
+        assert_eq!(fixed_language(&config), None);
+        let config: TranscriptionConfig =
+            toml::from_str("provider = \"groq\"\nlanguage = \"it\"").unwrap();
+        assert_eq!(fixed_language(&config).as_deref(), Some("it"));
+
+        assert_eq!(language_code("English"), "en");
+        assert_eq!(language_code("it"), "it");
+        assert_eq!(language_code("Swahili"), "swahili");
+    }
+
+    #[test]
+    fn test_whisper_prompt_appends_glossary() {
+        let mut config: TranscriptionConfig =
+            toml::from_str("provider = \"groq\"\nlanguage = \"it\"").unwrap();
+        assert_eq!(whisper_prompt(&config), None);
+        config.glossary = vec![
+            "Kubernetes".to_string(),
+            " ".to_string(),
+            "Obsidian".to_string(),
+        ];
+        assert_eq!(
+            whisper_prompt(&config).as_deref(),
+            Some("Glossario: Kubernetes, Obsidian.")
+        );
+        config.initial_prompt = Some("Note di lavoro su infrastruttura.".to_string());
+        assert_eq!(
+            whisper_prompt(&config).as_deref(),
+            Some("Note di lavoro su infrastruttura. Glossario: Kubernetes, Obsidian.")
+        );
+    }
+
+    #[test]
+    fn test_audio_extension_from_telegram_path() {
+        assert_eq!(audio_extension("voice/file_3.oga"), "oga");
+        assert_eq!(audio_extension("documents/file_5.M4A"), "m4a");
+        assert_eq!(audio_extension("voice/file_4"), "ogg");
+        let audio = AudioInput::Memory {
+            bytes: Arc::from(Vec::new()),
+            file_name: "audio_1.mp3".to_string(),
+        };
+        assert_eq!(audio.mime_type(), "audio/mpeg");
+    }
+
+    #[test]
+    fn test_model_cache_reuses_and_unloads() {
+        let cache: ModelCache<String> = ModelCache::new();
+        let mut loads = 0;
+
+        let first = cache
+            .get_or_load("base", || {
+                loads += 1;
+                Ok("model".to_string())
+            })
+            .unwrap();
+        let second = cache
+            .get_or_load("base", || {
+                loads += 1;
+                Ok("model".to_string())
+            })
+            .unwrap();
+        assert_eq!(loads, 1);
+        assert!(Arc::ptr_eq(&first, &second));
+
+        assert!(!cache.unload_if_idle(Duration::from_secs(3600)));
+        assert!(cache.unload_if_idle(Duration::ZERO));
+
+        // The caller's handle survives unloading
+        assert_eq!(first.as_str(), "model");
+
+        cache
+            .get_or_load("base", || {
+                loads += 1;
+                Ok("model".to_string())
+            })
+            .unwrap();
+        assert_eq!(loads, 2);
+    }
 }
@@ -0,0 +1,173 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::AiModelConfig;
+
+/// Serializes updates of the totals within the process.
+static USAGE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Tokens the model read and wrote, as reported by the provider.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Model requests that reported usage.
+    pub requests: u64,
+}
+
+impl TokenUsage {
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.requests += other.requests;
+    }
+
+    /// The usage added since `earlier`, a previous reading of the same total.
+    pub fn since(&self, earlier: &TokenUsage) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens: self.prompt_tokens.saturating_sub(earlier.prompt_tokens),
+            completion_tokens: self
+                .completion_tokens
+                .saturating_sub(earlier.completion_tokens),
+            requests: self.requests.saturating_sub(earlier.requests),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests == 0
+    }
+
+    /// Cost in the currency of the `*_cost_per_mtok` prices, if both are set.
+    pub fn cost(&self, config: &AiModelConfig) -> Option<f64> {
+        let input = config.input_cost_per_mtok?;
+        let output = config.output_cost_per_mtok?;
+        Some(
+            (self.prompt_tokens as f64 * input + self.completion_tokens as f64 * output)
+                / 1_000_000.0,
+        )
+    }
+
+    /// "1234 + 567 token (~0.0042)" for the reply and `/status`.
+    pub fn summary(&self, config: &AiModelConfig) -> String {
+        let mut summary = format!("{} + {} token", self.prompt_tokens, self.completion_tokens);
+        if let Some(cost) = self.cost(config) {
+            summary.push_str(&format!(" (~{:.4})", cost));
+        }
+        summary
+    }
+}
+
+/// Running total of the usage a provider reported for its requests.
+#[derive(Debug, Default)]
+pub struct UsageMeter(Mutex<TokenUsage>);
+
+impl UsageMeter {
+    pub fn record(&self, prompt_tokens: u64, completion_tokens: u64) {
+        self.0.lock().unwrap().add(&TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            requests: 1,
+        });
+    }
+
+    /// Record the usage in the `prompt` and `completion` fields of `usage`,
+    /// a provider's response (or part of it); nothing if it has neither.
+    pub fn record_fields(&self, usage: &serde_json::Value, prompt: &str, completion: &str) {
+        let (prompt, completion) = (usage[prompt].as_u64(), usage[completion].as_u64());
+        if prompt.is_some() || completion.is_some() {
+            self.record(prompt.unwrap_or(0), completion.unwrap_or(0));
+        }
+    }
+
+    pub fn total(&self) -> TokenUsage {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// Usage of all memos since `since`, shown by `/status`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub since: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub usage: TokenUsage,
+}
+
+/// Cumulative token usage in `<data_dir>/usage.json`.
+pub struct UsageStats {
+    path: PathBuf,
+}
+
+impl UsageStats {
+    pub fn new<P: AsRef<Path>>(data_dir: P) -> Self {
+        Self {
+            path: data_dir.as_ref().join("usage.json"),
+        }
+    }
+
+    pub fn totals(&self) -> Result<UsageTotals> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid usage totals: {}", self.path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(UsageTotals::default()),
+            Err(e) => Err(e)
+                .with_context(|| format!("Failed to read usage totals: {}", self.path.display())),
+        }
+    }
+
+    /// Add the usage of a memo to the totals.
+    pub fn add(&self, usage: &TokenUsage) -> Result<()> {
+        let _guard = USAGE_LOCK.lock().unwrap();
+        let mut totals = self.totals()?;
+        totals.since.get_or_insert_with(Utc::now);
+        totals.usage.add(usage);
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create data directory")?;
+        }
+        let content = serde_json::to_string(&totals).context("Failed to serialize usage")?;
+        std::fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write usage totals: {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_adds_up_and_costs() {
+        let meter = UsageMeter::default();
+        meter.record(1000, 200);
+        meter.record_fields(
+            &serde_json::json!({ "prompt_tokens": 500, "completion_tokens": 300 }),
+            "prompt_tokens",
+            "completion_tokens",
+        );
+        meter.record_fields(&serde_json::json!({}), "prompt_tokens", "completion_tokens");
+        let usage = meter.total();
+        assert_eq!(usage.requests, 2);
+
+        let mut config: AiModelConfig = toml::from_str(
+            "provider = \"openai_compatible\"\nmodel = \"gpt-4o-mini\"\n\
+            endpoint = \"https://api.openai.com/v1\"\ninput_cost_per_mtok = 0.15",
+        )
+        .unwrap();
+        assert_eq!(usage.summary(&config), "1500 + 500 token");
+        config.output_cost_per_mtok = Some(0.6);
+        assert_eq!(usage.summary(&config), "1500 + 500 token (~0.0005)");
+
+        let dir = std::env::temp_dir().join(format!("dot-usage-{}", uuid::Uuid::new_v4()));
+        let stats = UsageStats::new(&dir);
+        assert!(stats.totals().unwrap().since.is_none());
+        stats.add(&usage).unwrap();
+        stats.add(&usage).unwrap();
+        let totals = stats.totals().unwrap();
+        assert!(totals.since.is_some());
+        assert_eq!(totals.usage.prompt_tokens, 3000);
+        assert_eq!(totals.usage.requests, 4);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
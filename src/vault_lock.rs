@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::sync::OwnedMutexGuard;
+
+/// Advisory lock file at the vault root. External tools that rewrite notes
+/// (sync scripts, a second bot instance) can `flock` it to stay out of the
+/// bot's way.
+pub const LOCK_FILE: &str = ".dot.lock";
+
+/// One async lock per vault, shared by every task of this process.
+static LOCKS: LazyLock<Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(Default::default);
+
+/// Exclusive write access to a vault, released on drop.
+pub struct VaultGuard {
+    file: File,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl Drop for VaultGuard {
+    fn drop(&mut self) {
+        if let Err(e) = FileExt::unlock(&self.file) {
+            log::warn!("Failed to release vault lock: {}", e);
+        }
+    }
+}
+
+/// Wait for exclusive write access to the vault at `vault_dir`.
+///
+/// Everything that writes or rewrites notes takes this lock first, so
+/// concurrent memos and link-rewrite passes never interleave partial writes
+/// to the same files.
+pub async fn lock(vault_dir: &Path) -> Result<VaultGuard> {
+    std::fs::create_dir_all(vault_dir)
+        .with_context(|| format!("Failed to create vault: {}", vault_dir.display()))?;
+    let root = vault_dir
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve vault: {}", vault_dir.display()))?;
+
+    let mutex = LOCKS
+        .lock()
+        .unwrap()
+        .entry(root.clone())
+        .or_default()
+        .clone();
+    let guard = mutex.lock_owned().await;
+
+    // Another process may hold the file lock for a while; wait off the runtime
+    let path = root.join(LOCK_FILE);
+    let file = tokio::task::spawn_blocking(move || -> Result<File> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open vault lock: {}", path.display()))?;
+        file.lock_exclusive()
+            .with_context(|| format!("Failed to lock vault: {}", path.display()))?;
+        Ok(file)
+    })
+    .await
+    .context("Vault lock task failed")??;
+
+    Ok(VaultGuard {
+        file,
+        _guard: guard,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lock_excludes_tasks_and_processes() {
+        let dir = std::env::temp_dir().join(format!("dot-vault-lock-{}", uuid::Uuid::new_v4()));
+        let guard = lock(&dir).await.unwrap();
+
+        // Another task waits for the guard
+        let waiting = tokio::spawn({
+            let dir = dir.clone();
+            async move { lock(&dir).await.map(|_| ()) }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+
+        // External processes see the advisory lock
+        let external = File::open(dir.join(LOCK_FILE)).unwrap();
+        assert!(external.try_lock_exclusive().is_err());
+
+        drop(guard);
+        waiting.await.unwrap().unwrap();
+        assert!(external.try_lock_exclusive().is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}